@@ -0,0 +1,84 @@
+//! Compile the engine's BLAKE3 and SIMD C sources; no D toolchain involved.
+//!
+//! In the repository the sources are read from `source/infrastructure/utils`.
+//! Published crates carry a copy under `c/` (made by `tools/release.sh`),
+//! which wins when present. `BLDR_HASH_C_DIR` points at another checkout.
+//! Flags match the sources' own Makefiles so the objects are the ones the
+//! engine links.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=BLDR_HASH_C_DIR");
+    println!("cargo::rustc-check-cfg=cfg(bldr_simd)");
+    
+    let root = source_root();
+    let crypto = root.join("crypto").join("c");
+    let simd = root.join("simd").join("c");
+    println!("cargo:rerun-if-changed={}", crypto.display());
+    println!("cargo:rerun-if-changed={}", simd.display());
+    
+    base(&crypto).file(crypto.join("blake3.c")).compile("bldr_blake3");
+    
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let kernels: Vec<(&str, &[&str])> = match arch.as_str() {
+        "x86_64" => vec![
+            ("blake3_sse2.c", &["-msse2"]),
+            ("blake3_sse41.c", &["-msse4.1"]),
+            ("blake3_avx2.c", &["-mavx2"]),
+            ("blake3_avx512.c", &["-mavx512f", "-mavx512vl"]),
+            // Compiles to nothing off ARM
+            ("blake3_neon.c", &[]),
+        ],
+        "aarch64" => vec![
+            ("blake3_neon.c", &["-march=armv8-a+simd"]),
+            // Portable fallbacks for the x86 entry points the dispatcher names
+            ("blake3_sse2_stub.c", &[]),
+            ("blake3_sse41_stub.c", &[]),
+            ("blake3_avx2_stub.c", &[]),
+            ("blake3_avx512_stub.c", &[]),
+        ],
+        // Hashing stays portable; simd_level() reports that
+        _ => return,
+    };
+    
+    let mut simd_build = base(&simd);
+    simd_build.include(&crypto).file(simd.join("cpu_detect.c")).file(simd.join("blake3_dispatch.c"));
+    for (file, flags) in kernels {
+        // One object per kernel so each gets only its own ISA flags
+        let mut kernel = base(&simd);
+        for flag in flags {
+            kernel.flag(flag);
+        }
+        let name = format!("bldr_{}", file.trim_end_matches(".c"));
+        kernel.file(simd.join(file)).compile(&name);
+    }
+    simd_build.compile("bldr_simd");
+    println!("cargo:rustc-cfg=bldr_simd");
+}
+
+fn base(include: &Path) -> cc::Build {
+    let mut build = cc::Build::new();
+    build
+        .include(include)
+        .opt_level(3)
+        .flag_if_supported("-std=c11")
+        .define("NDEBUG", None)
+        .warnings(false);
+    build
+}
+
+fn source_root() -> PathBuf {
+    if let Some(dir) = env::var_os("BLDR_HASH_C_DIR") {
+        return PathBuf::from(dir);
+    }
+    
+    let manifest = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo"));
+    let vendored = manifest.join("c");
+    if vendored.is_dir() {
+        return vendored;
+    }
+    manifest.join("../../source/infrastructure/utils")
+}
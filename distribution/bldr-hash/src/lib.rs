@@ -0,0 +1,310 @@
+//! bldr's content hashing, without the engine.
+//!
+//! Links the same BLAKE3 C sources `libbuilder-core` is built from and
+//! mirrors the engine's file-hashing tiers (`FastHash` in
+//! `infrastructure/utils/files/hash.d`), so tools that compute or check bldr
+//! cache keys get byte-identical hex digests from a build that needs only a
+//! C compiler.
+//!
+//! ```no_run
+//! let digest = bldr_hash::hash_file("src/main.c").unwrap();
+//! assert_eq!(digest.len(), 64);
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::raw::c_char;
+use std::path::Path;
+
+pub const KEY_LEN: usize = 32;
+pub const OUT_LEN: usize = 32;
+
+// Size tiers and sampling layout of FastHash.hashFile; changing any of these
+// changes every cache key, so they must track hash.d exactly
+const SMALL_THRESHOLD: u64 = 1_048_576;
+const MEDIUM_THRESHOLD: u64 = 104_857_600;
+const SAMPLE_HEAD: u64 = 262_144;
+const SAMPLE_TAIL: u64 = 262_144;
+const SAMPLE_COUNT: u64 = 8;
+const SAMPLE_SIZE: u64 = 16_384;
+const LARGE_HEAD_SIZE: u64 = 524_288;
+const LARGE_TAIL_SIZE: u64 = 524_288;
+const LARGE_FILE_THRESHOLD: u64 = 1_048_576;
+const VERY_LARGE_THRESHOLD: u64 = 2_097_152;
+const LARGE_SAMPLE_COUNT: u64 = 16;
+const LARGE_STEP_DIVISOR: u64 = 17;
+const LARGE_SAMPLE_SIZE: u64 = 32_768;
+
+/// Layout of `blake3_hasher` in blake3.h.
+#[repr(C)]
+#[derive(Clone)]
+struct RawHasher {
+    cv: [u32; 8],
+    chunk_counter: u64,
+    buf: [u8; 64],
+    buf_len: u8,
+    blocks_compressed: u8,
+    flags: u8,
+}
+
+extern "C" {
+    fn blake3_hasher_init(hasher: *mut RawHasher);
+    fn blake3_hasher_init_keyed(hasher: *mut RawHasher, key: *const u8);
+    fn blake3_hasher_init_derive_key_raw(hasher: *mut RawHasher, context: *const u8, context_len: usize);
+    fn blake3_hasher_update(hasher: *mut RawHasher, input: *const u8, input_len: usize);
+    fn blake3_hasher_finalize_seek(hasher: *const RawHasher, seek: u64, out: *mut u8, out_len: usize);
+    fn blake3_hasher_reset(hasher: *mut RawHasher);
+    fn blake3_version() -> *const c_char;
+    #[cfg(bldr_simd)]
+    fn cpu_get_simd_level() -> u32;
+    #[cfg(bldr_simd)]
+    fn cpu_simd_level_name(level: u32) -> *const c_char;
+}
+
+/// Incremental BLAKE3 hasher.
+#[derive(Clone)]
+pub struct Hasher(RawHasher);
+
+impl Hasher {
+    fn init(f: impl FnOnce(*mut RawHasher)) -> Hasher {
+        let mut raw = std::mem::MaybeUninit::<RawHasher>::uninit();
+        f(raw.as_mut_ptr());
+        // SAFETY: every blake3_hasher_init* fills the whole struct
+        Hasher(unsafe { raw.assume_init() })
+    }
+    
+    pub fn new() -> Hasher {
+        // SAFETY: the pointer is valid for writes of a RawHasher
+        Hasher::init(|raw| unsafe { blake3_hasher_init(raw) })
+    }
+    
+    /// Keyed hashing (a MAC).
+    pub fn new_keyed(key: &[u8; KEY_LEN]) -> Hasher {
+        // SAFETY: as above; the key is KEY_LEN bytes
+        Hasher::init(|raw| unsafe { blake3_hasher_init_keyed(raw, key.as_ptr()) })
+    }
+    
+    /// Key derivation under a hardcoded, globally unique context string.
+    pub fn new_derive_key(context: &str) -> Hasher {
+        // SAFETY: as above; the context is passed with its length
+        Hasher::init(|raw| unsafe { blake3_hasher_init_derive_key_raw(raw, context.as_ptr(), context.len()) })
+    }
+    
+    pub fn update(&mut self, input: &[u8]) -> &mut Hasher {
+        // SAFETY: the input slice is valid for its length
+        unsafe { blake3_hasher_update(&mut self.0, input.as_ptr(), input.len()) };
+        self
+    }
+    
+    pub fn finalize(&self) -> [u8; OUT_LEN] {
+        let mut out = [0u8; OUT_LEN];
+        self.finalize_xof(0, &mut out);
+        out
+    }
+    
+    /// Extended output starting at byte `seek` of the output stream.
+    pub fn finalize_xof(&self, seek: u64, out: &mut [u8]) {
+        // SAFETY: the output slice is valid for its length
+        unsafe { blake3_hasher_finalize_seek(&self.0, seek, out.as_mut_ptr(), out.len()) }
+    }
+    
+    /// Lowercase hex of the default-length output, as the engine prints it.
+    pub fn finalize_hex(&self) -> String {
+        to_hex(&self.finalize())
+    }
+    
+    /// Start over with the original key or context.
+    pub fn reset(&mut self) {
+        // SAFETY: the hasher was initialized by one of the constructors
+        unsafe { blake3_hasher_reset(&mut self.0) }
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Hasher {
+        Hasher::new()
+    }
+}
+
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// BLAKE3 of `data`.
+pub fn hash(data: &[u8]) -> [u8; OUT_LEN] {
+    Hasher::new().update(data).finalize()
+}
+
+/// Hex BLAKE3 of `data` (`FastHash.hashBytes` / `hashString`).
+pub fn hash_hex(data: &[u8]) -> String {
+    to_hex(&hash(data))
+}
+
+/// Hex BLAKE3 of the strings fed in order (`FastHash.hashStrings`).
+pub fn hash_strings<S: AsRef<str>>(strings: &[S]) -> String {
+    let mut hasher = Hasher::new();
+    for s in strings {
+        hasher.update(s.as_ref().as_bytes());
+    }
+    hasher.finalize_hex()
+}
+
+/// The engine's cache-key hash of a file (`FastHash.hashFile`).
+///
+/// Files up to 1 MiB are hashed whole. Larger ones are sampled: their size,
+/// head, tail and evenly spaced middle slices. That is fine for change
+/// detection but not for integrity checks; use [`hash_file_complete`] there.
+/// A missing file hashes to the empty string, as in the engine.
+pub fn hash_file(path: impl AsRef<Path>) -> io::Result<String> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    
+    let size = path.metadata()?.len();
+    if size <= SMALL_THRESHOLD {
+        return hash_whole(path);
+    }
+    
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new();
+    hasher.update(&size.to_le_bytes());
+    if size <= MEDIUM_THRESHOLD {
+        sample_medium(&mut file, size, &mut hasher)?;
+    } else {
+        sample_large(&mut file, size, &mut hasher)?;
+    }
+    Ok(hasher.finalize_hex())
+}
+
+/// Hex BLAKE3 of a file's full content (`FastHash.hashFileComplete`).
+pub fn hash_file_complete(path: impl AsRef<Path>) -> io::Result<String> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    hash_whole(path)
+}
+
+fn hash_whole(path: &Path) -> io::Result<String> {
+    let mut hasher = Hasher::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize_hex())
+}
+
+/// hashFileSampled: 256 KiB head and tail, eight 16 KiB middle samples.
+fn sample_medium(file: &mut File, size: u64, hasher: &mut Hasher) -> io::Result<()> {
+    read_at(file, 0, SAMPLE_HEAD.min(size), hasher)?;
+    
+    if size > SAMPLE_HEAD + SAMPLE_TAIL {
+        read_at(file, size - SAMPLE_TAIL, SAMPLE_TAIL, hasher)?;
+    }
+    
+    if size > SAMPLE_HEAD + SAMPLE_TAIL + SAMPLE_SIZE * SAMPLE_COUNT {
+        let step = (size - SAMPLE_TAIL - SAMPLE_HEAD) / (SAMPLE_COUNT + 1);
+        for i in 0..SAMPLE_COUNT {
+            let pos = SAMPLE_HEAD + step * (i + 1);
+            read_at(file, pos, SAMPLE_SIZE.min(size - pos), hasher)?;
+        }
+    }
+    Ok(())
+}
+
+/// hashFileLargeSampled: 512 KiB head and tail, sixteen 32 KiB middle samples.
+fn sample_large(file: &mut File, size: u64, hasher: &mut Hasher) -> io::Result<()> {
+    read_at(file, 0, LARGE_HEAD_SIZE.min(size), hasher)?;
+    
+    if size > LARGE_FILE_THRESHOLD {
+        read_at(file, size - LARGE_TAIL_SIZE, LARGE_TAIL_SIZE, hasher)?;
+    }
+    
+    if size > VERY_LARGE_THRESHOLD {
+        let middle_end = size - LARGE_TAIL_SIZE;
+        let step = (middle_end - LARGE_HEAD_SIZE) / LARGE_STEP_DIVISOR;
+        for i in 0..LARGE_SAMPLE_COUNT {
+            let pos = LARGE_HEAD_SIZE + step * (i + 1);
+            read_at(file, pos, (pos + LARGE_SAMPLE_SIZE).min(middle_end) - pos, hasher)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_at(file: &mut File, pos: u64, len: u64, hasher: &mut Hasher) -> io::Result<()> {
+    file.seek(SeekFrom::Start(pos))?;
+    io::copy(&mut file.take(len), hasher)?;
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Version of the linked BLAKE3 sources.
+pub fn blake3_version_string() -> String {
+    // SAFETY: returns a static NUL-terminated string
+    unsafe { std::ffi::CStr::from_ptr(blake3_version()) }.to_string_lossy().into_owned()
+}
+
+/// SIMD level the engine's CPU detection picks on this machine
+/// ("AVX2", "NEON", ...), or "portable" where no kernels are built.
+pub fn simd_level() -> String {
+    #[cfg(bldr_simd)]
+    {
+        // SAFETY: both return plain values / static strings
+        unsafe { std::ffi::CStr::from_ptr(cpu_simd_level_name(cpu_get_simd_level())) }
+            .to_string_lossy()
+            .into_owned()
+    }
+    #[cfg(not(bldr_simd))]
+    {
+        "portable".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    
+    #[test]
+    fn matches_the_reference_vectors() {
+        assert_eq!(hash_hex(b""), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        assert_eq!(hash_hex(b"abc"), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+        assert_eq!(hash_strings(&["a", "bc"]), hash_hex(b"abc"));
+        
+        let mut xof = [0u8; 64];
+        Hasher::new().update(b"abc").finalize_xof(0, &mut xof);
+        assert_eq!(xof[..OUT_LEN], hash(b"abc"));
+        assert!(!simd_level().is_empty());
+    }
+    
+    #[test]
+    fn samples_files_over_a_mebibyte() {
+        let path = std::env::temp_dir().join(format!("bldr-hash-{}", std::process::id()));
+        let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        File::create(&path).unwrap().write_all(&data).unwrap();
+        
+        let size = data.len() as u64;
+        let mut expected = Hasher::new();
+        expected.update(&size.to_le_bytes());
+        expected.update(&data[..262_144]);
+        expected.update(&data[data.len() - 262_144..]);
+        let step = (data.len() - 2 * 262_144) / 9;
+        for i in 0..8 {
+            let pos = 262_144 + step * (i + 1);
+            expected.update(&data[pos..pos + 16_384]);
+        }
+        
+        assert_eq!(hash_file(&path).unwrap(), expected.finalize_hex());
+        assert_eq!(hash_file_complete(&path).unwrap(), hash_hex(&data));
+        std::fs::remove_file(&path).ok();
+        assert_eq!(hash_file(&path).unwrap(), "");
+    }
+}
@@ -0,0 +1,391 @@
+//! Locate `libbuilder-core.a` and tell cargo how to link it.
+//!
+//! By default the static library for the target is fetched from the GitHub
+//! release matching this crate's version and checked against the release's
+//! `SHA256SUMS` before use, so no D toolchain is needed. Set
+//! `BUILDER_CORE_LIB_DIR` to link a local `make lib` build instead. The
+//! download honours the wrapper's `BLDR_MAX_DOWNLOAD_RATE` cap (`500K`, `2M`)
+//! and, like the wrapper, goes over HTTPS with TLS 1.2 or later only.
+//!
+//! Targets without a prebuilt library, or any target with
+//! `BUILDER_CORE_FROM_SOURCE=1`, build from the release's git tag. The tag
+//! must carry a valid GPG signature from the project's signing keys
+//! (`https://github.com/GriffinCanCode.gpg`, or the armored keys in
+//! `BUILDER_CORE_SIGNING_KEYS`) before anything in it is compiled, so the
+//! source path has the same guarantee as the checksummed binaries.
+//!
+//! With the `dylib` feature the shared library is linked instead and copied
+//! next to the build's binaries (`target/<profile>/`), where an `$ORIGIN`
+//! (`@loader_path` on macOS) rpath finds it; replacing that file swaps the
+//! engine without relinking.
+//!
+//! The C header is installed to `OUT_DIR/include` and exported, with the
+//! library directory, as `DEP_BUILDER_CORE_INCLUDE` / `DEP_BUILDER_CORE_ROOT`
+//! for native code elsewhere in the workspace. The `pkg-config` feature also
+//! writes `builder-core.pc` (`DEP_BUILDER_CORE_PKGCONFIG`).
+
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RELEASES: &str = "https://github.com/GriffinCanCode/bldr/releases/download";
+const SOURCE_REPO: &str = "https://github.com/GriffinCanCode/bldr.git";
+const SIGNING_KEYS: &str = "https://github.com/GriffinCanCode.gpg";
+const PROGRESS_EVERY: Duration = Duration::from_secs(5);
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=include");
+    println!("cargo:rerun-if-env-changed=BUILDER_CORE_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=BUILDER_CORE_DOWNLOAD_BASE");
+    println!("cargo:rerun-if-env-changed=BUILDER_CORE_FROM_SOURCE");
+    println!("cargo:rerun-if-env-changed=BUILDER_CORE_SOURCE_REPO");
+    println!("cargo:rerun-if-env-changed=BUILDER_CORE_SIGNING_KEYS");
+    
+    // docs.rs builds without network access and never links
+    if env::var_os("DOCS_RS").is_some() {
+        return;
+    }
+    
+    let out = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let dylib = env::var_os("CARGO_FEATURE_DYLIB").is_some();
+    let dir = match env::var_os("BUILDER_CORE_LIB_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let from_source = env::var("BUILDER_CORE_FROM_SOURCE").is_ok_and(|v| v != "0") || asset_name().is_err();
+            let result = if from_source { build_from_source(&out, dylib) } else { fetch(&out, dylib) };
+            result.unwrap_or_else(|e| {
+                panic!(
+                    "builder-core-sys: {}\n\
+                     Build the library locally with `make {}` and set BUILDER_CORE_LIB_DIR to dist/lib.",
+                    e,
+                    make_target(dylib)
+                )
+            })
+        }
+    };
+    
+    let lib_dir = if dylib { link_shared(&dir) } else { link(&dir) };
+    let include = install_headers(&out);
+    println!("cargo:root={}", lib_dir.display());
+    println!("cargo:include={}", include.display());
+    
+    if env::var_os("CARGO_FEATURE_PKG_CONFIG").is_some() {
+        let pc = write_pkg_config(&out, &lib_dir, &include, dylib);
+        println!("cargo:pkgconfig={}", pc.display());
+    }
+}
+
+/// File name of the library to link for the target.
+fn library_file(dylib: bool) -> &'static str {
+    match (dylib, env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str()) {
+        (false, _) => "libbuilder-core.a",
+        (true, "macos") => "libbuilder-core.dylib",
+        (true, _) => "libbuilder-core.so",
+    }
+}
+
+fn make_target(dylib: bool) -> &'static str {
+    if dylib { "lib-shared" } else { "lib" }
+}
+
+/// Release asset for the target being built, e.g. `libbuilder-core-linux-amd64`.
+fn asset_name() -> Result<String, String> {
+    let os = match env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str() {
+        "linux" => "linux",
+        "macos" => "darwin",
+        other => return Err(format!("no prebuilt libbuilder-core for target OS '{}'", other)),
+    };
+    let arch = match env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default().as_str() {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        other => return Err(format!("no prebuilt libbuilder-core for target arch '{}'", other)),
+    };
+    Ok(format!("libbuilder-core-{}-{}", os, arch))
+}
+
+/// Download and verify the prebuilt library into `out`; reused across builds.
+fn fetch(out: &Path, dylib: bool) -> Result<PathBuf, String> {
+    let dir = out.join("lib");
+    let wanted = library_file(dylib);
+    if dir.join(wanted).is_file() {
+        return Ok(dir);
+    }
+    
+    let version = env::var("CARGO_PKG_VERSION").expect("CARGO_PKG_VERSION is set by cargo");
+    let base = env::var("BUILDER_CORE_DOWNLOAD_BASE").unwrap_or_else(|_| format!("{}/v{}", RELEASES, version));
+    let base = base.trim_end_matches('/');
+    let asset = format!("{}.tar.gz", asset_name()?);
+    
+    let archive = out.join(&asset);
+    let sums = out.join("SHA256SUMS");
+    download(&format!("{}/{}", base, asset), &archive)?;
+    download(&format!("{}/SHA256SUMS", base), &sums)?;
+    
+    let expected = fs::read_to_string(&sums)
+        .map_err(|e| format!("cannot read {}: {}", sums.display(), e))?
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*') == asset)
+        .map(|(digest, _)| digest.to_ascii_lowercase())
+        .ok_or_else(|| format!("SHA256SUMS has no entry for {}", asset))?;
+    let bytes = fs::read(&archive).map_err(|e| format!("cannot read {}: {}", archive.display(), e))?;
+    let actual: String = Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+    if actual != expected {
+        fs::remove_file(&archive).ok();
+        return Err(format!("checksum mismatch for {}: expected {}, got {}", asset, expected, actual));
+    }
+    
+    fs::create_dir_all(&dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&dir)
+        .status()
+        .map_err(|e| format!("failed to run tar: {}", e))?;
+    fs::remove_file(&archive).ok();
+    if !status.success() || !dir.join(wanted).is_file() {
+        return Err(format!("{} does not contain {}", asset, wanted));
+    }
+    Ok(dir)
+}
+
+/// Check out the release tag, verify its signature and build the library.
+fn build_from_source(out: &Path, dylib: bool) -> Result<PathBuf, String> {
+    let src = out.join("bldr-src");
+    let dir = src.join("dist").join("lib");
+    let wanted = library_file(dylib);
+    if dir.join(wanted).is_file() {
+        return Ok(dir);
+    }
+    
+    let tag = format!("v{}", env::var("CARGO_PKG_VERSION").expect("CARGO_PKG_VERSION is set by cargo"));
+    let repo = env::var("BUILDER_CORE_SOURCE_REPO").unwrap_or_else(|_| SOURCE_REPO.to_string());
+    if src.exists() {
+        fs::remove_dir_all(&src).map_err(|e| format!("cannot remove {}: {}", src.display(), e))?;
+    }
+    run(Command::new("git").args(["clone", "--quiet", "--depth", "1", "--branch", &tag, &repo]).arg(&src))?;
+    
+    verify_tag(out, &src, &tag)?;
+    
+    run(Command::new("make").arg("-C").arg(&src).arg(make_target(dylib)))?;
+    if !dir.join(wanted).is_file() {
+        return Err(format!("building {} did not produce {}", tag, wanted));
+    }
+    Ok(dir)
+}
+
+/// Require a good signature on `tag` from a key in the project's keyring.
+///
+/// The keys go into a keyring private to this build, so only they count as
+/// a good signature, whatever the user's own keyring trusts.
+fn verify_tag(out: &Path, src: &Path, tag: &str) -> Result<(), String> {
+    let home = out.join("gnupg");
+    fs::create_dir_all(&home).map_err(|e| format!("cannot create {}: {}", home.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&home, fs::Permissions::from_mode(0o700)).ok();
+    }
+    
+    let keys = match env::var_os("BUILDER_CORE_SIGNING_KEYS") {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let keys = out.join("signing-keys.asc");
+            download(SIGNING_KEYS, &keys)?;
+            keys
+        }
+    };
+    run(Command::new("gpg").env("GNUPGHOME", &home).args(["--batch", "--quiet", "--import"]).arg(&keys))
+        .map_err(|e| format!("cannot import signing keys from {}: {}", keys.display(), e))?;
+    
+    let output = Command::new("git")
+        .env("GNUPGHOME", &home)
+        .arg("-C")
+        .arg(src)
+        .args(["verify-tag", "--raw", tag])
+        .output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    // --raw prints gpg's status lines; exit status alone also fails on an
+    // unsigned lightweight tag, which is what we want
+    let status = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !status.lines().any(|l| l.starts_with("[GNUPG:] VALIDSIG")) {
+        return Err(format!(
+            "tag {} is not signed by a bldr release key; refusing to build it\n{}",
+            tag,
+            status.trim()
+        ));
+    }
+    Ok(())
+}
+
+fn run(cmd: &mut Command) -> Result<(), String> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let status = cmd.status().map_err(|e| format!("failed to run {}: {}", program, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed ({})", program, status))
+    }
+}
+
+fn download(url: &str, dest: &Path) -> Result<(), String> {
+    let mut cmd = Command::new("curl");
+    // HTTPS with TLS 1.2 or later only; `file://` serves vendored mirrors
+    cmd.args(["-fsSL", "--retry", "3", "--tlsv1.2", "--proto", "=https,file", "--proto-redir", "=https"]);
+    if let Some(rate) = env::var("BLDR_MAX_DOWNLOAD_RATE").ok().filter(|rate| !rate.trim().is_empty()) {
+        cmd.args(["--limit-rate", rate.trim()]);
+    }
+    let mut child = cmd
+        .arg("-o")
+        .arg(dest)
+        .arg(url)
+        .spawn()
+        .map_err(|e| format!("failed to run curl: {}", e))?;
+    
+    // Build script output is never a terminal; log a line every few seconds
+    // (shown by `cargo build -vv`) so a slow first build is visibly alive
+    let started = Instant::now();
+    let mut logged = started;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| format!("failed to run curl: {}", e))? {
+            break status;
+        }
+        if logged.elapsed() >= PROGRESS_EVERY {
+            logged = Instant::now();
+            let size = fs::metadata(dest).map(|meta| meta.len()).unwrap_or(0);
+            eprintln!(
+                "downloading {}: {:.1} MB after {}s",
+                url,
+                size as f64 / (1024.0 * 1024.0),
+                started.elapsed().as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(200));
+    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("download of {} failed", url))
+    }
+}
+
+/// Link the engine plus the D runtime and C dependencies bundled beside it.
+fn link(dir: &Path) -> PathBuf {
+    println!("cargo:rustc-link-search=native={}", dir.display());
+    println!("cargo:rustc-link-lib=static=builder-core");
+    
+    // The release archive bundles the runtime the library was compiled with
+    for bundled in ["phobos2-ldc", "druntime-ldc", "tree-sitter"] {
+        if dir.join(format!("lib{}.a", bundled)).is_file() {
+            println!("cargo:rustc-link-lib=static={}", bundled);
+        }
+    }
+    if !dir.join("libtree-sitter.a").is_file() {
+        println!("cargo:rustc-link-lib=dylib=tree-sitter");
+    }
+    
+    match env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str() {
+        "linux" => {
+            for lib in ["pthread", "m", "dl"] {
+                println!("cargo:rustc-link-lib=dylib={}", lib);
+            }
+        }
+        "macos" => println!("cargo:rustc-link-search=native=/opt/homebrew/lib"),
+        _ => {}
+    }
+    dir.to_path_buf()
+}
+
+/// Link the shared library and install it beside the build's binaries.
+fn link_shared(dir: &Path) -> PathBuf {
+    let file = library_file(true);
+    let origin = if env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("macos") {
+        "@loader_path"
+    } else {
+        "$ORIGIN"
+    };
+    
+    // OUT_DIR is target/<profile>/build/<crate>-<hash>/out
+    let out = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let profile_dir = out.ancestors().nth(3).expect("OUT_DIR is inside the target directory");
+    let installed = profile_dir.join(file);
+    fs::copy(dir.join(file), &installed).unwrap_or_else(|e| {
+        panic!("builder-core-sys: cannot install {}: {}", installed.display(), e)
+    });
+    // Unit tests and examples run from deps/ and examples/
+    for sub in ["deps", "examples"] {
+        fs::create_dir_all(profile_dir.join(sub)).ok();
+        fs::copy(dir.join(file), profile_dir.join(sub).join(file)).ok();
+    }
+    
+    println!("cargo:rustc-link-search=native={}", profile_dir.display());
+    println!("cargo:rustc-link-lib=dylib=builder-core");
+    // Link args only apply to this package's own targets; dependents read
+    // DEP_BUILDER_CORE_RPATH and emit the same flag from their build.rs
+    println!("cargo:rustc-link-arg=-Wl,-rpath,{}", origin);
+    println!("cargo:rpath={}", origin);
+    profile_dir.to_path_buf()
+}
+
+/// Copy the public headers to `OUT_DIR/include`.
+fn install_headers(out: &Path) -> PathBuf {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("include");
+    let dest = out.join("include");
+    fs::create_dir_all(&dest).unwrap_or_else(|e| panic!("builder-core-sys: cannot create {}: {}", dest.display(), e));
+    let entries = fs::read_dir(&src).unwrap_or_else(|e| panic!("builder-core-sys: cannot read {}: {}", src.display(), e));
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "h") {
+            fs::copy(&path, dest.join(entry.file_name()))
+                .unwrap_or_else(|e| panic!("builder-core-sys: cannot install {}: {}", path.display(), e));
+        }
+    }
+    dest
+}
+
+/// Write `OUT_DIR/pkgconfig/builder-core.pc` describing the linked library.
+fn write_pkg_config(out: &Path, lib_dir: &Path, include: &Path, dylib: bool) -> PathBuf {
+    let private = match (dylib, env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str()) {
+        (true, _) => String::new(),
+        (false, os) => {
+            let mut libs: Vec<&str> = ["phobos2-ldc", "druntime-ldc"]
+                .into_iter()
+                .filter(|lib| lib_dir.join(format!("lib{}.a", lib)).is_file())
+                .collect();
+            libs.push("tree-sitter");
+            if os == "linux" {
+                libs.extend(["pthread", "m", "dl"]);
+            }
+            let flags: Vec<String> = libs.iter().map(|lib| format!("-l{}", lib)).collect();
+            format!("Libs.private: {}\n", flags.join(" "))
+        }
+    };
+    let pc = format!(
+        "libdir={}\n\
+         includedir={}\n\
+         \n\
+         Name: builder-core\n\
+         Description: The bldr build engine\n\
+         Version: {}\n\
+         Libs: -L${{libdir}} -lbuilder-core\n\
+         {}\
+         Cflags: -I${{includedir}}\n",
+        lib_dir.display(),
+        include.display(),
+        env!("CARGO_PKG_VERSION"),
+        private
+    );
+    
+    let dir = out.join("pkgconfig");
+    let path = dir.join("builder-core.pc");
+    fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&path, pc))
+        .unwrap_or_else(|e| panic!("builder-core-sys: cannot write {}: {}", path.display(), e));
+    dir
+}
@@ -0,0 +1,144 @@
+/* libbuilder-core C API - Header */
+/* Link with -lbuilder-core; builder-core.pc lists the runtime libraries the static archive needs */
+
+#ifndef BUILDER_CORE_H
+#define BUILDER_CORE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Event kinds (builder_event.kind); values match the engine's EventType */
+typedef enum {
+    BUILDER_EVENT_BUILD_STARTED = 0,
+    BUILDER_EVENT_BUILD_COMPLETED = 1,
+    BUILDER_EVENT_BUILD_FAILED = 2,
+    BUILDER_EVENT_TARGET_STARTED = 3,
+    BUILDER_EVENT_TARGET_COMPLETED = 4,
+    BUILDER_EVENT_TARGET_FAILED = 5,
+    BUILDER_EVENT_TARGET_CACHED = 6,
+    BUILDER_EVENT_TARGET_PROGRESS = 7,
+    BUILDER_EVENT_MESSAGE = 8,
+    BUILDER_EVENT_WARNING = 9,
+    BUILDER_EVENT_ERROR = 10,
+    BUILDER_EVENT_STATISTICS = 11,
+    BUILDER_EVENT_BUILD_PROGRESS = 12
+} builder_event_kind;
+
+/*
+ * One build event. Strings are only valid for the duration of the callback.
+ * BUILDER_EVENT_BUILD_PROGRESS carries completed/total targets, a fraction
+ * weighted by each target's duration in previous builds, and an ETA.
+ */
+typedef struct builder_event {
+    int32_t kind;
+    const char* target;   /* NULL for build-level events */
+    const char* message;  /* failure reason, message text or phase */
+    uint64_t completed;
+    uint64_t total;
+    double fraction;      /* 0.0 to 1.0 */
+    int64_t elapsed_ms;
+    int64_t eta_ms;       /* -1 when unknown */
+} builder_event;
+
+typedef void (*builder_event_fn)(const builder_event* event, void* user_data);
+
+/*
+ * Register a callback for build events (NULL unregisters). Set it before
+ * c_run_builder; calls come from build threads but never overlap.
+ */
+void c_builder_set_event_callback(builder_event_fn callback, void* user_data);
+
+/*
+ * Run the engine with argv-style arguments, exactly like the bldr
+ * executable (argv[0] is the program name). The D runtime is started and
+ * stopped around the call. Returns the process exit code.
+ */
+int c_run_builder(int argc, char** argv);
+
+/* Log levels (builder_log_record.level); values match the engine's LogLevel */
+typedef enum {
+    BUILDER_LOG_TRACE = 0,
+    BUILDER_LOG_DEBUG = 1,
+    BUILDER_LOG_INFO = 2,
+    BUILDER_LOG_WARNING = 3,
+    BUILDER_LOG_ERROR = 4,
+    BUILDER_LOG_CRITICAL = 5
+} builder_log_level;
+
+typedef struct builder_log_field {
+    const char* key;
+    const char* value;
+} builder_log_field;
+
+/* One log record. Strings are only valid for the duration of the callback. */
+typedef struct builder_log_record {
+    int32_t level;
+    const char* module;   /* D module that logged, e.g. "engine.caching.targets.cache" */
+    const char* message;
+    const char* target;   /* build target from the thread's log context, or NULL */
+    const builder_log_field* fields;
+    size_t field_count;
+} builder_log_record;
+
+typedef void (*builder_log_fn)(const builder_log_record* record, void* user_data);
+
+/*
+ * Send the engine's log records to a callback instead of the console
+ * (NULL restores console output). May be called from several threads at once.
+ */
+void c_builder_set_log_callback(builder_log_fn callback, void* user_data);
+
+/* One output file produced by the last build */
+typedef struct builder_artifact {
+    const char* name;     /* logical name (the output's file name) */
+    const char* path;     /* absolute path on disk */
+    const char* hash;     /* BLAKE3 of the full content, hex */
+    uint64_t size;
+    const char* target;   /* producing target */
+} builder_artifact;
+
+/*
+ * Artifacts of the last c_run_builder call, built or restored from cache.
+ * Stores the count in *count; the array is valid until the next build.
+ */
+const builder_artifact* c_builder_artifacts(size_t* count);
+
+/*
+ * Why targets of the last c_run_builder call rebuilt, as JSON: per-target
+ * reasons (not_cached, input_changed, dependency_changed, flags_changed,
+ * forced) with the build's cache hit rate, and totals accumulated across
+ * builds in the cache directory. NULL if no build ran; owned by the
+ * library and valid until the next build.
+ */
+const char* c_builder_rebuild_stats(void);
+
+/*
+ * Detected toolchains as JSON: every toolchain with its tools' paths and
+ * versions, plus the one the engine would select for `language` ("c",
+ * "cpp", "d"; NULL for any) on `platform` (a target triple; NULL for the
+ * host). Returns NULL and sets *error (if non-NULL) on failure. Free both
+ * strings with c_builder_free_string.
+ */
+char* c_builder_probe_toolchains(const char* language, const char* platform, char** error);
+
+/*
+ * Statistics for the cache in `cache_dir` (".builder-cache" when NULL) as
+ * JSON: entries, sizes and age distributions per local tier, hit, miss and
+ * eviction totals across builds, and the remote server's report when a
+ * remote cache is configured. Returns NULL and sets *error (if non-NULL) on
+ * failure. Free both strings with c_builder_free_string.
+ */
+char* c_builder_cache_stats(const char* cache_dir, char** error);
+
+/* Free a string returned by the c_builder_* API */
+void c_builder_free_string(char* str);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BUILDER_CORE_H */
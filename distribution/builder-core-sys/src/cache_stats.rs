@@ -0,0 +1,183 @@
+//! Cache statistics: what a cache directory holds and how builds used it.
+
+use crate::{c_builder_cache_stats, c_builder_free_string, ENGINE};
+use serde::Deserialize;
+use std::ffi::{c_char, CStr, CString};
+use std::path::{Path, PathBuf};
+
+/// Entry counts by age, in the engine's fixed buckets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct AgeHistogram {
+    #[serde(rename = "<1h")]
+    pub hour: u64,
+    #[serde(rename = "<1d")]
+    pub day: u64,
+    #[serde(rename = "<7d")]
+    pub week: u64,
+    #[serde(rename = "<30d")]
+    pub month: u64,
+    #[serde(rename = ">=30d")]
+    pub older: u64,
+}
+
+impl AgeHistogram {
+    /// `(label, count)` per bucket, youngest first.
+    pub fn buckets(&self) -> [(&'static str, u64); 5] {
+        [
+            ("<1h", self.hour),
+            ("<1d", self.day),
+            ("<7d", self.week),
+            ("<30d", self.month),
+            (">=30d", self.older),
+        ]
+    }
+}
+
+/// One local cache tier.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TierStats {
+    pub entries: u64,
+    /// Estimated size on disk.
+    pub bytes: u64,
+    /// Entries by time since they were written (target tier only).
+    pub ages: Option<AgeHistogram>,
+    /// Entries by time since a build last used them (target tier only).
+    pub idle: Option<AgeHistogram>,
+}
+
+/// The content-addressed blob store.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobStats {
+    pub count: u64,
+    pub bytes: u64,
+    pub deduplication_ratio: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct LocalStats {
+    pub targets: TierStats,
+    pub actions: TierStats,
+    pub blobs: BlobStats,
+}
+
+/// Totals over every build recorded in the cache directory.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheUsage {
+    pub builds: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Percentage, 0 to 100.
+    pub hit_rate: f64,
+    pub remote_hits: u64,
+    pub remote_misses: u64,
+    pub remote_hit_rate: f64,
+    pub evictions: u64,
+    pub bytes_evicted: u64,
+    /// First recorded build (ISO 8601).
+    pub since: Option<String>,
+}
+
+/// The remote cache server's own statistics, covering all of its clients.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RemoteStats {
+    pub url: String,
+    /// Why the server could not report; the other fields are then empty.
+    pub error: Option<String>,
+    pub entries: u64,
+    pub bytes: u64,
+    /// Storage limit before the server evicts.
+    pub capacity: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    pub evictions: u64,
+    pub bytes_evicted: u64,
+    /// Artifacts by time since they were stored.
+    pub ages: Option<AgeHistogram>,
+}
+
+/// Result of [`cache_stats`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub cache_dir: PathBuf,
+    pub local: LocalStats,
+    pub usage: CacheUsage,
+    /// `None` without a remote cache (`BUILDER_REMOTE_CACHE_URL`).
+    pub remote: Option<RemoteStats>,
+}
+
+/// Statistics for the cache in `cache_dir` (`None` for `.builder-cache` in
+/// the current directory).
+pub fn cache_stats(cache_dir: Option<&Path>) -> Result<CacheStats, String> {
+    let cache_dir = cache_dir
+        .map(|dir| CString::new(dir.to_string_lossy().into_owned()))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    
+    let _guard = ENGINE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut error: *mut c_char = std::ptr::null_mut();
+    // SAFETY: the argument is null or a valid C string that outlives the
+    // call; returned strings are copied before being freed
+    let json = unsafe {
+        let dir = cache_dir.as_ref().map_or(std::ptr::null(), |s| s.as_ptr());
+        let raw = c_builder_cache_stats(dir, &mut error);
+        if raw.is_null() {
+            let message = if error.is_null() {
+                "cache statistics unavailable".to_string()
+            } else {
+                CStr::from_ptr(error).to_string_lossy().into_owned()
+            };
+            c_builder_free_string(error);
+            return Err(message);
+        }
+        let json = CStr::from_ptr(raw).to_string_lossy().into_owned();
+        c_builder_free_string(raw);
+        json
+    };
+    parse_stats(&json)
+}
+
+fn parse_stats(json: &str) -> Result<CacheStats, String> {
+    serde_json::from_str(json).map_err(|e| format!("invalid cache statistics: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_stats_with_unreachable_remote() {
+        let json = r#"{
+            "cacheDir": ".builder-cache",
+            "local": {
+                "targets": {
+                    "entries": 12, "bytes": 6144,
+                    "ages": {"<1h": 2, "<1d": 4, "<7d": 5, "<30d": 1, ">=30d": 0},
+                    "idle": {"<1h": 10, "<1d": 2, "<7d": 0, "<30d": 0, ">=30d": 0}
+                },
+                "actions": {"entries": 40, "bytes": 20480},
+                "blobs": {"count": 30, "bytes": 1048576, "deduplicationRatio": 0.25}
+            },
+            "usage": {
+                "builds": 3, "hits": 30, "misses": 6, "hitRate": 83.33,
+                "remoteHits": 0, "remoteMisses": 6, "remoteHitRate": 0,
+                "evictions": 2, "bytesEvicted": 1024, "since": "2026-10-01T09:00:00Z"
+            },
+            "remote": {"url": "http://cache:8080", "error": "Connection refused"}
+        }"#;
+        let stats = parse_stats(json).unwrap();
+        assert_eq!(stats.local.targets.ages.unwrap().buckets()[2], ("<7d", 5));
+        assert!(stats.local.actions.ages.is_none());
+        assert_eq!(stats.usage.evictions, 2);
+        let remote = stats.remote.unwrap();
+        assert_eq!(remote.error.as_deref(), Some("Connection refused"));
+        assert_eq!(remote.entries, 0);
+        
+        let local_only = json.replace(r#"{"url": "http://cache:8080", "error": "Connection refused"}"#, "null");
+        assert!(parse_stats(&local_only).unwrap().remote.is_none());
+    }
+}
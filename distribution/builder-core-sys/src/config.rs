@@ -0,0 +1,137 @@
+//! Typed build options: target, parallelism and scheduling.
+//!
+//! A [`BuildConfig`] is turned into the `build` arguments [`run`] takes, so
+//! it maps one to one onto the engine's flags: `jobs` is `--jobs`,
+//! `priority` is `--priority` and each `cpu_weight` a `--cpu-weight`.
+//! Batch builds use half the cores unless `jobs` says otherwise and run their
+//! worker threads at lowered OS priority, which suits builds an IDE or
+//! service starts in the background next to interactive ones.
+
+use crate::result::{run, BuildResult};
+
+/// Scheduling class of a build.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// All cores at normal priority.
+    #[default]
+    Interactive,
+    /// Half the cores by default, at lowered priority.
+    Batch,
+}
+
+impl Priority {
+    fn as_str(self) -> &'static str {
+        match self {
+            Priority::Interactive => "interactive",
+            Priority::Batch => "batch",
+        }
+    }
+}
+
+/// Options for one build, run with [`BuildConfig::run`].
+///
+/// ```no_run
+/// use builder_core_sys::{BuildConfig, Priority};
+///
+/// let result = BuildConfig::new()
+///     .target("//app:server")
+///     .jobs(4)
+///     .priority(Priority::Batch)
+///     .cpu_weight("//app:link", 4)
+///     .run();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildConfig {
+    target: Option<String>,
+    jobs: usize,
+    priority: Priority,
+    cpu_weights: Vec<(String, usize)>,
+    extra: Vec<String>,
+}
+
+impl BuildConfig {
+    /// Build everything, with the engine's defaults.
+    pub fn new() -> BuildConfig {
+        BuildConfig::default()
+    }
+    
+    /// Build one target (`//app:server`, `//app:*`) instead of all of them.
+    pub fn target(mut self, target: impl Into<String>) -> BuildConfig {
+        self.target = Some(target.into());
+        self
+    }
+    
+    /// Run at most `jobs` actions (CPU slots) at once; 0 picks from the core
+    /// count and priority.
+    pub fn jobs(mut self, jobs: usize) -> BuildConfig {
+        self.jobs = jobs;
+        self
+    }
+    
+    pub fn priority(mut self, priority: Priority) -> BuildConfig {
+        self.priority = priority;
+        self
+    }
+    
+    /// Count actions of targets matching `pattern` (an id, `//pkg:*` or
+    /// `//pkg/...`) as `slots` jobs, for actions that run threads of their
+    /// own. The largest matching weight applies.
+    pub fn cpu_weight(mut self, pattern: impl Into<String>, slots: usize) -> BuildConfig {
+        self.cpu_weights.push((pattern.into(), slots.max(1)));
+        self
+    }
+    
+    /// Pass any other flag through (`--force`, `--mode=quiet`).
+    pub fn arg(mut self, arg: impl Into<String>) -> BuildConfig {
+        self.extra.push(arg.into());
+        self
+    }
+    
+    /// The engine arguments for this configuration.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
+        args.extend(self.target.iter().cloned());
+        if self.jobs > 0 {
+            args.push(format!("--jobs={}", self.jobs));
+        }
+        if self.priority != Priority::Interactive {
+            args.push(format!("--priority={}", self.priority.as_str()));
+        }
+        for (pattern, slots) in &self.cpu_weights {
+            args.push(format!("--cpu-weight={}={}", pattern, slots));
+        }
+        args.extend(self.extra.iter().cloned());
+        args
+    }
+    
+    pub fn run(&self) -> BuildResult {
+        run(self.args())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn maps_options_onto_engine_flags() {
+        assert_eq!(BuildConfig::new().args(), ["build"]);
+        
+        let config = BuildConfig::new()
+            .target("//app:server")
+            .jobs(4)
+            .priority(Priority::Batch)
+            .cpu_weight("//app:link", 4)
+            .cpu_weight("//tools/...", 0)
+            .arg("--force");
+        assert_eq!(config.args(), [
+            "build",
+            "//app:server",
+            "--jobs=4",
+            "--priority=batch",
+            "--cpu-weight=//app:link=4",
+            "--cpu-weight=//tools/...=1",
+            "--force",
+        ]);
+    }
+}
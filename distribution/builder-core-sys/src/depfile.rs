@@ -0,0 +1,226 @@
+//! Makefile-style dependency files (`.d`) as written by `gcc -MD`,
+//! `clang -MD`, `ldc2 --makedeps` and friends.
+//!
+//! The syntax is the subset of Make those compilers emit, read the way GCC
+//! writes it: `\ ` and `\#` escape a space or `#` (with `2n` preceding
+//! backslashes standing for `n` literal ones), `$$` is a literal `$`, a
+//! trailing backslash continues the line, and any other backslash is part of
+//! the path so Windows paths survive. A `:` only separates targets from
+//! prerequisites when whitespace or the end of the line follows it, which
+//! keeps drive letters (`C:\src\a.h`) intact.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One `targets: prerequisites` rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepfileRule {
+    pub targets: Vec<PathBuf>,
+    pub prerequisites: Vec<PathBuf>,
+}
+
+/// A parsed depfile. `-MP` adds an empty rule per header; those are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Depfile {
+    pub rules: Vec<DepfileRule>,
+}
+
+impl Depfile {
+    pub fn parse(text: &str) -> Result<Depfile, String> {
+        let mut rules = Vec::new();
+        let mut words = Vec::new();
+        let mut targets = None;
+        
+        for (line, token) in tokenize(text) {
+            match token {
+                Token::Word(word) => words.push(PathBuf::from(word)),
+                Token::Colon if targets.is_some() => {
+                    return Err(format!("line {}: more than one ':' in a rule", line));
+                }
+                Token::Colon if words.is_empty() => {
+                    return Err(format!("line {}: rule has no targets", line));
+                }
+                Token::Colon => targets = Some(std::mem::take(&mut words)),
+                Token::Newline => match targets.take() {
+                    Some(targets) => rules.push(DepfileRule {
+                        targets,
+                        prerequisites: std::mem::take(&mut words),
+                    }),
+                    None if words.is_empty() => {}
+                    None => return Err(format!("line {}: expected ':' after {}", line, words[0].display())),
+                },
+            }
+        }
+        
+        Ok(Depfile { rules })
+    }
+    
+    pub fn read(path: impl AsRef<Path>) -> Result<Depfile, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Depfile::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+    
+    /// Every target, in order, without duplicates.
+    pub fn targets(&self) -> Vec<&Path> {
+        unique(self.rules.iter().flat_map(|r| &r.targets))
+    }
+    
+    /// Every prerequisite (the discovered inputs), in order, without duplicates.
+    pub fn prerequisites(&self) -> Vec<&Path> {
+        unique(self.rules.iter().flat_map(|r| &r.prerequisites))
+    }
+    
+    /// Prerequisites of the rules that name `target`.
+    pub fn prerequisites_of(&self, target: impl AsRef<Path>) -> Vec<&Path> {
+        let target = target.as_ref();
+        unique(
+            self.rules
+                .iter()
+                .filter(|r| r.targets.iter().any(|t| t == target))
+                .flat_map(|r| &r.prerequisites),
+        )
+    }
+}
+
+fn unique<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> Vec<&'a Path> {
+    let mut seen = Vec::new();
+    for path in paths {
+        if !seen.contains(&path.as_path()) {
+            seen.push(path.as_path());
+        }
+    }
+    seen
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Colon,
+    Newline,
+}
+
+/// Tokens with the line they end on; always ends with a newline.
+fn tokenize(text: &str) -> Vec<(usize, Token)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut line = 1;
+    let mut i = 0;
+    
+    let flush = |word: &mut String, tokens: &mut Vec<(usize, Token)>, line: usize| {
+        if !word.is_empty() {
+            tokens.push((line, Token::Word(std::mem::take(word))));
+        }
+    };
+    let line_end = |i: usize| match chars.get(i) {
+        Some('\n') => Some(1),
+        Some('\r') if chars.get(i + 1) == Some(&'\n') => Some(2),
+        None => Some(0),
+        _ => None,
+    };
+    
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(len) = line_end(i) {
+            flush(&mut word, &mut tokens, line);
+            tokens.push((line, Token::Newline));
+            line += 1;
+            i += len;
+            continue;
+        }
+        
+        match c {
+            ' ' | '\t' => flush(&mut word, &mut tokens, line),
+            '#' => {
+                flush(&mut word, &mut tokens, line);
+                while line_end(i).is_none() {
+                    i += 1;
+                }
+                continue;
+            }
+            '$' if chars.get(i + 1) == Some(&'$') => {
+                word.push('$');
+                i += 1;
+            }
+            ':' if matches!(chars.get(i + 1), Some(' ' | '\t')) || line_end(i + 1).is_some() => {
+                flush(&mut word, &mut tokens, line);
+                tokens.push((line, Token::Colon));
+            }
+            '\\' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '\\').count();
+                let next = i + run;
+                match chars.get(next) {
+                    Some(' ' | '\t' | '#') => {
+                        word.extend(std::iter::repeat_n('\\', run / 2));
+                        if run % 2 == 1 {
+                            // Escaped: part of the path
+                            word.push(chars[next]);
+                            i = next + 1;
+                        } else {
+                            i = next;
+                        }
+                        continue;
+                    }
+                    _ if line_end(next).is_some_and(|len| len > 0) => {
+                        // Continuation: the last backslash joins the lines
+                        word.extend(std::iter::repeat_n('\\', run - 1));
+                        flush(&mut word, &mut tokens, line);
+                        line += 1;
+                        i = next + line_end(next).unwrap_or(0);
+                        continue;
+                    }
+                    _ => {
+                        word.extend(std::iter::repeat_n('\\', run));
+                        i = next;
+                        continue;
+                    }
+                }
+            }
+            _ => word.push(c),
+        }
+        i += 1;
+    }
+    
+    flush(&mut word, &mut tokens, line);
+    tokens.push((line, Token::Newline));
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_gcc_output_with_escapes() {
+        let text = "out/main.o: src/main.c include/my\\ header.h \\\n  include/hash\\#tag.h lib/$$ORIGIN.h \\\r\n  C:\\sdk\\inc\\win.h\n\n# -MP\ninclude/my\\ header.h:\n";
+        let depfile = Depfile::parse(text).unwrap();
+        
+        assert_eq!(depfile.rules.len(), 2);
+        assert_eq!(depfile.targets(), [Path::new("out/main.o"), Path::new("include/my header.h")]);
+        assert_eq!(
+            depfile.prerequisites_of("out/main.o"),
+            [
+                Path::new("src/main.c"),
+                Path::new("include/my header.h"),
+                Path::new("include/hash#tag.h"),
+                Path::new("lib/$ORIGIN.h"),
+                Path::new("C:\\sdk\\inc\\win.h"),
+            ]
+        );
+        assert!(depfile.rules[1].prerequisites.is_empty());
+    }
+    
+    #[test]
+    fn handles_backslash_runs_and_errors() {
+        // Two backslashes before a space: one literal backslash, then a separator
+        let depfile = Depfile::parse("a.o b.o: dir\\\\ c.h # trailing comment").unwrap();
+        assert_eq!(depfile.rules[0].targets.len(), 2);
+        assert_eq!(depfile.prerequisites(), [Path::new("dir\\"), Path::new("c.h")]);
+        
+        assert!(Depfile::parse("a.o b.c\n").unwrap_err().starts_with("line 1"));
+        assert!(Depfile::parse("a.o: b: c\n").is_err());
+        assert!(Depfile::parse(": b.c\n").is_err());
+        assert_eq!(Depfile::parse("").unwrap(), Depfile::default());
+    }
+}
@@ -0,0 +1,220 @@
+//! Raw bindings to `libbuilder-core`, the bldr build engine built as a static
+//! library (`dub build --config=library`).
+//!
+//! The build script links a prebuilt library from the GitHub release that
+//! matches this crate's version, verified against the release checksums, so
+//! embedding the engine needs no D toolchain. Point `BUILDER_CORE_LIB_DIR`
+//! at a local `make lib` build to link that instead. Targets without a
+//! prebuilt library (or `BUILDER_CORE_FROM_SOURCE=1`) build the release's
+//! git tag, after checking that it carries a GPG signature from the
+//! project's signing keys.
+//!
+//! The `dylib` feature links `libbuilder-core.{so,dylib}` instead and installs
+//! it into `target/<profile>/`. Cargo does not pass link arguments on to
+//! dependents, so binaries add the rpath themselves from their build script:
+//!
+//! ```text
+//! // build.rs
+//! if let Ok(rpath) = std::env::var("DEP_BUILDER_CORE_RPATH") {
+//!     println!("cargo:rustc-link-arg=-Wl,-rpath,{}", rpath);
+//! }
+//! ```
+//!
+//! [`run`] wraps [`c_run_builder`] and returns a [`BuildResult`] listing the
+//! artifacts the build produced and why each target rebuilt;
+//! [`BuildConfig`] builds those arguments from typed options, including job
+//! limits, per-target CPU weights and the batch priority class.
+//! [`probe_toolchains`] reports the C, C++ and D compilers the engine
+//! detected and which it would select for a target. [`cache_stats`]
+//! describes a cache directory: what each tier holds, how old it is, and the
+//! hits, misses and evictions builds recorded there. The `tracing` feature
+//! adds [`forward_logs_to_tracing`], which routes the engine's own logging
+//! into the `tracing` ecosystem. [`Depfile`] parses the Makefile-style
+//! dependency files compilers write with `-MD`.
+
+mod cache_stats;
+mod config;
+mod depfile;
+mod rebuilds;
+mod result;
+mod toolchain;
+#[cfg(feature = "tracing")]
+mod tracing_sink;
+
+pub use cache_stats::{cache_stats, AgeHistogram, BlobStats, CacheStats, CacheUsage, LocalStats, RemoteStats, TierStats};
+pub use config::{BuildConfig, Priority};
+pub use depfile::{Depfile, DepfileRule};
+pub use rebuilds::{BuildRebuilds, RebuildReason, RebuildStats, RebuildTotals, TargetRebuild};
+pub use result::{run, Artifact, BuildResult};
+pub use toolchain::{probe_toolchains, Tool, Toolchain, ToolchainReport};
+#[cfg(feature = "tracing")]
+pub use tracing_sink::forward_logs_to_tracing;
+
+use std::ffi::{c_char, c_int, c_void};
+use std::sync::Mutex;
+
+/// The engine keeps per-process state; calls into it must not overlap.
+pub(crate) static ENGINE: Mutex<()> = Mutex::new(());
+
+/// Values of [`builder_event::kind`]; they match the engine's `EventType`.
+pub const BUILDER_EVENT_BUILD_STARTED: i32 = 0;
+pub const BUILDER_EVENT_BUILD_COMPLETED: i32 = 1;
+pub const BUILDER_EVENT_BUILD_FAILED: i32 = 2;
+pub const BUILDER_EVENT_TARGET_STARTED: i32 = 3;
+pub const BUILDER_EVENT_TARGET_COMPLETED: i32 = 4;
+pub const BUILDER_EVENT_TARGET_FAILED: i32 = 5;
+pub const BUILDER_EVENT_TARGET_CACHED: i32 = 6;
+pub const BUILDER_EVENT_TARGET_PROGRESS: i32 = 7;
+pub const BUILDER_EVENT_MESSAGE: i32 = 8;
+pub const BUILDER_EVENT_WARNING: i32 = 9;
+pub const BUILDER_EVENT_ERROR: i32 = 10;
+pub const BUILDER_EVENT_STATISTICS: i32 = 11;
+pub const BUILDER_EVENT_BUILD_PROGRESS: i32 = 12;
+
+/// One build event. The strings are only valid during the callback.
+///
+/// `BUILDER_EVENT_BUILD_PROGRESS` carries completed/total targets, a
+/// `fraction` weighted by each target's duration in previous builds and an
+/// `eta_ms` estimate, enough to drive a determinate progress bar.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub struct builder_event {
+    pub kind: i32,
+    /// Null for build-level events.
+    pub target: *const c_char,
+    /// Failure reason, message text or phase; may be null.
+    pub message: *const c_char,
+    pub completed: u64,
+    pub total: u64,
+    /// 0.0 to 1.0.
+    pub fraction: f64,
+    pub elapsed_ms: i64,
+    /// -1 when unknown.
+    pub eta_ms: i64,
+}
+
+#[allow(non_camel_case_types)]
+pub type builder_event_fn = Option<unsafe extern "C" fn(event: *const builder_event, user_data: *mut c_void)>;
+
+/// Values of [`builder_log_record::level`]; they match the engine's `LogLevel`.
+pub const BUILDER_LOG_TRACE: i32 = 0;
+pub const BUILDER_LOG_DEBUG: i32 = 1;
+pub const BUILDER_LOG_INFO: i32 = 2;
+pub const BUILDER_LOG_WARNING: i32 = 3;
+pub const BUILDER_LOG_ERROR: i32 = 4;
+pub const BUILDER_LOG_CRITICAL: i32 = 5;
+
+/// One structured field of a log record.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub struct builder_log_field {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+/// One log record from the engine. The strings are only valid during the
+/// callback.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub struct builder_log_record {
+    pub level: i32,
+    /// D module that logged, e.g. `engine.caching.targets.cache`.
+    pub module: *const c_char,
+    pub message: *const c_char,
+    /// Build target from the logging thread's context; may be null.
+    pub target: *const c_char,
+    pub fields: *const builder_log_field,
+    pub field_count: usize,
+}
+
+#[allow(non_camel_case_types)]
+pub type builder_log_fn = Option<unsafe extern "C" fn(record: *const builder_log_record, user_data: *mut c_void)>;
+
+/// One output file produced by the last build.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub struct builder_artifact {
+    /// Logical name (the output's file name).
+    pub name: *const c_char,
+    /// Absolute path on disk.
+    pub path: *const c_char,
+    /// BLAKE3 of the full content, hex.
+    pub hash: *const c_char,
+    pub size: u64,
+    /// Producing target.
+    pub target: *const c_char,
+}
+
+extern "C" {
+    /// Run the engine with `argv`-style arguments, exactly like the `bldr`
+    /// executable (`argv[0]` is the program name). The D runtime is started
+    /// and stopped around the call. Returns the process exit code.
+    ///
+    /// # Safety
+    ///
+    /// `argv` must point to `argc` valid, NUL-terminated strings.
+    pub fn c_run_builder(argc: c_int, argv: *mut *mut c_char) -> c_int;
+    
+    /// Register a callback for build events (`None` unregisters). Set it
+    /// before [`c_run_builder`]; calls come from build threads but never
+    /// overlap.
+    ///
+    /// # Safety
+    ///
+    /// `user_data` must stay valid for as long as the callback is registered.
+    pub fn c_builder_set_event_callback(callback: builder_event_fn, user_data: *mut c_void);
+    
+    /// Send the engine's log records to `callback` instead of the console
+    /// (`None` restores console output). The callback may run on several
+    /// build threads at once.
+    ///
+    /// # Safety
+    ///
+    /// `user_data` must stay valid for as long as the callback is registered.
+    pub fn c_builder_set_log_callback(callback: builder_log_fn, user_data: *mut c_void);
+    
+    /// Artifacts of the last [`c_run_builder`] call, built or restored from
+    /// cache. The array is valid until the next build.
+    ///
+    /// # Safety
+    ///
+    /// `count` must be null or point to writable memory.
+    pub fn c_builder_artifacts(count: *mut usize) -> *const builder_artifact;
+    
+    /// Why targets of the last [`c_run_builder`] call rebuilt, as JSON, with
+    /// this build's and cumulative cache hit rates. Null if no build ran;
+    /// owned by the library and valid until the next build.
+    pub fn c_builder_rebuild_stats() -> *const c_char;
+    
+    /// Detected toolchains as JSON, with the one the engine would select for
+    /// `language` (`c`, `cpp`, `d`; null for any) on `platform` (a target
+    /// triple; null for the host). Returns null and sets `*error` on failure.
+    /// Free both strings with [`c_builder_free_string`].
+    ///
+    /// # Safety
+    ///
+    /// `language` and `platform` must be null or NUL-terminated; `error`
+    /// must be null or point to writable memory.
+    pub fn c_builder_probe_toolchains(language: *const c_char, platform: *const c_char, error: *mut *mut c_char) -> *mut c_char;
+    
+    /// Statistics for the cache in `cache_dir` (`.builder-cache` when null)
+    /// as JSON. Returns null and sets `*error` on failure. Free both strings
+    /// with [`c_builder_free_string`].
+    ///
+    /// # Safety
+    ///
+    /// `cache_dir` must be null or NUL-terminated; `error` must be null or
+    /// point to writable memory.
+    pub fn c_builder_cache_stats(cache_dir: *const c_char, error: *mut *mut c_char) -> *mut c_char;
+    
+    /// Free a string returned by the `c_builder_*` API.
+    ///
+    /// # Safety
+    ///
+    /// `str` must be null or a string the library returned and not yet freed.
+    pub fn c_builder_free_string(str: *mut c_char);
+}
@@ -0,0 +1,105 @@
+//! Why targets rebuilt, per build and accumulated across builds.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Why a target could not be reused from the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebuildReason {
+    /// Reused from the cache; not rebuilt.
+    UpToDate,
+    /// No cache entry: first build, evicted or expired.
+    NotCached,
+    /// A source file changed or disappeared.
+    InputChanged,
+    /// A dependency was rebuilt with different output.
+    DependencyChanged,
+    /// Compiler flags or language config changed.
+    FlagsChanged,
+    /// The build ran with `--force`.
+    Forced,
+}
+
+/// The cache verdict for one target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetRebuild {
+    pub target: String,
+    pub reason: RebuildReason,
+    /// Changed source file or dependency; empty when there is none.
+    pub detail: String,
+}
+
+/// Verdict counts over one or more builds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildTotals {
+    pub builds: u64,
+    pub targets: u64,
+    /// Targets reused from the cache.
+    pub hits: u64,
+    /// `hits / targets`, 0.0 to 1.0.
+    pub hit_rate: f64,
+    pub reasons: BTreeMap<RebuildReason, u64>,
+}
+
+/// This build's totals plus every target's verdict.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildRebuilds {
+    #[serde(flatten)]
+    pub totals: RebuildTotals,
+    pub rebuilds: Vec<TargetRebuild>,
+}
+
+/// Rebuild statistics of one [`run`](crate::run).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RebuildStats {
+    pub build: BuildRebuilds,
+    /// Totals accumulated in the cache directory, this build included.
+    pub cumulative: RebuildTotals,
+}
+
+impl RebuildStats {
+    pub(crate) fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid rebuild statistics: {}", e))
+    }
+    
+    /// Targets that were rebuilt rather than reused, with the reason.
+    pub fn rebuilt(&self) -> impl Iterator<Item = &TargetRebuild> {
+        self.build.rebuilds.iter().filter(|r| r.reason != RebuildReason::UpToDate)
+    }
+    
+    /// The statistics as pretty-printed JSON, e.g. to keep alongside CI logs.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("rebuild statistics serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_report_and_round_trips() {
+        let json = r#"{
+            "build": {
+                "builds": 1, "targets": 2, "hits": 1, "hitRate": 0.5,
+                "reasons": {"up_to_date": 1, "not_cached": 0, "input_changed": 1,
+                            "dependency_changed": 0, "flags_changed": 0, "forced": 0},
+                "rebuilds": [
+                    {"target": "//lib:core", "reason": "up_to_date", "detail": ""},
+                    {"target": "//app:server", "reason": "input_changed", "detail": "app/main.c"}
+                ]
+            },
+            "cumulative": {
+                "builds": 4, "targets": 8, "hits": 6, "hitRate": 0.75,
+                "reasons": {"up_to_date": 6, "input_changed": 2}
+            }
+        }"#;
+        let stats = RebuildStats::from_json(json).unwrap();
+        let rebuilt: Vec<&str> = stats.rebuilt().map(|r| r.target.as_str()).collect();
+        assert_eq!(rebuilt, ["//app:server"]);
+        assert_eq!(stats.cumulative.reasons[&RebuildReason::InputChanged], 2);
+        assert_eq!(RebuildStats::from_json(&stats.to_json()).unwrap(), stats);
+    }
+}
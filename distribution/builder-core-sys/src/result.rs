@@ -0,0 +1,135 @@
+//! Safe entry point: run the engine and collect what it produced.
+
+use crate::rebuilds::RebuildStats;
+use crate::{builder_artifact, c_builder_artifacts, c_builder_rebuild_stats, c_run_builder, ENGINE};
+use std::ffi::{c_char, CStr, CString};
+use std::path::PathBuf;
+
+/// One output file of a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    /// Logical name (the output's file name).
+    pub name: String,
+    pub path: PathBuf,
+    /// BLAKE3 of the full content, hex.
+    pub hash: String,
+    pub size: u64,
+    /// Target that produced it.
+    pub target: String,
+}
+
+/// Outcome of one [`run`].
+#[derive(Debug, Clone)]
+pub struct BuildResult {
+    exit_code: i32,
+    artifacts: Vec<Artifact>,
+    rebuild_stats: Option<RebuildStats>,
+}
+
+impl BuildResult {
+    /// Exit code the `bldr` executable would have returned.
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+    
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+    
+    /// Every artifact of the build, including targets restored from cache.
+    pub fn artifacts(&self) -> &[Artifact] {
+        &self.artifacts
+    }
+    
+    /// Artifacts produced by `target` (for example `//app:server`).
+    pub fn artifacts_for<'a>(&'a self, target: &'a str) -> impl Iterator<Item = &'a Artifact> + 'a {
+        self.artifacts.iter().filter(move |a| a.target == target)
+    }
+    
+    /// Why each target rebuilt, with cache hit rates for this build and
+    /// across builds. `None` if the command did not build anything.
+    pub fn rebuild_stats(&self) -> Option<&RebuildStats> {
+        self.rebuild_stats.as_ref()
+    }
+}
+
+/// Run the engine with the arguments the `bldr` executable would take
+/// (without the program name), e.g. `run(["build", "//app:server"])`.
+pub fn run<I, S>(args: I) -> BuildResult
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    // Interior NULs cannot be passed on; drop them rather than fail
+    let owned: Vec<CString> = std::iter::once("bldr".to_string())
+        .chain(args.into_iter().map(|a| a.as_ref().replace('\0', "")))
+        .map(|a| CString::new(a).expect("NULs were removed"))
+        .collect();
+    let mut argv: Vec<*mut c_char> = owned.iter().map(|a| a.as_ptr() as *mut c_char).collect();
+    
+    let _guard = ENGINE.lock().unwrap_or_else(|e| e.into_inner());
+    // SAFETY: argv holds argc valid C strings that outlive the call, and the
+    // artifact array is copied before the lock is released
+    unsafe {
+        let exit_code = c_run_builder(argv.len() as i32, argv.as_mut_ptr());
+        let mut count = 0usize;
+        let raw = c_builder_artifacts(&mut count);
+        let artifacts = if raw.is_null() {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(raw, count).iter().map(|a| to_artifact(a)).collect()
+        };
+        let stats = c_builder_rebuild_stats();
+        // The engine wrote this JSON; a report that fails to parse is dropped
+        let rebuild_stats = if stats.is_null() {
+            None
+        } else {
+            RebuildStats::from_json(&CStr::from_ptr(stats).to_string_lossy()).ok()
+        };
+        BuildResult { exit_code, artifacts, rebuild_stats }
+    }
+}
+
+/// # Safety
+///
+/// The strings in `raw` must be null or valid NUL-terminated strings.
+unsafe fn to_artifact(raw: &builder_artifact) -> Artifact {
+    let text = |ptr: *const c_char| {
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        }
+    };
+    Artifact {
+        name: text(raw.name),
+        path: PathBuf::from(text(raw.path)),
+        hash: text(raw.hash),
+        size: raw.size,
+        target: text(raw.target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn looks_up_artifacts_by_target() {
+        let artifact = |name: &str, target: &str| Artifact {
+            name: name.to_string(),
+            path: PathBuf::from("/out").join(name),
+            hash: String::new(),
+            size: 1,
+            target: target.to_string(),
+        };
+        let result = BuildResult {
+            exit_code: 0,
+            artifacts: vec![artifact("server", "//app:server"), artifact("libcore.a", "//lib:core"), artifact("server.map", "//app:server")],
+            rebuild_stats: None,
+        };
+        let names: Vec<&str> = result.artifacts_for("//app:server").map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["server", "server.map"]);
+        assert_eq!(result.artifacts_for("//missing").count(), 0);
+    }
+}
@@ -0,0 +1,108 @@
+//! Compiler detection: which toolchains the engine found and which it picks.
+
+use crate::{c_builder_free_string, c_builder_probe_toolchains, ENGINE};
+use serde::Deserialize;
+use std::ffi::{c_char, CStr, CString};
+use std::path::PathBuf;
+
+/// One executable of a toolchain.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub path: PathBuf,
+    pub version: String,
+    /// `Compiler`, `Linker`, `Archiver`, `Assembler`, ...
+    pub kind: String,
+}
+
+/// A detected toolchain.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Toolchain {
+    pub id: String,
+    pub name: String,
+    /// Target triple it builds for.
+    pub target: String,
+    pub tools: Vec<Tool>,
+}
+
+impl Toolchain {
+    /// The toolchain's compilers.
+    pub fn compilers(&self) -> impl Iterator<Item = &Tool> {
+        self.tools.iter().filter(|t| t.kind == "Compiler")
+    }
+}
+
+/// Result of [`probe_toolchains`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolchainReport {
+    /// Host triple.
+    pub host: String,
+    /// Triple the selection was made for.
+    pub platform: String,
+    /// Requested language; empty for any.
+    pub language: String,
+    pub toolchains: Vec<Toolchain>,
+    /// Toolchain the engine would use, if any fits.
+    pub selected: Option<Toolchain>,
+}
+
+/// Detect toolchains and pick the one the engine would use for `language`
+/// (`"c"`, `"cpp"`, `"d"`; `None` for any) on `platform` (a target triple;
+/// `None` for the host).
+pub fn probe_toolchains(language: Option<&str>, platform: Option<&str>) -> Result<ToolchainReport, String> {
+    let language = language.map(CString::new).transpose().map_err(|e| e.to_string())?;
+    let platform = platform.map(CString::new).transpose().map_err(|e| e.to_string())?;
+    let ptr = |s: &Option<CString>| s.as_ref().map_or(std::ptr::null(), |s| s.as_ptr());
+    
+    let _guard = ENGINE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut error: *mut c_char = std::ptr::null_mut();
+    // SAFETY: the arguments are null or valid C strings that outlive the
+    // call; returned strings are copied before being freed
+    let json = unsafe {
+        let raw = c_builder_probe_toolchains(ptr(&language), ptr(&platform), &mut error);
+        if raw.is_null() {
+            let message = if error.is_null() {
+                "toolchain probe failed".to_string()
+            } else {
+                CStr::from_ptr(error).to_string_lossy().into_owned()
+            };
+            c_builder_free_string(error);
+            return Err(message);
+        }
+        let json = CStr::from_ptr(raw).to_string_lossy().into_owned();
+        c_builder_free_string(raw);
+        json
+    };
+    parse_report(&json)
+}
+
+fn parse_report(json: &str) -> Result<ToolchainReport, String> {
+    serde_json::from_str(json).map_err(|e| format!("invalid toolchain report: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_report() {
+        let json = r#"{
+            "host": "x86_64-unknown-linux-gnu",
+            "platform": "x86_64-unknown-linux-gnu",
+            "language": "c",
+            "toolchains": [{
+                "id": "gcc-13.2.0", "name": "gcc", "target": "x86_64-unknown-linux-gnu",
+                "tools": [
+                    {"name": "gcc", "path": "/usr/bin/gcc", "version": "13.2.0", "kind": "Compiler"},
+                    {"name": "ld", "path": "/usr/bin/ld", "version": "2.42.0", "kind": "Linker"}
+                ]
+            }],
+            "selected": null
+        }"#;
+        let report = parse_report(json).unwrap();
+        assert_eq!(report.toolchains.len(), 1);
+        assert!(report.selected.is_none());
+        let compilers: Vec<&str> = report.toolchains[0].compilers().map(|t| t.name.as_str()).collect();
+        assert_eq!(compilers, ["gcc"]);
+    }
+}
@@ -0,0 +1,101 @@
+//! Forward the engine's log records into `tracing`.
+//!
+//! Levels map one to one (critical becomes `ERROR`). `tracing` targets must
+//! be static, so records are filed under `builder_core::<package>` by the
+//! top-level D package (`engine`, `frontend`, `infrastructure`, `languages`)
+//! and carry the full D module as the `module` field. The build target is
+//! the `build_target` field and the record's structured fields are rendered
+//! as `key=value` pairs in `fields`.
+
+use crate::{builder_log_field, builder_log_record, c_builder_set_log_callback};
+use std::ffi::{c_char, c_void, CStr};
+use std::fmt;
+
+/// Route all engine logging to the current `tracing` dispatcher instead of
+/// the console. Call before running the engine.
+pub fn forward_logs_to_tracing() {
+    // SAFETY: the callback takes no user data
+    unsafe { c_builder_set_log_callback(Some(on_record), std::ptr::null_mut()) }
+}
+
+struct Fields<'a>(&'a [builder_log_field]);
+
+impl fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            // SAFETY: the engine passes NUL-terminated strings valid for the call
+            let (key, value) = unsafe { (text(field.key), text(field.value)) };
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Borrow a C string as text; null becomes empty.
+///
+/// # Safety
+///
+/// `ptr` must be null or a valid NUL-terminated string.
+unsafe fn text<'a>(ptr: *const c_char) -> std::borrow::Cow<'a, str> {
+    if ptr.is_null() {
+        "".into()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy()
+    }
+}
+
+macro_rules! emit {
+    ($target:literal, $level:expr, $module:expr, $build:expr, $fields:expr, $message:expr) => {
+        match $level {
+            crate::BUILDER_LOG_TRACE => tracing::trace!(target: $target, module = %$module, build_target = $build, fields = %$fields, "{}", $message),
+            crate::BUILDER_LOG_DEBUG => tracing::debug!(target: $target, module = %$module, build_target = $build, fields = %$fields, "{}", $message),
+            crate::BUILDER_LOG_INFO => tracing::info!(target: $target, module = %$module, build_target = $build, fields = %$fields, "{}", $message),
+            crate::BUILDER_LOG_WARNING => tracing::warn!(target: $target, module = %$module, build_target = $build, fields = %$fields, "{}", $message),
+            _ => tracing::error!(target: $target, module = %$module, build_target = $build, fields = %$fields, "{}", $message),
+        }
+    };
+}
+
+unsafe extern "C" fn on_record(record: *const builder_log_record, _user_data: *mut c_void) {
+    let Some(record) = record.as_ref() else {
+        return;
+    };
+    let module = text(record.module);
+    let message = text(record.message);
+    let build = (!record.target.is_null()).then(|| text(record.target));
+    let build = build.as_deref();
+    let fields = if record.fields.is_null() {
+        Fields(&[])
+    } else {
+        Fields(std::slice::from_raw_parts(record.fields, record.field_count))
+    };
+    
+    let level = record.level;
+    match module.split('.').next().unwrap_or("") {
+        "engine" => emit!("builder_core::engine", level, module, build, fields, message),
+        "frontend" => emit!("builder_core::frontend", level, module, build, fields, message),
+        "infrastructure" => emit!("builder_core::infrastructure", level, module, build, fields, message),
+        "languages" => emit!("builder_core::languages", level, module, build, fields, message),
+        _ => emit!("builder_core", level, module, build, fields, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn renders_fields_as_pairs() {
+        let key = c"cache_dir";
+        let value = c".builder-cache";
+        let fields = [
+            builder_log_field { key: key.as_ptr(), value: value.as_ptr() },
+            builder_log_field { key: c"hits".as_ptr(), value: c"3".as_ptr() },
+        ];
+        assert_eq!(Fields(&fields).to_string(), "cache_dir=.builder-cache hits=3");
+        assert_eq!(Fields(&[]).to_string(), "");
+    }
+}
@@ -0,0 +1,89 @@
+//! Wrapper startup overhead on the warm path.
+//!
+//! Runs the wrapper against a stub core binary in a throwaway cache and
+//! compares it with executing the stub directly. Fails if the median
+//! overhead exceeds the budget (2ms, override with `BLDR_BENCH_BUDGET_US`).
+//!
+//!     cargo bench --bench startup
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{exit, Command};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 200;
+
+fn median(cmd: &mut Command) -> Duration {
+    let mut samples: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let status = cmd.status().expect("failed to spawn");
+            assert!(status.success(), "benchmark command failed");
+            start.elapsed()
+        })
+        .collect();
+    samples.sort();
+    samples[RUNS / 2]
+}
+
+#[cfg(unix)]
+fn install_stub(dir: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    
+    fs::create_dir_all(dir).unwrap();
+    let stub = dir.join("bldr");
+    fs::write(&stub, "#!/bin/sh\nexit 0\n").unwrap();
+    fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(unix)]
+fn main() {
+    let root = env::temp_dir().join(format!("bldr-startup-bench-{}", std::process::id()));
+    let home = root.join("home");
+    let xdg = root.join("cache");
+    let version = env!("CARGO_PKG_VERSION");
+    
+    // dirs::cache_dir() is XDG_CACHE_HOME on Linux and ~/Library/Caches on macOS
+    let linux_dir = xdg.join("bldr").join(version);
+    let macos_dir = home.join("Library/Caches/bldr").join(version);
+    install_stub(&linux_dir);
+    install_stub(&macos_dir);
+    
+    let wrapper = env!("CARGO_BIN_EXE_bldr");
+    let mut through_wrapper = Command::new(wrapper);
+    through_wrapper.arg("--version").env("HOME", &home).env("XDG_CACHE_HOME", &xdg);
+    
+    let stub = if cfg!(target_os = "macos") { macos_dir } else { linux_dir }.join("bldr");
+    let mut direct = Command::new(&stub);
+    direct.arg("--version");
+    
+    // Prime the resolution record and the page cache
+    through_wrapper.status().unwrap();
+    direct.status().unwrap();
+    
+    let base = median(&mut direct);
+    let wrapped = median(&mut through_wrapper);
+    let overhead = wrapped.saturating_sub(base);
+    fs::remove_dir_all(&root).ok();
+    
+    let budget = env::var("BLDR_BENCH_BUDGET_US")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_micros)
+        .unwrap_or(Duration::from_millis(2));
+    
+    println!("direct:   {:?} (median of {})", base, RUNS);
+    println!("wrapped:  {:?}", wrapped);
+    println!("overhead: {:?} (budget {:?})", overhead, budget);
+    
+    if overhead > budget {
+        eprintln!("startup overhead exceeds budget");
+        exit(1);
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("startup bench requires a Unix shell for the stub binary; skipped");
+}
@@ -0,0 +1,112 @@
+//! `@argfile` and `BLDR_ARGS` expansion of the forwarded arguments.
+//!
+//! `BLDR_ARGS` holds flags every invocation should get (`--mode=plain
+//! --no-changelog` in a CI template); they are split like a shell would and
+//! go in front of the command line. An `@path` argument is replaced by the
+//! arguments in that file, one or more per line, with blank lines and `#`
+//! comments skipped. Files are not expanded recursively, and arguments after
+//! `--` are passed on untouched, so a literal `@name` can follow `--`.
+
+use std::env;
+use std::fs;
+
+/// `BLDR_ARGS` followed by `args`, with `@argfile`s replaced by their
+/// contents.
+pub fn expand(args: Vec<String>) -> Result<Vec<String>, String> {
+    let mut expanded = match env::var("BLDR_ARGS") {
+        Ok(value) => split_words(&value).map_err(|e| format!("BLDR_ARGS: {}", e))?,
+        Err(_) => Vec::new(),
+    };
+    
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args.by_ref());
+            break;
+        }
+        match arg.strip_prefix('@').filter(|path| !path.is_empty()) {
+            Some(path) => {
+                let contents = fs::read_to_string(path).map_err(|e| format!("cannot read argument file {}: {}", path, e))?;
+                expanded.extend(parse(&contents).map_err(|e| format!("{}: {}", path, e))?);
+            }
+            None => expanded.push(arg),
+        }
+    }
+    Ok(expanded)
+}
+
+/// The arguments in an argument file.
+fn parse(contents: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        args.extend(split_words(line).map_err(|e| format!("line {}: {}", number + 1, e))?);
+    }
+    Ok(args)
+}
+
+/// Split on whitespace, honouring single and double quotes and backslashes.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                current.push(chars.next().ok_or("trailing backslash")?);
+                in_word = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    
+    if quote.is_some() {
+        return Err("unterminated quote".to_string());
+    }
+    if in_word {
+        words.push(current);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn expands_argument_files_before_the_separator() {
+        let path = env::temp_dir().join(format!("bldr-args-{}", std::process::id()));
+        fs::write(&path, "# shared CI flags\n--mode=plain\n\n--remote 'label=big box'\n").unwrap();
+        let arg = format!("@{}", path.display());
+        
+        let args = vec!["build".to_string(), arg.clone(), "//app".to_string(), "--".to_string(), arg.clone()];
+        let expanded = expand(args).unwrap();
+        fs::remove_file(&path).ok();
+        
+        assert_eq!(expanded, ["build", "--mode=plain", "--remote", "label=big box", "//app", "--", arg.as_str()]);
+        assert!(expand(vec!["@/nonexistent/bldr-args".to_string()]).is_err());
+        assert!(parse("--mode 'plain").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! GitHub artifact attestation (SLSA build provenance) check for downloaded
+//! release archives.
+//!
+//! With `BLDR_ATTESTATION_VERIFY` set (or `attestation` among the policy's
+//! `verification` schemes), `gh attestation verify` fetches the provenance
+//! GitHub recorded for the archive's digest and checks that it was built by
+//! the release workflow of the expected repository, from the tag of the
+//! version being installed. `BLDR_ATTESTATION_VERIFY=ci` requires the check
+//! only when `CI` is set, so one shared setting enforces it on runners
+//! without needing `gh` on every laptop. `gh` needs a token (`GH_TOKEN`) to
+//! read attestations.
+
+use crate::github;
+use crate::policy::Mode;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+const RELEASE_WORKFLOW: &str = "release.yml";
+
+/// Apply the configured policy to the archive of `version`. Errors mean the
+/// archive must not be used.
+pub fn check(archive: &Path, version: &str) -> Result<(), String> {
+    let mode = Mode::for_scheme("attestation", "BLDR_ATTESTATION_VERIFY");
+    if mode == Mode::Off {
+        return Ok(());
+    }
+    
+    let repo = env::var("BLDR_ATTESTATION_REPO").unwrap_or_else(|_| github::repo());
+    let workflow = env::var("BLDR_ATTESTATION_WORKFLOW").unwrap_or_else(|_| RELEASE_WORKFLOW.to_string());
+    let signer = format!("{}/.github/workflows/{}", repo, workflow.trim_start_matches(".github/workflows/"));
+    
+    match verify(archive, &repo, &signer, version) {
+        Ok(()) => {
+            eprintln!("Build provenance: built by {} from v{}", signer, version);
+            Ok(())
+        }
+        Err(e) if mode == Mode::Warn => {
+            eprintln!("bldr: warning: build provenance check failed: {}", e);
+            Ok(())
+        }
+        Err(e) => Err(format!("build provenance check failed: {}", e)),
+    }
+}
+
+fn verify(archive: &Path, repo: &str, signer: &str, version: &str) -> Result<(), String> {
+    let output = Command::new("gh")
+        .env("GH_HOST", github::host())
+        .args(["attestation", "verify"])
+        .arg(archive)
+        .args(["--repo", repo, "--signer-workflow", signer])
+        .arg(format!("--source-ref=refs/tags/{}", github::tag(version)))
+        .output()
+        .map_err(|e| format!("failed to run gh (is the GitHub CLI installed?): {}", e))?;
+    
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "no attestation by {} for {}: {}",
+            signer,
+            github::tag(version),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
@@ -0,0 +1,172 @@
+//! Executable headers: which platform a core binary was built for.
+//!
+//! Read from the ELF, Mach-O (thin or universal) or PE header after
+//! unpacking, so a mislabelled or wrong release asset (an amd64 core on an
+//! arm64 machine) fails the install with a clear message instead of an
+//! "exec format error" on every run.
+
+use crate::platform;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Enough of the file for every header read here, PE's included.
+const HEADER_BYTES: u64 = 4096;
+
+/// Check that `path` is an executable for this machine.
+pub fn check(path: &Path) -> Result<(), String> {
+    let mut header = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(HEADER_BYTES).read_to_end(&mut header))
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let host = platform::current();
+    let built_for = platforms(&header);
+    if built_for.contains(&host) {
+        return Ok(());
+    }
+    match built_for.first() {
+        None => Err(format!("{} is not an executable (no ELF, Mach-O or PE header)", path.display())),
+        Some((os, arch)) => Err(format!(
+            "{} is a {}-{} binary, but this machine is {}-{}; the release asset is probably mislabelled",
+            path.display(),
+            os,
+            arch,
+            host.0,
+            host.1
+        )),
+    }
+}
+
+/// `(os, arch)` of each platform the executable `header` runs on, named as
+/// in [`platform::current`]; empty when it is not an executable.
+fn platforms(header: &[u8]) -> Vec<(&'static str, &'static str)> {
+    let u16_le = |at: usize| header.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_le = |at: usize| header.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let u32_be = |at: usize| header.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    
+    match header.get(..4) {
+        Some([0x7f, b'E', b'L', b'F']) => {
+            // e_machine, in the byte order EI_DATA names
+            let machine = match header.get(5) {
+                Some(2) => header.get(18..20).map(|b| u16::from_be_bytes([b[0], b[1]])),
+                _ => u16_le(18),
+            };
+            let arch = match machine {
+                Some(62) => "amd64",
+                Some(183) => "arm64",
+                Some(3) => "386",
+                Some(40) => "arm",
+                _ => "unknown",
+            };
+            vec![("linux", arch)]
+        }
+        Some([0xcf, 0xfa, 0xed, 0xfe]) => u32_le(4).map(|cpu| vec![("darwin", macho_arch(cpu))]).unwrap_or_default(),
+        Some([0xfe, 0xed, 0xfa, 0xcf]) => u32_be(4).map(|cpu| vec![("darwin", macho_arch(cpu))]).unwrap_or_default(),
+        Some([0xca, 0xfe, 0xba, 0xbe]) => {
+            // Universal binary: a count, then 20-byte entries led by the CPU
+            // type. Java class files share the magic but read as huge counts.
+            let count = u32_be(4).unwrap_or(0) as usize;
+            if count > 32 {
+                return Vec::new();
+            }
+            (0..count).filter_map(|i| u32_be(8 + i * 20)).map(|cpu| ("darwin", macho_arch(cpu))).collect()
+        }
+        Some([b'M', b'Z', ..]) => {
+            let Some(pe) = u32_le(0x3c).map(|offset| offset as usize) else {
+                return Vec::new();
+            };
+            if header.get(pe..pe + 4) != Some(b"PE\0\0") {
+                return Vec::new();
+            }
+            let arch = match u16_le(pe + 4) {
+                Some(0x8664) => "amd64",
+                Some(0xaa64) => "arm64",
+                Some(0x14c) => "386",
+                _ => "unknown",
+            };
+            vec![("windows", arch)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn macho_arch(cpu: u32) -> &'static str {
+    match cpu {
+        0x0100_0007 => "amd64",
+        0x0100_000c => "arm64",
+        7 => "386",
+        _ => "unknown",
+    }
+}
+
+/// A minimal header for this machine's executables, for tests that build
+/// release archives.
+#[cfg(test)]
+pub fn host_header() -> Vec<u8> {
+    let (os, arch) = platform::current();
+    match os {
+        "darwin" => {
+            let cpu: u32 = if arch == "arm64" { 0x0100_000c } else { 0x0100_0007 };
+            [&[0xcf, 0xfa, 0xed, 0xfe][..], &cpu.to_le_bytes()].concat()
+        }
+        "windows" => {
+            let machine: u16 = if arch == "arm64" { 0xaa64 } else { 0x8664 };
+            let mut header = [0u8; 0x40];
+            header[..2].copy_from_slice(b"MZ");
+            header[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+            [&header[..], b"PE\0\0", &machine.to_le_bytes()].concat()
+        }
+        _ => {
+            let machine: u16 = if arch == "arm64" { 183 } else { 62 };
+            let mut header = vec![0u8; 20];
+            header[..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+            header[4] = 2; // 64-bit
+            header[5] = 1; // little-endian
+            header[18..20].copy_from_slice(&machine.to_le_bytes());
+            header
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn reads_the_platform_from_executable_headers() {
+        assert_eq!(platforms(&host_header()), [platform::current()]);
+        
+        let mut elf = elf_header(183);
+        assert_eq!(platforms(&elf), [("linux", "arm64")]);
+        elf[18] = 62;
+        assert_eq!(platforms(&elf), [("linux", "amd64")]);
+        
+        let thin = [&[0xcf, 0xfa, 0xed, 0xfe][..], &0x0100_000cu32.to_le_bytes()].concat();
+        assert_eq!(platforms(&thin), [("darwin", "arm64")]);
+        let mut fat = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 2];
+        for cpu in [0x0100_0007u32, 0x0100_000c] {
+            fat.extend(cpu.to_be_bytes());
+            fat.extend([0u8; 16]);
+        }
+        assert_eq!(platforms(&fat), [("darwin", "amd64"), ("darwin", "arm64")]);
+        assert!(platforms(&[0xca, 0xfe, 0xba, 0xbe, 0, 0x34, 0, 0x41]).is_empty());
+        
+        let mut pe = vec![0u8; 0x80];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3c] = 0x80;
+        pe.extend(b"PE\0\0");
+        pe.extend(0x8664u16.to_le_bytes());
+        assert_eq!(platforms(&pe), [("windows", "amd64")]);
+        
+        assert!(platforms(b"#!/bin/sh\n").is_empty());
+        assert!(platforms(b"MZ").is_empty());
+    }
+    
+    fn elf_header(machine: u8) -> Vec<u8> {
+        let mut header = vec![0u8; 20];
+        header[..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+        header[5] = 1;
+        header[18] = machine;
+        header
+    }
+}
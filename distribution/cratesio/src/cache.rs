@@ -0,0 +1,396 @@
+use crate::{install, perms};
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// File in a version's directory naming the URL it was installed from.
+const SOURCE_FILE: &str = ".source";
+/// File in a version's directory holding when it last ran (Unix seconds),
+/// rewritten at most once per [`USED_GRANULARITY`] so launching costs one
+/// small read.
+const USED_FILE: &str = ".used";
+const USED_GRANULARITY: Duration = Duration::from_secs(60 * 60);
+/// Directory in the root holding a lock file per version; kept outside the
+/// version directories so removing one never touches a held lock.
+const LOCKS_DIR: &str = ".locks";
+
+/// Root of the wrapper's cache; versions live in `<root>/<version>/`.
+/// `BLDR_CACHE_DIR` moves it, e.g. to keep test runs off the user's cache;
+/// otherwise it is `$XDG_CACHE_HOME/bldr` on every platform, then the
+/// platform's cache directory, then `$XDG_RUNTIME_DIR/bldr`, then a private
+/// directory of this user's in the temp directory: the first that can be
+/// written, as containers may have no home or a read-only one. Under sudo
+/// it is root's own cache, whatever `HOME` and `XDG_CACHE_HOME` sudo kept.
+pub fn root() -> PathBuf {
+    if let Some(dir) = env::var_os("BLDR_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = sudo_root() {
+        return dir.clone();
+    }
+    default_root().clone()
+}
+
+/// The default root, chosen once per process.
+fn default_root() -> &'static PathBuf {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        // The XDG spec says to ignore relative paths
+        let absolute = |var| env::var_os(var).map(PathBuf::from).filter(|dir| dir.is_absolute());
+        let preferred = absolute("XDG_CACHE_HOME").or_else(dirs::cache_dir).map(|dir| dir.join("bldr"));
+        let runtime = absolute("XDG_RUNTIME_DIR").map(|dir| dir.join("bldr"));
+        let mut unusable = None;
+        let usable = preferred.iter().chain(&runtime).find(|dir| match check_writable(dir) {
+            Ok(()) => true,
+            Err(e) => {
+                unusable.get_or_insert((dir.to_path_buf(), e));
+                false
+            }
+        });
+        let dir = usable.cloned().unwrap_or_else(|| temp_root().clone());
+        if let Some((unusable, e)) = unusable {
+            eprintln!(
+                "bldr: warning: cannot write to {} ({}); caching core versions in {} instead. \
+                 Set BLDR_CACHE_DIR to choose where.",
+                unusable.display(),
+                e,
+                dir.display()
+            );
+        }
+        dir
+    })
+}
+
+/// Create `dir` if needed and check that this user can write to it.
+pub fn check_writable(dir: &Path) -> io::Result<()> {
+    if !dir.is_dir() {
+        perms::create_dir_all(dir)?;
+    }
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        let path = CString::new(dir.as_os_str().as_bytes())?;
+        // SAFETY: path is a valid NUL-terminated string; access only reads it.
+        // Unlike a mode check it also fails on a read-only mount.
+        if unsafe { libc::access(path.as_ptr(), libc::W_OK | libc::X_OK) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let probe = dir.join(format!(".write-test-{}", std::process::id()));
+        File::create(&probe)?;
+        fs::remove_file(&probe)
+    }
+}
+
+/// `<temp>/bldr-<user>`, created private to this user. The temp directory
+/// is shared on Unix, so one another user made first is not used; this
+/// run gets a directory of its own instead.
+fn temp_root() -> &'static PathBuf {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        #[cfg(unix)]
+        let user = uid().to_string();
+        #[cfg(not(unix))]
+        let user = env::var("USERNAME").unwrap_or_else(|_| "user".to_string());
+        let dir = env::temp_dir().join(format!("bldr-{}", user));
+        if private_dir(&dir) {
+            return dir;
+        }
+        eprintln!("bldr: warning: {} belongs to another user; using a cache for this run only", dir.display());
+        let dir = env::temp_dir().join(format!("bldr-{}-{}", user, std::process::id()));
+        private_dir(&dir);
+        dir
+    })
+}
+
+/// Root's cache when running under sudo. `sudo` often keeps the invoking
+/// user's `HOME`, which would put root-owned versions in that user's cache
+/// where their own runs can neither update nor remove them; root's home is
+/// looked up in the password database instead, so the two never mix.
+#[cfg(unix)]
+fn sudo_root() -> Option<&'static PathBuf> {
+    static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        if uid() != 0 || env::var_os("SUDO_UID").is_none() {
+            return None;
+        }
+        let home = root_home().filter(|home| home.is_absolute());
+        let cache = if cfg!(target_os = "macos") { "Library/Caches" } else { ".cache" };
+        Some(home.map_or_else(|| temp_root().clone(), |home| home.join(cache).join("bldr")))
+    })
+    .as_ref()
+}
+
+#[cfg(not(unix))]
+fn sudo_root() -> Option<&'static PathBuf> {
+    None
+}
+
+#[cfg(unix)]
+fn root_home() -> Option<PathBuf> {
+    use std::ffi::{CStr, OsString};
+    use std::os::unix::ffi::OsStringExt;
+    // SAFETY: getpwuid returns null or a record that stays valid until the
+    // next call, and its fields are copied out before returning
+    unsafe {
+        let entry = libc::getpwuid(0);
+        if entry.is_null() || (*entry).pw_dir.is_null() {
+            return None;
+        }
+        let dir = CStr::from_ptr((*entry).pw_dir).to_bytes().to_vec();
+        Some(PathBuf::from(OsString::from_vec(dir)))
+    }
+}
+
+#[cfg(unix)]
+fn uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }
+}
+
+/// Check that no other user can swap out the core at `path` before it
+/// runs: it and every directory above it must belong to this user or root,
+/// and none may be writable by everyone, short of a sticky directory such as
+/// `/tmp`, or by a group, short of this user's own private group on their own
+/// files. Symlinks are resolved first; components not created yet (a first
+/// install) are skipped.
+#[cfg(unix)]
+pub fn check_secure(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::MetadataExt;
+    let Some(resolved) = path.ancestors().find_map(|p| fs::canonicalize(p).ok()) else {
+        return Ok(());
+    };
+    for dir in resolved.ancestors() {
+        let Ok(meta) = fs::metadata(dir) else {
+            continue;
+        };
+        let sticky = meta.is_dir() && meta.mode() & 0o1000 != 0;
+        let problem = if meta.uid() != uid() && meta.uid() != 0 {
+            format!("{} belongs to another user (uid {})", dir.display(), meta.uid())
+        } else if meta.mode() & 0o002 != 0 && !sticky {
+            format!("{} is writable by every user", dir.display())
+        } else if meta.mode() & 0o020 != 0 && !sticky && !(meta.uid() == uid() && private_group(meta.gid())) {
+            format!("{} is writable by its group (gid {})", dir.display(), meta.gid())
+        } else {
+            continue;
+        };
+        return Err(format!(
+            "refusing to run {}: {}, so another user could replace it.\n\
+             Make it writable by you alone (chmod go-w, or chown it to yourself), or set\n\
+             BLDR_CACHE_DIR to a directory only you can write to.",
+            path.display(),
+            problem
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `gid` is this user's private group: their login group, named
+/// after them, with no other members.
+#[cfg(unix)]
+fn private_group(gid: u32) -> bool {
+    use std::ffi::CStr;
+    // SAFETY: getpwuid and getgrgid return null or records that stay valid
+    // until the next call to the same function; both are read before returning
+    unsafe {
+        let user = libc::getpwuid(uid());
+        if user.is_null() || (*user).pw_gid != gid || (*user).pw_name.is_null() {
+            return false;
+        }
+        let group = libc::getgrgid(gid);
+        if group.is_null() || (*group).gr_name.is_null() {
+            return false;
+        }
+        let members = (*group).gr_mem;
+        CStr::from_ptr((*group).gr_name) == CStr::from_ptr((*user).pw_name) && (members.is_null() || (*members).is_null())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn check_secure(_: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+/// Create `dir` readable by this user alone, or check that it already is
+/// this user's own directory.
+fn private_dir(dir: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+        fs::DirBuilder::new().mode(0o700).create(dir).ok();
+        fs::symlink_metadata(dir).is_ok_and(|meta| meta.is_dir() && meta.uid() == uid())
+    }
+    #[cfg(not(unix))]
+    {
+        fs::create_dir_all(dir).is_ok()
+    }
+}
+
+/// Versions with an extracted core binary, as `(version, binary)`.
+pub fn installed() -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(root()) else {
+        return Vec::new();
+    };
+    
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let version = entry.file_name().to_str()?.to_string();
+            let binary = entry.path().join(install::binary_name());
+            binary.is_file().then_some((version, binary))
+        })
+        .collect()
+}
+
+/// Remember which mirror `dir`'s version was installed from.
+pub fn record_source(dir: &Path, url: &str) {
+    perms::write(&dir.join(SOURCE_FILE), format!("{}\n", url)).ok();
+}
+
+/// The URL `version` was installed from, when recorded.
+pub fn source(version: &str) -> Option<String> {
+    let contents = fs::read_to_string(root().join(version).join(SOURCE_FILE)).ok()?;
+    Some(contents.trim().to_string()).filter(|url| !url.is_empty())
+}
+
+/// Note that `version` is being run, for `bldr wrapper list`. Best effort.
+pub fn mark_used(version: &str) {
+    let recent = last_used(version)
+        .and_then(|t| t.elapsed().ok())
+        .is_some_and(|age| age < USED_GRANULARITY);
+    if !recent {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        perms::write(&root().join(version).join(USED_FILE), format!("{}\n", now)).ok();
+    }
+}
+
+/// When `version` last ran, if it ever did since being installed.
+pub fn last_used(version: &str) -> Option<SystemTime> {
+    let secs: u64 = fs::read_to_string(root().join(version).join(USED_FILE)).ok()?.trim().parse().ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Bytes `version`'s directory takes up.
+pub fn size(version: &str) -> u64 {
+    fn walk(dir: &Path) -> u64 {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return 0;
+        };
+        entries
+            .flatten()
+            .map(|entry| match entry.file_type() {
+                Ok(kind) if kind.is_dir() => walk(&entry.path()),
+                Ok(kind) if kind.is_file() => entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+                _ => 0,
+            })
+            .sum()
+    }
+    walk(&root().join(version))
+}
+
+/// Hold the install lock for `version` until the returned file is dropped,
+/// so concurrent invocations (parallel CI jobs) install it once: the others
+/// wait here, then find it cached. The lock is advisory and released by the
+/// OS if its holder dies.
+pub fn lock(version: &str) -> Result<File, String> {
+    lock_in(&root().join(LOCKS_DIR), version)
+}
+
+/// The install lock for `version` if no other process holds it, for work
+/// that should skip a version being installed rather than wait.
+pub fn try_lock(version: &str) -> Option<File> {
+    let path = root().join(LOCKS_DIR).join(format!("{}.lock", version));
+    perms::create_dir_all(path.parent()?).ok()?;
+    let file = OpenOptions::new().create(true).write(true).truncate(false).open(&path).ok()?;
+    file.try_lock().ok()?;
+    Some(file)
+}
+
+fn lock_in(dir: &Path, version: &str) -> Result<File, String> {
+    perms::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.lock", version));
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+    match file.try_lock() {
+        Ok(()) => return Ok(file),
+        Err(TryLockError::WouldBlock) => {}
+        Err(TryLockError::Error(e)) => return Err(format!("cannot lock {}: {}", path.display(), e)),
+    }
+    
+    eprintln!("Waiting for another bldr process to finish installing v{}...", version);
+    file.lock().map_err(|e| format!("cannot lock {}: {}", path.display(), e))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn one_holder_per_version() {
+        let dir = env::temp_dir().join(format!("bldr-locks-{}", std::process::id()));
+        let held = lock_in(&dir, "1.2.3").unwrap();
+        let other = File::options().write(true).open(dir.join("1.2.3.lock")).unwrap();
+        assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+        assert!(lock_in(&dir, "1.2.4").is_ok());
+        
+        drop(held);
+        assert!(other.try_lock().is_ok());
+        fs::remove_dir_all(&dir).ok();
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn refuses_cores_others_can_replace() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = env::temp_dir().join(format!("bldr-secure-{}", std::process::id()));
+        let binary = dir.join("1.2.3").join("bldr");
+        fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        fs::write(&binary, "").unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(check_secure(&binary).is_ok());
+        
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(check_secure(&binary).unwrap_err().contains("writable by every user"));
+        // Like /tmp: others can add entries but not replace this user's
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o1777)).unwrap();
+        assert!(check_secure(&binary).is_ok());
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(check_secure(&binary).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn refuses_cores_a_group_can_replace() {
+        use std::os::unix::fs::{chown, PermissionsExt};
+        let dir = env::temp_dir().join(format!("bldr-group-{}", std::process::id()));
+        let binary = dir.join("1.2.3").join("bldr");
+        fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        fs::write(&binary, "").unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o775)).unwrap();
+        
+        // SAFETY: getgid has no preconditions and cannot fail
+        let gid = unsafe { libc::getgid() };
+        assert_eq!(check_secure(&binary).is_ok(), private_group(gid));
+        // A group shared with others, where this user can hand the directory to one
+        let shared = (0..gid + 100).find(|&other| other != gid && !private_group(other) && chown(&dir, None, Some(other)).is_ok());
+        if shared.is_some() {
+            assert!(check_secure(&binary).unwrap_err().contains("writable by its group"));
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(check_secure(&binary).is_ok());
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,131 @@
+//! Release notes shown when the resolved version moves.
+//!
+//! Notes come from the GitHub release bodies (Markdown). We render a terse
+//! terminal version: headings in bold, bullets, links reduced to their text,
+//! and a line budget so an update never floods the scrollback.
+
+use std::env;
+use std::io::IsTerminal;
+
+/// Lines of notes shown per release before truncating.
+const MAX_LINES: usize = 12;
+
+/// `--no-changelog` on the command line or `BLDR_NO_CHANGELOG=1`.
+pub fn suppressed(flag: bool) -> bool {
+    flag || env::var("BLDR_NO_CHANGELOG").map(|v| v == "1").unwrap_or(false)
+}
+
+/// Print notes for each `(version, body)` the user moved across.
+pub fn print(from: &str, to: &str, notes: &[(String, String)]) {
+    let color = std::io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    eprintln!("bldr: updating {} -> {}", from, to);
+    
+    for (version, body) in notes {
+        eprintln!();
+        eprintln!("{}", bold(&format!("What's new in {}", version), color));
+        let rendered = render(body, color);
+        let lines: Vec<&str> = rendered.lines().collect();
+        for line in lines.iter().take(MAX_LINES) {
+            eprintln!("  {}", line);
+        }
+        if lines.len() > MAX_LINES {
+            eprintln!(
+                "  ... https://github.com/GriffinCanCode/bldr/releases/tag/v{}",
+                version
+            );
+        }
+    }
+    eprintln!();
+}
+
+/// Render Markdown for a terminal.
+pub fn render(markdown: &str, color: bool) -> String {
+    let mut out = Vec::new();
+    let mut in_code = false;
+    let mut last_blank = true;
+    
+    for raw in markdown.lines() {
+        let line = raw.trim_end();
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push(format!("    {}", line));
+            last_blank = false;
+            continue;
+        }
+        
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            if !last_blank {
+                out.push(String::new());
+            }
+            last_blank = true;
+            continue;
+        }
+        // Skip HTML comments and horizontal rules from release templates
+        if trimmed.starts_with("<!--") || trimmed.chars().all(|c| c == '-' || c == '*' || c == '_') {
+            continue;
+        }
+        
+        let rendered = if let Some(heading) = trimmed.strip_prefix('#') {
+            bold(inline(heading.trim_start_matches('#').trim()).as_str(), color)
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            let indent = (line.len() - trimmed.len()) / 2;
+            format!("{}• {}", "  ".repeat(indent), inline(item))
+        } else {
+            inline(trimmed)
+        };
+        out.push(rendered);
+        last_blank = false;
+    }
+    
+    while out.last().map(|l| l.is_empty()).unwrap_or(false) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+/// Strip inline markup: links, emphasis and code spans.
+fn inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    
+    while let Some(start) = rest.find('[') {
+        let after = &rest[start + 1..];
+        match after.find("](").and_then(|mid| after[mid..].find(')').map(|end| (mid, mid + end))) {
+            Some((mid, end)) => {
+                out.push_str(&rest[..start]);
+                out.push_str(&after[..mid]);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[..=start]);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    
+    out.replace("**", "").replace("__", "").replace('`', "")
+}
+
+fn bold(text: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[1m{}\x1b[0m", text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn renders_release_markdown() {
+        let body = "## Features\n\n- Faster **cache** lookups ([#12](https://x/12))\n  - nested `detail`\n\n<!-- template -->\n---\n";
+        assert_eq!(render(body, false), "Features\n\n• Faster cache lookups (#12)\n  • nested detail");
+    }
+}
@@ -0,0 +1,68 @@
+//! Release channels: `stable`, `beta` and `nightly`.
+//!
+//! A channel is what runs when nothing pins a version (no `bldr-<version>`
+//! name, `BLDR_VERSION` or `.bldr-version`/global pin). It comes from
+//! `BLDR_CHANNEL`, else the first line of `<config>/bldr/channel`; without
+//! either the wrapper runs the release it was built for.
+//!
+//! `stable` follows releases, `beta` also release candidates and betas, and
+//! `nightly` every prerelease, or the `nightly` tag when the project
+//! publishes one, rebuilt in place. Numbered releases never change, so every
+//! channel shares their `<cache>/<version>` directories; a channel's rolling
+//! tag gets its own `<cache>/<channel>` directory, and each channel records
+//! its own resolution, so switching channels and back leaves the other's
+//! install untouched.
+
+use crate::pin;
+use crate::version::Selector;
+use std::env;
+use std::path::PathBuf;
+
+pub const CHANNELS: &[&str] = &["stable", "beta", "nightly"];
+
+/// Selector for the configured channel, if any.
+pub fn selector() -> Option<Selector> {
+    let (name, origin) = match env::var("BLDR_CHANNEL").ok().filter(|v| !v.trim().is_empty()) {
+        Some(name) => (name, "BLDR_CHANNEL".to_string()),
+        None => {
+            let path = config_path()?;
+            (pin::read(&path)?, path.display().to_string())
+        }
+    };
+    match parse(&name) {
+        Some(channel) => Some(Selector::Channel(channel.to_string())),
+        None => {
+            eprintln!("bldr: ignoring unknown channel '{}' from {} (expected {})", name.trim(), origin, CHANNELS.join(", "));
+            None
+        }
+    }
+}
+
+/// `<config>/bldr/channel`.
+pub fn config_path() -> Option<PathBuf> {
+    pin::global_path().map(|path| path.with_file_name("channel"))
+}
+
+/// Canonical name of a channel; `latest` is another name for `stable`.
+pub fn parse(name: &str) -> Option<&'static str> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "stable" | "latest" => Some("stable"),
+        "beta" => Some("beta"),
+        "nightly" => Some("nightly"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn names_channels() {
+        assert_eq!(parse("stable"), Some("stable"));
+        assert_eq!(parse(" Latest\n"), Some("stable"));
+        assert_eq!(parse("NIGHTLY"), Some("nightly"));
+        assert_eq!(parse("canary"), None);
+        assert!(CHANNELS.iter().all(|name| parse(name) == Some(*name)));
+    }
+}
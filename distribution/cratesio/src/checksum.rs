@@ -0,0 +1,332 @@
+//! SHA-256 checks for downloads.
+//!
+//! Release archives are checked before anything is extracted or run, and a
+//! mismatch always rejects the archive. The crate's own version is checked
+//! against the digests embedded in it at release time (`pinned.sha256`), so
+//! a release edited after publication cannot change what `cargo install
+//! bldr` runs; an asset missing from that list is refused rather than
+//! checked against the release's own digest. Other versions use the digest
+//! the release publishes, its `SHA256SUMS` line or else `<asset>.sha256`;
+//! releases that publish neither (the mirror answers 404 for both) install
+//! with a warning unless `sha256` verification is required
+//! (`BLDR_SHA256_VERIFY`, or the policy's `verification` list). A mirror that
+//! fails or cannot be reached fails the install.
+
+use crate::http;
+use crate::policy::Mode;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Hex-encoded SHA-256 of a file.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Parse a `SHA256SUMS` file (`<hex>  <name>` per line, `*` marks binary mode).
+pub fn parse_sums(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+            let name = name.trim_start().trim_start_matches('*');
+            if hash.len() == 64 && !name.is_empty() {
+                Some((hash.to_ascii_lowercase(), name.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Digests of this crate's own release: a `version` line, then `SHA256SUMS`.
+const PINNED: &str = include_str!("pinned.sha256");
+
+/// The digest the archive at `url` of release `version` must have; `None`
+/// when nothing is known and `sha256` verification is not required.
+pub fn expected_for(url: &str, version: &str) -> Result<Option<String>, String> {
+    let name = url.rsplit('/').next().unwrap_or(url);
+    pinned_for(PINNED, version, name).unwrap_or_else(|| published_for(url))
+}
+
+/// What `pins` settles for asset `name` of `version`: its digest, or an
+/// error when `version` is pinned but `name` is not among its assets;
+/// `None` when `pins` is for another version.
+fn pinned_for(pins: &str, version: &str, name: &str) -> Option<Result<Option<String>, String>> {
+    if pinned_version(pins) != Some(version) {
+        return None;
+    }
+    Some(pinned(pins, version, name).map(Some).ok_or_else(|| {
+        format!("{} is not among the checksums embedded for bldr {}; refusing to trust the release's own", name, version)
+    }))
+}
+
+/// Whether the release lists a digest for the asset at `url`, i.e. publishes
+/// it; for this crate's own version the embedded list settles it without a
+/// request.
+pub fn listed(url: &str, version: &str) -> bool {
+    let name = url.rsplit('/').next().unwrap_or(url);
+    if pinned_version(PINNED) == Some(version) {
+        return pinned(PINNED, version, name).is_some();
+    }
+    published(url).is_ok_and(|digest| digest.is_some())
+}
+
+/// Whether the digests of `version`'s assets are embedded in the wrapper,
+/// so they can be checked without a request.
+pub fn pins(version: &str) -> bool {
+    pinned_version(PINNED) == Some(version)
+}
+
+fn pinned_version(pins: &str) -> Option<&str> {
+    pins.lines().find_map(|line| line.strip_prefix("version ")).map(str::trim)
+}
+
+/// The digest `pins` records for asset `name` of `version`.
+fn pinned(pins: &str, version: &str, name: &str) -> Option<String> {
+    if pinned_version(pins)? != version {
+        return None;
+    }
+    parse_sums(pins).into_iter().find(|(_, listed)| listed == name).map(|(hash, _)| hash)
+}
+
+/// The digest the release publishes for the asset at `url`.
+fn published_for(url: &str) -> Result<Option<String>, String> {
+    if let Some(expected) = published(url)? {
+        return Ok(Some(expected));
+    }
+    match Mode::for_scheme("sha256", "BLDR_SHA256_VERIFY") {
+        Mode::Require => Err(format!("the release publishes no SHA256SUMS or .sha256 for {}", url)),
+        _ => {
+            eprintln!("bldr: warning: the release publishes no checksum for {}; it is not verified", url);
+            Ok(None)
+        }
+    }
+}
+
+/// The digest published for the asset at `url`. `None` only when the mirror
+/// answers 404 for both `SHA256SUMS` and `<asset>.sha256`: a mirror that
+/// cannot be reached or fails cannot turn verification off.
+fn published(url: &str) -> Result<Option<String>, String> {
+    let (base, name) = url.rsplit_once('/').ok_or_else(|| format!("invalid release URL {}", url))?;
+    let listed = fetch_published(&format!("{}/SHA256SUMS", base))?
+        .and_then(|sums| parse_sums(&sums).into_iter().find(|(_, listed)| listed == name))
+        .map(|(hash, _)| hash);
+    if listed.is_some() {
+        return Ok(listed);
+    }
+    // `<hex>` alone or `<hex>  <name>`
+    let Some(single) = fetch_published(&format!("{}.sha256", url))? else {
+        return Ok(None);
+    };
+    let hash = single.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("{}.sha256 is not a SHA-256 digest", url));
+    }
+    Ok(Some(hash))
+}
+
+/// The text at `url`, or `None` when the mirror says there is none. Other
+/// failures are tried once more, then returned.
+fn fetch_published(url: &str) -> Result<Option<String>, String> {
+    let mut attempt = 1;
+    loop {
+        let failure = match http::get(url, &[]) {
+            Ok(response) if response.is_success() => return Ok(Some(response.body)),
+            Ok(response) if matches!(response.status, 404 | 410) => return Ok(None),
+            Ok(response) => format!("GET {} failed: HTTP {}", url, response.status),
+            Err(e) => e,
+        };
+        if attempt == 2 {
+            return Err(format!("cannot fetch the release's checksums: {}", failure));
+        }
+        attempt += 1;
+    }
+}
+
+/// Check a downloaded file against its expected digest.
+pub fn verify(path: &Path, expected: &str) -> Result<(), String> {
+    let actual = sha256_file(path).map_err(|e| format!("cannot hash {}: {}", path.display(), e))?;
+    if actual != expected {
+        return Err(format!(
+            "checksum mismatch for {}: expected sha256 {}, got {}",
+            path.display(),
+            expected,
+            actual
+        ));
+    }
+    Ok(())
+}
+
+/// Per-chunk SHA-256 digests published next to a release archive as
+/// `<asset>.chunks`: a `chunk-size <bytes>` line, then one hex digest per
+/// chunk in order.
+pub struct ChunkManifest {
+    chunk_size: u64,
+    digests: Vec<String>,
+}
+
+impl ChunkManifest {
+    pub fn parse(contents: &str) -> Result<ChunkManifest, String> {
+        let mut lines = contents.lines().map(str::trim).filter(|line| !line.is_empty());
+        let chunk_size = lines
+            .next()
+            .and_then(|line| line.strip_prefix("chunk-size "))
+            .and_then(|size| size.trim().parse::<u64>().ok())
+            .filter(|&size| size > 0)
+            .ok_or("chunk manifest has no chunk-size line")?;
+        
+        let digests: Vec<String> = lines.map(str::to_ascii_lowercase).collect();
+        if let Some(bad) = digests.iter().find(|d| d.len() != 64 || !d.bytes().all(|b| b.is_ascii_hexdigit())) {
+            return Err(format!("invalid digest in chunk manifest: {}", bad));
+        }
+        
+        Ok(ChunkManifest { chunk_size, digests })
+    }
+    
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+    
+    pub fn count(&self) -> usize {
+        self.digests.len()
+    }
+    
+    /// First and last byte of chunk `i`; the last chunk's end is left open,
+    /// as the manifest does not say how short it is.
+    pub fn range(&self, i: usize) -> (u64, Option<u64>) {
+        let start = i as u64 * self.chunk_size;
+        let end = (i + 1 < self.digests.len()).then(|| start + self.chunk_size - 1);
+        (start, end)
+    }
+    
+    /// Whether `data` is chunk `i`.
+    pub fn matches(&self, i: usize, data: &[u8]) -> bool {
+        self.digests.get(i).is_some_and(|digest| format!("{:x}", Sha256::digest(data)) == *digest)
+    }
+    
+    /// Length of the leading run of whole chunks in `path` that match the
+    /// manifest. A trailing partial chunk cannot be checked and is not counted.
+    pub fn verified_prefix(&self, path: &Path) -> io::Result<u64> {
+        let mut file = File::open(path)?;
+        let mut buf = vec![0u8; self.chunk_size as usize];
+        let mut verified = 0;
+        
+        for digest in &self.digests {
+            if read_full(&mut file, &mut buf)? < buf.len() {
+                break;
+            }
+            if format!("{:x}", Sha256::digest(&buf)) != *digest {
+                break;
+            }
+            verified += self.chunk_size;
+        }
+        
+        Ok(verified)
+    }
+}
+
+/// Fill `buf` unless EOF comes first; returns the bytes read.
+fn read_full(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn keeps_chunks_up_to_the_first_corrupt_one() {
+        let data: Vec<u8> = (0..10u8).collect();
+        let digests: Vec<String> = data.chunks(4).map(|c| format!("{:x}", Sha256::digest(c))).collect();
+        let manifest = ChunkManifest::parse(&format!("chunk-size 4\n{}\n", digests.join("\n"))).unwrap();
+        
+        let path = std::env::temp_dir().join(format!("bldr-chunks-{}", std::process::id()));
+        std::fs::write(&path, &data[..9]).unwrap();
+        assert_eq!(manifest.verified_prefix(&path).unwrap(), 8);
+        
+        let mut corrupt = data.clone();
+        corrupt[5] ^= 0xff;
+        std::fs::write(&path, &corrupt).unwrap();
+        assert_eq!(manifest.verified_prefix(&path).unwrap(), 4);
+        std::fs::remove_file(&path).ok();
+        
+        assert_eq!(manifest.range(1), (4, Some(7)));
+        assert_eq!(manifest.range(2), (8, None));
+        assert!(manifest.matches(2, &data[8..]));
+        assert!(!manifest.matches(2, &data[7..]));
+        
+        assert!(ChunkManifest::parse("deadbeef\n").is_err());
+        assert!(ChunkManifest::parse("chunk-size 4\nnot-a-digest\n").is_err());
+    }
+    
+    #[test]
+    fn checks_published_digests() {
+        let dir = std::env::temp_dir().join(format!("bldr-sums-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("bldr-linux-amd64.tar.gz");
+        std::fs::write(&archive, "release").unwrap();
+        let digest = sha256_file(&archive).unwrap();
+        std::fs::write(dir.join("SHA256SUMS"), format!("{}  bldr-linux-amd64.tar.gz\n", digest)).unwrap();
+        std::fs::write(dir.join("bldr-darwin-arm64.tar.gz.sha256"), format!("{}\n", "b".repeat(64))).unwrap();
+        
+        let url = |name: &str| format!("file://{}/{}", dir.display(), name);
+        assert_eq!(published(&url("bldr-linux-amd64.tar.gz")), Ok(Some(digest.clone())));
+        assert_eq!(published(&url("bldr-darwin-arm64.tar.gz")), Ok(Some("b".repeat(64))));
+        assert_eq!(published(&url("bldr-windows-amd64.tar.gz")), Ok(None));
+        
+        // Unreadable, unlike missing, is not "nothing published"
+        let broken = dir.join("broken");
+        std::fs::create_dir_all(broken.join("SHA256SUMS")).unwrap();
+        assert!(published(&format!("file://{}/bldr-linux-amd64.tar.gz", broken.display())).is_err());
+        
+        assert!(verify(&archive, &digest).is_ok());
+        std::fs::write(&archive, "tampered").unwrap();
+        assert!(verify(&archive, &digest).unwrap_err().contains("checksum mismatch"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+    
+    #[test]
+    fn pins_only_the_embedded_version() {
+        let hash = "c".repeat(64);
+        let pins = format!("# written at release time\nversion 2.0.4\n{}  bldr-linux-amd64.tar.gz\n", hash);
+        assert_eq!(pinned(&pins, "2.0.4", "bldr-linux-amd64.tar.gz"), Some(hash));
+        assert_eq!(pinned(&pins, "2.0.4", "bldr-darwin-arm64.tar.gz"), None);
+        assert_eq!(pinned(&pins, "2.0.3", "bldr-linux-amd64.tar.gz"), None);
+        
+        assert_eq!(pinned_for(&pins, "2.0.4", "bldr-linux-amd64.tar.gz"), Some(Ok(Some("c".repeat(64)))));
+        assert!(pinned_for(&pins, "2.0.4", "bldr-darwin-arm64.tar.gz").unwrap().unwrap_err().contains("not among"));
+        assert_eq!(pinned_for(&pins, "2.0.3", "bldr-linux-amd64.tar.gz"), None);
+    }
+    
+    #[test]
+    fn parses_text_and_binary_mode_entries() {
+        let hash = "a".repeat(64);
+        let sums = format!("{}  bldr-linux-amd64.tar.gz\n{} *SHA256SUMS.sig\n\ngarbage\n", hash, hash);
+        let parsed = parse_sums(&sums);
+        
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].1, "bldr-linux-amd64.tar.gz");
+        assert_eq!(parsed[1].1, "SHA256SUMS.sig");
+    }
+}
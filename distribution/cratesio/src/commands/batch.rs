@@ -0,0 +1,258 @@
+//! `bldr wrapper batch`: run many core commands through one warm process.
+//!
+//! Commands are read from stdin, one per line: a JSON array of arguments
+//! (`["build", "//app:lib"]`), a JSON object with `args` and an optional
+//! `name`, or plain words with shell-style quoting (`build //app:lib`).
+//! The version is resolved once and the commands are streamed to a single
+//! `bldr __batch` session; each command's output is relayed as it runs and
+//! its exit code is reported at the end. Cores without the batch protocol
+//! get one process per command instead. When a build daemon is listening
+//! (`bldr wrapper daemon`), its warm sessions run the commands instead.
+
+use super::daemon;
+use crate::argfile::split_words;
+use crate::{channel, pin, policy, version};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, PipeReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// First line a core writes when it accepts a batch session.
+const HEADER: &str = ":bldr-batch 1";
+/// Written after each command's output, followed by its exit code.
+pub(super) const MARKER: &str = ":bldr-batch-exit ";
+
+#[derive(Deserialize)]
+struct Request {
+    args: Vec<String>,
+    name: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Outcome {
+    name: String,
+    code: i32,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let mut json = false;
+    let mut fail_fast = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--fail-fast" => fail_fast = true,
+            other => {
+                eprintln!("bldr wrapper batch: unexpected argument '{}'", other);
+                eprintln!("Usage: bldr wrapper batch [--fail-fast] [--json] < commands");
+                return 2;
+            }
+        }
+    }
+    
+    let mut commands = Vec::new();
+    for (number, line) in io::stdin().lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("bldr wrapper batch: cannot read commands: {}", e);
+                return 1;
+            }
+        };
+        match parse_line(&line) {
+            Ok(Some(command)) => commands.push(command),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("bldr wrapper batch: line {}: {}", number + 1, e);
+                return 2;
+            }
+        }
+    }
+    
+    let binary = match resolve_binary() {
+        Ok(binary) => binary,
+        Err(e) => {
+            eprintln!("bldr wrapper batch: {}", e);
+            return 1;
+        }
+    };
+    
+    let mut daemon = daemon::connect();
+    let mut session: Option<Session> = None;
+    let mut batch_supported = true;
+    let mut outcomes = Vec::new();
+    for (name, args) in commands {
+        if let Some(client) = daemon.as_mut() {
+            match client.run(&binary, &args, &mut io::stdout()) {
+                Some(code) => {
+                    outcomes.push(Outcome { name, code });
+                    if code != 0 && fail_fast {
+                        break;
+                    }
+                    continue;
+                }
+                None => {
+                    eprintln!("bldr wrapper batch: lost the build daemon; continuing without it");
+                    daemon = None;
+                }
+            }
+        }
+        
+        if session.is_none() && batch_supported {
+            session = Session::start(Command::new(&binary), false);
+            batch_supported = session.is_some();
+        }
+        let code = match session.as_mut() {
+            Some(active) => match active.run(&args, &mut io::stdout()) {
+                Some(code) => code,
+                // The core exited mid-command; the next one gets a new session
+                None => session.take().map(Session::finish).unwrap_or(1),
+            },
+            None => Command::new(&binary).args(&args).status().map(|s| s.code().unwrap_or(1)).unwrap_or(1),
+        };
+        
+        outcomes.push(Outcome { name, code });
+        if code != 0 && fail_fast {
+            break;
+        }
+    }
+    if let Some(session) = session {
+        session.finish();
+    }
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&outcomes).unwrap_or_default());
+    } else {
+        eprintln!();
+        for outcome in &outcomes {
+            let label = if outcome.code == 0 { "ok" } else { "FAIL" };
+            eprintln!("{:<5} {:>3}  {}", label, outcome.code, outcome.name);
+        }
+    }
+    
+    if outcomes.iter().all(|o| o.code == 0) {
+        0
+    } else {
+        1
+    }
+}
+
+/// `(display name, arguments)` for one input line; `None` for blanks and
+/// `#` comments.
+fn parse_line(line: &str) -> Result<Option<(String, Vec<String>)>, String> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Ok(None);
+    }
+    
+    let (name, args) = if trimmed.starts_with('[') {
+        let args: Vec<String> = serde_json::from_str(trimmed).map_err(|e| e.to_string())?;
+        (None, args)
+    } else if trimmed.starts_with('{') {
+        let request: Request = serde_json::from_str(trimmed).map_err(|e| e.to_string())?;
+        (request.name, request.args)
+    } else {
+        (None, split_words(trimmed)?)
+    };
+    
+    if args.is_empty() {
+        return Err("empty command".to_string());
+    }
+    let name = name.unwrap_or_else(|| args.join(" "));
+    Ok(Some((name, args)))
+}
+
+/// Resolve and fetch the pinned core (once for a whole batch).
+pub(super) fn resolve_binary() -> Result<PathBuf, String> {
+    let selector = pin::from_env().or_else(pin::selector).or_else(channel::selector).unwrap_or_default();
+    let resolved = version::resolve(&selector)?;
+    policy::current()?.check_version(&resolved.version)?;
+    crate::get_or_download_binary(&resolved.version, crate::consent::assumed(false))
+        .ok_or_else(|| format!("cannot install bldr {}", resolved.version))
+}
+
+pub(super) struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<PipeReader>,
+}
+
+impl Session {
+    /// Start `<core> __batch`, with its stderr in the relayed output if
+    /// `merge_stderr`; `None` when the core does not speak the protocol.
+    pub(super) fn start(mut core: Command, merge_stderr: bool) -> Option<Session> {
+        let (reader, writer) = io::pipe().ok()?;
+        if merge_stderr {
+            core.stderr(writer.try_clone().ok()?);
+        }
+        let mut child = core
+            .arg("__batch")
+            .stdin(Stdio::piped())
+            .stdout(writer)
+            .spawn()
+            .ok()?;
+        // The command holds copies of the pipe's write end; reads only see
+        // the end of the output once those are closed
+        drop(core);
+        let stdin = child.stdin.take()?;
+        let mut stdout = BufReader::new(reader);
+        
+        let mut header = String::new();
+        stdout.read_line(&mut header).ok();
+        if header.trim_end() != HEADER {
+            child.kill().ok();
+            child.wait().ok();
+            return None;
+        }
+        Some(Session { child, stdin, stdout })
+    }
+    
+    /// Run one command, relaying its output to `out`; `None` if the session
+    /// died.
+    pub(super) fn run(&mut self, args: &[String], out: &mut dyn Write) -> Option<i32> {
+        let request = serde_json::to_string(args).ok()?;
+        writeln!(self.stdin, "{}", request).ok()?;
+        self.stdin.flush().ok()?;
+        
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.stdout.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            // Output without a trailing newline runs into the marker
+            if let Some(at) = line.rfind(MARKER) {
+                out.write_all(&line.as_bytes()[..at]).ok();
+                out.flush().ok();
+                return line[at + MARKER.len()..].trim().parse().ok();
+            }
+            out.write_all(line.as_bytes()).ok();
+        }
+    }
+    
+    /// Close the session and return the process's exit code.
+    pub(super) fn finish(mut self) -> i32 {
+        drop(self.stdin);
+        self.child.wait().map(|s| s.code().unwrap_or(1)).unwrap_or(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_each_line_format() {
+        let words = |line: &str| parse_line(line).unwrap().map(|(_, args)| args);
+        assert_eq!(words(r#"["build", "//app:lib"]"#), Some(vec!["build".to_string(), "//app:lib".to_string()]));
+        assert_eq!(words(r#"query 'deps(//...)' --format="json""#), Some(vec![
+            "query".to_string(),
+            "deps(//...)".to_string(),
+            "--format=json".to_string(),
+        ]));
+        assert_eq!(words("  # comment"), None);
+        
+        let (name, args) = parse_line(r#"{"name": "lint", "args": ["test", "//lint"]}"#).unwrap().unwrap();
+        assert_eq!((name.as_str(), args.len()), ("lint", 2));
+        assert!(parse_line("build 'unterminated").is_err());
+    }
+}
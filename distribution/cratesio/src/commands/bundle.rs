@@ -0,0 +1,212 @@
+//! `bldr wrapper bundle [<version>...] [--platform <os/arch>...] [--output <file>]`:
+//! pack releases into one archive to carry into an air-gapped network.
+//!
+//! Each version (by default the one this directory runs) is downloaded and
+//! verified for each platform (by default this one) as on first run, then
+//! packed with a `SHA256SUMS` of the verified digests, whatever signatures
+//! the mirror publishes beside each archive, and a `bundle.json` describing
+//! the contents, into `bldr-offline.tar.gz` by default:
+//!
+//! ```text
+//! bundle.json
+//! v<version>/SHA256SUMS
+//! v<version>/bldr-<os>-<arch>.tar.gz (.asc, .minisig, .cosign.bundle)
+//! ```
+//!
+//! `bldr wrapper install --from-file bldr-offline.tar.gz` on the other side
+//! installs this host's archive of every version in it (or only
+//! `--version`'s), with the same checks as any local archive.
+
+use super::prefetch::{parse_platform, resolve};
+use super::{normalize, value};
+use crate::{cache, channel, checksum, http, perms, pin, platform, VERSION};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "Usage: bldr wrapper bundle [<version>...] [--platform <os/arch>...] [--output <file>]";
+const MANIFEST: &str = "bundle.json";
+/// Signatures published beside an archive, carried when the mirror has them.
+const SIGNATURES: &[&str] = &[".asc", ".minisig", ".cosign.bundle"];
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let mut versions = Vec::new();
+    let mut platforms = Vec::new();
+    let mut output = PathBuf::from("bldr-offline.tar.gz");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--platform" => value(&mut iter, arg).and_then(|v| parse_platform(&v)).map(|p| platforms.push(p)),
+            "--output" | "-o" => value(&mut iter, arg).map(|v| output = PathBuf::from(v)),
+            other if !other.starts_with('-') => {
+                versions.push(other.to_string());
+                Ok(())
+            }
+            other => Err(format!("unexpected argument '{}'", other)),
+        };
+        if let Err(e) = parsed {
+            eprintln!("bldr wrapper bundle: {}", e);
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    }
+    if versions.is_empty() {
+        let selector = pin::from_env().or_else(pin::selector).or_else(channel::selector).unwrap_or_default();
+        versions.push(selector.label().to_string());
+    }
+    if platforms.is_empty() {
+        platforms.push(platform::current());
+    }
+    
+    let staging = cache::root().join(format!(".bundle.tmp{}", std::process::id()));
+    let packed = collect(&staging, &versions, &platforms).and_then(|releases| pack(&staging, &output).map(|()| releases));
+    fs::remove_dir_all(&staging).ok();
+    match packed {
+        Ok(releases) => {
+            eprintln!("Bundled {} archive(s) into {}", releases, output.display());
+            eprintln!("Install on the other side with `bldr wrapper install --from-file {}`.", output.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("bldr wrapper bundle: {}", e);
+            1
+        }
+    }
+}
+
+/// Download each version for each platform into `staging`, laid out as in
+/// the bundle, and return how many archives there are.
+fn collect(staging: &Path, versions: &[String], platforms: &[(&str, &str)]) -> Result<usize, String> {
+    let mut releases = Vec::new();
+    for requested in versions {
+        let version = resolve(requested).map_err(|e| format!("cannot resolve {}: {}", requested, e))?;
+        let dir = staging.join(format!("v{}", version));
+        perms::create_dir_all(&dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        let mut sums = String::new();
+        for &(os, arch) in platforms {
+            eprintln!("Downloading bldr v{} for {}-{}...", version, os, arch);
+            let (source, archive) = crate::fetch_release(&dir, &version, &crate::platform_mirrors(&version, os, arch))
+                .ok_or_else(|| format!("cannot download bldr {} for {}-{}", version, os, arch))?;
+            // Downloads land as bldr.tar.gz; keep the release's own name
+            let name = source.rsplit('/').next().unwrap_or(&source).to_string();
+            let sha256 = checksum::sha256_file(&archive).map_err(|e| format!("cannot hash {}: {}", archive.display(), e))?;
+            fs::rename(&archive, dir.join(&name)).map_err(|e| format!("cannot move {} into place: {}", name, e))?;
+            for extension in SIGNATURES {
+                let signature = dir.join(format!("{}{}", name, extension));
+                if http::download(&format!("{}{}", source, extension), &signature).is_err() {
+                    fs::remove_file(&signature).ok();
+                }
+            }
+            sums.push_str(&format!("{}  {}\n", sha256, name));
+            releases.push(serde_json::json!({
+                "version": version,
+                "platform": format!("{}-{}", os, arch),
+                "asset": name,
+                "sha256": sha256,
+                "source": source,
+            }));
+        }
+        perms::write(&dir.join("SHA256SUMS"), sums).map_err(|e| format!("cannot write SHA256SUMS: {}", e))?;
+    }
+    
+    let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let manifest = serde_json::json!({
+        "format": 1,
+        "wrapper": VERSION,
+        "created": created,
+        "releases": releases,
+    });
+    let manifest = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    perms::write(&staging.join(MANIFEST), manifest + "\n").map_err(|e| format!("cannot write {}: {}", MANIFEST, e))?;
+    Ok(releases.len())
+}
+
+/// Write `staging` as a gzipped tarball at `output`, the manifest first so
+/// [`is_bundle`] finds it without reading the rest.
+fn pack(staging: &Path, output: &Path) -> Result<(), String> {
+    let partial = output.with_file_name(format!(".{}.partial", output.file_name().unwrap_or_default().to_string_lossy()));
+    let written = (|| {
+        let mut tarball = tar::Builder::new(GzEncoder::new(File::create(&partial)?, Compression::default()));
+        tarball.append_path_with_name(staging.join(MANIFEST), MANIFEST)?;
+        let mut dirs: Vec<_> = fs::read_dir(staging)?.flatten().filter(|entry| entry.path().is_dir()).map(|entry| entry.file_name()).collect();
+        dirs.sort();
+        for dir in dirs {
+            tarball.append_dir_all(&dir, staging.join(&dir))?;
+        }
+        tarball.into_inner()?.finish()?;
+        fs::rename(&partial, output)
+    })();
+    written.map_err(|e| {
+        fs::remove_file(&partial).ok();
+        format!("cannot write {}: {}", output.display(), e)
+    })
+}
+
+/// Whether `archive` is a bundle rather than a single release archive.
+pub(super) fn is_bundle(archive: &Path) -> bool {
+    let Ok(file) = File::open(archive) else {
+        return false;
+    };
+    let mut tarball = tar::Archive::new(GzDecoder::new(file));
+    let first = tarball.entries().ok().and_then(|mut entries| entries.next()?.ok());
+    first.is_some_and(|entry| entry.path().is_ok_and(|path| path == Path::new(MANIFEST)))
+}
+
+/// Install this platform's archive of each version in the bundle (only
+/// `version` when given) into the cache.
+pub(super) fn install(bundle: &Path, version: Option<&str>, force: bool) -> i32 {
+    let staging = cache::root().join(format!(".bundle.tmp{}", std::process::id()));
+    let installed = install_from(bundle, &staging, version, force);
+    fs::remove_dir_all(&staging).ok();
+    match installed {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("bldr wrapper install: {}", e);
+            1
+        }
+    }
+}
+
+fn install_from(bundle: &Path, staging: &Path, version: Option<&str>, force: bool) -> Result<(), String> {
+    crate::install::unpack(bundle, staging)?;
+    let manifest = fs::read_to_string(staging.join(MANIFEST)).map_err(|e| format!("cannot read {}: {}", MANIFEST, e))?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).map_err(|e| format!("invalid {}: {}", MANIFEST, e))?;
+    let (os, arch) = platform::current();
+    let host = format!("{}-{}", os, arch);
+    let releases = manifest["releases"].as_array().cloned().unwrap_or_default();
+    let wanted: Vec<(&str, &str)> = releases
+        .iter()
+        .filter(|release| release["platform"] == host.as_str())
+        .filter_map(|release| Some((release["version"].as_str()?, release["asset"].as_str()?)))
+        .filter(|(v, _)| version.is_none_or(|wanted| wanted == *v))
+        .collect();
+    if wanted.is_empty() {
+        let carried: Vec<String> = releases
+            .iter()
+            .filter_map(|release| Some(format!("{} for {}", release["version"].as_str()?, release["platform"].as_str()?)))
+            .collect();
+        let missing = match version {
+            Some(version) => format!("no bldr {} for {}", version, host),
+            None => format!("nothing for {}", host),
+        };
+        return Err(format!("{} carries {}, only {}", bundle.display(), missing, carried.join(", ")));
+    }
+    
+    for (version, asset) in wanted {
+        let _lock = cache::lock(version)
+            .map_err(|e| eprintln!("bldr wrapper install: warning: {}; installing without a lock", e))
+            .ok();
+        let binary = cache::root().join(version).join(crate::install::binary_name());
+        if binary.exists() && !force {
+            eprintln!("bldr v{} is already installed at {}; --force replaces it", version, binary.display());
+            continue;
+        }
+        let url = http::file_url(&staging.join(format!("v{}", version)).join(asset))?;
+        crate::install_release(version, &[(url, None)]).ok_or_else(|| format!("cannot install bldr {} from the bundle", version))?;
+    }
+    Ok(())
+}
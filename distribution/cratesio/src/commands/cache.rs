@@ -0,0 +1,449 @@
+//! `bldr wrapper cache stats`: the build cache at a glance. `cache dir`,
+//! `size`, `dedupe`, `clean` and `gc` instead manage the wrapper's own cache of
+//! installed core versions (see [`crate::gc`] for `gc`).
+//!
+//! The pinned core reports on its cache directory with `bldr cache stats`
+//! (JSON: entries and sizes per tier, age distributions, and hit, miss and
+//! eviction totals across builds, plus the remote server's view when
+//! `BUILDER_REMOTE_CACHE_URL` is set). This renders that report as tables so
+//! retention limits can be tuned from data; `--json` passes it through.
+
+use super::batch::resolve_binary;
+use super::select::human_size;
+use super::{normalize, value};
+use crate::{cache, dedupe, gc, install, version};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    cache_dir: String,
+    local: Local,
+    usage: Usage,
+    remote: Option<Remote>,
+}
+
+#[derive(Deserialize)]
+struct Local {
+    targets: Tier,
+    actions: Tier,
+    blobs: Blobs,
+}
+
+#[derive(Deserialize)]
+struct Tier {
+    entries: u64,
+    bytes: u64,
+    ages: Option<Ages>,
+    idle: Option<Ages>,
+}
+
+#[derive(Deserialize)]
+struct Blobs {
+    count: u64,
+    bytes: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Usage {
+    builds: u64,
+    hits: u64,
+    misses: u64,
+    hit_rate: f64,
+    remote_hits: u64,
+    remote_misses: u64,
+    remote_hit_rate: f64,
+    evictions: u64,
+    bytes_evicted: u64,
+    since: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct Remote {
+    url: String,
+    error: Option<String>,
+    entries: u64,
+    bytes: u64,
+    capacity: u64,
+    hits: u64,
+    misses: u64,
+    hit_rate: f64,
+    evictions: u64,
+    bytes_evicted: u64,
+    ages: Option<Ages>,
+}
+
+/// Entry counts per age bucket, as the core labels them.
+#[derive(Deserialize)]
+struct Ages {
+    #[serde(rename = "<1h")]
+    hour: u64,
+    #[serde(rename = "<1d")]
+    day: u64,
+    #[serde(rename = "<7d")]
+    week: u64,
+    #[serde(rename = "<30d")]
+    month: u64,
+    #[serde(rename = ">=30d")]
+    older: u64,
+}
+
+impl Ages {
+    fn buckets(&self) -> [(&'static str, u64); 5] {
+        [
+            ("<1h", self.hour),
+            ("<1d", self.day),
+            ("<7d", self.week),
+            ("<30d", self.month),
+            (">=30d", self.older),
+        ]
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    match args.first().map(String::as_str) {
+        Some("stats") => stats(&args[1..]),
+        Some("gc") => gc(&args[1..]),
+        Some("dir") if args.len() == 1 => {
+            println!("{}", cache::root().display());
+            0
+        }
+        Some("size") if args.len() == 1 => size(),
+        Some("dedupe") if args.len() == 1 => dedupe(),
+        Some("clean") => clean(&args[1..]),
+        _ => {
+            print_usage();
+            2
+        }
+    }
+}
+
+fn stats(args: &[String]) -> i32 {
+    
+    let mut json = false;
+    let mut cache_dir = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--cache-dir" => match value(&mut iter, arg) {
+                Ok(dir) => cache_dir = Some(dir),
+                Err(e) => {
+                    eprintln!("bldr wrapper cache: {}", e);
+                    return 2;
+                }
+            },
+            other => {
+                eprintln!("bldr wrapper cache: unexpected argument '{}'", other);
+                print_usage();
+                return 2;
+            }
+        }
+    }
+    
+    let output = match core_stats(cache_dir.as_deref()) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("bldr wrapper cache: {}", e);
+            return 1;
+        }
+    };
+    if json {
+        println!("{}", output.trim_end());
+        return 0;
+    }
+    
+    match serde_json::from_str::<Report>(&output) {
+        Ok(report) => {
+            print!("{}", render(&report));
+            0
+        }
+        Err(e) => {
+            eprintln!("bldr wrapper cache: unreadable statistics from the core: {}", e);
+            1
+        }
+    }
+}
+
+/// `bldr wrapper cache gc`: evict installed versions beyond the limits.
+fn gc(args: &[String]) -> i32 {
+    let mut limits = match gc::Limits::from_env() {
+        Ok(limits) => limits,
+        Err(e) => {
+            eprintln!("bldr wrapper cache: {}", e);
+            return 2;
+        }
+    };
+    let mut dry_run = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--keep" => value(&mut iter, arg).and_then(|v| gc::parse_keep(&v)).map(|keep| limits.keep = keep),
+            "--max-size" => value(&mut iter, arg).and_then(|v| gc::parse_size(&v)).map(|max| limits.max_size = Some(max)),
+            "--dry-run" => {
+                dry_run = true;
+                Ok(())
+            }
+            other => Err(format!("unexpected argument '{}'", other)),
+        };
+        if let Err(e) = parsed {
+            eprintln!("bldr wrapper cache gc: {}", e);
+            print_usage();
+            return 2;
+        }
+    }
+    
+    let removed = gc::collect(limits, &[], dry_run);
+    for (version, size) in &removed {
+        println!("{} {:<14} {:>9}", if dry_run { "would remove" } else { "removed" }, version, human_size(*size));
+    }
+    let freed: u64 = removed.iter().map(|(_, size)| size).sum();
+    eprintln!(
+        "{} {} version(s), {}",
+        if dry_run { "Would free" } else { "Freed" },
+        removed.len(),
+        human_size(freed)
+    );
+    0
+}
+
+/// `bldr wrapper cache size`: space taken per installed version.
+fn size() -> i32 {
+    let root = cache::root();
+    let mut versions: Vec<String> = cache::installed().into_iter().map(|(version, _)| version).collect();
+    versions.sort_by(|a, b| version::compare(b, a));
+    
+    let mut total = 0;
+    for version in &versions {
+        let bytes = cache::size(version);
+        total += bytes;
+        println!("{:<14} {:>9}", version, human_size(bytes));
+    }
+    // Release metadata, crash reports, profiles, staging
+    let other = directory_size(&root).saturating_sub(total);
+    println!("{:<14} {:>9}", "other", human_size(other));
+    println!("{:<14} {:>9}  {}", "total", human_size(total + other), root.display());
+    0
+}
+
+fn directory_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| match entry.file_type() {
+                    Ok(kind) if kind.is_dir() => directory_size(&entry.path()),
+                    Ok(kind) if kind.is_file() => entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+                    _ => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// `bldr wrapper cache dedupe`: link files identical across installed
+/// versions, as installs do unless `BLDR_NO_DEDUPE` is set.
+fn dedupe() -> i32 {
+    let mut versions: Vec<String> = cache::installed().into_iter().map(|(version, _)| version).collect();
+    versions.sort_by(|a, b| version::compare(a, b));
+    let mut total = 0;
+    for version in &versions {
+        let Some(_lock) = cache::try_lock(version) else {
+            eprintln!("Skipping bldr {}: another bldr process is installing it", version);
+            continue;
+        };
+        let saved = dedupe::version_against_others(version);
+        if saved > 0 {
+            println!("{:<14} {:>9} saved", version, human_size(saved));
+        }
+        total += saved;
+    }
+    eprintln!("Saved {} across {} version(s)", human_size(total), versions.len());
+    0
+}
+
+/// `bldr wrapper cache clean`: remove incomplete installs, one version
+/// (`--version`) or every version (`--all`).
+fn clean(args: &[String]) -> i32 {
+    let root = cache::root();
+    let targets: Vec<String> = match args {
+        [] => fs::read_dir(&root)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                    .filter(|name| matches!(version::Selector::parse(name), Some(version::Selector::Exact(_))))
+                    .filter(|name| !root.join(name).join(install::binary_name()).exists())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        [flag] if flag == "--all" => cache::installed().into_iter().map(|(version, _)| version).collect(),
+        [flag, version] if flag == "--version" => {
+            let version = version.trim_start_matches('v').to_string();
+            if !root.join(&version).is_dir() {
+                eprintln!("bldr wrapper cache clean: bldr {} is not in the cache", version);
+                return 1;
+            }
+            vec![version]
+        }
+        _ => {
+            print_usage();
+            return 2;
+        }
+    };
+    
+    let mut failed = false;
+    for version in targets {
+        // Not while another process installs or runs from it
+        let Some(_lock) = cache::try_lock(&version) else {
+            eprintln!("Skipping bldr {}: another bldr process is installing it", version);
+            continue;
+        };
+        let bytes = cache::size(&version);
+        match fs::remove_dir_all(root.join(&version)) {
+            Ok(()) => println!("removed {:<14} {:>9}", version, human_size(bytes)),
+            Err(e) => {
+                eprintln!("bldr wrapper cache clean: cannot remove {}: {}", root.join(&version).display(), e);
+                failed = true;
+            }
+        }
+    }
+    i32::from(failed)
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper cache stats [--json] [--cache-dir <dir>]");
+    eprintln!("       bldr wrapper cache dir | size | dedupe");
+    eprintln!("       bldr wrapper cache clean [--all | --version <v>]");
+    eprintln!("       bldr wrapper cache gc [--keep <n>] [--max-size <size>] [--dry-run]");
+    eprintln!();
+    eprintln!("stats: show entries, sizes, hit rates, evictions and entry ages for the build");
+    eprintln!("cache (default .builder-cache) and the remote cache server, if configured.");
+    eprintln!("dir, size: where installed core versions are cached, and the space each takes.");
+    eprintln!("dedupe: link files identical across installed versions to save space.");
+    eprintln!("clean: remove incomplete installs, one version or --all of them.");
+    eprintln!("gc: remove the least recently used core versions beyond --keep (default");
+    eprintln!("BLDR_CACHE_KEEP or 5) or --max-size (default BLDR_CACHE_MAX_SIZE).");
+}
+
+/// `bldr cache stats` from the pinned core.
+fn core_stats(cache_dir: Option<&str>) -> Result<String, String> {
+    let binary = resolve_binary()?;
+    let mut cmd = Command::new(&binary);
+    cmd.args(["cache", "stats"]);
+    if let Some(dir) = cache_dir {
+        cmd.args(["--cache-dir", dir]);
+    }
+    
+    let output = cmd.output().map_err(|e| format!("failed to run {}: {}", binary.display(), e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => "the core does not report cache statistics (update bldr)".to_string(),
+            message => message.trim_start_matches("Error: ").to_string(),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| "the core returned non-UTF-8 statistics".to_string())
+}
+
+fn render(report: &Report) -> String {
+    let mut out = String::new();
+    let usage = &report.usage;
+    let local = &report.local;
+    
+    let since = usage.since.as_deref().map(|s| format!(" since {}", &s[..s.len().min(10)])).unwrap_or_default();
+    writeln!(out, "Cache {} ({} builds recorded{})", report.cache_dir, usage.builds, since).ok();
+    writeln!(out).ok();
+    
+    writeln!(out, "Local").ok();
+    writeln!(out, "  {:<12} {:>10} entries  {:>10}", "Targets", local.targets.entries, human_size(local.targets.bytes)).ok();
+    writeln!(out, "  {:<12} {:>10} entries  {:>10}", "Actions", local.actions.entries, human_size(local.actions.bytes)).ok();
+    writeln!(out, "  {:<12} {:>10} blobs    {:>10}", "Blobs", local.blobs.count, human_size(local.blobs.bytes)).ok();
+    writeln!(out, "  {:<12} {:>9.1}%  ({} hits, {} misses)", "Hit rate", usage.hit_rate, usage.hits, usage.misses).ok();
+    if usage.remote_hits + usage.remote_misses > 0 {
+        writeln!(
+            out,
+            "  {:<12} {:>9.1}%  ({} hits, {} misses)",
+            "Remote hits", usage.remote_hit_rate, usage.remote_hits, usage.remote_misses
+        )
+        .ok();
+    }
+    writeln!(out, "  {:<12} {:>10} entries  {:>10}", "Evicted", usage.evictions, human_size(usage.bytes_evicted)).ok();
+    
+    if let (Some(ages), Some(idle)) = (&local.targets.ages, &local.targets.idle) {
+        writeln!(out).ok();
+        writeln!(out, "  {:<12} {:>10} {:>10}", "Target age", "written", "last used").ok();
+        for ((label, written), (_, used)) in ages.buckets().into_iter().zip(idle.buckets()) {
+            writeln!(out, "  {:<12} {:>10} {:>10}", label, written, used).ok();
+        }
+    }
+    
+    let Some(remote) = &report.remote else {
+        return out;
+    };
+    writeln!(out).ok();
+    writeln!(out, "Remote {}", remote.url).ok();
+    if let Some(error) = &remote.error {
+        writeln!(out, "  unavailable: {}", error).ok();
+        return out;
+    }
+    
+    let used = if remote.capacity > 0 {
+        format!(" of {} ({:.0}%)", human_size(remote.capacity), remote.bytes as f64 * 100.0 / remote.capacity as f64)
+    } else {
+        String::new()
+    };
+    writeln!(out, "  {:<12} {:>10} entries  {:>10}{}", "Stored", remote.entries, human_size(remote.bytes), used).ok();
+    writeln!(out, "  {:<12} {:>9.1}%  ({} hits, {} misses)", "Hit rate", remote.hit_rate, remote.hits, remote.misses).ok();
+    writeln!(out, "  {:<12} {:>10} entries  {:>10}", "Evicted", remote.evictions, human_size(remote.bytes_evicted)).ok();
+    if let Some(ages) = &remote.ages {
+        writeln!(out).ok();
+        writeln!(out, "  {:<12} {:>10}", "Stored age", "entries").ok();
+        for (label, count) in ages.buckets() {
+            writeln!(out, "  {:<12} {:>10}", label, count).ok();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn renders_local_and_unreachable_remote() {
+        let json = r#"{
+            "cacheDir": ".builder-cache",
+            "local": {
+                "targets": {
+                    "entries": 12, "bytes": 6144,
+                    "ages": {"<1h": 2, "<1d": 4, "<7d": 5, "<30d": 1, ">=30d": 0},
+                    "idle": {"<1h": 10, "<1d": 2, "<7d": 0, "<30d": 0, ">=30d": 0}
+                },
+                "actions": {"entries": 40, "bytes": 20480},
+                "blobs": {"count": 30, "bytes": 1048576, "deduplicationRatio": 100}
+            },
+            "usage": {
+                "builds": 3, "hits": 30, "misses": 6, "hitRate": 83.33,
+                "remoteHits": 0, "remoteMisses": 0, "remoteHitRate": 0,
+                "evictions": 2, "bytesEvicted": 1024, "since": "2026-10-01T09:00:00.123Z"
+            },
+            "remote": {"url": "http://cache:8080", "error": "Connection refused"}
+        }"#;
+        let rendered = render(&serde_json::from_str(json).unwrap());
+        
+        assert!(rendered.starts_with("Cache .builder-cache (3 builds recorded since 2026-10-01)\n"));
+        assert!(rendered.contains("  Hit rate          83.3%  (30 hits, 6 misses)\n"));
+        assert!(rendered.contains("  Evicted               2 entries      1.0 KB\n"));
+        assert!(rendered.contains("  <7d                   5          0\n"));
+        assert!(!rendered.contains("Remote hits"));
+        assert!(rendered.ends_with("Remote http://cache:8080\n  unavailable: Connection refused\n"));
+    }
+}
@@ -0,0 +1,219 @@
+//! `bldr wrapper ci init`: generate the recommended CI workflow.
+//!
+//! The workflow installs this wrapper version, caches the downloaded core
+//! versions and the build cache keyed on `.bldr-version` (so changing the
+//! pin starts a fresh cache), builds and tests through the wrapper, which
+//! picks the pinned version up by itself, and uploads the build outputs.
+
+use super::{normalize, value};
+use crate::pin;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_ARTIFACTS: &str = "bin/";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Provider {
+    Github,
+    Gitlab,
+}
+
+impl Provider {
+    fn parse(name: &str) -> Option<Provider> {
+        match name {
+            "github" => Some(Provider::Github),
+            "gitlab" => Some(Provider::Gitlab),
+            _ => None,
+        }
+    }
+    
+    fn default_path(self) -> &'static str {
+        match self {
+            Provider::Github => ".github/workflows/bldr.yml",
+            Provider::Gitlab => ".gitlab-ci.yml",
+        }
+    }
+}
+
+struct Options {
+    provider: Provider,
+    output: Option<PathBuf>,
+    artifacts: String,
+    force: bool,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    if args.first().map(String::as_str) != Some("init") {
+        print_usage();
+        return 2;
+    }
+    let opts = match parse_options(&args[1..]) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("bldr wrapper ci: {}", e);
+            print_usage();
+            return 2;
+        }
+    };
+    
+    let path = opts.output.clone().unwrap_or_else(|| PathBuf::from(opts.provider.default_path()));
+    if path.exists() && !opts.force {
+        eprintln!("bldr wrapper ci: {} already exists (use --force to overwrite)", path.display());
+        return 1;
+    }
+    if let Err(e) = write(&path, &render(opts.provider, &opts.artifacts)) {
+        eprintln!("bldr wrapper ci: cannot write {}: {}", path.display(), e);
+        return 1;
+    }
+    eprintln!("Wrote {}", path.display());
+    
+    if !Path::new(pin::PROJECT_FILE).is_file() {
+        eprintln!("No {} here: CI will use the latest release.", pin::PROJECT_FILE);
+        eprintln!("Pin the version you build with: bldr wrapper select");
+    }
+    0
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper ci init --provider <github|gitlab> [options]");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --output <file>      Where to write (default .github/workflows/bldr.yml or .gitlab-ci.yml)");
+    eprintln!("  --artifacts <path>   Build outputs to upload (default {})", DEFAULT_ARTIFACTS);
+    eprintln!("  --force              Overwrite an existing file");
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let args = normalize(args);
+    let mut provider = None;
+    let mut output = None;
+    let mut artifacts = DEFAULT_ARTIFACTS.to_string();
+    let mut force = false;
+    
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--provider" => {
+                let name = value(&mut iter, arg)?;
+                provider = Some(Provider::parse(&name).ok_or_else(|| format!("unsupported provider '{}'", name))?);
+            }
+            "--output" | "-o" => output = Some(PathBuf::from(value(&mut iter, arg)?)),
+            "--artifacts" => artifacts = value(&mut iter, arg)?,
+            "--force" => force = true,
+            other => return Err(format!("unexpected argument '{}'", other)),
+        }
+    }
+    
+    let provider = provider.ok_or("choose a provider with --provider github|gitlab")?;
+    Ok(Options { provider, output, artifacts, force })
+}
+
+fn write(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+fn render(provider: Provider, artifacts: &str) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    match provider {
+        Provider::Github => format!(
+            r#"# Generated by `bldr wrapper ci init --provider github`.
+# The wrapper builds with the version pinned in .bldr-version.
+name: bldr
+
+on:
+  push:
+    branches: [main]
+  pull_request:
+
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+
+      - name: Cache bldr
+        uses: actions/cache@v4
+        with:
+          path: |
+            ~/.cargo/bin/bldr
+            ~/.cache/bldr
+            .builder-cache
+          key: bldr-${{{{ runner.os }}}}-${{{{ hashFiles('.bldr-version') }}}}-${{{{ github.sha }}}}
+          restore-keys: |
+            bldr-${{{{ runner.os }}}}-${{{{ hashFiles('.bldr-version') }}}}-
+
+      - name: Install the bldr wrapper
+        run: command -v bldr || cargo install bldr --locked --version {version}
+
+      - name: Build
+        run: bldr build
+
+      - name: Test
+        run: bldr test
+
+      - name: Upload build outputs
+        uses: actions/upload-artifact@v4
+        with:
+          name: bldr-outputs
+          path: {artifacts}
+          if-no-files-found: warn
+"#,
+            version = version,
+            artifacts = artifacts,
+        ),
+        Provider::Gitlab => format!(
+            r#"# Generated by `bldr wrapper ci init --provider gitlab`.
+# The wrapper builds with the version pinned in .bldr-version.
+bldr:
+  image: rust:latest
+  variables:
+    # GitLab only caches paths inside the project
+    CARGO_HOME: "$CI_PROJECT_DIR/.cargo"
+    XDG_CACHE_HOME: "$CI_PROJECT_DIR/.cache"
+  cache:
+    key:
+      prefix: bldr
+      files:
+        - .bldr-version
+    paths:
+      - .cargo/bin/bldr
+      - .cache/bldr
+      - .builder-cache
+  before_script:
+    - export PATH="$CARGO_HOME/bin:$PATH"
+    - command -v bldr || cargo install bldr --locked --version {version}
+  script:
+    - bldr build
+    - bldr test
+  artifacts:
+    paths:
+      - {artifacts}
+    expire_in: 1 week
+"#,
+            version = version,
+            artifacts = artifacts,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn renders_both_providers() {
+        let github = render(Provider::Github, "dist/");
+        assert!(github.contains("key: bldr-${{ runner.os }}-${{ hashFiles('.bldr-version') }}-${{ github.sha }}\n"));
+        assert!(github.contains(&format!("cargo install bldr --locked --version {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(github.contains("          path: dist/\n"));
+        
+        let gitlab = render(Provider::Gitlab, DEFAULT_ARTIFACTS);
+        assert!(gitlab.contains("      files:\n        - .bldr-version\n"));
+        assert!(gitlab.contains("    paths:\n      - bin/\n"));
+        assert!(parse_options(&["--provider=gitlab".to_string()]).unwrap().provider == Provider::Gitlab);
+        assert!(parse_options(&["--provider".to_string(), "jenkins".to_string()]).is_err());
+    }
+}
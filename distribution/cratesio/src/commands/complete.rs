@@ -0,0 +1,289 @@
+//! Shell completion that follows the pinned core version.
+//!
+//! `bldr wrapper completions <shell>` prints a script whose completer calls
+//! back into `bldr wrapper complete -- <words>` on every <TAB>. That merges
+//! the wrapper's own commands and flags with whatever the installed core
+//! reports through `bldr __complete <words>`, so completions always match the
+//! version that would actually run. Nothing is downloaded at completion time;
+//! if the core is not installed or predates the protocol, a fixed list of
+//! long-standing core commands is offered instead.
+
+use super::hook::{posix_quote, powershell_quote, Shell};
+use super::COMMANDS;
+use crate::{channel, pin, version};
+use std::env;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// First line of a core's `__complete` output.
+const HEADER: &str = ":bldr-complete 1";
+
+/// How long a <TAB> may wait on the core.
+const CHILD_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Flags the wrapper consumes before launching the core.
+const WRAPPER_FLAGS: &[(&str, &str)] = &[
+    ("--no-changelog", "Do not print release notes when the version moves"),
+    ("--print-binary-path", "Print the core binary's path, installing it if needed, instead of running it"),
+    ("--profile-child", "Run the build under the platform profiler"),
+    ("--yes", "Download the core on first use without asking"),
+];
+
+/// Core commands offered when the core cannot be asked.
+const FALLBACK: &[(&str, &str)] = &[
+    ("build", "Build all targets or a specific target"),
+    ("test", "Run test targets with reporting"),
+    ("watch", "Watch for changes and rebuild automatically"),
+    ("clean", "Remove build artifacts and cache"),
+    ("graph", "Visualize dependency graph"),
+    ("query", "Query targets and dependencies"),
+    ("init", "Initialize Builderfile with auto-detection"),
+    ("help", "Show detailed help for a command"),
+    ("version", "Show version information"),
+];
+
+type Candidates = Vec<(String, String)>;
+
+/// `bldr wrapper completions <shell>`
+pub fn script(args: &[String]) -> i32 {
+    let Some(shell) = args.first().and_then(|name| Shell::parse(name)) else {
+        eprintln!("Usage: bldr wrapper completions <bash|zsh|fish|powershell>");
+        return 2;
+    };
+    let exe = match env::current_exe() {
+        Ok(exe) => exe.display().to_string(),
+        Err(e) => {
+            eprintln!("bldr wrapper completions: cannot locate the bldr executable: {}", e);
+            return 1;
+        }
+    };
+    print!("{}", render_script(shell, &exe));
+    0
+}
+
+/// `bldr wrapper complete [--new-word] -- <command> <words...> <current>`
+///
+/// `--new-word` stands in for an empty current word, for shells that cannot
+/// pass empty arguments to native commands.
+pub fn run(args: &[String]) -> i32 {
+    let (new_word, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "--new-word" => (true, rest),
+        _ => (false, args),
+    };
+    let mut words = match args.split_first() {
+        Some((separator, rest)) if separator == "--" => rest.to_vec(),
+        _ => args.to_vec(),
+    };
+    if new_word {
+        words.push(String::new());
+    }
+    let words = words.as_slice();
+    if words.is_empty() {
+        return 0;
+    }
+    
+    let mut candidates = wrapper_candidates(words);
+    if !matches!(words.get(1).map(String::as_str), Some("wrapper" | "self")) {
+        let core = query_core(words).unwrap_or_else(|| fallback(words));
+        for (name, description) in core {
+            if !candidates.iter().any(|(n, _)| *n == name) {
+                candidates.push((name, description));
+            }
+        }
+    }
+    
+    for (name, description) in candidates {
+        println!("{}\t{}", name, description);
+    }
+    0
+}
+
+/// Candidates the wrapper itself owns. `words[0]` is the command name and the
+/// last word is the one being completed.
+fn wrapper_candidates(words: &[String]) -> Candidates {
+    let current = words.last().map(String::as_str).unwrap_or("");
+    let filter = |list: &[(&str, &str)]| -> Candidates {
+        list.iter()
+            .filter(|(name, _)| name.starts_with(current))
+            .map(|(name, description)| (name.to_string(), description.to_string()))
+            .collect()
+    };
+    
+    match words {
+        [_, _] if current.starts_with('-') => filter(WRAPPER_FLAGS),
+        [_, _] => filter(&[
+            ("wrapper", "Commands handled by the launcher itself"),
+            ("self", "Commands handled by the launcher itself"),
+        ]),
+        [_, w, _] if w == "wrapper" || w == "self" => {
+            let mut names: Vec<(&str, &str)> = COMMANDS
+                .iter()
+                .map(|(usage, description)| (usage.split(' ').next().unwrap_or(usage), *description))
+                .collect();
+            // `cache stats` and `cache gc` are one command
+            names.dedup_by_key(|(name, _)| *name);
+            filter(&names)
+        }
+        [_, w, command, _] if (w == "wrapper" || w == "self") && (command == "hook" || command == "completions") => {
+            filter(&[("bash", ""), ("zsh", ""), ("fish", ""), ("powershell", "")])
+        }
+        _ if current.starts_with('-') => filter(WRAPPER_FLAGS),
+        _ => Vec::new(),
+    }
+}
+
+/// Ask the installed core, bounded by `CHILD_TIMEOUT`.
+fn query_core(words: &[String]) -> Option<Candidates> {
+    let selector = version::Selector::from_argv0(&words[0])
+        .or_else(pin::from_env)
+        .or_else(pin::selector)
+        .or_else(channel::selector)
+        .unwrap_or_default();
+    let binary = version::installed_binary(&selector)?;
+    
+    let output = run_bounded(&binary, &words[1..])?;
+    parse_core(&output)
+}
+
+fn run_bounded(binary: &Path, words: &[String]) -> Option<String> {
+    let mut child = Command::new(binary)
+        .arg("__complete")
+        .args(words)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < CHILD_TIMEOUT => thread::sleep(Duration::from_millis(5)),
+            _ => {
+                child.kill().ok();
+                child.wait().ok();
+                return None;
+            }
+        }
+    }
+    
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    Some(output)
+}
+
+/// Candidates from `__complete` output, or `None` when the core answered
+/// with something other than the protocol (an older release printing help).
+fn parse_core(output: &str) -> Option<Candidates> {
+    let mut lines = output.lines();
+    if lines.next()?.trim_end() != HEADER {
+        return None;
+    }
+    Some(
+        lines
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (name, description) = line.split_once('\t').unwrap_or((line, ""));
+                (name.to_string(), description.to_string())
+            })
+            .collect(),
+    )
+}
+
+fn fallback(words: &[String]) -> Candidates {
+    let current = words.last().map(String::as_str).unwrap_or("");
+    if words.len() != 2 || current.starts_with('-') {
+        return Vec::new();
+    }
+    FALLBACK
+        .iter()
+        .filter(|(name, _)| name.starts_with(current))
+        .map(|(name, description)| (name.to_string(), description.to_string()))
+        .collect()
+}
+
+pub(super) fn render_script(shell: Shell, exe: &str) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"_bldr_complete() {{
+  local IFS=$'\n'
+  COMPREPLY=($({exe} wrapper complete -- "${{COMP_WORDS[@]:0:COMP_CWORD+1}}" 2>/dev/null | cut -f1))
+}}
+complete -o default -F _bldr_complete bldr
+"#,
+            exe = posix_quote(exe)
+        ),
+        Shell::Zsh => format!(
+            r#"_bldr_complete() {{
+  local -a candidates
+  local line
+  for line in "${{(@f)$({exe} wrapper complete -- "${{(@)words[1,CURRENT]}}" 2>/dev/null)}}"; do
+    [[ -n $line ]] || continue
+    candidates+=("${{${{line%%$'\t'*}}//:/\\:}}:${{line#*$'\t'}}")
+  done
+  _describe 'bldr' candidates || _files
+}}
+compdef _bldr_complete bldr
+"#,
+            exe = posix_quote(exe)
+        ),
+        Shell::Fish => format!(
+            r#"function __bldr_complete
+    {exe} wrapper complete -- (commandline -opc) (commandline -ct) 2>/dev/null
+end
+complete -c bldr -a '(__bldr_complete)'
+"#,
+            exe = posix_quote(exe)
+        ),
+        Shell::Powershell => format!(
+            r#"Register-ArgumentCompleter -Native -CommandName bldr -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | Where-Object {{ $_.Extent.EndOffset -le $cursorPosition }} | ForEach-Object {{ $_.ToString() }})
+    $flags = @(if ($wordToComplete -eq '') {{ '--new-word' }})
+    & {exe} wrapper complete @flags -- @words 2>$null | ForEach-Object {{
+        $name, $description = $_ -split "`t", 2
+        if (-not $description) {{ $description = $name }}
+        [System.Management.Automation.CompletionResult]::new($name, $name, 'ParameterValue', $description)
+    }}
+}}
+"#,
+            exe = powershell_quote(exe)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn words(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+    
+    #[test]
+    fn offers_wrapper_commands_and_flags() {
+        let names = |c: Candidates| c.into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        assert_eq!(names(wrapper_candidates(&words(&["bldr", "w"]))), ["wrapper"]);
+        assert_eq!(names(wrapper_candidates(&words(&["bldr", "wrapper", "se"]))), ["select", "selftest"]);
+        assert_eq!(names(wrapper_candidates(&words(&["bldr", "self", "u"]))), ["uninstall", "update", "use", "updater"]);
+        assert_eq!(names(wrapper_candidates(&words(&["bldr", "wrapper", "ca"]))), ["cache"]);
+        assert_eq!(names(wrapper_candidates(&words(&["bldr", "build", "--no"]))), ["--no-changelog"]);
+        assert_eq!(names(wrapper_candidates(&words(&["bldr", "wrapper", "hook", "f"]))), ["fish"]);
+    }
+    
+    #[test]
+    fn ignores_cores_without_the_protocol() {
+        assert_eq!(parse_core("bldr - Mixed-Language Build System\n"), None);
+        assert_eq!(
+            parse_core(":bldr-complete 1\nbuild\tBuild targets\ngraph\n"),
+            Some(vec![
+                ("build".to_string(), "Build targets".to_string()),
+                ("graph".to_string(), String::new()),
+            ])
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! `bldr wrapper config [path]`: the settings in effect, from the user's
+//! `wrapper.toml`, the project's `.bldr-wrapper.toml` and the environment,
+//! in that order of precedence reversed (see [`crate::config`]).
+//!
+//! Every key is listed with the variable it stands for, so the output also
+//! serves as the list of what the files may say. `path` prints where the
+//! user's file goes, for `$EDITOR "$(bldr wrapper config path)"`.
+
+use crate::config::{self, Scope, SETTINGS};
+use std::env;
+
+pub fn run(args: &[String]) -> i32 {
+    match args.first().map(String::as_str) {
+        None => show(),
+        Some("path") if args.len() == 1 => match config::user_path() {
+            Some(path) => {
+                println!("{}", path.display());
+                0
+            }
+            None => {
+                eprintln!("bldr wrapper config: cannot determine the config directory");
+                1
+            }
+        },
+        _ => {
+            eprintln!("Usage: bldr wrapper config [path]");
+            2
+        }
+    }
+}
+
+fn show() -> i32 {
+    let describe = |path: Option<std::path::PathBuf>| match path {
+        Some(path) if path.is_file() => path.display().to_string(),
+        Some(path) => format!("{} (none)", path.display()),
+        None => "(none)".to_string(),
+    };
+    println!("user     {}", describe(config::user_path()));
+    println!("project  {}", describe(config::project_path()));
+    println!();
+    for setting in SETTINGS {
+        let scope = if setting.scope == Scope::User { " (user only)" } else { "" };
+        match env::var(setting.var) {
+            Ok(value) => {
+                let origin = config::origin(setting.var).map(|path| path.display().to_string()).unwrap_or_else(|| "environment".to_string());
+                println!("{:<28} {}={}  from {}", setting.key, setting.var, value, origin);
+            }
+            Err(_) => println!("{:<28} {}{}", setting.key, setting.var, scope),
+        }
+    }
+    0
+}
@@ -0,0 +1,644 @@
+//! `bldr wrapper daemon`: keep warm core sessions across invocations.
+//!
+//! `serve` listens locally and runs each request in a `bldr __batch` session
+//! kept per core binary and working directory, so repeated builds skip
+//! process start-up and keep the core's in-memory caches. Clients send one
+//! JSON line per command (`{"binary", "cwd", "args"}`) and read the output
+//! back, ended by the batch protocol's exit marker. `bldr wrapper batch`
+//! uses a listening daemon automatically (`BLDR_DAEMON=0` opts out).
+//!
+//! On Unix the daemon listens on a socket only its user may open, and
+//! checks each client's credentials besides. Under systemd it is activated:
+//! `enable` installs a user `bldr-daemon.socket`, systemd starts `serve` on
+//! the first connection and hands it the listening socket (`LISTEN_FDS`), and
+//! the daemon exits after `--idle` seconds without clients while systemd
+//! keeps the socket open for the next one. Without systemd, `serve` binds the
+//! socket itself.
+//!
+//! Elsewhere it listens on a loopback port. The port and a token drawn at
+//! start-up go in an endpoint file only its owner can read, and clients must
+//! present the token first. On Windows, `daemon service` runs the daemon as a
+//! service so it survives logoff.
+
+use super::batch::{Session, MARKER};
+use super::{normalize, value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_IDLE_SECS: u64 = 600;
+/// Name of the systemd units and of the Windows service.
+pub(super) const UNIT: &str = "bldr-daemon";
+
+/// Set to make `serve` shut down (the Windows service's stop control).
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Where clients find the daemon: `BLDR_DAEMON_SOCKET`, else `daemon.sock`
+/// (Unix) or `daemon.endpoint` in `$XDG_RUNTIME_DIR/bldr`, where the systemd
+/// socket unit puts it, or else in the wrapper's cache directory.
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = env::var_os("BLDR_DAEMON_SOCKET") {
+        return PathBuf::from(path);
+    }
+    dirs::runtime_dir()
+        .map(|dir| dir.join("bldr"))
+        .unwrap_or_else(crate::cache::root)
+        .join(transport::FILE_NAME)
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let result = match args.first().map(String::as_str) {
+        Some("serve") => parse_idle(&args[1..]).and_then(serve),
+        Some("enable") => parse_idle(&args[1..]).and_then(enable),
+        Some("disable") => disable(),
+        Some("service") => service(&args[1..]),
+        Some("status") => {
+            let listening = connect().is_some();
+            println!("endpoint:  {}", socket_path().display());
+            println!("listening: {}", if listening { "yes" } else { "no" });
+            Ok(())
+        }
+        _ => {
+            print_usage();
+            return 2;
+        }
+    };
+    
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("bldr wrapper daemon: {}", e);
+            1
+        }
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper daemon <command>");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  serve [--idle SECS]    Run the daemon in the foreground (exits after SECS idle, default {}; 0 never)", DEFAULT_IDLE_SECS);
+    eprintln!("  enable [--idle SECS]   Install a systemd user socket that starts the daemon on demand");
+    eprintln!("  disable                Remove the systemd units");
+    eprintln!("  service <command>      Manage the Windows service (install, uninstall, start, stop)");
+    eprintln!("  status                 Show whether a daemon is listening");
+}
+
+fn parse_idle(args: &[String]) -> Result<u64, String> {
+    let mut idle = DEFAULT_IDLE_SECS;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--idle" => {
+                idle = value(&mut iter, arg)?
+                    .parse()
+                    .map_err(|_| "--idle takes a number of seconds".to_string())?
+            }
+            other => return Err(format!("unexpected argument '{}'", other)),
+        }
+    }
+    Ok(idle)
+}
+
+#[cfg(unix)]
+fn enable(idle: u64) -> Result<(), String> {
+    systemd::enable(idle)
+}
+
+#[cfg(not(unix))]
+fn enable(_idle: u64) -> Result<(), String> {
+    Err("on-demand start needs systemd; use `bldr wrapper daemon service install` on Windows".to_string())
+}
+
+#[cfg(unix)]
+pub(super) fn disable() -> Result<(), String> {
+    systemd::disable()
+}
+
+#[cfg(not(unix))]
+pub(super) fn disable() -> Result<(), String> {
+    Err("nothing to disable; use `bldr wrapper daemon service uninstall` on Windows".to_string())
+}
+
+/// Whether socket activation is set up.
+#[cfg(unix)]
+pub(super) fn enabled() -> bool {
+    systemd::enabled()
+}
+
+#[cfg(not(unix))]
+pub(super) fn enabled() -> bool {
+    false
+}
+
+#[cfg(windows)]
+fn service(args: &[String]) -> Result<(), String> {
+    super::daemon_service::run(args)
+}
+
+#[cfg(not(windows))]
+fn service(_args: &[String]) -> Result<(), String> {
+    Err("services are a Windows feature; use `bldr wrapper daemon enable` for on-demand start".to_string())
+}
+
+/// Ask a running `serve` to shut down.
+#[cfg(windows)]
+pub(super) fn stop() {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+/// Report a problem the daemon cannot pass to a client.
+fn warn(message: &str) {
+    eprintln!("bldr daemon: {}", message);
+    #[cfg(windows)]
+    super::daemon_service::report_error(message);
+}
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    binary: PathBuf,
+    cwd: PathBuf,
+    args: Vec<String>,
+}
+
+/// Connection to a running (or socket-activated) daemon.
+pub struct Client {
+    stream: transport::Stream,
+    reader: BufReader<transport::Stream>,
+}
+
+impl Client {
+    /// Run one command in the daemon, relaying its output to `out`; `None`
+    /// if the daemon went away.
+    pub fn run(&mut self, binary: &Path, args: &[String], out: &mut dyn Write) -> Option<i32> {
+        let request = Request {
+            binary: binary.to_path_buf(),
+            cwd: env::current_dir().ok()?,
+            args: args.to_vec(),
+        };
+        writeln!(self.stream, "{}", serde_json::to_string(&request).ok()?).ok()?;
+        
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            if let Some(at) = line.rfind(MARKER) {
+                out.write_all(&line.as_bytes()[..at]).ok();
+                out.flush().ok();
+                return line[at + MARKER.len()..].trim().parse().ok();
+            }
+            out.write_all(line.as_bytes()).ok();
+        }
+    }
+}
+
+/// `None` when no daemon is listening or `BLDR_DAEMON=0`.
+pub fn connect() -> Option<Client> {
+    if env::var("BLDR_DAEMON").is_ok_and(|v| v == "0") {
+        return None;
+    }
+    let stream = transport::dial(&socket_path())?;
+    let reader = BufReader::new(stream.try_clone().ok()?);
+    Some(Client { stream, reader })
+}
+
+/// Idle sessions by core binary and working directory.
+type Pool = Mutex<HashMap<(PathBuf, PathBuf), Vec<Session>>>;
+
+/// Serve until `idle` seconds pass without clients (never if 0) or [`stop`].
+pub(super) fn serve(idle: u64) -> Result<(), String> {
+    let listener = transport::Listener::open(&socket_path())?;
+    eprintln!("bldr daemon listening on {}", listener.describe());
+    
+    let pool: Arc<Pool> = Arc::default();
+    let clients = Arc::new(AtomicUsize::new(0));
+    let mut last_activity = Instant::now();
+    while !STOP.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok(stream) => {
+                last_activity = Instant::now();
+                clients.fetch_add(1, Ordering::SeqCst);
+                let (pool, clients) = (Arc::clone(&pool), Arc::clone(&clients));
+                thread::spawn(move || {
+                    handle(stream, &pool);
+                    clients.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if clients.load(Ordering::SeqCst) > 0 {
+                    last_activity = Instant::now();
+                } else if idle > 0 && last_activity.elapsed() >= Duration::from_secs(idle) {
+                    eprintln!("bldr daemon idle for {}s, exiting", idle);
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => {
+                warn(&format!("accept failed: {}", e));
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+    
+    let sessions = std::mem::take(&mut *pool.lock().unwrap_or_else(|e| e.into_inner()));
+    sessions.into_values().flatten().for_each(|session| {
+        session.finish();
+    });
+    listener.close();
+    Ok(())
+}
+
+fn handle(stream: transport::Stream, pool: &Pool) {
+    stream.set_nonblocking(false).ok();
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut out = stream;
+    let mut lines = BufReader::new(reader).lines();
+    if !transport::admit(&out, &mut lines) {
+        return;
+    }
+    for line in lines {
+        let Ok(line) = line else {
+            return;
+        };
+        let code = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => execute(&request, &mut out, pool),
+            Err(e) => {
+                warn(&format!("bad request: {}", e));
+                writeln!(out, "bldr daemon: bad request: {}", e).ok();
+                2
+            }
+        };
+        if writeln!(out, "{}{}", MARKER, code).is_err() {
+            return;
+        }
+    }
+}
+
+/// Run a request in a pooled session, starting one if none is idle.
+fn execute(request: &Request, out: &mut transport::Stream, pool: &Pool) -> i32 {
+    let key = (request.binary.clone(), request.cwd.clone());
+    let idle = pool.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&key).and_then(Vec::pop);
+    let Some(mut session) = idle.or_else(|| Session::start(core(request), true)) else {
+        // No batch protocol: one process per command
+        return match core(request).args(&request.args).output() {
+            Ok(output) => {
+                out.write_all(&output.stdout).ok();
+                out.write_all(&output.stderr).ok();
+                output.status.code().unwrap_or(1)
+            }
+            Err(e) => {
+                warn(&format!("cannot run {}: {}", request.binary.display(), e));
+                writeln!(out, "bldr daemon: cannot run {}: {}", request.binary.display(), e).ok();
+                1
+            }
+        };
+    };
+    
+    match session.run(&request.args, out) {
+        Some(code) => {
+            pool.lock().unwrap_or_else(|e| e.into_inner()).entry(key).or_default().push(session);
+            code
+        }
+        None => {
+            let code = session.finish();
+            warn(&format!("{} exited mid-command ({})", request.binary.display(), code));
+            code
+        }
+    }
+}
+
+/// The core, run from the client's directory. systemd's variables stay
+/// with us.
+fn core(request: &Request) -> Command {
+    let mut command = Command::new(&request.binary);
+    command
+        .current_dir(&request.cwd)
+        .env_remove("LISTEN_PID")
+        .env_remove("LISTEN_FDS")
+        .env_remove("LISTEN_FDNAMES");
+    command
+}
+
+#[cfg(unix)]
+mod transport {
+    use std::env;
+    use std::fs;
+    use std::io::{self, BufReader, Lines};
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    
+    pub const FILE_NAME: &str = "daemon.sock";
+    /// First descriptor systemd passes (`SD_LISTEN_FDS_START`).
+    const LISTEN_FDS_START: i32 = 3;
+    
+    pub type Stream = UnixStream;
+    
+    pub struct Listener {
+        inner: UnixListener,
+        path: PathBuf,
+        /// Handed over by systemd, which owns the socket file.
+        activated: bool,
+    }
+    
+    impl Listener {
+        pub fn open(path: &Path) -> Result<Listener, String> {
+            let (inner, activated) = match activated_listener() {
+                Some(inner) => (inner, true),
+                None => (bind(path)?, false),
+            };
+            inner.set_nonblocking(true).map_err(|e| e.to_string())?;
+            Ok(Listener { inner, path: path.to_path_buf(), activated })
+        }
+        
+        pub fn accept(&self) -> io::Result<Stream> {
+            self.inner.accept().map(|(stream, _)| stream)
+        }
+        
+        pub fn describe(&self) -> String {
+            let activated = if self.activated { " (socket activated)" } else { "" };
+            format!("{}{}", self.path.display(), activated)
+        }
+        
+        pub fn close(self) {
+            if !self.activated {
+                fs::remove_file(&self.path).ok();
+            }
+        }
+    }
+    
+    pub fn dial(path: &Path) -> Option<Stream> {
+        UnixStream::connect(path).ok()
+    }
+    
+    /// Only the user the daemon runs as: the socket is theirs alone, and
+    /// the peer's credentials are checked in case its directory is not.
+    pub fn admit(stream: &Stream, _lines: &mut Lines<BufReader<Stream>>) -> bool {
+        // SAFETY: getuid cannot fail
+        peer_uid(stream).is_some_and(|uid| uid == unsafe { libc::getuid() })
+    }
+    
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn peer_uid(stream: &Stream) -> Option<u32> {
+        let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        // SAFETY: the descriptor is an open Unix socket, and `cred` and
+        // `len` describe a buffer of the size SO_PEERCRED fills
+        let rc = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut cred as *mut libc::ucred).cast(),
+                &mut len,
+            )
+        };
+        (rc == 0).then_some(cred.uid)
+    }
+    
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn peer_uid(stream: &Stream) -> Option<u32> {
+        let (mut uid, mut gid) = (0, 0);
+        // SAFETY: the descriptor is an open Unix socket
+        let rc = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+        (rc == 0).then_some(uid)
+    }
+    
+    /// The socket systemd passed us, if this process was socket activated.
+    fn activated_listener() -> Option<UnixListener> {
+        let pid: u32 = env::var("LISTEN_PID").ok()?.parse().ok()?;
+        let fds: i32 = env::var("LISTEN_FDS").ok()?.parse().ok()?;
+        if pid != std::process::id() || fds < 1 {
+            return None;
+        }
+        // SAFETY: systemd hands this process fd 3 as an open listening
+        // socket, and nothing else in the process has taken ownership of it
+        Some(unsafe { UnixListener::from_raw_fd(LISTEN_FDS_START) })
+    }
+    
+    fn bind(path: &Path) -> Result<UnixListener, String> {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("a daemon is already listening on {}", path.display()));
+        }
+        // Left behind by a daemon that did not shut down cleanly
+        fs::remove_file(path).ok();
+        // Directories made here are the user's alone; the socket is too,
+        // whatever the directory it lands in allows
+        if let Some(dir) = path.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        }
+        let listener = UnixListener::bind(path).map_err(|e| format!("cannot listen on {}: {}", path.display(), e))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("cannot restrict {}: {}", path.display(), e))?;
+        Ok(listener)
+    }
+}
+
+#[cfg(not(unix))]
+mod transport {
+    use std::collections::hash_map::RandomState;
+    use std::fs;
+    use std::hash::BuildHasher;
+    use std::io::{self, BufReader, Lines, Write};
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    
+    pub const FILE_NAME: &str = "daemon.endpoint";
+    
+    /// Token clients must send first, drawn when the daemon starts.
+    static TOKEN: OnceLock<String> = OnceLock::new();
+    
+    pub type Stream = TcpStream;
+    
+    pub struct Listener {
+        inner: TcpListener,
+        path: PathBuf,
+        address: SocketAddr,
+    }
+    
+    impl Listener {
+        pub fn open(path: &Path) -> Result<Listener, String> {
+            if dial(path).is_some() {
+                return Err(format!("a daemon is already listening (see {})", path.display()));
+            }
+            let inner = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| format!("cannot listen: {}", e))?;
+            inner.set_nonblocking(true).map_err(|e| e.to_string())?;
+            let address = inner.local_addr().map_err(|e| e.to_string())?;
+            
+            // The file inherits the owner-only permissions of the user's
+            // profile, so only that user learns the token
+            let token = TOKEN.get_or_init(random_token);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+            }
+            fs::write(path, format!("{} {}\n", address, token))
+                .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+            Ok(Listener { inner, path: path.to_path_buf(), address })
+        }
+        
+        pub fn accept(&self) -> io::Result<Stream> {
+            self.inner.accept().map(|(stream, _)| stream)
+        }
+        
+        pub fn describe(&self) -> String {
+            format!("{} (endpoint {})", self.address, self.path.display())
+        }
+        
+        pub fn close(self) {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+    
+    pub fn dial(path: &Path) -> Option<Stream> {
+        let endpoint = fs::read_to_string(path).ok()?;
+        let (address, token) = endpoint.trim().split_once(' ')?;
+        let mut stream = TcpStream::connect_timeout(&address.parse().ok()?, Duration::from_secs(1)).ok()?;
+        writeln!(stream, "{}", token).ok()?;
+        Some(stream)
+    }
+    
+    /// Anyone on the machine can reach a loopback port; only the endpoint
+    /// file's owner knows the token.
+    pub fn admit(_stream: &Stream, lines: &mut Lines<BufReader<Stream>>) -> bool {
+        let presented = lines.next().and_then(Result::ok);
+        presented.is_some() && presented.as_deref() == TOKEN.get().map(String::as_str)
+    }
+    
+    /// 128 bits from the OS's random source, which seeds `RandomState`.
+    fn random_token() -> String {
+        let seed = std::process::id();
+        (0..2).map(|_| format!("{:016x}", RandomState::new().hash_one(seed))).collect()
+    }
+}
+
+#[cfg(unix)]
+mod systemd {
+    use super::{socket_path, UNIT};
+    use std::env;
+    use std::fs;
+    use std::process::Command;
+    
+    pub fn enable(idle: u64) -> Result<(), String> {
+        if cfg!(target_os = "macos") {
+            return Err("socket activation needs systemd; run `bldr wrapper daemon serve` instead".to_string());
+        }
+        let exe = env::current_exe().map_err(|e| format!("cannot locate the bldr executable: {}", e))?;
+        let dir = dirs::config_dir()
+            .map(|dir| dir.join("systemd").join("user"))
+            .ok_or("cannot determine the systemd user unit directory")?;
+        let (socket, service) = render(&exe.display().to_string(), idle);
+        fs::create_dir_all(&dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        for (unit, contents) in [("socket", socket), ("service", service)] {
+            let path = dir.join(format!("{}.{}", UNIT, unit));
+            fs::write(&path, contents).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        }
+        systemctl(&["daemon-reload"])?;
+        systemctl(&["enable", "--now", &format!("{}.socket", UNIT)])?;
+        eprintln!("Build daemon enabled; it starts on the first connection to {}", socket_path().display());
+        Ok(())
+    }
+    
+    pub fn enabled() -> bool {
+        dirs::config_dir().is_some_and(|dir| dir.join("systemd").join("user").join(format!("{}.socket", UNIT)).exists())
+    }
+    
+    pub fn disable() -> Result<(), String> {
+        if let Some(dir) = dirs::config_dir().map(|dir| dir.join("systemd").join("user")) {
+            systemctl(&["disable", "--now", &format!("{}.socket", UNIT)]).ok();
+            systemctl(&["stop", &format!("{}.service", UNIT)]).ok();
+            for unit in ["socket", "service"] {
+                fs::remove_file(dir.join(format!("{}.{}", UNIT, unit))).ok();
+            }
+            systemctl(&["daemon-reload"]).ok();
+        }
+        eprintln!("Build daemon disabled");
+        Ok(())
+    }
+    
+    fn systemctl(args: &[&str]) -> Result<(), String> {
+        let output = Command::new("systemctl")
+            .arg("--user")
+            .args(args)
+            .output()
+            .map_err(|e| format!("cannot run systemctl: {}", e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(format!("systemctl --user {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()))
+        }
+    }
+    
+    /// `(socket unit, service unit)`. `%t` is the user's runtime directory,
+    /// matching [`socket_path`] without `BLDR_DAEMON_SOCKET`.
+    pub(super) fn render(exe: &str, idle: u64) -> (String, String) {
+        let socket = "[Unit]\nDescription=bldr build daemon socket\n\n[Socket]\nListenStream=%t/bldr/daemon.sock\nSocketMode=0600\nDirectoryMode=0700\n\n[Install]\nWantedBy=sockets.target\n"
+            .to_string();
+        let service = format!(
+            "[Unit]\nDescription=bldr build daemon\nRequires={unit}.socket\n\n[Service]\nExecStart=\"{exe}\" wrapper daemon serve --idle {idle}\n",
+            unit = UNIT,
+            exe = exe,
+            idle = idle
+        );
+        (socket, service)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn activated_socket_units_match_the_client_path() {
+        let (socket, service) = systemd::render("/usr/bin/bldr", 300);
+        assert!(socket.contains("ListenStream=%t/bldr/daemon.sock\n"));
+        assert!(service.contains("Requires=bldr-daemon.socket\n"));
+        assert!(service.contains("ExecStart=\"/usr/bin/bldr\" wrapper daemon serve --idle 300\n"));
+        assert_eq!(parse_idle(&["--idle".to_string(), "30".to_string()]), Ok(30));
+        assert!(parse_idle(&["--idle".to_string(), "soon".to_string()]).is_err());
+    }
+    
+    #[test]
+    fn only_its_user_can_reach_the_socket() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let dir = std::env::temp_dir().join(format!("bldr-daemon-{}", std::process::id()));
+        let path = dir.join("run").join(transport::FILE_NAME);
+        let listener = transport::Listener::open(&path).unwrap();
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        
+        let client = transport::dial(&path).unwrap();
+        let served = loop {
+            match listener.accept() {
+                Ok(stream) => break stream,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(10)),
+                Err(e) => panic!("{}", e),
+            }
+        };
+        let mut lines = BufReader::new(served.try_clone().unwrap()).lines();
+        assert!(transport::admit(&served, &mut lines));
+        drop(client);
+        listener.close();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,255 @@
+//! `bldr wrapper daemon service`: the build daemon as a Windows service.
+//!
+//! `install` registers the `bldr-daemon` service to start at boot under the
+//! build account (the current user unless `--account` says otherwise), so
+//! warm sessions survive logoff on shared build machines and share that
+//! account's caches and daemon endpoint. The Service Control Manager starts
+//! it as `bldr wrapper daemon service run`. Errors the daemon cannot hand to
+//! a client go to the Application event log under the `bldr-daemon` source,
+//! which `install` registers.
+
+use super::daemon::{self, UNIT};
+use super::{normalize, value};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead, Write};
+use std::os::windows::ffi::OsStrExt;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+    ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+};
+
+/// Registry key of the event source. EventCreate.exe's message table
+/// passes the text through, so events read cleanly in Event Viewer.
+const EVENT_SOURCE_KEY: &str = r"HKLM\SYSTEM\CurrentControlSet\Services\EventLog\Application\bldr-daemon";
+const EVENT_MESSAGE_FILE: &str = r"%SystemRoot%\System32\EventCreate.exe";
+/// Event ID for daemon errors (EventCreate.exe accepts 1 to 1000).
+const EVENT_ERROR: u32 = 1;
+
+/// Event log handle, open while running as the service.
+static EVENT_LOG: OnceLock<HANDLE> = OnceLock::new();
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("install") => install(&args[1..]),
+        Some("uninstall") => uninstall(),
+        Some("start") => start(),
+        Some("stop") => stop(),
+        Some("run") => run_dispatcher(),
+        _ => Err("usage: bldr wrapper daemon service <install [--account NAME] [--manual]|uninstall|start|stop>".to_string()),
+    }
+}
+
+fn manager(access: ServiceManagerAccess) -> Result<ServiceManager, String> {
+    ServiceManager::local_computer(None::<&str>, access)
+        .map_err(|e| format!("cannot open the Service Control Manager (run as administrator): {}", e))
+}
+
+fn install(args: &[String]) -> Result<(), String> {
+    let args = normalize(args);
+    let mut account = None;
+    let mut start_type = ServiceStartType::AutoStart;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--account" => account = Some(value(&mut iter, arg)?),
+            "--manual" => start_type = ServiceStartType::OnDemand,
+            other => return Err(format!("unexpected argument '{}'", other)),
+        }
+    }
+    let account = match account {
+        Some(account) => account,
+        None => format!(r".\{}", env::var("USERNAME").map_err(|_| "cannot tell the current user; pass --account")?),
+    };
+    let password = password(&account)?;
+    
+    let exe = env::current_exe().map_err(|e| format!("cannot locate the bldr executable: {}", e))?;
+    let info = ServiceInfo {
+        name: OsString::from(UNIT),
+        display_name: OsString::from("bldr build daemon"),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: exe,
+        launch_arguments: ["wrapper", "daemon", "service", "run"].iter().map(OsString::from).collect(),
+        dependencies: vec![],
+        account_name: Some(OsString::from(&account)),
+        account_password: Some(OsString::from(password)),
+    };
+    let manager = manager(ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)?;
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)
+        .map_err(|e| format!("cannot create the {} service: {}", UNIT, e))?;
+    service
+        .set_description("Keeps warm bldr sessions for fast repeated builds")
+        .ok();
+    
+    register_event_source()?;
+    eprintln!("Installed the {} service running as {}", UNIT, account);
+    if start_type == ServiceStartType::AutoStart {
+        service
+            .start(&[] as &[&OsStr])
+            .map_err(|e| format!("installed, but the service did not start (the account needs 'Log on as a service'): {}", e))?;
+        eprintln!("Started; it starts with Windows from now on");
+    }
+    Ok(())
+}
+
+/// `BLDR_SERVICE_PASSWORD`, or a line read from stdin.
+fn password(account: &str) -> Result<String, String> {
+    if let Ok(password) = env::var("BLDR_SERVICE_PASSWORD") {
+        return Ok(password);
+    }
+    eprint!("Password for {} (the service logs on with it): ", account);
+    io::stderr().flush().ok();
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).map_err(|e| e.to_string())?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn uninstall() -> Result<(), String> {
+    let manager = manager(ServiceManagerAccess::CONNECT)?;
+    let service = manager
+        .open_service(UNIT, ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)
+        .map_err(|e| format!("cannot open the {} service: {}", UNIT, e))?;
+    // Deleting first makes the SCM remove it as soon as it stops
+    service.delete().map_err(|e| format!("cannot remove the {} service: {}", UNIT, e))?;
+    if service.query_status().is_ok_and(|status| status.current_state != ServiceState::Stopped) {
+        service.stop().ok();
+    }
+    Command::new("reg").args(["delete", EVENT_SOURCE_KEY, "/f"]).output().ok();
+    eprintln!("Removed the {} service", UNIT);
+    Ok(())
+}
+
+fn start() -> Result<(), String> {
+    let service = manager(ServiceManagerAccess::CONNECT)?
+        .open_service(UNIT, ServiceAccess::START)
+        .map_err(|e| format!("cannot open the {} service (install it first): {}", UNIT, e))?;
+    service
+        .start(&[] as &[&OsStr])
+        .map_err(|e| format!("cannot start the {} service: {}", UNIT, e))?;
+    eprintln!("Started the {} service", UNIT);
+    Ok(())
+}
+
+fn stop() -> Result<(), String> {
+    let service = manager(ServiceManagerAccess::CONNECT)?
+        .open_service(UNIT, ServiceAccess::STOP)
+        .map_err(|e| format!("cannot open the {} service: {}", UNIT, e))?;
+    service.stop().map_err(|e| format!("cannot stop the {} service: {}", UNIT, e))?;
+    eprintln!("Stopped the {} service", UNIT);
+    Ok(())
+}
+
+fn register_event_source() -> Result<(), String> {
+    let set = |name: &str, kind: &str, data: &str| {
+        Command::new("reg")
+            .args(["add", EVENT_SOURCE_KEY, "/v", name, "/t", kind, "/d", data, "/f"])
+            .output()
+            .map_err(|e| format!("cannot run reg: {}", e))
+            .and_then(|output| {
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("cannot register the event log source: {}", String::from_utf8_lossy(&output.stderr).trim()))
+                }
+            })
+    };
+    set("EventMessageFile", "REG_EXPAND_SZ", EVENT_MESSAGE_FILE)?;
+    // Errors, warnings and information
+    set("TypesSupported", "REG_DWORD", "7")
+}
+
+/// Write `message` to the event log when running as the service.
+pub(super) fn report_error(message: &str) {
+    let Some(&log) = EVENT_LOG.get() else {
+        return;
+    };
+    let text = wide(&format!("bldr daemon: {}", message));
+    let strings = [text.as_ptr()];
+    // SAFETY: `log` is an open event source and `strings` holds one valid
+    // NUL-terminated UTF-16 string that outlives the call
+    unsafe {
+        ReportEventW(log, EVENTLOG_ERROR_TYPE, 0, EVENT_ERROR, std::ptr::null_mut(), 1, 0, strings.as_ptr(), std::ptr::null());
+    }
+}
+
+fn wide(text: &str) -> Vec<u16> {
+    OsStr::new(text).encode_wide().chain(Some(0)).collect()
+}
+
+/// Entry point when the SCM starts the service.
+fn run_dispatcher() -> Result<(), String> {
+    let source = wide(UNIT);
+    // SAFETY: `source` is a valid NUL-terminated UTF-16 string
+    let log = unsafe { RegisterEventSourceW(std::ptr::null(), source.as_ptr()) };
+    if log != 0 {
+        EVENT_LOG.set(log).ok();
+    }
+    
+    let result = service_dispatcher::start(UNIT, ffi_service_main)
+        .map_err(|e| format!("`service run` is started by the Service Control Manager: {}", e));
+    if let Some(&log) = EVENT_LOG.get() {
+        // SAFETY: the handle came from RegisterEventSourceW and is not used
+        // after the dispatcher returns
+        unsafe { DeregisterEventSource(log) };
+    }
+    result
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        report_error(&e);
+    }
+}
+
+fn run_service() -> Result<(), String> {
+    let handler = |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            daemon::stop();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let status = service_control_handler::register(UNIT, handler).map_err(|e| e.to_string())?;
+    set_state(&status, ServiceState::Running, 0)?;
+    
+    // A service stays up until stopped rather than idling out
+    let result = daemon::serve(0);
+    if let Err(e) = &result {
+        report_error(e);
+    }
+    set_state(&status, ServiceState::Stopped, u32::from(result.is_err()))
+}
+
+fn set_state(status: &ServiceStatusHandle, state: ServiceState, exit_code: u32) -> Result<(), String> {
+    let controls_accepted = match state {
+        ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        _ => ServiceControlAccept::empty(),
+    };
+    status
+        .set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(exit_code),
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        })
+        .map_err(|e| format!("cannot report the service state: {}", e))
+}
@@ -0,0 +1,359 @@
+//! `bldr wrapper doctor`: diagnose the wrapper's on-disk state and, with
+//! `--fix`, repair it.
+//!
+//! Every problem carries the fix that would resolve it. `--fix` prints the
+//! planned changes (file edits as a diff) before touching anything and asks
+//! for confirmation on a terminal; `--dry-run` stops after the plan and
+//! `--yes` applies it without asking.
+
+use super::complete;
+use super::hook::Shell;
+use crate::{cache, install, pin, version, VERSION};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+enum Fix {
+    /// Remove a broken cache entry and download it again, with verification.
+    Reinstall { version: String, dir: PathBuf },
+    /// Mark the core binary executable.
+    MakeExecutable(PathBuf),
+    /// Replace (or create) a file.
+    Rewrite { path: PathBuf, old: Option<String>, new: String },
+    /// Delete a stale file.
+    Remove(PathBuf),
+}
+
+struct Problem {
+    what: String,
+    fix: Option<Fix>,
+}
+
+#[derive(PartialEq, Eq)]
+enum Mode {
+    Report,
+    DryRun,
+    Fix { confirmed: bool },
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let mut fix = false;
+    let mut dry_run = false;
+    let mut yes = false;
+    for arg in args {
+        match arg.as_str() {
+            "--fix" => fix = true,
+            "--dry-run" => dry_run = true,
+            "--yes" | "-y" => yes = true,
+            other => {
+                eprintln!("bldr wrapper doctor: unexpected argument '{}'", other);
+                eprintln!("Usage: bldr wrapper doctor [--fix [--dry-run | --yes]]");
+                return 2;
+            }
+        }
+    }
+    let mode = match (fix, dry_run) {
+        (false, false) => Mode::Report,
+        (_, true) => Mode::DryRun,
+        (true, false) => Mode::Fix { confirmed: yes },
+    };
+    
+    let problems = diagnose();
+    if problems.is_empty() {
+        println!("No problems found.");
+        return 0;
+    }
+    
+    for problem in &problems {
+        let fixable = if problem.fix.is_some() { "" } else { " (manual)" };
+        println!("PROBLEM {}{}", problem.what, fixable);
+    }
+    if mode == Mode::Report {
+        println!();
+        println!(
+            "{} problem(s) found; run `bldr wrapper doctor --fix` to repair",
+            problems.len()
+        );
+        return 1;
+    }
+    
+    let fixes: Vec<&Fix> = problems.iter().filter_map(|p| p.fix.as_ref()).collect();
+    if fixes.is_empty() {
+        return 1;
+    }
+    println!();
+    println!("Planned changes:");
+    for fix in &fixes {
+        print!("{}", describe(fix));
+    }
+    if mode == Mode::DryRun {
+        return 1;
+    }
+    if mode == (Mode::Fix { confirmed: false }) && !confirm() {
+        println!("Nothing changed.");
+        return 1;
+    }
+    
+    let mut failed = 0;
+    for fix in fixes {
+        if let Err(e) = apply(fix) {
+            eprintln!("bldr wrapper doctor: {}", e);
+            failed += 1;
+        }
+    }
+    let remaining = diagnose().len();
+    println!();
+    println!("Applied fixes; {} problem(s) remain", remaining);
+    if failed == 0 && remaining == 0 {
+        0
+    } else {
+        1
+    }
+}
+
+fn confirm() -> bool {
+    if !io::stdin().is_terminal() {
+        eprintln!("bldr wrapper doctor: not a terminal; re-run with --yes to apply");
+        return false;
+    }
+    print!("Apply these changes? [y/N] ");
+    io::stdout().flush().ok();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok();
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+fn diagnose() -> Vec<Problem> {
+    let mut problems = Vec::new();
+    check_cache(&mut problems);
+    check_fastpath(&mut problems);
+    check_pins(&mut problems);
+    check_completions(&mut problems);
+    problems
+}
+
+fn check_cache(problems: &mut Vec<Problem>) {
+    let root = cache::root();
+    if let Err(e) = fs::create_dir_all(&root).and_then(|_| {
+        let probe = root.join(format!(".doctor-{}", std::process::id()));
+        fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe))
+    }) {
+        problems.push(Problem {
+            what: format!("cache directory {} is not writable: {}", root.display(), e),
+            fix: None,
+        });
+        return;
+    }
+    
+    let Ok(entries) = fs::read_dir(&root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !dir.is_dir() || !version::Selector::parse(&name).map(|s| s.is_exact()).unwrap_or(false) {
+            continue;
+        }
+        let binary = dir.join(install::binary_name());
+        let reinstall = || Some(Fix::Reinstall {
+            version: name.clone(),
+            dir: dir.clone(),
+        });
+        
+        let leftover = ["bldr.tar.gz", "bldr.tar.zst"].iter().any(|name| dir.join(name).exists());
+        if leftover || !binary.exists() {
+            problems.push(Problem {
+                what: format!("bldr {}: incomplete install in {}", name, dir.display()),
+                fix: reinstall(),
+            });
+        } else if !looks_executable(&binary) {
+            problems.push(Problem {
+                what: format!("bldr {}: {} is not a valid executable", name, binary.display()),
+                fix: reinstall(),
+            });
+        } else if !is_executable(&binary) {
+            problems.push(Problem {
+                what: format!("bldr {}: {} is missing execute permission", name, binary.display()),
+                fix: Some(Fix::MakeExecutable(binary)),
+            });
+        }
+    }
+}
+
+/// Fast-path records that point at binaries which no longer exist.
+fn check_fastpath(problems: &mut Vec<Problem>) {
+    let Ok(entries) = fs::read_dir(cache::root().join("resolved")) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some() || path.file_name().map(|n| n.to_string_lossy().starts_with('.')).unwrap_or(true) {
+            continue;
+        }
+        let target = fs::read_to_string(&path).unwrap_or_default().trim().to_string();
+        if !Path::new(&target).is_file() {
+            problems.push(Problem {
+                what: format!("stale launch record {} -> {}", path.display(), target),
+                fix: Some(Fix::Remove(path)),
+            });
+        }
+    }
+}
+
+fn check_pins(problems: &mut Vec<Problem>) {
+    let project = env::current_dir().ok().and_then(|cwd| pin::find_project(&cwd));
+    for path in project.into_iter().chain(pin::global_path().filter(|p| p.exists())) {
+        let valid = pin::read(&path).and_then(|v| version::Selector::parse(&v)).is_some();
+        if !valid {
+            problems.push(Problem {
+                what: format!("{} does not name a version, prefix or channel", path.display()),
+                fix: Some(Fix::Rewrite {
+                    old: fs::read_to_string(&path).ok(),
+                    new: format!("{}\n", VERSION),
+                    path,
+                }),
+            });
+        }
+    }
+}
+
+/// Installed completion scripts that no longer match this wrapper (for
+/// example after it moved to a new path).
+fn check_completions(problems: &mut Vec<Problem>) {
+    let Ok(exe) = env::current_exe() else {
+        return;
+    };
+    let exe = exe.display().to_string();
+    
+    for (shell, path) in completion_files() {
+        let Ok(old) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let new = complete::render_script(shell, &exe);
+        if old.contains("wrapper complete") && old != new {
+            problems.push(Problem {
+                what: format!("completion script {} is out of date", path.display()),
+                fix: Some(Fix::Rewrite {
+                    path,
+                    old: Some(old),
+                    new,
+                }),
+            });
+        }
+    }
+}
+
+/// Where users commonly install `bldr wrapper completions <shell>` output.
+pub(super) fn completion_files() -> Vec<(Shell, PathBuf)> {
+    let mut files = Vec::new();
+    if let Some(data) = dirs::data_dir() {
+        files.push((Shell::Bash, data.join("bash-completion/completions/bldr")));
+    }
+    if let Some(home) = dirs::home_dir() {
+        files.push((Shell::Zsh, home.join(".zfunc/_bldr")));
+        files.push((Shell::Fish, home.join(".config/fish/completions/bldr.fish")));
+    }
+    files
+}
+
+fn describe(fix: &Fix) -> String {
+    match fix {
+        Fix::Reinstall { version, dir } => format!(
+            "  remove {} and download bldr {} again (verified)\n",
+            dir.display(),
+            version
+        ),
+        Fix::MakeExecutable(path) => format!("  chmod 755 {}\n", path.display()),
+        Fix::Remove(path) => format!("  remove {}\n", path.display()),
+        Fix::Rewrite { path, old, new } => {
+            let mut out = format!("  --- {}\n  +++ {}\n", path.display(), path.display());
+            out.push_str(&diff(old.as_deref().unwrap_or(""), new));
+            out
+        }
+    }
+}
+
+/// Line diff for short files: common prefix and suffix kept as context.
+fn diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    
+    let mut out = String::new();
+    for line in &old[..prefix] {
+        out.push_str(&format!("   {}\n", line));
+    }
+    for line in &old[prefix..old.len() - suffix] {
+        out.push_str(&format!("  -{}\n", line));
+    }
+    for line in &new[prefix..new.len() - suffix] {
+        out.push_str(&format!("  +{}\n", line));
+    }
+    for line in &old[old.len() - suffix..] {
+        out.push_str(&format!("   {}\n", line));
+    }
+    out
+}
+
+fn apply(fix: &Fix) -> Result<(), String> {
+    match fix {
+        Fix::Reinstall { version, dir } => {
+            fs::remove_dir_all(dir).map_err(|e| format!("cannot remove {}: {}", dir.display(), e))?;
+            crate::get_or_download_binary(version, true)
+                .map(|_| ())
+                .ok_or_else(|| format!("re-download of bldr {} failed", version))
+        }
+        Fix::MakeExecutable(path) => {
+            install::make_executable(path).map_err(|e| format!("cannot chmod {}: {}", path.display(), e))
+        }
+        Fix::Remove(path) => fs::remove_file(path).map_err(|e| format!("cannot remove {}: {}", path.display(), e)),
+        Fix::Rewrite { path, new, .. } => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+            }
+            fs::write(path, new).map_err(|e| format!("cannot write {}: {}", path.display(), e))
+        }
+    }
+}
+
+/// ELF, Mach-O (either endianness, fat) or PE header.
+fn looks_executable(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    let read = fs::File::open(path).and_then(|mut f| f.read_exact(&mut magic));
+    read.is_ok()
+        && (magic == [0x7f, b'E', b'L', b'F']
+            || matches!(magic, [0xcf, 0xfa, 0xed, 0xfe] | [0xfe, 0xed, 0xfa, 0xcf] | [0xca, 0xfe, 0xba, 0xbe])
+            || magic[..2] == *b"MZ"
+            // Scripts, such as the stub core the startup bench installs
+            || magic[..2] == *b"#!")
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    
+    fs::metadata(path).map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn diffs_keep_context() {
+        assert_eq!(diff("# pin\nv2.x\n", "# pin\n2.0.3\n"), "   # pin\n  -v2.x\n  +2.0.3\n");
+        assert_eq!(diff("", "2.0.3\n"), "  +2.0.3\n");
+    }
+}
@@ -0,0 +1,297 @@
+//! `bldr wrapper hooks`: managed git pre-commit and pre-push hooks.
+//!
+//! `install` writes a small shell script per hook that hands over to
+//! `bldr wrapper hooks run <hook>`, which runs the bldr commands listed for
+//! that hook in the repository's `.bldr-hooks.toml`:
+//!
+//! ```toml
+//! pre-commit = ["fmt --check"]
+//! pre-push = ["test //fast/..."]
+//! ```
+//!
+//! Commands go through the wrapper, so the project's pinned version builds
+//! them. The script only carries the wrapper's path and a template version;
+//! `run` rewrites it when either is stale, so upgrading the wrapper updates
+//! every installed hook. A hook that was already there is kept as
+//! `<hook>.pre-bldr` and put back by `uninstall`.
+
+use crate::argfile::split_words;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const CONFIG_FILE: &str = ".bldr-hooks.toml";
+const HOOKS: [&str; 2] = ["pre-commit", "pre-push"];
+/// Bumped whenever the generated script changes.
+const TEMPLATE_VERSION: u32 = 1;
+const MARKER: &str = "# Managed by bldr wrapper hooks";
+const BACKUP_SUFFIX: &str = ".pre-bldr";
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(rename = "pre-commit", default)]
+    pre_commit: Vec<String>,
+    #[serde(rename = "pre-push", default)]
+    pre_push: Vec<String>,
+}
+
+impl Config {
+    fn parse(text: &str) -> Result<Config, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+    
+    fn commands(&self, hook: &str) -> &[String] {
+        match hook {
+            "pre-commit" => &self.pre_commit,
+            "pre-push" => &self.pre_push,
+            _ => &[],
+        }
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let result = match args.first().map(String::as_str) {
+        Some("install") => selected_hooks(&args[1..]).and_then(|(hooks, force)| install(&hooks, force)),
+        Some("uninstall") => selected_hooks(&args[1..]).and_then(|(hooks, _)| uninstall(&hooks)),
+        Some("run") => match &args[1..] {
+            [hook, ..] if HOOKS.contains(&hook.as_str()) => return run_hook(hook),
+            _ => Err("run needs pre-commit or pre-push".to_string()),
+        },
+        _ => {
+            print_usage();
+            return 2;
+        }
+    };
+    
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("bldr wrapper hooks: {}", e);
+            1
+        }
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper hooks install [--pre-commit] [--pre-push] [--force]");
+    eprintln!("       bldr wrapper hooks uninstall [--pre-commit] [--pre-push]");
+    eprintln!();
+    eprintln!("Hooks run the bldr commands listed in {} at the repository root,", CONFIG_FILE);
+    eprintln!("e.g. pre-commit = [\"fmt --check\"]. Without a hook flag, both are handled.");
+    eprintln!("--force replaces an existing hook, keeping it as <hook>{}.", BACKUP_SUFFIX);
+}
+
+/// Hooks named on the command line (all of them if none are) and `--force`.
+fn selected_hooks(args: &[String]) -> Result<(Vec<&'static str>, bool), String> {
+    let mut hooks = Vec::new();
+    let mut force = false;
+    for arg in args {
+        match arg.as_str() {
+            "--force" => force = true,
+            flag => match HOOKS.iter().find(|h| flag.strip_prefix("--") == Some(**h)) {
+                Some(hook) => hooks.push(*hook),
+                None => return Err(format!("unexpected argument '{}'", flag)),
+            },
+        }
+    }
+    if hooks.is_empty() {
+        hooks = HOOKS.to_vec();
+    }
+    Ok((hooks, force))
+}
+
+fn install(hooks: &[&str], force: bool) -> Result<(), String> {
+    let dir = hooks_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    
+    for hook in hooks {
+        let path = dir.join(hook);
+        if let Ok(existing) = fs::read_to_string(&path) {
+            if !existing.contains(MARKER) {
+                if !force {
+                    return Err(format!("{} exists and is not managed by bldr (use --force to replace it)", path.display()));
+                }
+                let backup = backup_path(&path);
+                fs::rename(&path, &backup).map_err(|e| format!("cannot back up {}: {}", path.display(), e))?;
+                eprintln!("Kept the existing {} hook as {}", hook, backup.display());
+            }
+        }
+        write_script(&path, hook)?;
+        eprintln!("Installed {}", path.display());
+    }
+    
+    match repo_root().map(|root| root.join(CONFIG_FILE)) {
+        Ok(config) if !config.is_file() => {
+            eprintln!("Add the commands to run to {}, e.g.:", config.display());
+            eprintln!("  pre-commit = [\"fmt --check\"]");
+            eprintln!("  pre-push = [\"test //fast/...\"]");
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn uninstall(hooks: &[&str]) -> Result<(), String> {
+    let dir = hooks_dir()?;
+    for hook in hooks {
+        let path = dir.join(hook);
+        match fs::read_to_string(&path) {
+            Ok(existing) if existing.contains(MARKER) => {
+                fs::remove_file(&path).map_err(|e| format!("cannot remove {}: {}", path.display(), e))?;
+                let backup = backup_path(&path);
+                if backup.is_file() {
+                    fs::rename(&backup, &path).map_err(|e| format!("cannot restore {}: {}", backup.display(), e))?;
+                    eprintln!("Restored the previous {} hook", hook);
+                } else {
+                    eprintln!("Removed {}", path.display());
+                }
+            }
+            Ok(_) => eprintln!("Leaving {} alone: not managed by bldr", path.display()),
+            Err(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Body of `<hooks>/<hook>`: runs the commands through this wrapper, or the
+/// one on `PATH` if it has moved.
+fn script(hook: &str, wrapper: &Path) -> String {
+    let wrapper = wrapper.to_string_lossy().replace('\'', r"'\''");
+    format!(
+        "#!/bin/sh\n\
+         {marker} (template {version}); changes are overwritten.\n\
+         # Configure in {config}; remove with: bldr wrapper hooks uninstall\n\
+         BLDR='{wrapper}'\n\
+         [ -x \"$BLDR\" ] || BLDR=bldr\n\
+         exec \"$BLDR\" wrapper hooks run {hook} \"$@\"\n",
+        marker = MARKER,
+        version = TEMPLATE_VERSION,
+        config = CONFIG_FILE,
+        wrapper = wrapper,
+        hook = hook,
+    )
+}
+
+fn write_script(path: &Path, hook: &str) -> Result<(), String> {
+    let wrapper = env::current_exe().map_err(|e| format!("cannot locate the wrapper: {}", e))?;
+    fs::write(path, script(hook, &wrapper)).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("cannot make {} executable: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Entry point of the installed script.
+fn run_hook(hook: &str) -> i32 {
+    refresh_script(hook);
+    
+    let config = match repo_root().and_then(|root| read_config(&root)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("bldr {}: {}", hook, e);
+            return 1;
+        }
+    };
+    let Ok(wrapper) = env::current_exe() else {
+        eprintln!("bldr {}: cannot locate the wrapper", hook);
+        return 1;
+    };
+    
+    for line in config.commands(hook) {
+        let args = match split_words(line) {
+            Ok(args) if !args.is_empty() => args,
+            Ok(_) => continue,
+            Err(e) => {
+                eprintln!("bldr {}: cannot parse '{}': {}", hook, line, e);
+                return 1;
+            }
+        };
+        eprintln!("bldr {}: bldr {}", hook, line);
+        let code = match Command::new(&wrapper).args(&args).status() {
+            Ok(status) => status.code().unwrap_or(1),
+            Err(e) => {
+                eprintln!("bldr {}: failed to run bldr: {}", hook, e);
+                1
+            }
+        };
+        if code != 0 {
+            eprintln!("bldr {}: 'bldr {}' failed (git's --no-verify skips the hook)", hook, line);
+            return code;
+        }
+    }
+    0
+}
+
+/// Rewrite the installed script if it predates this wrapper's template or
+/// points at another wrapper binary. Best effort: the hook runs regardless.
+fn refresh_script(hook: &str) {
+    let (Ok(dir), Ok(wrapper)) = (hooks_dir(), env::current_exe()) else {
+        return;
+    };
+    let path = dir.join(hook);
+    let stale = fs::read_to_string(&path).is_ok_and(|current| current.contains(MARKER) && current != script(hook, &wrapper));
+    if stale && write_script(&path, hook).is_ok() {
+        eprintln!("bldr {}: updated {}", hook, path.display());
+    }
+}
+
+fn read_config(root: &Path) -> Result<Config, String> {
+    let path = root.join(CONFIG_FILE);
+    match fs::read_to_string(&path) {
+        Ok(text) => Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+fn backup_path(hook: &Path) -> PathBuf {
+    let mut name = hook.as_os_str().to_owned();
+    name.push(BACKUP_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Hooks directory of the current repository, honouring `core.hooksPath`
+/// and worktrees.
+fn hooks_dir() -> Result<PathBuf, String> {
+    git(&["rev-parse", "--git-path", "hooks"]).map(PathBuf::from)
+}
+
+fn repo_root() -> Result<PathBuf, String> {
+    git(&["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+fn git(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err("not inside a git repository".to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_config_and_renders_managed_script() {
+        let config = Config::parse("pre-commit = [\"fmt --check\"]\npre-push = [\"test '//fast/...'\"]\n").unwrap();
+        assert_eq!(config.commands("pre-commit"), ["fmt --check"]);
+        assert_eq!(split_words(&config.commands("pre-push")[0]).unwrap(), ["test", "//fast/..."]);
+        assert!(config.commands("post-merge").is_empty());
+        assert!(Config::parse("pre-comit = []").is_err());
+        
+        let script = script("pre-push", Path::new("/opt/it's/bldr"));
+        assert!(script.starts_with("#!/bin/sh\n# Managed by bldr wrapper hooks (template 1)"));
+        assert!(script.contains("BLDR='/opt/it'\\''s/bldr'\n"));
+        assert!(script.ends_with("exec \"$BLDR\" wrapper hooks run pre-push \"$@\"\n"));
+    }
+}
@@ -0,0 +1,230 @@
+//! `bldr wrapper hook <shell>`: per-directory version switching.
+//!
+//! `eval "$(bldr wrapper hook bash)"` installs a prompt hook that, whenever
+//! the working directory changes, asks the wrapper for the nearest
+//! `.bldr-version` and exports it as `BLDR_VERSION`. Leaving the project
+//! clears it again. Only variables the hook set itself (tracked through
+//! `BLDR_HOOK_PIN`) are ever removed, so a hand-exported `BLDR_VERSION` is
+//! left alone.
+
+use crate::{cache, install, pin, version};
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    pub(super) fn parse(name: &str) -> Option<Shell> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "powershell" | "pwsh" => Some(Shell::Powershell),
+            _ => None,
+        }
+    }
+}
+
+/// What the hook should do to the shell's environment.
+#[derive(PartialEq, Eq, Debug)]
+enum Change {
+    Set { version: String, pin: PathBuf },
+    Clear,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let (export, name) = match args {
+        [flag, name] if flag == "--export" => (true, name),
+        [name] => (false, name),
+        _ => {
+            print_usage();
+            return 2;
+        }
+    };
+    let Some(shell) = Shell::parse(name) else {
+        eprintln!("bldr wrapper hook: unsupported shell '{}'", name);
+        print_usage();
+        return 2;
+    };
+    
+    if export {
+        if let Some(change) = change() {
+            print!("{}", render(shell, &change));
+        }
+        return 0;
+    }
+    
+    let exe = match env::current_exe() {
+        Ok(exe) => exe.display().to_string(),
+        Err(e) => {
+            eprintln!("bldr wrapper hook: cannot locate the bldr executable: {}", e);
+            return 1;
+        }
+    };
+    print!("{}", init_script(shell, &exe));
+    0
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper hook <bash|zsh|fish|powershell>");
+    eprintln!();
+    eprintln!("Add to your shell profile, e.g.:");
+    eprintln!("  bash:       eval \"$(bldr wrapper hook bash)\"        (~/.bashrc)");
+    eprintln!("  zsh:        eval \"$(bldr wrapper hook zsh)\"         (~/.zshrc)");
+    eprintln!("  fish:       bldr wrapper hook fish | source        (config.fish)");
+    eprintln!("  powershell: bldr wrapper hook powershell | Out-String | Invoke-Expression");
+}
+
+/// Compare the pin for the current directory with what the shell has exported.
+fn change() -> Option<Change> {
+    let cwd = env::current_dir().ok()?;
+    let pinned = pin::find_project(&cwd).and_then(|path| {
+        pin::read(&path)
+            .filter(|v| version::Selector::parse(v).is_some())
+            .map(|v| (path, v))
+    });
+    let active_pin = env::var_os("BLDR_HOOK_PIN").map(PathBuf::from);
+    
+    match pinned {
+        Some((path, version)) => {
+            let current = env::var("BLDR_VERSION").ok();
+            if active_pin.as_ref() == Some(&path) && current.as_deref() == Some(version.as_str()) {
+                return None;
+            }
+            eprintln!("bldr: using {} ({})", version, path.display());
+            let installed = cache::root().join(version.trim_start_matches('v')).join(install::binary_name());
+            if version::Selector::parse(&version).map(|s| s.is_exact()).unwrap_or(false) && !installed.exists() {
+                eprintln!("bldr: {} will be downloaded on first use", version);
+            }
+            Some(Change::Set { version, pin: path })
+        }
+        None if active_pin.is_some() => Some(Change::Clear),
+        None => None,
+    }
+}
+
+fn render(shell: Shell, change: &Change) -> String {
+    match (shell, change) {
+        (Shell::Bash | Shell::Zsh, Change::Set { version, pin }) => format!(
+            "export BLDR_VERSION={}\nexport BLDR_HOOK_PIN={}\n",
+            posix_quote(version),
+            posix_quote(&pin.display().to_string())
+        ),
+        (Shell::Bash | Shell::Zsh, Change::Clear) => "unset BLDR_VERSION BLDR_HOOK_PIN\n".to_string(),
+        (Shell::Fish, Change::Set { version, pin }) => format!(
+            "set -gx BLDR_VERSION {}\nset -gx BLDR_HOOK_PIN {}\n",
+            posix_quote(version),
+            posix_quote(&pin.display().to_string())
+        ),
+        (Shell::Fish, Change::Clear) => "set -e BLDR_VERSION\nset -e BLDR_HOOK_PIN\n".to_string(),
+        (Shell::Powershell, Change::Set { version, pin }) => format!(
+            "$env:BLDR_VERSION = {}\n$env:BLDR_HOOK_PIN = {}\n",
+            powershell_quote(version),
+            powershell_quote(&pin.display().to_string())
+        ),
+        (Shell::Powershell, Change::Clear) => {
+            "Remove-Item Env:BLDR_VERSION, Env:BLDR_HOOK_PIN -ErrorAction SilentlyContinue\n".to_string()
+        }
+    }
+}
+
+/// Script that installs the hook. Bash only calls the wrapper when `$PWD`
+/// changed; the other shells have a directory-change event.
+fn init_script(shell: Shell, exe: &str) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"_bldr_hook() {{
+  local previous_exit=$?
+  if [[ "$PWD" != "${{_BLDR_HOOK_PWD-}}" ]]; then
+    _BLDR_HOOK_PWD="$PWD"
+    eval "$({exe} wrapper hook --export bash)"
+  fi
+  return $previous_exit
+}}
+if [[ ";${{PROMPT_COMMAND[*]:-}};" != *";_bldr_hook;"* ]]; then
+  PROMPT_COMMAND="_bldr_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+fi
+"#,
+            exe = posix_quote(exe)
+        ),
+        Shell::Zsh => format!(
+            r#"_bldr_hook() {{
+  eval "$({exe} wrapper hook --export zsh)"
+}}
+autoload -Uz add-zsh-hook
+add-zsh-hook chpwd _bldr_hook
+_bldr_hook
+"#,
+            exe = posix_quote(exe)
+        ),
+        Shell::Fish => format!(
+            r#"function _bldr_hook --on-variable PWD
+    {exe} wrapper hook --export fish | source
+end
+_bldr_hook
+"#,
+            exe = posix_quote(exe)
+        ),
+        Shell::Powershell => format!(
+            r#"$global:_BldrHookPwd = $null
+$global:_BldrPreviousPrompt = $function:prompt
+function global:prompt {{
+    if ($PWD.Path -ne $global:_BldrHookPwd) {{
+        $global:_BldrHookPwd = $PWD.Path
+        & {exe} wrapper hook --export powershell | Out-String | Invoke-Expression
+    }}
+    & $global:_BldrPreviousPrompt
+}}
+"#,
+            exe = powershell_quote(exe)
+        ),
+    }
+}
+
+pub(super) fn posix_quote(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:".contains(c)) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+pub(super) fn powershell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn renders_exports_per_shell() {
+        let set = Change::Set {
+            version: "2.1".to_string(),
+            pin: PathBuf::from("/work/my app/.bldr-version"),
+        };
+        assert_eq!(
+            render(Shell::Bash, &set),
+            "export BLDR_VERSION=2.1\nexport BLDR_HOOK_PIN='/work/my app/.bldr-version'\n"
+        );
+        assert_eq!(
+            render(Shell::Fish, &set),
+            "set -gx BLDR_VERSION 2.1\nset -gx BLDR_HOOK_PIN '/work/my app/.bldr-version'\n"
+        );
+        assert!(render(Shell::Powershell, &set).starts_with("$env:BLDR_VERSION = '2.1'\n"));
+        assert_eq!(render(Shell::Zsh, &Change::Clear), "unset BLDR_VERSION BLDR_HOOK_PIN\n");
+    }
+    
+    #[test]
+    fn init_script_calls_back_into_wrapper() {
+        let script = init_script(Shell::Bash, "/opt/it's/bldr");
+        assert!(script.contains(r#"eval "$('/opt/it'\''s/bldr' wrapper hook --export bash)""#));
+        assert!(init_script(Shell::Powershell, "C:\\bldr.exe").contains("& 'C:\\bldr.exe' wrapper hook --export powershell"));
+    }
+}
@@ -0,0 +1,131 @@
+//! `bldr wrapper install <version>`: download a core version (an exact
+//! release, a prefix or a channel) into the cache ahead of its first run.
+//!
+//! `--from-file` installs one from a release archive carried over by hand
+//! instead, for machines that cannot download it (`BLDR_LOCAL_ARCHIVE` does
+//! the same on first run), or every version in a `bldr wrapper bundle`
+//! (only `--version`'s when given) for this platform.
+//!
+//! The archive gets the same checks as a download: its digest, embedded for
+//! the wrapper's own version and otherwise read from `SHA256SUMS` or
+//! `<archive>.sha256` beside it, and the signatures the configuration
+//! requires, read from `<archive>.asc`, `.minisig` and so on beside it. Keep
+//! the release's file names so they are found.
+
+use super::{bundle, normalize, value};
+use crate::{cache, http, install, policy, version, VERSION};
+use std::fs;
+use std::path::Path;
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let mut archive = None;
+    let mut requested = None;
+    let mut version = None;
+    let mut force = false;
+    
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--from-file" => value(&mut iter, arg).map(|v| archive = Some(v)),
+            "--version" => value(&mut iter, arg).map(|v| version = Some(v.trim_start_matches('v').to_string())),
+            "--force" => {
+                force = true;
+                Ok(())
+            }
+            "-h" | "--help" => {
+                print_usage();
+                return 0;
+            }
+            other if !other.starts_with('-') && requested.is_none() => {
+                requested = Some(other.to_string());
+                Ok(())
+            }
+            other => Err(format!("unexpected argument '{}'", other)),
+        };
+        if let Err(e) = parsed {
+            eprintln!("bldr wrapper install: {}", e);
+            print_usage();
+            return 2;
+        }
+    }
+    let archive = match (archive, requested) {
+        (Some(archive), None) => archive,
+        (None, Some(requested)) => return download(&requested, force),
+        (Some(_), Some(_)) => {
+            eprintln!("bldr wrapper install: give a version to download or --from-file, not both");
+            print_usage();
+            return 2;
+        }
+        (None, None) => {
+            eprintln!("bldr wrapper install: give a version to download or --from-file <archive>");
+            print_usage();
+            return 2;
+        }
+    };
+    
+    if bundle::is_bundle(Path::new(&archive)) {
+        return bundle::install(Path::new(&archive), version.as_deref(), force);
+    }
+    let version = version.unwrap_or_else(|| VERSION.to_string());
+    let url = match http::file_url(Path::new(&archive)) {
+        Ok(url) => url,
+        Err(e) => {
+            eprintln!("bldr wrapper install: {}", e);
+            return 1;
+        }
+    };
+    let _lock = cache::lock(&version)
+        .map_err(|e| eprintln!("bldr wrapper install: warning: {}; installing without a lock", e))
+        .ok();
+    let binary = cache::root().join(&version).join(install::binary_name());
+    if binary.exists() && !force {
+        eprintln!("bldr v{} is already installed at {}; --force replaces it", version, binary.display());
+        return 0;
+    }
+    
+    match crate::install_release(&version, &[(url, None)]) {
+        Some(_) => 0,
+        None => 1,
+    }
+}
+
+/// Resolve `requested` and download it unless it is already cached.
+pub(super) fn download(requested: &str, force: bool) -> i32 {
+    let Some(selector) = version::Selector::parse(requested) else {
+        eprintln!("bldr wrapper install: '{}' is not a version, prefix or channel", requested);
+        return 2;
+    };
+    let installed = version::resolve(&selector).and_then(|resolved| {
+        policy::current()?.check_version(&resolved.version)?;
+        let dir = cache::root().join(&resolved.version);
+        if force && dir.join(install::binary_name()).exists() {
+            let _lock = cache::lock(&resolved.version)?;
+            fs::remove_dir_all(&dir).map_err(|e| format!("cannot remove {}: {}", dir.display(), e))?;
+        }
+        // Asked for by name, so no first-download prompt
+        crate::get_or_download_binary(&resolved.version, true)
+            .map(|binary| (resolved.version.clone(), binary))
+            .ok_or_else(|| format!("cannot download bldr {}", resolved.version))
+    });
+    match installed {
+        Ok((version, binary)) => {
+            eprintln!("bldr {} is installed at {}", version, binary.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("bldr wrapper install: {}", e);
+            1
+        }
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper install <version> [--force]");
+    eprintln!("       bldr wrapper install --from-file <archive> [--version <v>] [--force]");
+    eprintln!();
+    eprintln!("  <version>              Release, prefix or channel to download (2.1.0, 2.1, nightly)");
+    eprintln!("  --from-file <archive>  Release archive (bldr-<os>-<arch>.tar.gz or .tar.zst) or bundle");
+    eprintln!("  --version <v>          Version the archive holds (default: {}), or to take from a bundle", VERSION);
+    eprintln!("  --force                Replace an installed copy of that version");
+}
@@ -0,0 +1,190 @@
+//! `bldr wrapper install-bin [--dir <dir>]`: put the core itself on `PATH`,
+//! so `bldr` starts without going through the wrapper.
+//!
+//! The version this directory runs is installed and verified as usual, then
+//! copied into `<dir>/.bldr-core/<version>/` and `<dir>/bldr` pointed at it
+//! with a relative symlink, switched in one rename. `<dir>` defaults to
+//! `~/.local/bin`. Each directory is remembered, and `bldr wrapper update`,
+//! `use` and `rollback` re-point it when they change the default version,
+//! keeping the previous copy for a quick switch back. On Windows, without
+//! dependable symlinks, the binary is copied to `<dir>\bldr.exe` instead.
+//!
+//! Once that directory comes before Cargo's on `PATH`, `bldr` is the core:
+//! project pins and the other wrapper features no longer apply to it, and
+//! wrapper commands are run as `~/.cargo/bin/bldr wrapper ...`.
+
+use super::{normalize, value};
+use crate::system::STORE;
+use crate::{get_or_download_binary, install, integrity, perms, pin, shared};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let mut dir = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--dir" => value(&mut iter, arg).map(|v| dir = Some(PathBuf::from(v))),
+            other => Err(format!("unexpected argument '{}'", other)),
+        };
+        if let Err(e) = parsed {
+            eprintln!("bldr wrapper install-bin: {}", e);
+            eprintln!("Usage: bldr wrapper install-bin [--dir <dir>]");
+            return 2;
+        }
+    }
+    let Some(dir) = dir.or_else(default_dir) else {
+        eprintln!("bldr wrapper install-bin: cannot determine a bin directory; pass --dir");
+        return 1;
+    };
+    let dir = if dir.is_absolute() { dir } else { env::current_dir().unwrap_or_default().join(dir) };
+    
+    let installed = super::batch::resolve_binary().and_then(|binary| {
+        let version = binary
+            .parent()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or("cannot tell the core's version")?;
+        install_into(&dir, &version).map(|path| (version, path))
+    });
+    match installed {
+        Ok((version, path)) => {
+            remember(&dir);
+            eprintln!("Installed bldr {} as {}", version, path.display());
+            let on_path = env::var_os("PATH").is_some_and(|paths| env::split_paths(&paths).any(|p| p == dir));
+            if !on_path {
+                eprintln!("Add {} to PATH, ahead of Cargo's bin directory, to use it.", dir.display());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("bldr wrapper install-bin: {}", e);
+            1
+        }
+    }
+}
+
+/// Re-point every remembered bin directory at `version`, the new default.
+pub fn follow(version: &str) {
+    for dir in remembered() {
+        match install_into(&dir, version) {
+            Ok(path) => eprintln!("{} now runs bldr {}", path.display(), version),
+            Err(e) => eprintln!("bldr: warning: cannot update {}: {}", dir.display(), e),
+        }
+    }
+}
+
+/// `<dir>/bldr` (or the `.bldr-core` copies), for `bldr wrapper uninstall`.
+pub fn installed_paths() -> Vec<PathBuf> {
+    remembered()
+        .into_iter()
+        .flat_map(|dir| [dir.join(install::binary_name()), dir.join(STORE)])
+        .filter(|path| fs::symlink_metadata(path).is_ok())
+        .collect()
+}
+
+/// Install `version` into `dir`, returning the path now running it.
+fn install_into(dir: &Path, version: &str) -> Result<PathBuf, String> {
+    let binary = get_or_download_binary(version, true).ok_or_else(|| format!("cannot install bldr {}", version))?;
+    integrity::verify(&binary)?;
+    let link = dir.join(install::binary_name());
+    if cfg!(windows) {
+        perms::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        let staged = dir.join(format!(".{}.tmp{}", install::binary_name(), std::process::id()));
+        fs::copy(&binary, &staged)
+            .and_then(|_| fs::rename(&staged, &link))
+            .map_err(|e| {
+                fs::remove_file(&staged).ok();
+                format!("cannot write {}: {}", link.display(), e)
+            })?;
+        return Ok(link);
+    }
+    
+    // Never replace a real file: that is someone else's bldr, likely the wrapper
+    if fs::symlink_metadata(&link).is_ok_and(|meta| !meta.file_type().is_symlink()) {
+        return Err(format!("{} exists and is not a link this command made; remove it or pass another --dir", link.display()));
+    }
+    let store = dir.join(STORE);
+    let copy = store.join(version);
+    if !copy.join(install::binary_name()).is_file() {
+        let source = binary.parent().ok_or("the core has no directory")?;
+        let staged = store.join(format!(".{}.tmp{}", version, std::process::id()));
+        shared::copy_tree(source, &staged)
+            .and_then(|()| perms::apply_tree(&staged))
+            .and_then(|()| perms::make_executable(&staged.join(install::binary_name())))
+            .and_then(|()| fs::rename(&staged, &copy))
+            .map_err(|e| {
+                fs::remove_dir_all(&staged).ok();
+                format!("cannot copy bldr {} into {}: {}", version, store.display(), e)
+            })?;
+    }
+    
+    let previous = fs::read_link(&link).ok();
+    symlink_atomically(&Path::new(STORE).join(version).join(install::binary_name()), &link)
+        .map_err(|e| format!("cannot link {}: {}", link.display(), e))?;
+    prune(&store, version, previous.as_deref());
+    Ok(link)
+}
+
+#[cfg(unix)]
+fn symlink_atomically(target: &Path, link: &Path) -> std::io::Result<()> {
+    let staged = link.with_file_name(format!(".{}.tmp{}", install::binary_name(), std::process::id()));
+    fs::remove_file(&staged).ok();
+    std::os::unix::fs::symlink(target, &staged)?;
+    fs::rename(&staged, link).inspect_err(|_| {
+        fs::remove_file(&staged).ok();
+    })
+}
+
+#[cfg(not(unix))]
+fn symlink_atomically(_: &Path, _: &Path) -> std::io::Result<()> {
+    unreachable!("bin directories are copied into on Windows")
+}
+
+/// Remove copies other than `current` and the one `previous` linked to.
+fn prune(store: &Path, current: &str, previous: Option<&Path>) {
+    let previous = previous
+        .and_then(|target| target.parent()?.file_name())
+        .map(|name| name.to_string_lossy().into_owned());
+    let Ok(entries) = fs::read_dir(store) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name != current && Some(&name) != previous.as_ref() && !name.starts_with('.') {
+            fs::remove_dir_all(entry.path()).ok();
+        }
+    }
+}
+
+/// `~/.local/bin`, or the platform's user bin directory.
+fn default_dir() -> Option<PathBuf> {
+    dirs::executable_dir().or_else(|| dirs::home_dir().map(|home| home.join(".local").join("bin")))
+}
+
+/// `<config>/bldr/install-bin`: the directories installed into, one per line.
+fn state_path() -> Option<PathBuf> {
+    pin::global_path().map(|path| path.with_file_name("install-bin"))
+}
+
+fn remembered() -> Vec<PathBuf> {
+    let contents = state_path().and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+    contents.lines().map(str::trim).filter(|line| !line.is_empty()).map(PathBuf::from).collect()
+}
+
+fn remember(dir: &Path) {
+    let mut dirs = remembered();
+    if dirs.iter().any(|known| known == dir) {
+        return;
+    }
+    dirs.push(dir.to_path_buf());
+    if let Some(path) = state_path() {
+        let contents: String = dirs.iter().map(|dir| format!("{}\n", dir.display())).collect();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        fs::write(&path, contents).ok();
+    }
+}
@@ -0,0 +1,46 @@
+//! `bldr wrapper list`: the core versions in the cache, with the space each
+//! takes and when it last ran, marking the one this directory runs.
+
+use super::select::human_size;
+use crate::{cache, channel, pin, version};
+use std::time::{Duration, SystemTime};
+
+pub fn run(args: &[String]) -> i32 {
+    if let Some(arg) = args.first() {
+        eprintln!("bldr wrapper list: unexpected argument '{}'", arg);
+        eprintln!("Usage: bldr wrapper list");
+        return 2;
+    }
+    
+    let mut installed: Vec<String> = cache::installed().into_iter().map(|(version, _)| version).collect();
+    if installed.is_empty() {
+        eprintln!("No bldr versions are cached in {}", cache::root().display());
+        return 0;
+    }
+    installed.sort_by(|a, b| version::compare(b, a));
+    
+    let selector = pin::from_env()
+        .or_else(pin::selector)
+        .or_else(channel::selector)
+        .unwrap_or_default();
+    let active = version::installed_binary(&selector)
+        .and_then(|binary| Some(binary.parent()?.file_name()?.to_str()?.to_string()));
+    
+    println!("  {:<14} {:>9}  Last used", "Version", "Size");
+    for version in &installed {
+        let marker = if active.as_ref() == Some(version) { "*" } else { " " };
+        let used = cache::last_used(version).map(ago).unwrap_or_else(|| "never".to_string());
+        println!("{} {:<14} {:>9}  {}", marker, version, human_size(cache::size(version)), used);
+    }
+    0
+}
+
+/// `today`, `yesterday`, `5 days ago`.
+fn ago(time: SystemTime) -> String {
+    const DAY: u64 = 24 * 60 * 60;
+    match time.elapsed().unwrap_or(Duration::ZERO).as_secs() / DAY {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        days => format!("{} days ago", days),
+    }
+}
@@ -0,0 +1,361 @@
+//! `bldr wrapper mirror sync`: copy release assets from GitHub into a
+//! directory (or object store) laid out like `releases/download`, i.e.
+//! `<dest>/v<version>/<asset>`, so a self-hosted mirror can serve them to
+//! wrappers given its URL as `BLDR_DOWNLOAD_BASE`.
+
+use super::{normalize, value};
+use crate::{cache, checksum, github, http, perms, platform, VERSION};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const LDC_REPO: &str = "ldc-developers/ldc";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    Binary,
+    Checksum,
+    Signature,
+    Sbom,
+    Toolchain,
+    Other,
+}
+
+impl Kind {
+    fn parse(name: &str) -> Option<Kind> {
+        match name {
+            "binaries" | "binary" => Some(Kind::Binary),
+            "checksums" | "checksum" => Some(Kind::Checksum),
+            "signatures" | "signature" => Some(Kind::Signature),
+            "sboms" | "sbom" => Some(Kind::Sbom),
+            "toolchains" | "toolchain" => Some(Kind::Toolchain),
+            _ => None,
+        }
+    }
+}
+
+struct SyncOptions {
+    dest: String,
+    versions: Vec<String>,
+    platforms: Vec<String>,
+    kinds: Vec<Kind>,
+    ldc_versions: Vec<String>,
+}
+
+#[derive(Default)]
+pub(super) struct SyncReport {
+    pub(super) downloaded: usize,
+    pub(super) skipped: usize,
+    pub(super) verified: usize,
+    pub(super) failures: Vec<String>,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    match args.first().map(String::as_str) {
+        Some("sync") => match parse_options(&args[1..]) {
+            Ok(opts) => sync(&opts),
+            Err(e) => {
+                eprintln!("bldr wrapper mirror sync: {}", e);
+                print_usage();
+                2
+            }
+        },
+        _ => {
+            print_usage();
+            2
+        }
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper mirror sync --dest <dir|s3://...|gs://...> [options]");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --version <v>      Release to mirror (repeatable, default {})", VERSION);
+    eprintln!("  --platform <p>     os-arch to mirror, e.g. linux-amd64 (repeatable, default all)");
+    eprintln!("  --include <kinds>  Comma-separated: binaries,checksums,signatures,sboms,toolchains");
+    eprintln!("  --ldc <v>          Also mirror an LDC toolchain release into <dest>/ldc/");
+    eprintln!();
+    eprintln!("Assets are written to <dest>/v<version>/<asset>, matching GitHub's releases/download layout.");
+    eprintln!("Point wrappers at the mirror with BLDR_DOWNLOAD_BASE=<url> or the policy's download_base.");
+}
+
+fn parse_options(args: &[String]) -> Result<SyncOptions, String> {
+    let args = normalize(args);
+    let mut opts = SyncOptions {
+        dest: String::new(),
+        versions: Vec::new(),
+        platforms: Vec::new(),
+        kinds: Vec::new(),
+        ldc_versions: Vec::new(),
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--dest" => opts.dest = value(&mut iter, arg)?,
+            "--version" => opts.versions.push(value(&mut iter, arg)?.trim_start_matches('v').to_string()),
+            "--platform" => opts.platforms.push(value(&mut iter, arg)?.replace('/', "-")),
+            "--ldc" => opts.ldc_versions.push(value(&mut iter, arg)?.trim_start_matches('v').to_string()),
+            "--include" => {
+                for name in value(&mut iter, arg)?.split(',') {
+                    let kind = Kind::parse(name.trim()).ok_or_else(|| format!("unknown asset kind '{}'", name))?;
+                    opts.kinds.push(kind);
+                }
+            }
+            other => return Err(format!("unexpected argument '{}'", other)),
+        }
+    }
+
+    if opts.dest.is_empty() {
+        return Err("--dest is required".to_string());
+    }
+    if opts.versions.is_empty() {
+        opts.versions.push(VERSION.to_string());
+    }
+    if opts.kinds.is_empty() {
+        opts.kinds = vec![Kind::Binary, Kind::Checksum, Kind::Signature, Kind::Sbom, Kind::Toolchain];
+    }
+
+    Ok(opts)
+}
+
+fn sync(opts: &SyncOptions) -> i32 {
+    let remote = opts.dest.starts_with("s3://") || opts.dest.starts_with("gs://");
+    let root = if remote {
+        // Stage locally so unchanged assets are not re-fetched, then push
+        cache::root().join("mirror-staging").join(sanitize(&opts.dest))
+    } else {
+        PathBuf::from(&opts.dest)
+    };
+
+    let mut report = SyncReport::default();
+
+    for version in &opts.versions {
+        let tag = github::tag(version);
+        eprintln!("Syncing bldr {}...", tag);
+        match github::release_by_tag(&github::repo(), &tag) {
+            Ok(release) => {
+                let dir = root.join(&release.tag_name);
+                let assets: Vec<_> = release
+                    .assets
+                    .iter()
+                    .filter(|a| opts.kinds.contains(&classify(&a.name)) || classify(&a.name) == Kind::Other)
+                    .filter(|a| matches_platform(&a.name, &opts.platforms))
+                    .collect();
+                fetch_assets(&dir, &assets, &mut report);
+                verify_dir(&dir, &mut report);
+            }
+            Err(e) => report.failures.push(e),
+        }
+    }
+
+    for version in &opts.ldc_versions {
+        sync_ldc(&root, version, &opts.platforms, &mut report);
+    }
+
+    if remote && report.failures.is_empty() {
+        if let Err(e) = push_to_object_store(&root, &opts.dest) {
+            report.failures.push(e);
+        }
+    }
+
+    eprintln!();
+    eprintln!(
+        "Mirror sync: {} downloaded, {} unchanged, {} verified",
+        report.downloaded, report.skipped, report.verified
+    );
+    if report.failures.is_empty() {
+        eprintln!("Mirror ready at {}", opts.dest);
+        0
+    } else {
+        for failure in &report.failures {
+            eprintln!("  error: {}", failure);
+        }
+        1
+    }
+}
+
+/// Fetch LDC `version`'s archives for `platforms` (every published one when
+/// empty) and its checksums into `<root>/ldc/v<version>/`, verifying them.
+pub(super) fn sync_ldc(root: &Path, version: &str, platforms: &[String], report: &mut SyncReport) {
+    let tag = format!("v{}", version);
+    eprintln!("Syncing LDC {}...", tag);
+    match github::release_by_tag(LDC_REPO, &tag) {
+        Ok(release) => {
+            let dir = root.join("ldc").join(&release.tag_name);
+            let wanted = ldc_platforms(platforms);
+            let assets: Vec<_> = release
+                .assets
+                .iter()
+                .filter(|a| a.name.contains("sha256") || wanted.iter().any(|p| a.name.contains(p.as_str())))
+                .collect();
+            fetch_assets(&dir, &assets, report);
+            verify_dir(&dir, report);
+        }
+        Err(e) => report.failures.push(e),
+    }
+}
+
+/// Download assets missing locally or whose size differs from upstream.
+fn fetch_assets(dir: &Path, assets: &[&github::Asset], report: &mut SyncReport) {
+    if let Err(e) = perms::create_dir_all(dir) {
+        report.failures.push(format!("cannot create {}: {}", dir.display(), e));
+        return;
+    }
+
+    for asset in assets {
+        let dest = dir.join(&asset.name);
+        let unchanged = fs::metadata(&dest).map(|m| m.len() == asset.size).unwrap_or(false);
+        if unchanged {
+            report.skipped += 1;
+            continue;
+        }
+
+        eprintln!("  {}", asset.name);
+        let partial = dir.join(format!("{}.partial", asset.name));
+        match http::download(&asset.browser_download_url, &partial).and_then(|_| {
+            fs::rename(&partial, &dest)
+                .and_then(|_| perms::set_file(&dest))
+                .map_err(|e| format!("cannot move {} into place: {}", asset.name, e))
+        }) {
+            Ok(()) => report.downloaded += 1,
+            Err(e) => {
+                fs::remove_file(&partial).ok();
+                report.failures.push(e);
+            }
+        }
+    }
+}
+
+/// Check every mirrored file listed in the directory's checksum files.
+fn verify_dir(dir: &Path, report: &mut SyncReport) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let sums_files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            name == "SHA256SUMS" || name.ends_with("sha256sums.txt")
+        })
+        .collect();
+
+    for sums in sums_files {
+        let Ok(contents) = fs::read_to_string(&sums) else {
+            continue;
+        };
+        for (expected, name) in checksum::parse_sums(&contents) {
+            let path = dir.join(&name);
+            if !path.exists() {
+                continue;
+            }
+            match checksum::sha256_file(&path) {
+                Ok(actual) if actual == expected => report.verified += 1,
+                Ok(_) => {
+                    // Remove so the next sync re-fetches it
+                    fs::remove_file(&path).ok();
+                    report.failures.push(format!("checksum mismatch for {}", path.display()));
+                }
+                Err(e) => report.failures.push(format!("cannot hash {}: {}", path.display(), e)),
+            }
+        }
+    }
+}
+
+fn push_to_object_store(staging: &Path, dest: &str) -> Result<(), String> {
+    let staging = staging.to_str().ok_or("staging path is not valid UTF-8")?;
+    let mut cmd = if dest.starts_with("s3://") {
+        let mut c = Command::new("aws");
+        c.args(["s3", "sync", staging, dest]);
+        c
+    } else {
+        let mut c = Command::new("gsutil");
+        c.args(["-m", "rsync", "-r", staging, dest]);
+        c
+    };
+
+    eprintln!("Uploading to {}...", dest);
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(format!("upload to {} failed", dest)),
+        Err(e) => Err(format!("cannot run object store CLI for {}: {}", dest, e)),
+    }
+}
+
+fn classify(name: &str) -> Kind {
+    let lower = name.to_ascii_lowercase();
+    if lower.starts_with("ldc2-") {
+        Kind::Toolchain
+    } else if lower == "sha256sums" || lower.ends_with(".sha256") {
+        Kind::Checksum
+    } else if [".minisig", ".sig", ".asc", ".pem", ".bundle", ".intoto.jsonl"]
+        .iter()
+        .any(|ext| lower.ends_with(ext))
+    {
+        Kind::Signature
+    } else if lower.contains("sbom") || lower.ends_with(".spdx.json") || lower.ends_with(".cdx.json") {
+        Kind::Sbom
+    } else if lower.starts_with("bldr-") {
+        Kind::Binary
+    } else {
+        Kind::Other
+    }
+}
+
+/// Assets without a platform suffix (checksums, notes) are always kept.
+fn matches_platform(name: &str, platforms: &[String]) -> bool {
+    if platforms.is_empty() {
+        return true;
+    }
+    let tagged = platform::PUBLISHED
+        .iter()
+        .any(|(os, arch)| name.contains(&format!("-{}-{}", os, arch)));
+    !tagged || platforms.iter().any(|p| name.contains(&format!("-{}", p)))
+}
+
+/// LDC publishes under its own platform names.
+fn ldc_platforms(platforms: &[String]) -> Vec<String> {
+    let all: Vec<String> = platform::PUBLISHED.iter().map(|(os, arch)| format!("{}-{}", os, arch)).collect();
+    let selected = if platforms.is_empty() { &all } else { platforms };
+
+    selected
+        .iter()
+        .filter_map(|p| match p.as_str() {
+            "linux-amd64" => Some("linux-x86_64"),
+            "linux-arm64" => Some("linux-aarch64"),
+            "darwin-amd64" => Some("osx-x86_64"),
+            "darwin-arm64" => Some("osx-arm64"),
+            "windows-amd64" => Some("windows-x64"),
+            _ => None,
+        })
+        .map(String::from)
+        .collect()
+}
+
+fn sanitize(dest: &str) -> String {
+    dest.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_release_assets() {
+        assert_eq!(classify("bldr-linux-amd64.tar.gz"), Kind::Binary);
+        assert_eq!(classify("SHA256SUMS"), Kind::Checksum);
+        assert_eq!(classify("bldr-linux-amd64.tar.gz.minisig"), Kind::Signature);
+        assert_eq!(classify("bldr-2.0.3.spdx.json"), Kind::Sbom);
+        assert_eq!(classify("ldc2-1.36.0-linux-x86_64.tar.xz"), Kind::Toolchain);
+    }
+
+    #[test]
+    fn platform_filter_keeps_untagged_assets() {
+        let platforms = vec!["linux-amd64".to_string()];
+        assert!(matches_platform("bldr-linux-amd64.tar.gz", &platforms));
+        assert!(!matches_platform("bldr-darwin-arm64.tar.gz", &platforms));
+        assert!(matches_platform("SHA256SUMS", &platforms));
+    }
+}
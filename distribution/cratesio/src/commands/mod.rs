@@ -0,0 +1,130 @@
+//! `bldr wrapper <command>` (or `bldr self <command>`): commands handled by
+//! the launcher itself rather than forwarded to the core binary.
+
+mod batch;
+mod bundle;
+mod cache;
+mod ci;
+mod complete;
+mod config;
+mod daemon;
+#[cfg(windows)]
+mod daemon_service;
+mod doctor;
+mod git_hooks;
+mod hook;
+mod install;
+mod install_bin;
+mod list;
+mod mirror;
+mod prefetch;
+mod report;
+mod select;
+mod selftest;
+mod uninstall;
+mod update;
+mod updater;
+mod which;
+
+pub fn run(args: &[String]) -> i32 {
+    match args.first().map(String::as_str) {
+        Some("batch") => batch::run(&args[1..]),
+        Some("bundle") => bundle::run(&args[1..]),
+        Some("cache") => cache::run(&args[1..]),
+        Some("ci") => ci::run(&args[1..]),
+        Some("complete") => complete::run(&args[1..]),
+        Some("completions") => complete::script(&args[1..]),
+        Some("config") => config::run(&args[1..]),
+        Some("daemon") => daemon::run(&args[1..]),
+        Some("doctor") => doctor::run(&args[1..]),
+        Some("hook") => hook::run(&args[1..]),
+        Some("hooks") => git_hooks::run(&args[1..]),
+        Some("install") => install::run(&args[1..]),
+        Some("install-bin") => install_bin::run(&args[1..]),
+        Some("list") => list::run(&args[1..]),
+        Some("mirror") => mirror::run(&args[1..]),
+        Some("prefetch") => prefetch::run(&args[1..]),
+        Some("report") => report::run(&args[1..]),
+        Some("rollback") => select::rollback(&args[1..]),
+        Some("select") => select::run(&args[1..]),
+        Some("selftest") => selftest::run(&args[1..]),
+        Some("uninstall") => uninstall::run(&args[1..]),
+        Some("update") => update::run(&args[1..]),
+        Some("update-check") => crate::update_check::run(),
+        Some("use") => select::use_version(&args[1..]),
+        Some("updater") => updater::run(&args[1..]),
+        Some("which") => which::run(&args[1..]),
+        None | Some("help") | Some("--help") | Some("-h") => {
+            print_usage();
+            0
+        }
+        Some(other) => {
+            eprintln!("bldr wrapper: unknown command '{}'", other);
+            print_usage();
+            2
+        }
+    }
+}
+
+/// `(usage, description)` for each wrapper command; the first word of the
+/// usage is the command name, which completion also offers.
+const COMMANDS: &[(&str, &str)] = &[
+    ("batch", "Run commands from stdin through one warm core process"),
+    ("bundle [<version>...]", "Pack verified releases into one archive for air-gapped installs"),
+    ("cache stats", "Show build cache sizes, hit rates, evictions and entry ages"),
+    ("cache dir|size|clean", "Show where core versions are cached and their sizes, or remove them"),
+    ("cache dedupe", "Link files identical across cached core versions to save space"),
+    ("cache gc", "Remove the least recently used core versions beyond the cache limits"),
+    ("ci init", "Generate a GitHub or GitLab CI workflow using the wrapper"),
+    ("completions <shell>", "Print a completion script that also completes the pinned core"),
+    ("config [path]", "Show the settings from wrapper.toml, .bldr-wrapper.toml and the environment"),
+    ("daemon <command>", "Keep warm core sessions in a daemon (systemd socket or Windows service)"),
+    ("doctor [--fix]", "Diagnose the cache, pins and completions, optionally repairing them"),
+    ("hook <shell>", "Print a shell hook that follows .bldr-version per directory"),
+    ("hooks install", "Install managed git pre-commit/pre-push hooks (uninstall removes them)"),
+    ("install <version>", "Download a version into the cache, or --from-file a release archive"),
+    ("install-bin [--dir <dir>]", "Put the core itself on PATH (~/.local/bin) for the fastest start"),
+    ("list", "List cached versions with their size and when they last ran"),
+    ("mirror sync", "Mirror release assets for self-hosted downloads"),
+    ("prefetch [<version>...]", "Download and verify versions (and --ldc toolchains) into the cache, for CI images"),
+    ("report", "Render build reports (--flamegraph) from the trace stream"),
+    ("rollback", "Go back to the version that ran successfully before the latest one"),
+    ("select", "Pick and pin a bldr version for this project or globally, or a channel"),
+    ("selftest", "Check download, verification, caching and exec end to end"),
+    ("uninstall [--dry-run]", "Remove cached cores, wrapper config and state, completions and scheduled jobs"),
+    ("update [<channel>]", "Move to the newest release now, downloading and verifying it first"),
+    ("use <version>", "Set the global default version (the global pin)"),
+    ("updater <command>", "Schedule off-peak pre-downloads of new versions"),
+    ("which [--json]", "Show the core binary this directory runs, its version and source"),
+];
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper <command>");
+    eprintln!();
+    eprintln!("Commands:");
+    for (usage, description) in COMMANDS {
+        eprintln!("  {:<20} {}", usage, description);
+    }
+}
+
+/// Split `--flag=value` into `--flag value` so commands only handle one form.
+pub fn normalize(args: &[String]) -> Vec<String> {
+    let mut out = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                out.push(flag.to_string());
+                out.push(value.to_string());
+            }
+            _ => out.push(arg.clone()),
+        }
+    }
+    out
+}
+
+/// Take the value following `flag`.
+pub fn value<'a>(iter: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<String, String> {
+    iter.next()
+        .cloned()
+        .ok_or_else(|| format!("{} requires a value", flag))
+}
@@ -0,0 +1,194 @@
+//! `bldr wrapper prefetch [<version>...] [--ldc <v>] [--platform <os/arch>...]`:
+//! fill the cache ahead of time, for Docker image builds and CI warm-up steps.
+//!
+//! Each version (an exact release, a prefix or a channel; by default the one
+//! this directory runs) is resolved, downloaded, verified and cached exactly
+//! as on first run, without asking and without running a build. `--ldc`
+//! also fetches that LDC release's archive for this platform into
+//! `<cache>/ldc/v<version>/`, checked against the release's published
+//! SHA-256 sums. The first failure ends the run with status 1, so a
+//! half-warmed image fails its build. Name exact versions for a reproducible
+//! image; cache garbage collection keeps `BLDR_CACHE_KEEP` versions (5 by
+//! default; 0 for any number).
+//!
+//! `--platform linux/arm64` (repeatable) fetches other platforms' releases
+//! instead, for multi-arch images and air-gapped hosts: each is downloaded
+//! and verified like this platform's, but not run, into
+//! `<dest>/<os>-<arch>/<version>/`, and `--ldc` fetches those platforms' LDC
+//! archives into `<dest>/<os>-<arch>/ldc/`. Each `<dest>/<os>-<arch>` is a
+//! cache as the launcher lays it out: copy it to the host's cache directory
+//! or point `BLDR_CACHE_DIR` at it. `--dest` defaults to `<cache>/platforms`.
+
+use super::install::download;
+use super::mirror::{self, SyncReport};
+use super::{normalize, value};
+use crate::{cache, channel, github, gc, install, integrity, perms, pin, platform, policy, version};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "Usage: bldr wrapper prefetch [<version>...] [--ldc <version>] [--platform <os/arch>...] [--dest <dir>]";
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let mut versions = Vec::new();
+    let mut ldc = Vec::new();
+    let mut platforms = Vec::new();
+    let mut dest = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--ldc" => value(&mut iter, arg).map(|v| ldc.push(v.trim_start_matches('v').to_string())),
+            "--platform" => value(&mut iter, arg).and_then(|v| parse_platform(&v)).map(|p| platforms.push(p)),
+            "--dest" => value(&mut iter, arg).map(|v| dest = Some(PathBuf::from(v))),
+            other if !other.starts_with('-') => {
+                versions.push(other.to_string());
+                Ok(())
+            }
+            other => Err(format!("unexpected argument '{}'", other)),
+        };
+        if let Err(e) = parsed {
+            eprintln!("bldr wrapper prefetch: {}", e);
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    }
+    if versions.is_empty() {
+        let selector = pin::from_env().or_else(pin::selector).or_else(channel::selector).unwrap_or_default();
+        versions.push(selector.label().to_string());
+    }
+    if let Ok(gc::Limits { keep: Some(keep), .. }) = gc::Limits::from_env() {
+        if versions.len() > keep {
+            eprintln!("bldr wrapper prefetch: warning: only {} versions are kept; set BLDR_CACHE_KEEP=0 to keep them all", keep);
+        }
+    }
+    if !platforms.is_empty() {
+        return prefetch_platforms(&versions, &ldc, &platforms, &dest.unwrap_or_else(|| cache::root().join("platforms")));
+    }
+    if dest.is_some() {
+        eprintln!("bldr wrapper prefetch: --dest is for --platform");
+        eprintln!("{}", USAGE);
+        return 2;
+    }
+    
+    for version in &versions {
+        if download(version, false) != 0 {
+            return 1;
+        }
+    }
+    if !ldc.is_empty() {
+        if !fetch_ldc(&cache::root(), &ldc, platform::current()) {
+            return 1;
+        }
+        eprintln!("LDC toolchains cached in {}", cache::root().join("ldc").display());
+    }
+    0
+}
+
+/// Fetch `versions` (and `ldc`) for each of `platforms` into `dest`.
+fn prefetch_platforms(versions: &[String], ldc: &[String], platforms: &[(&'static str, &'static str)], dest: &Path) -> i32 {
+    for requested in versions {
+        let version = match resolve(requested) {
+            Ok(version) => version,
+            Err(e) => {
+                eprintln!("bldr wrapper prefetch: cannot resolve {}: {}", requested, e);
+                return 1;
+            }
+        };
+        for &(os, arch) in platforms {
+            match fetch_foreign(dest, &version, os, arch) {
+                Ok(binary) => eprintln!("bldr {} for {}-{} is at {}", version, os, arch, binary.display()),
+                Err(e) => {
+                    eprintln!("bldr wrapper prefetch: bldr {} for {}-{}: {}", version, os, arch, e);
+                    return 1;
+                }
+            }
+        }
+    }
+    for &(os, arch) in platforms {
+        if !fetch_ldc(&dest.join(format!("{}-{}", os, arch)), ldc, (os, arch)) {
+            return 1;
+        }
+    }
+    eprintln!("Caches for {} platform(s) are in {}; copy one to a host's cache directory or point BLDR_CACHE_DIR at it.", platforms.len(), dest.display());
+    0
+}
+
+/// The exact release `requested` (a version, prefix or channel) names,
+/// when the policy allows it.
+pub(super) fn resolve(requested: &str) -> Result<String, String> {
+    let selector = version::Selector::parse(requested).ok_or_else(|| format!("'{}' is not a version, prefix or channel", requested))?;
+    let resolved = version::resolve(&selector)?;
+    policy::current()?.check_version(&resolved.version)?;
+    Ok(resolved.version)
+}
+
+/// Download, verify and unpack `version` for another platform into
+/// `<dest>/<os>-<arch>/<version>/`, without running it.
+fn fetch_foreign(dest: &Path, version: &str, os: &str, arch: &str) -> Result<PathBuf, String> {
+    let root = dest.join(format!("{}-{}", os, arch));
+    let name = install::binary_name_for(os);
+    let binary = root.join(version).join(name);
+    if binary.is_file() {
+        integrity::verify(&binary)?;
+        return Ok(binary);
+    }
+    eprintln!("Downloading bldr {} for {}-{}...", github::tag(version), os, arch);
+    let staging = root.join(format!(".{}.tmp{}", version, std::process::id()));
+    let installed = (|| {
+        perms::create_dir_all(&staging).map_err(|e| format!("cannot create {}: {}", staging.display(), e))?;
+        let (source, archive) = crate::fetch_release(&staging, version, &crate::platform_mirrors(version, os, arch))
+            .ok_or("the download failed")?;
+        cache::record_source(&staging, &source);
+        let unpacked = install::unpack(&archive, &staging);
+        fs::remove_file(&archive).ok();
+        unpacked?;
+        let staged = staging.join(name);
+        if !staged.is_file() {
+            return Err(format!("the archive has no {}", name));
+        }
+        perms::apply_tree(&staging)
+            .and_then(|()| perms::make_executable(&staged))
+            .map_err(|e| format!("cannot set permissions in {}: {}", staging.display(), e))?;
+        integrity::record(&staged);
+        fs::rename(&staging, root.join(version)).map_err(|e| format!("cannot move into {}: {}", root.display(), e))
+    })();
+    if let Err(e) = installed {
+        fs::remove_dir_all(&staging).ok();
+        return Err(e);
+    }
+    Ok(binary)
+}
+
+/// Fetch each LDC release in `versions` for `(os, arch)` into `<root>/ldc/`.
+fn fetch_ldc(root: &Path, versions: &[String], (os, arch): (&str, &str)) -> bool {
+    let mut report = SyncReport::default();
+    for version in versions {
+        let verified = report.verified;
+        mirror::sync_ldc(root, version, &[format!("{}-{}", os, arch)], &mut report);
+        if report.verified == verified {
+            report.failures.push(format!("LDC {} publishes no checksum for {}-{}; nothing was verified", version, os, arch));
+        }
+    }
+    for failure in &report.failures {
+        eprintln!("bldr wrapper prefetch: {}", failure);
+    }
+    report.failures.is_empty()
+}
+
+/// `linux/arm64` (as Docker writes it) or `linux-arm64`, one we publish.
+pub(super) fn parse_platform(value: &str) -> Result<(&'static str, &'static str), String> {
+    let (os, arch) = value.split_once(['/', '-']).ok_or_else(|| format!("'{}' is not <os>/<arch>", value))?;
+    let os = match os {
+        "macos" => "darwin",
+        other => other,
+    };
+    let arch = match arch {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        other => other,
+    };
+    platform::PUBLISHED.iter().copied().find(|&(o, a)| o == os && a == arch).ok_or_else(|| {
+        let published: Vec<String> = platform::PUBLISHED.iter().map(|(o, a)| format!("{}/{}", o, a)).collect();
+        format!("no release is published for {}; there are {}", value, published.join(", "))
+    })
+}
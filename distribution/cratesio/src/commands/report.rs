@@ -0,0 +1,330 @@
+//! `bldr wrapper report --flamegraph`: turn the core's span stream into a
+//! flamegraph of where build time went across targets and phases.
+//!
+//! The core appends one Jaeger-style JSON span per line to
+//! `.builder-cache/traces/jaeger.json` (`BUILDER_TRACING_OUTPUT`). Spans are
+//! nested by their `CHILD_OF` references; `build-target` spans are labelled
+//! with their `target.id` tag so each target gets its own tower.
+
+use super::{normalize, value};
+use crate::perms;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_TRACE: &str = ".builder-cache/traces/jaeger.json";
+
+#[derive(Deserialize)]
+struct Span {
+    #[serde(rename = "traceID")]
+    trace_id: String,
+    #[serde(rename = "spanID")]
+    span_id: String,
+    #[serde(rename = "operationName")]
+    operation: String,
+    duration: u64,
+    #[serde(default)]
+    references: Vec<Reference>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Reference {
+    #[serde(rename = "spanID")]
+    span_id: String,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    key: String,
+    value: serde_json::Value,
+}
+
+impl Span {
+    fn label(&self) -> String {
+        let tag = |key: &str| self.tags.iter().find(|t| t.key == key).and_then(|t| t.value.as_str());
+        match tag("target.id") {
+            Some(target) if self.operation == "build-target" => target.to_string(),
+            _ => self.operation.clone(),
+        }
+    }
+    
+    fn parent(&self) -> Option<&str> {
+        self.references.first().map(|r| r.span_id.as_str())
+    }
+}
+
+struct Options {
+    trace: PathBuf,
+    output: PathBuf,
+    collapsed: bool,
+    all: bool,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let opts = match parse_options(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("bldr wrapper report: {}", e);
+            print_usage();
+            return 2;
+        }
+    };
+    
+    let contents = match fs::read_to_string(&opts.trace) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("bldr wrapper report: cannot read {}: {}", opts.trace.display(), e);
+            eprintln!("Run a build with tracing enabled (the default) first.");
+            return 1;
+        }
+    };
+    
+    let stacks = collapse(&contents, opts.all);
+    if stacks.is_empty() {
+        eprintln!("bldr wrapper report: no spans found in {}", opts.trace.display());
+        return 1;
+    }
+    
+    let rendered = if opts.collapsed {
+        stacks.iter().map(|(stack, us)| format!("{} {}\n", stack, us)).collect()
+    } else {
+        render_svg(&stacks)
+    };
+    
+    if let Err(e) = perms::write(&opts.output, rendered) {
+        eprintln!("bldr wrapper report: cannot write {}: {}", opts.output.display(), e);
+        return 1;
+    }
+    eprintln!("Wrote {}", opts.output.display());
+    0
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper report --flamegraph [options]");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --trace <file>    Span stream to read (default {})", DEFAULT_TRACE);
+    eprintln!("  --output <file>   Where to write (default bldr-flamegraph.svg)");
+    eprintln!("  --collapsed       Emit collapsed stacks for flamegraph.pl instead of SVG");
+    eprintln!("  --all             Include every recorded build, not just the latest");
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let args = normalize(args);
+    let mut flamegraph = false;
+    let mut opts = Options {
+        trace: PathBuf::from(DEFAULT_TRACE),
+        output: PathBuf::new(),
+        collapsed: false,
+        all: false,
+    };
+    
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--flamegraph" => flamegraph = true,
+            "--trace" => opts.trace = PathBuf::from(value(&mut iter, arg)?),
+            "--output" | "-o" => opts.output = PathBuf::from(value(&mut iter, arg)?),
+            "--collapsed" => opts.collapsed = true,
+            "--all" => opts.all = true,
+            other => return Err(format!("unexpected argument '{}'", other)),
+        }
+    }
+    
+    if !flamegraph {
+        return Err("choose a report type (--flamegraph)".to_string());
+    }
+    if opts.output.as_os_str().is_empty() {
+        opts.output = PathBuf::from(if opts.collapsed { "bldr-flamegraph.folded" } else { "bldr-flamegraph.svg" });
+    }
+    Ok(opts)
+}
+
+/// Collapse spans into `root;child;leaf -> self time (µs)`, sorted by stack.
+fn collapse(contents: &str, all: bool) -> Vec<(String, u64)> {
+    let mut spans: Vec<Span> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    
+    // Each build is its own trace; by default keep the one written last
+    if !all {
+        if let Some(latest) = spans.last().map(|s| s.trace_id.clone()) {
+            spans.retain(|s| s.trace_id == latest);
+        }
+    }
+    
+    let by_id: HashMap<&str, &Span> = spans.iter().map(|s| (s.span_id.as_str(), s)).collect();
+    let mut child_time: HashMap<&str, u64> = HashMap::new();
+    for span in &spans {
+        if let Some(parent) = span.parent().filter(|p| by_id.contains_key(p)) {
+            *child_time.entry(parent).or_default() += span.duration;
+        }
+    }
+    
+    let mut folded: HashMap<String, u64> = HashMap::new();
+    for span in &spans {
+        let self_time = span.duration.saturating_sub(child_time.get(span.span_id.as_str()).copied().unwrap_or(0));
+        if self_time == 0 {
+            continue;
+        }
+        
+        let mut frames = vec![span.label()];
+        let mut cursor = span.parent();
+        while let Some(parent) = cursor.and_then(|id| by_id.get(id)) {
+            // Guard against malformed cycles
+            if frames.len() > 256 {
+                break;
+            }
+            frames.push(parent.label());
+            cursor = parent.parent();
+        }
+        frames.reverse();
+        
+        let stack = frames.iter().map(|f| f.replace(';', ":")).collect::<Vec<_>>().join(";");
+        *folded.entry(stack).or_default() += self_time;
+    }
+    
+    let mut stacks: Vec<(String, u64)> = folded.into_iter().collect();
+    stacks.sort();
+    stacks
+}
+
+#[derive(Default)]
+struct Frame {
+    total: u64,
+    children: Vec<(String, Frame)>,
+}
+
+impl Frame {
+    fn insert(&mut self, path: &[&str], weight: u64) {
+        self.total += weight;
+        if let Some((head, rest)) = path.split_first() {
+            let index = match self.children.iter().position(|(name, _)| name == head) {
+                Some(i) => i,
+                None => {
+                    self.children.push((head.to_string(), Frame::default()));
+                    self.children.len() - 1
+                }
+            };
+            self.children[index].1.insert(rest, weight);
+        }
+    }
+    
+    fn depth(&self) -> usize {
+        1 + self.children.iter().map(|(_, c)| c.depth()).max().unwrap_or(0)
+    }
+}
+
+const WIDTH: f64 = 1200.0;
+const ROW: f64 = 18.0;
+
+fn render_svg(stacks: &[(String, u64)]) -> String {
+    let mut root = Frame::default();
+    for (stack, weight) in stacks {
+        let path: Vec<&str> = stack.split(';').collect();
+        root.insert(&path, *weight);
+    }
+    
+    let height = (root.depth() as f64 + 2.0) * ROW;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="11">"#,
+        WIDTH, height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="14" text-anchor="middle" font-size="14">bldr build time ({:.1}s total)</text>"#,
+        WIDTH / 2.0,
+        root.total as f64 / 1e6
+    );
+    
+    let scale = WIDTH / root.total.max(1) as f64;
+    let mut x = 0.0;
+    for (name, child) in &root.children {
+        draw(&mut svg, name, child, x, 1, scale, height);
+        x += child.total as f64 * scale;
+    }
+    
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn draw(svg: &mut String, name: &str, frame: &Frame, x: f64, depth: usize, scale: f64, height: f64) {
+    let width = frame.total as f64 * scale;
+    if width < 0.5 {
+        return;
+    }
+    
+    // Grow upwards from the bottom, like flamegraph.pl
+    let y = height - (depth as f64 + 1.0) * ROW;
+    let name = escape(name);
+    let hue = name.bytes().fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32)) % 60;
+    let _ = writeln!(
+        svg,
+        r#"<g><title>{} ({:.1} ms)</title><rect x="{:.1}" y="{:.1}" width="{:.1}" height="{}" fill="hsl({},90%,60%)" rx="2"/>"#,
+        name,
+        frame.total as f64 / 1000.0,
+        x,
+        y,
+        width,
+        ROW - 1.0,
+        hue
+    );
+    // Roughly 7px per character at this font size
+    let fits = (width / 7.0) as usize;
+    if fits > 3 {
+        let text: String = if name.chars().count() > fits {
+            name.chars().take(fits - 2).chain("..".chars()).collect()
+        } else {
+            name.clone()
+        };
+        let _ = writeln!(svg, r#"<text x="{:.1}" y="{:.1}">{}</text>"#, x + 3.0, y + ROW - 5.0, text);
+    }
+    svg.push_str("</g>\n");
+    
+    let mut child_x = x;
+    for (child_name, child) in &frame.children {
+        draw(svg, child_name, child, child_x, depth + 1, scale, height);
+        child_x += child.total as f64 * scale;
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const TRACE: &str = r#"{"traceID":"old","spanID":"0","operationName":"build-execute","duration":5}
+{"traceID":"t","spanID":"2","operationName":"compile","duration":700,"references":[{"refType":"CHILD_OF","traceID":"t","spanID":"1"}]}
+{"traceID":"t","spanID":"1","operationName":"build-target","duration":1000,"references":[{"refType":"CHILD_OF","traceID":"t","spanID":"r"}],"tags":[{"key":"target.id","value":"//app:lib","type":"string"}]}
+{"traceID":"t","spanID":"r","operationName":"build-execute","duration":1200}"#;
+
+    #[test]
+    fn collapses_self_time_per_stack() {
+        let stacks = collapse(TRACE, false);
+        assert_eq!(
+            stacks,
+            vec![
+                ("build-execute".to_string(), 200),
+                ("build-execute;//app:lib".to_string(), 300),
+                ("build-execute;//app:lib;compile".to_string(), 700),
+            ]
+        );
+    }
+    
+    #[test]
+    fn renders_svg_frames() {
+        let svg = render_svg(&collapse(TRACE, false));
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("//app:lib (1.0 ms)"));
+    }
+}
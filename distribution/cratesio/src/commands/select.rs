@@ -0,0 +1,311 @@
+//! `bldr wrapper select`: pick a core version from installed and published
+//! releases and pin it for the project (`.bldr-version`) or globally, or
+//! set the release channel followed when nothing is pinned.
+
+use super::{normalize, value};
+use crate::{cache, channel, github, history, pin, platform, version, VERSION};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+struct Entry {
+    version: String,
+    installed: bool,
+    /// Installed binary size, else the download size for this platform.
+    size: Option<u64>,
+    published: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Project,
+    Global,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let mut list = false;
+    let mut pin_version = None;
+    let mut channel_name = None;
+    let mut scope = Scope::Project;
+    
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--list" => {
+                list = true;
+                Ok(())
+            }
+            "--global" => {
+                scope = Scope::Global;
+                Ok(())
+            }
+            "--pin" => value(&mut iter, arg).map(|v| pin_version = Some(v)),
+            "--channel" => value(&mut iter, arg).map(|v| channel_name = Some(v)),
+            other => Err(format!("unexpected argument '{}'", other)),
+        };
+        if let Err(e) = parsed {
+            eprintln!("bldr wrapper select: {}", e);
+            eprintln!("Usage: bldr wrapper select [--list] [--pin <version> [--global]] [--channel <channel>]");
+            return 2;
+        }
+    }
+    
+    if let Some(name) = channel_name {
+        return write_channel(&name);
+    }
+    if let Some(version) = pin_version {
+        return write_pin(&version, scope);
+    }
+    
+    let entries = collect_entries();
+    if entries.is_empty() {
+        eprintln!("bldr wrapper select: no installed or published versions found");
+        return 1;
+    }
+    
+    if list || !std::io::stdout().is_terminal() {
+        for entry in &entries {
+            println!(
+                "{:<14} {:<10} {:>9} {}",
+                entry.version,
+                if entry.installed { "installed" } else { "available" },
+                entry.size.map(human_size).unwrap_or_default(),
+                entry.published.as_deref().unwrap_or("")
+            );
+        }
+        return 0;
+    }
+    
+    match pick(&entries) {
+        Ok(Some((version, scope))) => write_pin(&version, scope),
+        Ok(None) => 0,
+        Err(e) => {
+            eprintln!("bldr wrapper select: {}", e);
+            1
+        }
+    }
+}
+
+fn collect_entries() -> Vec<Entry> {
+    let mut entries: Vec<Entry> = cache::installed()
+        .into_iter()
+        .map(|(version, binary)| Entry {
+            size: fs::metadata(&binary).ok().map(|m| m.len()),
+            version,
+            installed: true,
+            published: None,
+        })
+        .collect();
+    
+    let (os, arch) = platform::current();
+    let asset = format!("{}.tar.gz", platform::asset_name(os, arch));
+    match github::releases(&github::repo()) {
+        Ok(releases) => {
+            for release in releases.iter().filter(|r| !r.draft) {
+                let tag = release.tag_name.trim_start_matches('v').to_string();
+                let published = release.published_at.as_ref().map(|d| d.chars().take(10).collect());
+                let download = release.assets.iter().find(|a| a.name == asset).map(|a| a.size);
+                
+                match entries.iter_mut().find(|e| e.version == tag) {
+                    Some(existing) => existing.published = published,
+                    None => entries.push(Entry {
+                        version: tag,
+                        installed: false,
+                        size: download,
+                        published,
+                    }),
+                }
+            }
+        }
+        Err(e) => eprintln!("bldr wrapper select: cannot list releases ({}); showing installed versions", e),
+    }
+    
+    entries.sort_by(|a, b| version::compare(&b.version, &a.version));
+    entries
+}
+
+/// Interactive picker; returns the chosen version and pin scope.
+fn pick(entries: &[Entry]) -> Result<Option<(String, Scope)>, String> {
+    let current = current_pin();
+    let mut state = TableState::default();
+    state.select(Some(entries.iter().position(|e| Some(&e.version) == current.as_ref()).unwrap_or(0)));
+    
+    let mut terminal = ratatui::init();
+    let result = loop {
+        let drawn = terminal.draw(|frame| {
+            let [table_area, help_area] = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+            
+            let rows = entries.iter().map(|e| {
+                let marker = if Some(&e.version) == current.as_ref() { "*" } else { " " };
+                Row::new(vec![
+                    format!("{} {}", marker, e.version),
+                    if e.installed { "installed".to_string() } else { "available".to_string() },
+                    e.size.map(human_size).unwrap_or_default(),
+                    e.published.clone().unwrap_or_default(),
+                ])
+            });
+            let table = Table::new(
+                rows,
+                [Constraint::Length(16), Constraint::Length(11), Constraint::Length(10), Constraint::Min(10)],
+            )
+            .header(Row::new(vec!["  Version", "Status", "Size", "Released"]).style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(" bldr versions "))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(table, table_area, &mut state);
+            
+            let help = Line::from(" ↑/↓ move   enter/p pin for project   g pin globally   q quit");
+            frame.render_widget(Paragraph::new(help), help_area);
+        });
+        if let Err(e) = drawn {
+            break Err(e.to_string());
+        }
+        
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e.to_string()),
+        };
+        let selected = state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down | KeyCode::Char('j') => state.select(Some((selected + 1).min(entries.len() - 1))),
+            KeyCode::Enter | KeyCode::Char('p') => break Ok(Some((entries[selected].version.clone(), Scope::Project))),
+            KeyCode::Char('g') => break Ok(Some((entries[selected].version.clone(), Scope::Global))),
+            KeyCode::Esc | KeyCode::Char('q') => break Ok(None),
+            _ => {}
+        }
+    };
+    
+    ratatui::restore();
+    result
+}
+
+fn current_pin() -> Option<String> {
+    let project = env::current_dir().ok().and_then(|cwd| pin::find_project(&cwd));
+    project
+        .into_iter()
+        .chain(pin::global_path())
+        .find_map(|path| pin::read(&path))
+        .or_else(|| Some(VERSION.to_string()))
+}
+
+fn write_pin(version: &str, scope: Scope) -> i32 {
+    if version::Selector::parse(version).is_none() {
+        eprintln!("bldr wrapper select: '{}' is not a version, prefix or channel", version);
+        return 2;
+    }
+    
+    let path: Option<PathBuf> = match scope {
+        Scope::Project => env::current_dir().ok().map(|cwd| cwd.join(pin::PROJECT_FILE)),
+        Scope::Global => pin::global_path(),
+    };
+    let Some(path) = path else {
+        eprintln!("bldr wrapper select: cannot determine where to write the pin");
+        return 1;
+    };
+    
+    match pin::write(&path, version.trim_start_matches('v')) {
+        Ok(()) => {
+            eprintln!("Pinned bldr {} in {}", version, path.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("bldr wrapper select: cannot write {}: {}", path.display(), e);
+            1
+        }
+    }
+}
+
+/// `bldr wrapper use <version>`: make `version` the global default (the
+/// global pin), downloading an exact release now so the switch is ready.
+pub fn use_version(args: &[String]) -> i32 {
+    let [requested] = args else {
+        eprintln!("Usage: bldr wrapper use <version|prefix|channel>");
+        return 2;
+    };
+    let code = write_pin(requested, Scope::Global);
+    if code != 0 {
+        return code;
+    }
+    if let Some(version::Selector::Exact(version)) = version::Selector::parse(requested) {
+        if crate::get_or_download_binary(&version, true).is_none() {
+            eprintln!("bldr wrapper use: cannot download bldr {}; it is downloaded on first run instead", version);
+        } else {
+            super::install_bin::follow(&version);
+        }
+    }
+    if let Some(project) = env::current_dir().ok().and_then(|cwd| pin::find_project(&cwd)) {
+        eprintln!("Note: {} still pins this project", project.display());
+    }
+    0
+}
+
+/// `bldr wrapper rollback`: make the version that ran successfully before
+/// the latest one the global default again.
+pub fn rollback(args: &[String]) -> i32 {
+    if let Some(arg) = args.first() {
+        eprintln!("bldr wrapper rollback: unexpected argument '{}'", arg);
+        eprintln!("Usage: bldr wrapper rollback");
+        return 2;
+    }
+    let Some(previous) = history::previous() else {
+        eprintln!("bldr wrapper rollback: no earlier version has run successfully");
+        return 1;
+    };
+    use_version(&[previous])
+}
+
+fn write_channel(name: &str) -> i32 {
+    let Some(channel) = channel::parse(name) else {
+        eprintln!("bldr wrapper select: '{}' is not a channel (expected {})", name, channel::CHANNELS.join(", "));
+        return 2;
+    };
+    let Some(path) = channel::config_path() else {
+        eprintln!("bldr wrapper select: cannot determine where to write the channel");
+        return 1;
+    };
+    
+    match pin::write(&path, channel) {
+        Ok(()) => {
+            eprintln!("Following the {} channel ({})", channel, path.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("bldr wrapper select: cannot write {}: {}", path.display(), e);
+            1
+        }
+    }
+}
+
+pub(super) fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(5 * 1024 * 1024 + 512 * 1024), "5.5 MB");
+    }
+}
@@ -0,0 +1,270 @@
+//! `bldr wrapper selftest`: exercise the wrapper's own machinery end to end
+//! against a local fixture and report a pass/fail matrix.
+
+use crate::{cache, checksum, github, http, install, platform, VERSION};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const STUB_OUTPUT: &str = "bldr-selftest-ok";
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Pass,
+    Fail,
+    Skip,
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    outcome: Outcome,
+    detail: String,
+}
+
+struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn record(&mut self, name: &'static str, result: Result<String, String>) -> bool {
+        let (outcome, detail) = match result {
+            Ok(detail) => (Outcome::Pass, detail),
+            Err(detail) => (Outcome::Fail, detail),
+        };
+        self.checks.push(Check { name, outcome, detail });
+        outcome == Outcome::Pass
+    }
+    
+    fn skip(&mut self, name: &'static str, reason: &str) {
+        self.checks.push(Check {
+            name,
+            outcome: Outcome::Skip,
+            detail: reason.to_string(),
+        });
+    }
+    
+    fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.outcome != Outcome::Fail)
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let mut json = false;
+    let mut network = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--network" => network = true,
+            "-h" | "--help" => {
+                print_usage();
+                return 0;
+            }
+            other => {
+                eprintln!("bldr wrapper selftest: unexpected argument '{}'", other);
+                print_usage();
+                return 2;
+            }
+        }
+    }
+    
+    let scratch = env::temp_dir().join(format!("bldr-selftest-{}", std::process::id()));
+    let report = run_checks(&scratch, network);
+    fs::remove_dir_all(&scratch).ok();
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report.checks).unwrap_or_default());
+    } else {
+        for check in &report.checks {
+            let label = match check.outcome {
+                Outcome::Pass => "PASS",
+                Outcome::Fail => "FAIL",
+                Outcome::Skip => "SKIP",
+            };
+            println!("{:<5} {:<13} {}", label, check.name, check.detail);
+        }
+    }
+    
+    if report.passed() {
+        0
+    } else {
+        1
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper selftest [--json] [--network]");
+    eprintln!();
+    eprintln!("  --json      Print the result matrix as JSON");
+    eprintln!("  --network   Also check that the release for this platform is reachable");
+}
+
+fn run_checks(scratch: &Path, network: bool) -> Report {
+    let mut report = Report { checks: Vec::new() };
+    
+    report.record("platform", check_platform());
+    report.record("cache", check_cache_writable());
+    
+    let fixture = match make_fixture(scratch) {
+        Ok(path) => path,
+        Err(e) => {
+            report.record("fixture", Err(e));
+            for name in ["download", "verification", "extraction", "locking", "exec"] {
+                report.skip(name, "no fixture archive");
+            }
+            return report;
+        }
+    };
+    
+    let downloaded = scratch.join("download").join("bldr.tar.gz");
+    let download_ok = report.record("download", check_download(&fixture, &downloaded));
+    
+    if download_ok {
+        report.record("verification", check_verification(&fixture, &downloaded));
+    } else {
+        report.skip("verification", "download failed");
+    }
+    
+    let install_dir = scratch.join("cache").join(VERSION);
+    let extracted = if download_ok {
+        let result = fs::create_dir_all(&install_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| install::extract(&downloaded, &install_dir));
+        let binary = result.as_ref().ok().cloned();
+        report.record("extraction", result.map(|p| format!("unpacked {}", p.display())));
+        binary
+    } else {
+        report.skip("extraction", "download failed");
+        None
+    };
+    
+    report.record("locking", check_locking(&install_dir));
+    
+    match extracted {
+        Some(binary) if cfg!(unix) => {
+            report.record("exec", check_exec(&binary));
+        }
+        Some(_) => report.skip("exec", "fixture is a shell script"),
+        None => report.skip("exec", "extraction failed"),
+    }
+    
+    if network {
+        report.record("network", check_network());
+    }
+    
+    report
+}
+
+fn check_platform() -> Result<String, String> {
+    let (os, arch) = platform::current();
+    if platform::PUBLISHED.contains(&(os, arch)) {
+        Ok(format!("{}-{} has published binaries", os, arch))
+    } else {
+        Err(format!("no published binaries for {}-{}", os, arch))
+    }
+}
+
+fn check_cache_writable() -> Result<String, String> {
+    let root = cache::root();
+    let probe = root.join(format!(".selftest-{}", std::process::id()));
+    fs::create_dir_all(&root)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe))
+        .map(|_| format!("{} is writable", root.display()))
+        .map_err(|e| format!("{}: {}", root.display(), e))
+}
+
+/// A release-shaped archive whose binary just prints a marker.
+fn make_fixture(scratch: &Path) -> Result<PathBuf, String> {
+    let staging = scratch.join("fixture");
+    fs::create_dir_all(&staging).map_err(|e| format!("cannot create {}: {}", staging.display(), e))?;
+    fs::write(staging.join(install::binary_name()), format!("#!/bin/sh\necho {}\n", STUB_OUTPUT))
+        .map_err(|e| format!("cannot write fixture: {}", e))?;
+    
+    // Executable in the archive, as in a release
+    install::make_executable(&staging.join(install::binary_name())).map_err(|e| e.to_string())?;
+    
+    let archive = scratch.join("fixture.tar.gz");
+    let file = File::create(&archive).map_err(|e| format!("cannot create {}: {}", archive.display(), e))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder
+        .append_path_with_name(staging.join(install::binary_name()), install::binary_name())
+        .and_then(|_| builder.into_inner()?.finish())
+        .map_err(|e| format!("failed to build fixture archive: {}", e))?;
+    Ok(archive)
+}
+
+fn check_download(fixture: &Path, dest: &Path) -> Result<String, String> {
+    let parent = dest.parent().ok_or("bad download path")?;
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    let url = format!("file://{}", fixture.display());
+    http::download(&url, dest)?;
+    let size = fs::metadata(dest).map_err(|e| e.to_string())?.len();
+    Ok(format!("fetched {} bytes", size))
+}
+
+fn check_verification(fixture: &Path, downloaded: &Path) -> Result<String, String> {
+    let expected = checksum::sha256_file(fixture).map_err(|e| e.to_string())?;
+    let actual = checksum::sha256_file(downloaded).map_err(|e| e.to_string())?;
+    if expected != actual {
+        return Err("downloaded digest does not match fixture".to_string());
+    }
+    
+    // A corrupted copy must be caught
+    let tampered = downloaded.with_extension("tampered");
+    let mut bytes = fs::read(downloaded).map_err(|e| e.to_string())?;
+    if let Some(last) = bytes.last_mut() {
+        *last ^= 0xff;
+    }
+    fs::write(&tampered, &bytes).map_err(|e| e.to_string())?;
+    let tampered_digest = checksum::sha256_file(&tampered).map_err(|e| e.to_string())?;
+    fs::remove_file(&tampered).ok();
+    
+    if tampered_digest == expected {
+        Err("tampered archive was not detected".to_string())
+    } else {
+        Ok(format!("sha256 {} matches; tampering detected", &actual[..12]))
+    }
+}
+
+fn check_locking(dir: &Path) -> Result<String, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let lock = dir.join(".lock");
+    let first = OpenOptions::new().write(true).create_new(true).open(&lock);
+    let second = OpenOptions::new().write(true).create_new(true).open(&lock);
+    fs::remove_file(&lock).ok();
+    
+    match (first, second) {
+        (Ok(_), Err(_)) => Ok("exclusive lock creation works".to_string()),
+        (Ok(_), Ok(_)) => Err("second lock holder was not excluded".to_string()),
+        (Err(e), _) => Err(format!("cannot create {}: {}", lock.display(), e)),
+    }
+}
+
+fn check_exec(binary: &Path) -> Result<String, String> {
+    let output = Command::new(binary)
+        .output()
+        .map_err(|e| format!("cannot execute {}: {}", binary.display(), e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() && stdout.trim() == STUB_OUTPUT {
+        Ok("child ran and exited cleanly".to_string())
+    } else {
+        Err(format!("unexpected child result: {}", output.status))
+    }
+}
+
+fn check_network() -> Result<String, String> {
+    let (os, arch) = platform::current();
+    let asset = platform::asset_name(os, arch);
+    let release = github::release_by_tag(&github::repo(), &format!("v{}", VERSION))?;
+    if release.assets.iter().any(|a| a.name.starts_with(&asset)) {
+        Ok(format!("{} v{} is downloadable", asset, VERSION))
+    } else {
+        Err(format!("release v{} has no {} asset", VERSION, asset))
+    }
+}
@@ -0,0 +1,191 @@
+//! `bldr wrapper uninstall` (`bldr self uninstall`): remove everything the
+//! wrapper put on this machine, ahead of removing the wrapper itself.
+//!
+//! That is what the wrapper keeps in its cache (every installed core
+//! version, the LDC toolchains and the wrapper's state, then the cache
+//! directory itself if nothing else is left in it), the wrapper's config directory (global pin, channel), the daemon's
+//! runtime directory, completion scripts in their usual places, cores put on
+//! `PATH` with `install-bin`, and the scheduled updater and build daemon if
+//! enabled. The shared team cache,
+//! project `.bldr-version` files, git hooks and the system policy are left
+//! alone. `--dry-run` lists what would go; otherwise it asks first unless
+//! `--yes` (or `BLDR_ASSUME_YES=1`) is given.
+
+use super::{daemon, doctor, install_bin, normalize, updater};
+use crate::version::Selector;
+use crate::{cache, consent, pin};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// What the wrapper keeps in its cache besides a directory per version.
+const CACHE_STATE: &[&str] = &[
+    ".consented",
+    ".locks",
+    "crashes",
+    "github",
+    "last-good",
+    "mirror-staging",
+    "platforms",
+    "profiles",
+    "resolved",
+    "update-check",
+    "updater",
+];
+/// Prefixes of scratch entries commands leave behind if interrupted.
+const CACHE_SCRATCH: &[&str] = &[".bundle.tmp", ".doctor-", ".selftest-"];
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let (mut dry_run, mut assume_yes) = (false, false);
+    for arg in &args {
+        match arg.as_str() {
+            "--dry-run" | "-n" => dry_run = true,
+            "--yes" | "-y" => assume_yes = true,
+            other => {
+                eprintln!("bldr wrapper uninstall: unexpected argument '{}'", other);
+                eprintln!("Usage: bldr wrapper uninstall [--dry-run] [--yes]");
+                return 2;
+            }
+        }
+    }
+    
+    let paths = paths();
+    let updater = updater::scheduled();
+    let daemon = daemon::enabled();
+    if paths.is_empty() && !updater && !daemon {
+        eprintln!("Nothing to remove; the wrapper has left nothing on this machine.");
+        return 0;
+    }
+    let verb = if dry_run { "Would remove" } else { "This removes" };
+    eprintln!("{}:", verb);
+    if updater {
+        eprintln!("  the scheduled background updater");
+    }
+    if daemon {
+        eprintln!("  the build daemon's socket activation");
+    }
+    for (path, what) in &paths {
+        eprintln!("  {}  ({})", path.display(), what);
+    }
+    if dry_run {
+        return 0;
+    }
+    if !consent::assumed(assume_yes) && !confirmed() {
+        eprintln!("bldr wrapper uninstall: nothing was removed");
+        return 1;
+    }
+    
+    let mut failed = false;
+    if updater {
+        failed |= updater::disable().map_err(|e| eprintln!("bldr wrapper uninstall: {}", e)).is_err();
+    }
+    if daemon {
+        failed |= daemon::disable().map_err(|e| eprintln!("bldr wrapper uninstall: {}", e)).is_err();
+    }
+    for (path, _) in &paths {
+        let removed = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+        if let Err(e) = removed {
+            eprintln!("bldr wrapper uninstall: cannot remove {}: {}", path.display(), e);
+            failed = true;
+        }
+    }
+    // Only once empty: anything left there is not the wrapper's to remove
+    let root = cache::root();
+    if root.is_dir() && fs::remove_dir(&root).is_err() {
+        eprintln!("Left {} in place; it holds files the wrapper did not put there.", root.display());
+    }
+    
+    let exe = env::current_exe().map(|exe| exe.display().to_string()).unwrap_or_else(|_| "bldr".to_string());
+    eprintln!("Removed the wrapper's files. Remove the wrapper itself ({}) with", exe);
+    eprintln!("`cargo uninstall bldr` or the package manager that installed it.");
+    i32::from(failed)
+}
+
+/// Files and directories the wrapper created that exist, and what each is.
+fn paths() -> Vec<(PathBuf, &'static str)> {
+    let mut paths = Vec::new();
+    if let Some(config) = pin::global_path().and_then(|path| Some(path.parent()?.to_path_buf())) {
+        paths.push((config, "global pin and channel"));
+    }
+    if let Some(runtime) = dirs::runtime_dir() {
+        paths.push((runtime.join("bldr"), "build daemon socket"));
+    }
+    for path in install_bin::installed_paths() {
+        paths.push((path, "core installed with install-bin"));
+    }
+    for (_, path) in doctor::completion_files() {
+        paths.push((path, "shell completions"));
+    }
+    // Choosing a directory can create it, so an empty one is no trace
+    paths.retain(|(path, _)| match fs::read_dir(path) {
+        Ok(mut entries) => entries.next().is_some(),
+        Err(_) => path.exists(),
+    });
+    let mut owned = cache_entries(&cache::root());
+    owned.append(&mut paths);
+    owned
+}
+
+/// Entries of the cache at `root` the wrapper put there: version
+/// directories, the LDC toolchains and its own state.
+fn cache_entries(root: &Path) -> Vec<(PathBuf, &'static str)> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut owned: Vec<(PathBuf, &'static str)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let is_dir = entry.path().is_dir();
+            let what = if name == "ldc" && is_dir {
+                "LDC toolchains"
+            } else if is_dir && matches!(Selector::parse(&name), Some(Selector::Exact(ref v) | Selector::Channel(ref v)) if *v == name) {
+                "installed core version"
+            } else if CACHE_STATE.contains(&name.as_str()) || CACHE_SCRATCH.iter().any(|prefix| name.starts_with(prefix)) {
+                "wrapper state"
+            } else {
+                return None;
+            };
+            Some((entry.path(), what))
+        })
+        .collect();
+    owned.sort();
+    owned
+}
+
+/// Ask on the terminal; anything but yes, or no terminal, declines.
+fn confirmed() -> bool {
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        eprintln!("bldr wrapper uninstall: pass --yes to remove these without a terminal to confirm on");
+        return false;
+    }
+    eprint!("Remove them? [y/N] ");
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).is_ok() && matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn removes_only_what_the_wrapper_put_in_the_cache() {
+        let root = std::env::temp_dir().join(format!("bldr-uninstall-{}", std::process::id()));
+        for dir in ["2.0.3", "nightly", "ldc", ".locks", "resolved", "notes", "v2.0.3"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [".consented", "update-check", "todo.txt"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        
+        let names: Vec<String> = cache_entries(&root)
+            .into_iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, [".consented", ".locks", "2.0.3", "ldc", "nightly", "resolved", "update-check"]);
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,142 @@
+//! `bldr wrapper update` (`bldr self update`): move to the newest release
+//! now rather than on the next resolution.
+//!
+//! A floating selector (the one given, `BLDR_VERSION`, a pin or the channel)
+//! is resolved against the releases API at once and its release downloaded.
+//! When the default is an exact version (the global pin, or the release the
+//! wrapper was built for when nothing is pinned), the newest stable release
+//! is downloaded and verified first, and only then written as the global pin
+//! in one step, so an interrupted update leaves the old default in place. A
+//! project pinned to an exact version is left alone; it changes with
+//! `bldr wrapper select --pin`.
+
+use super::normalize;
+use crate::{changelog, channel, consent, pin, policy, version, VERSION};
+use std::env;
+use std::path::PathBuf;
+
+/// Where the version this directory runs comes from.
+enum Current {
+    /// A floating selector: given, `BLDR_VERSION`, a pin or the channel.
+    Floating(version::Selector),
+    /// An exact version fixed by `BLDR_VERSION` or a project pin, and which.
+    Fixed(String, String),
+    /// An exact global pin, or none and so the wrapper's own version.
+    Global(Option<PathBuf>, String),
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let mut assume_yes = false;
+    let mut requested = None;
+    for arg in &args {
+        match arg.as_str() {
+            "--yes" | "-y" => assume_yes = true,
+            other if !other.starts_with('-') && requested.is_none() => requested = Some(other.to_string()),
+            other => {
+                eprintln!("bldr wrapper update: unexpected argument '{}'", other);
+                eprintln!("Usage: bldr wrapper update [<prefix|channel>] [--yes]");
+                return 2;
+            }
+        }
+    }
+    
+    let current = match &requested {
+        Some(requested) => match version::Selector::parse(requested) {
+            Some(version::Selector::Exact(version)) => {
+                eprintln!("bldr wrapper update: {} is an exact version; switch to it with `bldr wrapper use {}`", version, version);
+                return 2;
+            }
+            Some(selector) => Current::Floating(selector),
+            None => {
+                eprintln!("bldr wrapper update: '{}' is not a version, prefix or channel", requested);
+                return 2;
+            }
+        },
+        None => current(),
+    };
+    
+    let assume_yes = consent::assumed(assume_yes);
+    let outcome = match current {
+        Current::Floating(selector) => update(&selector, assume_yes).map(|version| {
+            eprintln!("bldr {} is at {}", selector.label(), version);
+        }),
+        Current::Fixed(version, origin) => {
+            eprintln!("bldr {} is pinned by {}; nothing to update.", version, origin);
+            eprintln!("Change the pin with: bldr wrapper select --pin <version>");
+            Ok(())
+        }
+        Current::Global(path, version) => switch_default(path, &version, assume_yes),
+    };
+    match outcome {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("bldr wrapper update: {}", e);
+            1
+        }
+    }
+}
+
+/// What this directory runs, looked up in the launcher's order.
+fn current() -> Current {
+    let exact_or_floating = |selector, origin: String| match selector {
+        version::Selector::Exact(version) => Current::Fixed(version, origin),
+        selector => Current::Floating(selector),
+    };
+    if let Some(selector) = pin::from_env() {
+        return exact_or_floating(selector, "BLDR_VERSION".to_string());
+    }
+    let project = env::current_dir().ok().and_then(|cwd| pin::find_project(&cwd));
+    if let Some(path) = project {
+        if let Some(selector) = pin::read(&path).and_then(|v| version::Selector::parse(&v)) {
+            return exact_or_floating(selector, path.display().to_string());
+        }
+    }
+    
+    let global = pin::global_path();
+    match global.as_deref().and_then(pin::read).and_then(|v| version::Selector::parse(&v)) {
+        Some(version::Selector::Exact(version)) => Current::Global(global, version),
+        Some(selector) => Current::Floating(selector),
+        None => match channel::selector() {
+            Some(selector) => Current::Floating(selector),
+            None => Current::Global(global, VERSION.to_string()),
+        },
+    }
+}
+
+/// Resolve a floating selector now and download what it points at.
+fn update(selector: &version::Selector, assume_yes: bool) -> Result<String, String> {
+    let policy = policy::current()?;
+    let resolved = version::refresh(selector)?;
+    policy.check_version(&resolved.version)?;
+    if let Some(previous) = &resolved.previous {
+        changelog::print(previous, &resolved.version, &resolved.notes);
+    }
+    download(&resolved.version, assume_yes)
+}
+
+/// Move the exact global default from `current` to the newest stable
+/// release once it is downloaded and verified.
+fn switch_default(path: Option<PathBuf>, current: &str, assume_yes: bool) -> Result<(), String> {
+    let path = path.ok_or("cannot determine where the global pin lives")?;
+    let selector = version::Selector::Channel("stable".to_string());
+    let newest = version::latest(&selector)?;
+    if version::compare(&newest, current).is_le() {
+        eprintln!("bldr {} is the newest release.", current);
+        return Ok(());
+    }
+    policy::current()?.check_version(&newest)?;
+    download(&newest, assume_yes)?;
+    
+    pin::write(&path, &newest).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    changelog::print(current, &newest, &version::notes(&selector, current, &newest));
+    eprintln!("bldr now runs {} by default ({})", newest, path.display());
+    super::install_bin::follow(&newest);
+    Ok(())
+}
+
+fn download(version: &str, assume_yes: bool) -> Result<String, String> {
+    crate::get_or_download_binary(version, assume_yes)
+        .map(|_| version.to_string())
+        .ok_or_else(|| format!("cannot download bldr {}", version))
+}
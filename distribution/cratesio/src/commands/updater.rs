@@ -0,0 +1,413 @@
+//! `bldr wrapper updater`: pre-download new versions off-peak.
+//!
+//! `enable` installs a user-level scheduled job (a systemd user timer, a
+//! launchd agent or a Windows scheduled task) that runs
+//! `bldr wrapper updater run` once a day inside the configured window. The
+//! run resolves the global pin and every floating selector this machine has
+//! used, without recording the new resolution, and downloads anything not yet
+//! cached. The next interactive invocation then moves to the new version (and
+//! prints its release notes) without waiting on a download.
+
+use super::{normalize, value};
+use crate::{cache, install, perms, pin, policy, version};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_WINDOW: &str = "02:00-05:00";
+const UNIT: &str = "bldr-updater";
+const LAUNCHD_LABEL: &str = "com.griffincancode.bldr-updater";
+
+/// Daily window as minutes after midnight; may wrap past midnight.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Window {
+    start: u32,
+    end: u32,
+}
+
+impl Window {
+    fn parse(s: &str) -> Option<Window> {
+        let (start, end) = s.split_once('-')?;
+        let minutes = |t: &str| -> Option<u32> {
+            let (h, m) = t.trim().split_once(':')?;
+            let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+            (h < 24 && m < 60).then_some(h * 60 + m)
+        };
+        let window = Window {
+            start: minutes(start)?,
+            end: minutes(end)?,
+        };
+        (window.start != window.end).then_some(window)
+    }
+    
+    fn contains(&self, minute: u32) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+    
+    fn length_secs(&self) -> u32 {
+        (self.end + 24 * 60 - self.start) % (24 * 60) * 60
+    }
+    
+    fn label(&self) -> String {
+        format!(
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let Some((command, rest)) = args.split_first() else {
+        print_usage();
+        return 2;
+    };
+    
+    let mut window = Window::parse(DEFAULT_WINDOW).expect("default window is valid");
+    let mut now = false;
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--window" => value(&mut iter, arg).and_then(|w| {
+                Window::parse(&w)
+                    .map(|parsed| window = parsed)
+                    .ok_or_else(|| format!("invalid window '{}' (expected HH:MM-HH:MM)", w))
+            }),
+            "--now" => {
+                now = true;
+                Ok(())
+            }
+            other => Err(format!("unexpected argument '{}'", other)),
+        };
+        if let Err(e) = parsed {
+            eprintln!("bldr wrapper updater: {}", e);
+            print_usage();
+            return 2;
+        }
+    }
+    
+    let result = match command.as_str() {
+        "enable" => enable(window),
+        "disable" => disable(),
+        "status" => status(),
+        "run" => update(window, now),
+        _ => {
+            print_usage();
+            return 2;
+        }
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("bldr wrapper updater: {}", e);
+            1
+        }
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: bldr wrapper updater <command> [options]");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  enable [--window HH:MM-HH:MM]   Schedule daily pre-downloads (default {})", DEFAULT_WINDOW);
+    eprintln!("  disable                         Remove the scheduled job");
+    eprintln!("  status                          Show whether the job is installed and when it last ran");
+    eprintln!("  run [--window ...] [--now]      Pre-download now (what the job runs)");
+}
+
+fn enable(window: Window) -> Result<(), String> {
+    let exe = env::current_exe().map_err(|e| format!("cannot locate the bldr executable: {}", e))?;
+    let exe = exe.display().to_string();
+    
+    if cfg!(target_os = "macos") {
+        let path = launchd_plist().ok_or("cannot determine the LaunchAgents directory")?;
+        write(&path, &render_launchd(&exe, window))?;
+        // Reload so a changed window takes effect
+        Command::new("launchctl").arg("unload").arg(&path).output().ok();
+        command("launchctl", &["load", "-w", &path.display().to_string()])?;
+    } else if cfg!(windows) {
+        let task = format!("\"{}\" wrapper updater run --window {}", exe, window.label());
+        let start = format!("{:02}:{:02}", window.start / 60, window.start % 60);
+        command(
+            "schtasks",
+            &["/Create", "/F", "/SC", "DAILY", "/ST", &start, "/TN", UNIT, "/TR", &task],
+        )?;
+    } else {
+        let dir = systemd_dir().ok_or("cannot determine the systemd user unit directory")?;
+        let (service, timer) = render_systemd(&exe, window);
+        write(&dir.join(format!("{}.service", UNIT)), &service)?;
+        write(&dir.join(format!("{}.timer", UNIT)), &timer)?;
+        command("systemctl", &["--user", "daemon-reload"])?;
+        command("systemctl", &["--user", "enable", "--now", &format!("{}.timer", UNIT)])?;
+    }
+    
+    eprintln!("Background updates enabled daily between {}", window.label());
+    Ok(())
+}
+
+pub(super) fn disable() -> Result<(), String> {
+    if cfg!(target_os = "macos") {
+        if let Some(path) = launchd_plist().filter(|p| p.exists()) {
+            Command::new("launchctl").arg("unload").arg("-w").arg(&path).output().ok();
+            fs::remove_file(&path).map_err(|e| format!("cannot remove {}: {}", path.display(), e))?;
+        }
+    } else if cfg!(windows) {
+        Command::new("schtasks").args(["/Delete", "/F", "/TN", UNIT]).output().ok();
+    } else if let Some(dir) = systemd_dir() {
+        Command::new("systemctl")
+            .args(["--user", "disable", "--now", &format!("{}.timer", UNIT)])
+            .output()
+            .ok();
+        for unit in ["service", "timer"] {
+            fs::remove_file(dir.join(format!("{}.{}", UNIT, unit))).ok();
+        }
+        Command::new("systemctl").args(["--user", "daemon-reload"]).output().ok();
+    }
+    
+    eprintln!("Background updates disabled");
+    Ok(())
+}
+
+fn status() -> Result<(), String> {
+    println!("scheduled: {}", if scheduled() { "yes" } else { "no" });
+    
+    let last = fs::read_to_string(state_dir().join("last-run")).ok();
+    println!("last run:  {}", last.as_deref().map(str::trim).unwrap_or("never"));
+    Ok(())
+}
+
+/// Whether the scheduled job is installed.
+pub(super) fn scheduled() -> bool {
+    if cfg!(target_os = "macos") {
+        launchd_plist().map(|p| p.exists()).unwrap_or(false)
+    } else if cfg!(windows) {
+        Command::new("schtasks")
+            .args(["/Query", "/TN", UNIT])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    } else {
+        systemd_dir().map(|d| d.join(format!("{}.timer", UNIT)).exists()).unwrap_or(false)
+    }
+}
+
+/// The scheduled job: resolve what this machine uses and download it.
+fn update(window: Window, now: bool) -> Result<(), String> {
+    // Missed timers can fire on wake; stay out of working hours
+    if !now {
+        if let Some(minute) = local_minute().filter(|m| !window.contains(*m)) {
+            eprintln!(
+                "bldr wrapper updater: {:02}:{:02} is outside {}; skipping (use --now to force)",
+                minute / 60,
+                minute % 60,
+                window.label()
+            );
+            return Ok(());
+        }
+    }
+    let policy = policy::current()?;
+    
+    let mut failures = 0;
+    for selector in selectors() {
+        let version = match version::latest(&selector) {
+            Ok(version) => version,
+            Err(e) => {
+                eprintln!("bldr wrapper updater: cannot resolve {}: {}", selector.label(), e);
+                failures += 1;
+                continue;
+            }
+        };
+        if cache::root().join(&version).join(install::binary_name()).exists() {
+            continue;
+        }
+        if policy.check_version(&version).and_then(|_| policy.check_download(&version)).is_err() {
+            continue;
+        }
+        eprintln!("bldr wrapper updater: pre-downloading {} for {}", version, selector.label());
+        if crate::get_or_download_binary(&version, true).is_none() {
+            failures += 1;
+        }
+    }
+    
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let state = state_dir();
+    perms::create_dir_all(&state).ok();
+    let outcome = if failures == 0 { "ok".to_string() } else { format!("{} failed", failures) };
+    perms::write(&state.join("last-run"), format!("{} ({})\n", stamp, outcome)).ok();
+    
+    if failures == 0 {
+        Ok(())
+    } else {
+        Err(format!("{} version(s) could not be updated", failures))
+    }
+}
+
+/// The global pin plus every floating selector with a recorded resolution.
+fn selectors() -> Vec<version::Selector> {
+    let mut selectors: Vec<version::Selector> = pin::global_path()
+        .and_then(|path| pin::read(&path))
+        .and_then(|v| version::Selector::parse(&v))
+        .into_iter()
+        .collect();
+    
+    if let Ok(entries) = fs::read_dir(cache::root().join("resolved")) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(selector) = name.strip_suffix(".version").and_then(version::Selector::parse) {
+                if !selectors.contains(&selector) {
+                    selectors.push(selector);
+                }
+            }
+        }
+    }
+    selectors
+}
+
+fn render_systemd(exe: &str, window: Window) -> (String, String) {
+    let service = format!(
+        "[Unit]\nDescription=Pre-download bldr updates\n\n[Service]\nType=oneshot\nExecStart=\"{}\" wrapper updater run --window {}\nNice=19\nIOSchedulingClass=idle\n",
+        exe,
+        window.label()
+    );
+    let timer = format!(
+        "[Unit]\nDescription=Pre-download bldr updates off-peak\n\n[Timer]\nOnCalendar=*-*-* {:02}:{:02}:00\nRandomizedDelaySec={}\nPersistent=false\n\n[Install]\nWantedBy=timers.target\n",
+        window.start / 60,
+        window.start % 60,
+        // Leave a few minutes at the end of the window for the download
+        window.length_secs().saturating_sub(600).max(60)
+    );
+    (service, timer)
+}
+
+fn render_launchd(exe: &str, window: Window) -> String {
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let log = state_dir().join("updater.log");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>wrapper</string>
+        <string>updater</string>
+        <string>run</string>
+        <string>--window</string>
+        <string>{window}</string>
+    </array>
+    <key>StartCalendarInterval</key>
+    <dict>
+        <key>Hour</key>
+        <integer>{hour}</integer>
+        <key>Minute</key>
+        <integer>{minute}</integer>
+    </dict>
+    <key>ProcessType</key>
+    <string>Background</string>
+    <key>LowPriorityIO</key>
+    <true/>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        exe = escape(exe),
+        window = window.label(),
+        hour = window.start / 60,
+        minute = window.start % 60,
+        log = escape(&log.display().to_string())
+    )
+}
+
+fn systemd_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("systemd").join("user"))
+}
+
+fn launchd_plist() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join("Library/LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL)))
+}
+
+fn state_dir() -> PathBuf {
+    cache::root().join("updater")
+}
+
+fn write(path: &std::path::Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    }
+    fs::write(path, contents).map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
+
+fn command(program: &str, args: &[&str]) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("cannot run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Local time of day in minutes; `None` where we leave timing to the scheduler.
+#[cfg(unix)]
+fn local_minute() -> Option<u32> {
+    // SAFETY: time/localtime_r only write to the locals passed in
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return None;
+        }
+        Some(tm.tm_hour as u32 * 60 + tm.tm_min as u32)
+    }
+}
+
+#[cfg(not(unix))]
+fn local_minute() -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let night = Window::parse("23:30-01:00").unwrap();
+        assert!(night.contains(23 * 60 + 45));
+        assert!(night.contains(30));
+        assert!(!night.contains(12 * 60));
+        assert_eq!(night.length_secs(), 90 * 60);
+        assert_eq!(night.label(), "23:30-01:00");
+        assert_eq!(Window::parse("25:00-01:00"), None);
+        assert_eq!(Window::parse("02:00-02:00"), None);
+    }
+    
+    #[test]
+    fn timer_starts_at_window_open() {
+        let (service, timer) = render_systemd("/usr/bin/bldr", Window::parse("02:00-05:00").unwrap());
+        assert!(service.contains("ExecStart=\"/usr/bin/bldr\" wrapper updater run --window 02:00-05:00"));
+        assert!(timer.contains("OnCalendar=*-*-* 02:00:00"));
+        assert!(timer.contains("RandomizedDelaySec=10200"));
+    }
+}
@@ -0,0 +1,116 @@
+//! `bldr wrapper which` (`bldr self which`): the core binary this directory
+//! runs, for scripts and editors that want to call it directly.
+//!
+//! Prints the binary's path, its version, what selected that version
+//! (`BLDR_VERSION`, a pin file, the channel or the wrapper's own release)
+//! and where the binary comes from: `BLDR_BIN`, which overrides the rest,
+//! the cache, a core of that version on `PATH` (the system), the shared team
+//! cache, or a download on first run. Nothing is downloaded; `bldr --print-binary-path`
+//! installs the core if needed and prints only its path. `--json` prints
+//! one object with the same fields.
+
+use super::normalize;
+use crate::{cache, channel, config, install, pin, policy, shared, system, version};
+use std::env;
+
+pub fn run(args: &[String]) -> i32 {
+    let args = normalize(args);
+    let json = match args.as_slice() {
+        [] => false,
+        [flag] if flag == "--json" => true,
+        _ => {
+            eprintln!("Usage: bldr wrapper which [--json]");
+            return 2;
+        }
+    };
+    
+    if let Some(path) = crate::bin_override() {
+        if json {
+            let report = serde_json::json!({
+                "path": path,
+                "version": null,
+                "selector": null,
+                "selectedBy": "BLDR_BIN",
+                "source": "BLDR_BIN",
+                "installed": path.is_file(),
+            });
+            println!("{}", report);
+            return 0;
+        }
+        println!("{}", path.display());
+        println!("  version   unknown; pins and channels do not apply");
+        println!("  source    BLDR_BIN");
+        return 0;
+    }
+    
+    let (selector, selected_by) = selected();
+    let resolved = version::resolve(&selector).and_then(|resolved| {
+        policy::current()?.check_version(&resolved.version)?;
+        Ok(resolved.version)
+    });
+    let version = match resolved {
+        Ok(version) => version,
+        Err(e) => {
+            eprintln!("bldr wrapper which: cannot resolve {}: {}", selector.label(), e);
+            return 1;
+        }
+    };
+    let mut path = cache::root().join(&version).join(install::binary_name());
+    let system = if path.is_file() { None } else { system::find(&version) };
+    let source = if path.is_file() {
+        "cache"
+    } else if let Some(core) = system {
+        path = core;
+        "system"
+    } else if shared::has(&version) {
+        "shared"
+    } else {
+        "download"
+    };
+    
+    if json {
+        let report = serde_json::json!({
+            "path": path,
+            "version": version,
+            "selector": selector.label(),
+            "selectedBy": selected_by,
+            "source": source,
+            "installed": matches!(source, "cache" | "system"),
+        });
+        println!("{}", report);
+        return 0;
+    }
+    println!("{}", path.display());
+    println!("  version   {} ({} from {})", version, selector.label(), selected_by);
+    match source {
+        "cache" => println!("  source    the cache"),
+        "system" => println!("  source    the system, on PATH"),
+        "shared" => println!("  source    the shared cache {}, copied on first run", shared::dir().unwrap_or_default().display()),
+        _ => println!("  source    not installed yet; downloaded on first run (or `bldr wrapper install {}`)", version),
+    }
+    0
+}
+
+/// The selector this directory runs and what set it, in the launcher's order.
+fn selected() -> (version::Selector, String) {
+    if let Some(selector) = pin::from_env() {
+        return (selector, "BLDR_VERSION".to_string());
+    }
+    let project = env::current_dir().ok().and_then(|cwd| pin::find_project(&cwd));
+    for path in project.into_iter().chain(pin::global_path()) {
+        if let Some(selector) = pin::read(&path).and_then(|v| version::Selector::parse(&v)) {
+            return (selector, path.display().to_string());
+        }
+    }
+    if let Some(selector) = channel::selector() {
+        let origin = match env::var("BLDR_CHANNEL") {
+            Ok(value) if !value.trim().is_empty() => match config::origin("BLDR_CHANNEL") {
+                Some(path) => path.display().to_string(),
+                None => "BLDR_CHANNEL".to_string(),
+            },
+            _ => channel::config_path().map(|path| path.display().to_string()).unwrap_or_default(),
+        };
+        return (selector, origin);
+    }
+    (version::Selector::default(), "the wrapper's own release".to_string())
+}
@@ -0,0 +1,293 @@
+//! Wrapper configuration: `<config>/bldr/wrapper.toml`, with a project's
+//! `.bldr-wrapper.toml` (nearest ancestor) over it, a persistent home for
+//! the `BLDR_*` variables.
+//!
+//! ```toml
+//! channel = "stable"
+//! offline = false
+//!
+//! [download]
+//! mirrors = ["https://artifacts.example.com/bldr", "https://github.com/GriffinCanCode/bldr/releases/download"]
+//! max_rate = "2M"
+//!
+//! [proxy]
+//! https = "http://proxy.example.com:3128"
+//! no_proxy = ["localhost", ".example.com"]
+//!
+//! [cache]
+//! dir = "~/.cache/bldr"
+//! keep = 3
+//!
+//! [update]
+//! check = false
+//!
+//! [verify]
+//! sha256 = "require"
+//! minisign = "require"
+//! ```
+//!
+//! Each key stands for the variable [`SETTINGS`] lists, and a variable set
+//! in the environment wins over both files; the system policy constrains
+//! whatever they say. `channel` only applies while no channel has been
+//! chosen with `bldr wrapper select` or `update <channel>`. A project file
+//! comes with whatever is checked out, so it may not redirect downloads,
+//! relax verification or move the cache: keys only the user's file may set
+//! are ignored there with a warning, and `verify` keys only count there when
+//! they require a scheme. `bldr wrapper config` shows every key, its value
+//! and where that comes from.
+//!
+//! A file that cannot be read or parsed, an unknown key or a value of the
+//! wrong type is warned about and skipped rather than stopping the wrapper.
+
+use crate::{channel, pin};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const PROJECT_FILE: &str = ".bldr-wrapper.toml";
+
+/// How a key's TOML value becomes its variable's.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// A string or number, as written.
+    Text,
+    /// A path; a leading `~` is the home directory.
+    Path,
+    /// A string or a list of strings, joined with commas.
+    List,
+    /// `true` or `false`, as `1` or `0`.
+    Flag,
+    /// `true` or `false` for a `BLDR_NO_*` variable, so inverted.
+    Negated,
+    /// A verification mode (`off`, `warn`, `ci`, `require`, or a boolean).
+    Mode,
+}
+
+/// Who may set a key.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scope {
+    /// The user's file and a project's.
+    Project,
+    /// Only the user's file.
+    User,
+}
+
+pub struct Setting {
+    pub key: &'static str,
+    pub var: &'static str,
+    pub kind: Kind,
+    pub scope: Scope,
+}
+
+const fn setting(key: &'static str, var: &'static str, kind: Kind, scope: Scope) -> Setting {
+    Setting { key, var, kind, scope }
+}
+
+/// Every key the files understand, in the order `bldr wrapper config` lists
+/// them.
+pub const SETTINGS: &[Setting] = &[
+    setting("channel", "BLDR_CHANNEL", Kind::Text, Scope::Project),
+    setting("offline", "BLDR_OFFLINE", Kind::Flag, Scope::Project),
+    setting("download.mirrors", "BLDR_DOWNLOAD_BASE", Kind::List, Scope::User),
+    setting("download.token_file", "BLDR_DOWNLOAD_TOKEN_FILE", Kind::Path, Scope::User),
+    setting("download.credential_helper", "BLDR_CREDENTIAL_HELPER", Kind::Text, Scope::User),
+    setting("download.ca_bundle", "BLDR_CA_BUNDLE", Kind::Path, Scope::User),
+    setting("download.max_rate", "BLDR_MAX_DOWNLOAD_RATE", Kind::Text, Scope::Project),
+    setting("download.connections", "BLDR_DOWNLOAD_CONNECTIONS", Kind::Text, Scope::Project),
+    setting("download.ip_family", "BLDR_IP_FAMILY", Kind::Text, Scope::User),
+    setting("download.http_client", "BLDR_HTTP_CLIENT", Kind::Text, Scope::User),
+    setting("proxy.https", "HTTPS_PROXY", Kind::Text, Scope::User),
+    setting("proxy.http", "HTTP_PROXY", Kind::Text, Scope::User),
+    setting("proxy.no_proxy", "NO_PROXY", Kind::List, Scope::User),
+    setting("proxy.socks", "BLDR_SOCKS_PROXY", Kind::Text, Scope::User),
+    setting("proxy.pac", "BLDR_PROXY_PAC", Kind::Text, Scope::User),
+    setting("proxy.system", "BLDR_SYSTEM_PROXY", Kind::Flag, Scope::User),
+    setting("cache.dir", "BLDR_CACHE_DIR", Kind::Path, Scope::User),
+    setting("cache.keep", "BLDR_CACHE_KEEP", Kind::Text, Scope::Project),
+    setting("cache.max_size", "BLDR_CACHE_MAX_SIZE", Kind::Text, Scope::Project),
+    setting("cache.shared", "BLDR_SHARED_CACHE", Kind::Path, Scope::User),
+    setting("cache.shared_readonly", "BLDR_SHARED_CACHE_READONLY", Kind::Flag, Scope::User),
+    setting("cache.dedupe", "BLDR_NO_DEDUPE", Kind::Negated, Scope::User),
+    setting("update.check", "BLDR_NO_UPDATE_CHECK", Kind::Negated, Scope::Project),
+    setting("update.check_interval", "BLDR_UPDATE_CHECK_INTERVAL", Kind::Text, Scope::Project),
+    setting("update.changelog", "BLDR_NO_CHANGELOG", Kind::Negated, Scope::Project),
+    setting("verify.sha256", "BLDR_SHA256_VERIFY", Kind::Mode, Scope::Project),
+    setting("verify.tlog", "BLDR_TLOG_VERIFY", Kind::Mode, Scope::Project),
+    setting("verify.gpg", "BLDR_GPG_VERIFY", Kind::Mode, Scope::Project),
+    setting("verify.minisign", "BLDR_REQUIRE_SIGNATURE", Kind::Mode, Scope::Project),
+    setting("verify.cosign", "BLDR_COSIGN_VERIFY", Kind::Mode, Scope::Project),
+    setting("verify.attestation", "BLDR_ATTESTATION_VERIFY", Kind::Mode, Scope::Project),
+    setting("verify.gpg_keyring", "BLDR_GPG_KEYRING", Kind::Path, Scope::User),
+];
+
+/// A variable one of the files set, and which.
+struct Applied {
+    setting: &'static Setting,
+    origin: PathBuf,
+}
+
+static APPLIED: OnceLock<Vec<Applied>> = OnceLock::new();
+
+/// `<config>/bldr/wrapper.toml`.
+pub fn user_path() -> Option<PathBuf> {
+    pin::global_path().map(|path| path.with_file_name("wrapper.toml"))
+}
+
+/// Nearest `.bldr-wrapper.toml` at or above the current directory.
+pub fn project_path() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors().map(|dir| dir.join(PROJECT_FILE)).find(|candidate| candidate.is_file())
+}
+
+/// Read both files and set each variable the environment leaves unset.
+/// Called first thing, while the process has a single thread.
+pub fn apply() {
+    APPLIED.get_or_init(|| {
+        let mut applied: Vec<Applied> = Vec::new();
+        let files = [(project_path(), Scope::Project), (user_path(), Scope::User)];
+        for (path, scope) in files {
+            let Some(path) = path.filter(|path| path.is_file()) else {
+                continue;
+            };
+            let (values, warnings) = match fs::read_to_string(&path) {
+                Ok(contents) => parse(&contents, scope),
+                Err(e) => (Vec::new(), vec![format!("cannot read it: {}", e)]),
+            };
+            for warning in warnings {
+                eprintln!("bldr: warning: {}: {}", path.display(), warning);
+            }
+            for (setting, value) in values {
+                // The project file was read first and wins; proxies are
+                // also set as https_proxy and so on
+                let set = env::var_os(setting.var).is_some() || env::var_os(setting.var.to_ascii_lowercase()).is_some();
+                if set || applied.iter().any(|a| a.setting.var == setting.var) {
+                    continue;
+                }
+                if setting.key == "channel" && channel::config_path().and_then(|path| pin::read(&path)).is_some() {
+                    continue;
+                }
+                env::set_var(setting.var, &value);
+                applied.push(Applied { setting, origin: path.clone() });
+            }
+        }
+        applied
+    });
+}
+
+/// The file that set `var`, when one did rather than the environment.
+pub fn origin(var: &str) -> Option<&'static Path> {
+    APPLIED.get()?.iter().find(|a| a.setting.var == var).map(|a| a.origin.as_path())
+}
+
+/// Each setting's value from `contents`, and warnings about the rest.
+fn parse(contents: &str, scope: Scope) -> (Vec<(&'static Setting, String)>, Vec<String>) {
+    let table: toml::Table = match toml::from_str(contents) {
+        Ok(table) => table,
+        Err(e) => return (Vec::new(), vec![format!("not valid TOML, ignored: {}", e)]),
+    };
+    let mut flat = Vec::new();
+    flatten("", &table, &mut flat);
+    
+    let (mut values, mut warnings) = (Vec::new(), Vec::new());
+    for (key, value) in flat {
+        let Some(setting) = SETTINGS.iter().find(|setting| setting.key == key) else {
+            warnings.push(format!("unknown key '{}'", key));
+            continue;
+        };
+        let Some(value) = convert(setting.kind, &value) else {
+            warnings.push(format!("'{}' has a value of the wrong type", key));
+            continue;
+        };
+        let allowed = match (scope, setting.scope) {
+            (Scope::User, _) => true,
+            (Scope::Project, Scope::User) => false,
+            (Scope::Project, Scope::Project) => setting.kind != Kind::Mode || requires(&value),
+        };
+        if !allowed {
+            let why = if setting.scope == Scope::User { "only the user's wrapper.toml may set it" } else { "a project may only require verification" };
+            warnings.push(format!("ignoring '{}': {}", key, why));
+            continue;
+        }
+        values.push((setting, value));
+    }
+    (values, warnings)
+}
+
+/// `[download] mirrors = ...` as `("download.mirrors", ...)`.
+fn flatten(prefix: &str, table: &toml::Table, out: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(table) => flatten(&key, table, out),
+            value => out.push((key, value.clone())),
+        }
+    }
+}
+
+fn convert(kind: Kind, value: &toml::Value) -> Option<String> {
+    use toml::Value;
+    match (kind, value) {
+        (Kind::Text, Value::String(s)) => Some(s.clone()),
+        (Kind::Text, Value::Integer(n)) => Some(n.to_string()),
+        (Kind::Path, Value::String(s)) => Some(expand_home(s)),
+        (Kind::List, Value::String(s)) => Some(s.clone()),
+        (Kind::List, Value::Array(items)) => {
+            let items: Option<Vec<&str>> = items.iter().map(Value::as_str).collect();
+            Some(items?.join(","))
+        }
+        (Kind::Flag, Value::Boolean(b)) => Some(if *b { "1" } else { "0" }.to_string()),
+        (Kind::Negated, Value::Boolean(b)) => Some(if *b { "0" } else { "1" }.to_string()),
+        (Kind::Mode, Value::String(s)) => Some(s.clone()),
+        (Kind::Mode, Value::Boolean(b)) => Some(if *b { "require" } else { "off" }.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a verification mode requires the scheme, as [`crate::policy::Mode`]
+/// reads it.
+fn requires(mode: &str) -> bool {
+    !matches!(mode.to_ascii_lowercase().as_str(), "" | "0" | "off" | "false" | "no" | "warn" | "ci")
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).display().to_string(),
+        _ if path == "~" => dirs::home_dir().map(|home| home.display().to_string()).unwrap_or_else(|| path.to_string()),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn projects_cannot_redirect_or_relax() {
+        let contents = r#"
+            channel = "beta"
+            [download]
+            mirrors = ["https://evil.example.com"]
+            [update]
+            check = false
+            [verify]
+            sha256 = "off"
+            minisign = "require"
+        "#;
+        let values = |scope| -> Vec<(&str, String)> {
+            parse(contents, scope).0.into_iter().map(|(setting, value)| (setting.var, value)).collect()
+        };
+        let project = values(Scope::Project);
+        assert!(project.contains(&("BLDR_CHANNEL", "beta".to_string())));
+        assert!(project.contains(&("BLDR_NO_UPDATE_CHECK", "1".to_string())));
+        assert!(project.contains(&("BLDR_REQUIRE_SIGNATURE", "require".to_string())));
+        assert!(!project.iter().any(|(var, _)| *var == "BLDR_DOWNLOAD_BASE" || *var == "BLDR_SHA256_VERIFY"));
+        
+        let user = values(Scope::User);
+        assert!(user.contains(&("BLDR_DOWNLOAD_BASE", "https://evil.example.com".to_string())));
+        assert!(user.contains(&("BLDR_SHA256_VERIFY", "off".to_string())));
+        
+        let (_, warnings) = parse("[cache]\nkeep = true\ncolour = 1\n", Scope::User);
+        assert_eq!(warnings.len(), 2);
+    }
+}
@@ -0,0 +1,71 @@
+//! First-run download consent.
+//!
+//! The crate installs a wrapper; the build tool itself is a separate binary
+//! fetched on first use. Before the first download the wrapper says what it
+//! is about to fetch, from where and into which directory, and asks. `--yes` or
+//! `BLDR_ASSUME_YES=1` answers in advance. Nobody is asked when stdin or
+//! stderr is not a terminal or `CI` is set, so scripts and pipelines carry
+//! on as before. Once a download is accepted, or any version is already
+//! cached, later versions download without asking.
+
+use crate::{cache, perms, platform};
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+/// Marker in the cache root recording that downloads were accepted.
+const MARKER: &str = ".consented";
+
+/// `--yes` on the command line or `BLDR_ASSUME_YES=1`.
+pub fn assumed(flag: bool) -> bool {
+    flag || env::var("BLDR_ASSUME_YES").is_ok_and(|value| matches!(value.trim(), "1" | "true" | "yes"))
+}
+
+/// Whether `version` may be downloaded from `source`, asking first when
+/// this is the first download on an interactive terminal.
+pub fn confirm(version: &str, source: &str, assume_yes: bool) -> bool {
+    if assume_yes || given() || !interactive() {
+        return true;
+    }
+    
+    let (os, arch) = platform::current();
+    eprintln!("bldr: this is a wrapper; the build tool itself is downloaded on first use.");
+    eprintln!("  Version:  v{} for {}-{}", version, os, arch);
+    eprintln!("  From:     {}", source);
+    eprintln!("  Into:     {}", cache::root().join(version).display());
+    eprintln!("  The archive's checksum is verified before anything runs.");
+    eprintln!("  Pass --yes or set BLDR_ASSUME_YES=1 to skip this question.");
+    eprint!("Download it now? [Y/n] ");
+    io::stderr().flush().ok();
+    
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    let accepted = matches!(answer.trim().to_ascii_lowercase().as_str(), "" | "y" | "yes");
+    if accepted {
+        remember();
+    }
+    accepted
+}
+
+/// Consent given before: recorded, or implied by an existing install.
+fn given() -> bool {
+    marker().is_file() || !cache::installed().is_empty()
+}
+
+fn interactive() -> bool {
+    io::stdin().is_terminal() && io::stderr().is_terminal() && env::var_os("CI").is_none()
+}
+
+fn remember() {
+    let marker = marker();
+    if let Some(root) = marker.parent() {
+        perms::create_dir_all(root).ok();
+    }
+    perms::write(&marker, "").ok();
+}
+
+fn marker() -> PathBuf {
+    cache::root().join(MARKER)
+}
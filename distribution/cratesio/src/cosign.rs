@@ -0,0 +1,101 @@
+//! Sigstore keyless signature check for downloaded release archives.
+//!
+//! With `BLDR_COSIGN_VERIFY` set (or `cosign` among the policy's
+//! `verification` schemes), `<asset>.cosign.bundle` is fetched next to the
+//! archive and checked by `cosign verify-blob`. The bundle's certificate
+//! must name the release workflow of the expected repository, at the tag of
+//! the version being installed, as issued to GitHub Actions; a signature by
+//! anyone else, even a valid one, is rejected. Unlike the other schemes
+//! there is no warn mode: once enabled, an archive that cannot be verified
+//! (no bundle, no `cosign`, no network) is not used.
+
+use crate::github;
+use crate::http;
+use crate::policy::Mode;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const GITHUB_ISSUER: &str = "https://token.actions.githubusercontent.com";
+const RELEASE_WORKFLOW: &str = "release.yml";
+
+/// Apply the configured policy to the archive of `version` fetched from
+/// `url`. Errors mean the archive must not be used.
+pub fn check(archive: &Path, url: &str, version: &str) -> Result<(), String> {
+    if Mode::for_scheme("cosign", "BLDR_COSIGN_VERIFY") == Mode::Off {
+        return Ok(());
+    }
+    
+    let identity = identity(
+        &github::host(),
+        &env::var("BLDR_COSIGN_REPO").unwrap_or_else(|_| github::repo()),
+        &env::var("BLDR_COSIGN_WORKFLOW").unwrap_or_else(|_| RELEASE_WORKFLOW.to_string()),
+        version,
+    );
+    match verify(archive, url, &identity) {
+        Ok(()) => {
+            eprintln!("cosign signature: signed by {}", identity);
+            Ok(())
+        }
+        Err(e) => Err(format!("cosign signature check failed: {}", e)),
+    }
+}
+
+/// The certificate identity GitHub Actions on `host` gives `workflow` of
+/// `repo` when it runs for the tag of `version`.
+fn identity(host: &str, repo: &str, workflow: &str, version: &str) -> String {
+    format!(
+        "https://{}/{}/.github/workflows/{}@refs/tags/{}",
+        host,
+        repo,
+        workflow.trim_start_matches(".github/workflows/"),
+        github::tag(version)
+    )
+}
+
+fn verify(archive: &Path, url: &str, identity: &str) -> Result<(), String> {
+    let mut bundle = archive.as_os_str().to_owned();
+    bundle.push(".cosign.bundle");
+    let bundle = PathBuf::from(bundle);
+    http::download(&format!("{}.cosign.bundle", url), &bundle)?;
+    
+    let issuer = env::var("BLDR_COSIGN_ISSUER").unwrap_or_else(|_| GITHUB_ISSUER.to_string());
+    let output = Command::new("cosign")
+        .arg("verify-blob")
+        .arg("--bundle")
+        .arg(&bundle)
+        .args(["--certificate-identity", identity, "--certificate-oidc-issuer", &issuer])
+        .arg(archive)
+        .output();
+    fs::remove_file(&bundle).ok();
+    let output = output.map_err(|e| format!("failed to run cosign (is it installed?): {}", e))?;
+    
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}.cosign.bundle is not a valid signature by {}: {}",
+            url,
+            identity,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn expects_the_release_workflow_at_the_version_tag() {
+        assert_eq!(
+            identity("github.com", "GriffinCanCode/bldr", "release.yml", "2.0.3"),
+            "https://github.com/GriffinCanCode/bldr/.github/workflows/release.yml@refs/tags/v2.0.3"
+        );
+        assert_eq!(
+            identity("github.corp.com", "example/bldr", ".github/workflows/publish.yml", "2.1.0"),
+            "https://github.corp.com/example/bldr/.github/workflows/publish.yml@refs/tags/v2.1.0"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Crash diagnostics for the core binary.
+//!
+//! When the child dies from a fatal signal we bundle what we can into
+//! `<cache>/crashes/<timestamp>-<pid>/`: a summary, the OS crash report
+//! (macOS DiagnosticReports, systemd-coredump), a core file, and a symbolicated
+//! backtrace when debug symbols for the release can be fetched. With
+//! `BLDR_CRASH_RETRY=1` the command is re-run once with core dumps enabled.
+
+use crate::{cache, github, http, install, platform, VERSION};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A child that was killed by a fatal signal.
+pub struct Crash {
+    pub signal: i32,
+    pub pid: u32,
+}
+
+/// Fatal signal that ended the child, if any.
+#[cfg(unix)]
+pub fn detect(status: &ExitStatus, pid: u32) -> Option<Crash> {
+    use std::os::unix::process::ExitStatusExt;
+    
+    let signal = status.signal()?;
+    // Interrupts and kills are the user's doing, not crashes
+    if signal_name(signal).is_some() {
+        Some(Crash { signal, pid })
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+pub fn detect(_status: &ExitStatus, _pid: u32) -> Option<Crash> {
+    None
+}
+
+fn signal_name(signal: i32) -> Option<&'static str> {
+    match signal {
+        4 => Some("SIGILL"),
+        6 => Some("SIGABRT"),
+        7 if cfg!(target_os = "linux") => Some("SIGBUS"),
+        10 if cfg!(target_os = "macos") => Some("SIGBUS"),
+        8 => Some("SIGFPE"),
+        11 => Some("SIGSEGV"),
+        _ => None,
+    }
+}
+
+/// Collect a crash bundle, optionally retry with core dumps, and return the
+/// exit code to report (`128 + signal`, like a shell).
+pub fn handle(crash: Crash, binary: &Path, version: &str, args: &[String], started: SystemTime) -> i32 {
+    let name = signal_name(crash.signal).unwrap_or("signal");
+    eprintln!();
+    eprintln!("bldr: the build tool crashed ({})", name);
+    
+    let dir = match bundle_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("bldr: cannot create crash report directory: {}", e);
+            return 128 + crash.signal;
+        }
+    };
+    
+    write_summary(&dir, &crash, version, args);
+    collect_os_report(&dir, &crash, started);
+    let mut core = find_core(&dir, crash.pid);
+    
+    if core.is_none() && env::var("BLDR_CRASH_RETRY").map(|v| v == "1").unwrap_or(false) {
+        eprintln!("bldr: re-running with core dumps enabled...");
+        if let Some(retry) = rerun_with_core_dumps(binary, args) {
+            collect_os_report(&dir, &retry, started);
+            core = find_core(&dir, retry.pid);
+        }
+    }
+    
+    if let Some(core) = &core {
+        symbolicate(&dir, binary, version, core);
+    }
+    
+    eprintln!("bldr: crash report written to {}", dir.display());
+    eprintln!("      Please attach it to an issue at https://github.com/GriffinCanCode/bldr/issues");
+    if core.is_none() {
+        eprintln!("      Re-run with BLDR_CRASH_RETRY=1 to also capture a core dump.");
+    }
+    128 + crash.signal
+}
+
+fn bundle_dir() -> std::io::Result<PathBuf> {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let dir = cache::root().join("crashes").join(format!("{}-{}", stamp, std::process::id()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn write_summary(dir: &Path, crash: &Crash, version: &str, args: &[String]) {
+    let (os, arch) = platform::current();
+    let mut summary = String::new();
+    summary.push_str(&format!("bldr version:    {}\n", version));
+    summary.push_str(&format!("wrapper version: {}\n", VERSION));
+    summary.push_str(&format!("platform:        {}-{}\n", os, arch));
+    if let Some(source) = cache::source(version) {
+        summary.push_str(&format!("downloaded from: {}\n", source));
+    }
+    summary.push_str(&format!(
+        "signal:          {} ({})\n",
+        signal_name(crash.signal).unwrap_or("unknown"),
+        crash.signal
+    ));
+    summary.push_str(&format!("pid:             {}\n", crash.pid));
+    summary.push_str(&format!("arguments:       {:?}\n", args));
+    if let Ok(cwd) = env::current_dir() {
+        summary.push_str(&format!("directory:       {}\n", cwd.display()));
+    }
+    if let Some(uname) = capture("uname", &["-a"]) {
+        summary.push_str(&format!("system:          {}\n", uname.trim()));
+    }
+    
+    summary.push_str("\nenvironment:\n");
+    let mut vars: Vec<(String, String)> = env::vars()
+        .filter(|(k, _)| k.starts_with("BLDR_") || k.starts_with("BUILDER_"))
+        // Never bundle credentials into something users post publicly
+        .filter(|(k, _)| !["TOKEN", "SECRET", "PASSWORD", "KEY"].iter().any(|s| k.contains(s)))
+        .collect();
+    vars.sort();
+    for (key, value) in vars {
+        summary.push_str(&format!("  {}={}\n", key, value));
+    }
+    
+    fs::write(dir.join("summary.txt"), summary).ok();
+}
+
+fn collect_os_report(dir: &Path, crash: &Crash, started: SystemTime) {
+    if cfg!(target_os = "macos") {
+        // ReportCrash writes ~/Library/Logs/DiagnosticReports/bldr-<date>.ips
+        let Some(home) = dirs::home_dir() else {
+            return;
+        };
+        let reports = home.join("Library/Logs/DiagnosticReports");
+        // The report appears a moment after the process dies
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        if let Ok(entries) = fs::read_dir(&reports) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let recent = entry.metadata().and_then(|m| m.modified()).map(|t| t >= started).unwrap_or(false);
+                if name.starts_with("bldr") && recent {
+                    fs::copy(entry.path(), dir.join(&name)).ok();
+                }
+            }
+        }
+    } else if cfg!(target_os = "linux") {
+        let pid = crash.pid.to_string();
+        if let Some(info) = capture("coredumpctl", &["info", "--no-pager", &pid]) {
+            fs::write(dir.join(format!("coredumpctl-{}.txt", pid)), info).ok();
+        }
+    }
+}
+
+/// Locate a core file for `pid` and copy it into the bundle.
+fn find_core(dir: &Path, pid: u32) -> Option<PathBuf> {
+    let dest = dir.join(format!("core.{}", pid));
+    
+    if cfg!(target_os = "linux") {
+        let pattern = fs::read_to_string("/proc/sys/kernel/core_pattern").unwrap_or_default();
+        if pattern.starts_with('|') {
+            // Piped to a handler such as systemd-coredump
+            let dest_str = dest.to_str()?;
+            let ok = Command::new("coredumpctl")
+                .args(["dump", "--no-pager", &pid.to_string(), "-o", dest_str])
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false);
+            return if ok && dest.exists() { Some(dest) } else { None };
+        }
+    }
+    
+    let cwd = env::current_dir().ok()?;
+    let candidates = [
+        cwd.join(format!("core.{}", pid)),
+        cwd.join("core"),
+        PathBuf::from(format!("/cores/core.{}", pid)),
+    ];
+    let found = candidates.iter().find(|p| p.is_file())?;
+    fs::rename(found, &dest).or_else(|_| fs::copy(found, &dest).map(|_| ())).ok()?;
+    Some(dest)
+}
+
+#[cfg(unix)]
+fn rerun_with_core_dumps(binary: &Path, args: &[String]) -> Option<Crash> {
+    use std::os::unix::process::CommandExt;
+    
+    let mut cmd = Command::new(binary);
+    cmd.args(args);
+    // SAFETY: getrlimit/setrlimit are async-signal-safe and only touch the child
+    unsafe {
+        cmd.pre_exec(|| {
+            // Raise the soft limit as far as the hard limit allows
+            let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+            if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) == 0 {
+                limit.rlim_cur = limit.rlim_max;
+                libc::setrlimit(libc::RLIMIT_CORE, &limit);
+            }
+            Ok(())
+        });
+    }
+    
+    let mut child = cmd.spawn().ok()?;
+    let pid = child.id();
+    let status = child.wait().ok()?;
+    detect(&status, pid)
+}
+
+#[cfg(not(unix))]
+fn rerun_with_core_dumps(_binary: &Path, _args: &[String]) -> Option<Crash> {
+    None
+}
+
+/// Backtrace from the core, using release debug symbols when published.
+fn symbolicate(dir: &Path, binary: &Path, version: &str, core: &Path) {
+    let symbols = fetch_debug_symbols(binary, version);
+    
+    let output = if cfg!(target_os = "macos") {
+        capture_path("lldb", &["--batch", "-o", "bt all", "-c"], core, binary)
+    } else {
+        let mut args = vec!["-batch".to_string()];
+        if let Some(symbols) = &symbols {
+            args.push("-ex".to_string());
+            args.push(format!("symbol-file {}", symbols.display()));
+        }
+        args.extend(["-ex", "thread apply all bt"].iter().map(|s| s.to_string()));
+        Command::new("gdb")
+            .args(&args)
+            .arg(binary)
+            .arg(core)
+            .output()
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+    };
+    
+    if let Some(backtrace) = output.filter(|o| !o.trim().is_empty()) {
+        fs::write(dir.join("backtrace.txt"), backtrace).ok();
+    }
+}
+
+/// Debug symbols live next to the binary once downloaded: `bldr.debug`
+/// (Linux) or `bldr.dSYM` (macOS), from the `<asset>-debug.tar.gz` asset.
+fn fetch_debug_symbols(binary: &Path, version: &str) -> Option<PathBuf> {
+    let install_dir = binary.parent()?;
+    let name = if cfg!(target_os = "macos") { "bldr.dSYM" } else { "bldr.debug" };
+    let symbols = install_dir.join(name);
+    if symbols.exists() {
+        return Some(symbols);
+    }
+    
+    let (os, arch) = platform::current();
+    let archive = install_dir.join("debug.tar.gz");
+    let fetched = github::asset_urls(version, &format!("{}-debug.tar.gz", platform::asset_name(os, arch)))
+        .iter()
+        .any(|url| http::download(url, &archive).is_ok());
+    let extracted = fetched && install::unpack(&archive, install_dir).is_ok();
+    fs::remove_file(&archive).ok();
+    
+    if extracted && symbols.exists() {
+        Some(symbols)
+    } else {
+        None
+    }
+}
+
+fn capture(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+fn capture_path(program: &str, args: &[&str], core: &Path, binary: &Path) -> Option<String> {
+    let output = Command::new(program).args(args).arg(core).arg(binary).output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn only_fatal_signals_are_crashes() {
+        assert_eq!(signal_name(11), Some("SIGSEGV"));
+        assert_eq!(signal_name(6), Some("SIGABRT"));
+        assert_eq!(signal_name(2), None); // SIGINT
+        assert_eq!(signal_name(9), None); // SIGKILL
+    }
+}
@@ -0,0 +1,272 @@
+//! Credentials for authenticated mirrors and proxies.
+//!
+//! For each host a download or proxy connection goes to, credentials come
+//! from the first of:
+//!
+//! - For the GitHub API's host, the GitHub token (see [`github::token`]).
+//! - For the hosts of the download mirrors only, a token in
+//!   `BLDR_DOWNLOAD_TOKEN`, or in the file named by
+//!   `BLDR_DOWNLOAD_TOKEN_FILE` or the policy's `download_token_file`.
+//! - `BLDR_CREDENTIAL_HELPER`, a command run through the shell with `get`
+//!   appended that speaks git's credential helper protocol: it reads
+//!   `protocol=`, `host=` and `path=` lines on stdin and prints `username=`
+//!   and `password=` (a token). `git credential-store`, `git
+//!   credential-osxkeychain` or a script reading a vault all work.
+//! - `~/.netrc` (`%USERPROFILE%\_netrc` on Windows, or `NETRC`), by
+//!   `machine` entry. The catch-all `default` entry is ignored, so nothing
+//!   is sent to GitHub unless a `machine` entry names it.
+//!
+//! Mirrors get the credentials as HTTP Basic authentication, or a bearer
+//! token when there is a password but no user name (an Artifactory or
+//! Nexus access token); proxies, as the user info of the proxy URL when it
+//! has none of its own. Lookups are made once per host and process.
+
+use crate::{github, net, policy};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credential {
+    pub username: String,
+    pub password: String,
+}
+
+/// The `Authorization` header for `url`, when there are credentials for its
+/// host.
+pub fn auth_header(url: &str) -> Option<String> {
+    let credential = lookup(url)?;
+    if credential.username.is_empty() {
+        return Some(format!("Authorization: Bearer {}", credential.password));
+    }
+    Some(format!(
+        "Authorization: Basic {}",
+        base64_encode(format!("{}:{}", credential.username, credential.password).as_bytes())
+    ))
+}
+
+/// `proxy` with credentials for its host filled in, unless it has some.
+pub fn proxy_with_credentials(proxy: &str) -> String {
+    let (scheme, rest) = match proxy.split_once("://") {
+        Some((scheme, rest)) => (scheme, rest),
+        None => ("http", proxy),
+    };
+    if rest.contains('@') {
+        return proxy.to_string();
+    }
+    match lookup(&format!("{}://{}", scheme, rest)) {
+        Some(credential) => format!(
+            "{}://{}:{}@{}",
+            scheme,
+            percent_encode(&credential.username),
+            percent_encode(&credential.password),
+            rest
+        ),
+        None => proxy.to_string(),
+    }
+}
+
+fn lookup(url: &str) -> Option<Credential> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<Credential>>>> = OnceLock::new();
+    let (protocol, rest) = url.split_once("://")?;
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = authority.rsplit('@').next()?.to_ascii_lowercase();
+    
+    let mut cache = CACHE.get_or_init(Default::default).lock().ok()?;
+    cache
+        .entry(host.clone())
+        .or_insert_with(|| {
+            github_token(&host)
+                .or_else(|| mirror_token(&host))
+                .or_else(|| from_helper(protocol, &host, path))
+                .or_else(|| from_netrc(&host))
+        })
+        .clone()
+}
+
+fn github_token(host: &str) -> Option<Credential> {
+    if net::host_port(&github::api_url()).map(|(api, _)| api) != Some(host.to_string()) {
+        return None;
+    }
+    github::token().map(|token| Credential {
+        username: String::new(),
+        password: token,
+    })
+}
+
+/// The configured token, when `host` (with any port) serves a mirror other
+/// than GitHub itself.
+fn mirror_token(host: &str) -> Option<Credential> {
+    let is_mirror = host != github::host() && github::download_bases().iter().any(|base| {
+        let authority = base.split_once("://").map_or(base.as_str(), |(_, rest)| rest);
+        let authority = authority.split('/').next().unwrap_or_default();
+        authority.rsplit('@').next().is_some_and(|a| a.eq_ignore_ascii_case(host))
+    });
+    if !is_mirror {
+        return None;
+    }
+    
+    let token = match env::var("BLDR_DOWNLOAD_TOKEN").ok().filter(|t| !t.trim().is_empty()) {
+        Some(token) => token,
+        None => {
+            let path = env::var_os("BLDR_DOWNLOAD_TOKEN_FILE")
+                .map(PathBuf::from)
+                .or_else(|| policy::current().ok().and_then(|p| p.download_token_file.clone()))?;
+            fs::read_to_string(&path)
+                .map_err(|e| eprintln!("bldr: warning: cannot read {}: {}", path.display(), e))
+                .ok()?
+        }
+    };
+    Some(Credential {
+        username: String::new(),
+        password: token.trim().to_string(),
+    })
+}
+
+fn from_helper(protocol: &str, host: &str, path: &str) -> Option<Credential> {
+    let helper = env::var("BLDR_CREDENTIAL_HELPER").ok().filter(|h| !h.trim().is_empty())?;
+    let command = format!("{} get", helper);
+    let mut shell = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", &command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", &command]);
+        cmd
+    };
+    let mut child = shell
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| eprintln!("bldr: warning: cannot run credential helper: {}", e))
+        .ok()?;
+    
+    // The host keeps its port, as git sends it
+    let request = format!("protocol={}\nhost={}\npath={}\n\n", protocol, host, path);
+    child.stdin.take()?.write_all(request.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        eprintln!("bldr: warning: credential helper failed for {} ({})", host, output.status);
+        return None;
+    }
+    parse_helper_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_helper_output(output: &str) -> Option<Credential> {
+    let field = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .map(str::to_string)
+    };
+    let password = field("password")?;
+    Some(Credential {
+        username: field("username").unwrap_or_default(),
+        password,
+    })
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+    dirs::home_dir().map(|home| home.join(name))
+}
+
+fn from_netrc(host: &str) -> Option<Credential> {
+    let contents = fs::read_to_string(netrc_path()?).ok()?;
+    // Entries name hosts without a port
+    let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    parse_netrc(&contents, name)
+}
+
+/// The `login`/`password` of the `machine` entry for `host`.
+fn parse_netrc(contents: &str, host: &str) -> Option<Credential> {
+    let mut tokens = contents.split_whitespace();
+    let mut current: Option<bool> = None;
+    let mut login = None;
+    let mut password = None;
+    
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" | "default" => {
+                if current == Some(true) {
+                    break;
+                }
+                current = Some(token == "machine" && tokens.next().is_some_and(|m| m.eq_ignore_ascii_case(host)));
+            }
+            "login" => login = tokens.next().filter(|_| current == Some(true)).or(login),
+            "password" => password = tokens.next().filter(|_| current == Some(true)).or(password),
+            "account" => {
+                tokens.next();
+            }
+            // Macros run to the next blank line, which whitespace splitting
+            // cannot see; stop rather than misread their bodies
+            "macdef" if current != Some(true) => return None,
+            _ => {}
+        }
+    }
+    
+    Some(Credential {
+        username: login.unwrap_or_default().to_string(),
+        password: password?.to_string(),
+    })
+}
+
+pub fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn reads_netrc_and_helper_output() {
+        let netrc = "default login anon password guest\n\
+                     machine mirror.corp login ci password s3cret\n\
+                     machine proxy.corp\n  login alice\n  password p@ss\n";
+        let creds = |username: &str, password: &str| Credential {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+        assert_eq!(parse_netrc(netrc, "mirror.corp"), Some(creds("ci", "s3cret")));
+        assert_eq!(parse_netrc(netrc, "proxy.corp"), Some(creds("alice", "p@ss")));
+        assert_eq!(parse_netrc(netrc, "github.com"), None);
+        
+        assert_eq!(parse_helper_output("username=bot\npassword=tok\n"), Some(creds("bot", "tok")));
+        assert_eq!(parse_helper_output("quit=1\n"), None);
+        
+        assert_eq!(base64_encode(b"ci:s3cret"), "Y2k6czNjcmV0");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(percent_encode("p@ss:w"), "p%40ss%3Aw");
+    }
+}
@@ -0,0 +1,186 @@
+//! Deduplication of files identical across installed core versions.
+//!
+//! Consecutive releases ship most support files (standard library sources,
+//! licences, data) unchanged. Once a version is installed, each of its files
+//! identical to the one at the same path in another installed version is
+//! replaced by a reflink to it where the filesystem has them (`FICLONE` on
+//! Btrfs and XFS, `clonefile` on APFS), else by a hard link, and otherwise
+//! stays a copy. Hard links are safe here because installed versions are
+//! never modified in place, only removed whole. `BLDR_NO_DEDUPE=1` turns it
+//! off; `bldr wrapper cache dedupe` applies it to the whole cache.
+
+use crate::cache;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Deduplicate the just installed `version` against the other versions.
+pub fn after_install(version: &str) {
+    let disabled = env::var("BLDR_NO_DEDUPE").is_ok_and(|v| !matches!(v.trim(), "" | "0" | "false" | "no"));
+    if !disabled {
+        version_against_others(version);
+    }
+}
+
+/// Deduplicate `version` against every other installed version, returning
+/// the bytes no longer stored twice.
+pub fn version_against_others(version: &str) -> u64 {
+    let root = cache::root();
+    let others: Vec<PathBuf> = cache::installed()
+        .into_iter()
+        .filter(|(other, _)| other != version)
+        .map(|(other, _)| root.join(other))
+        .collect();
+    dedupe(&root.join(version), &others)
+}
+
+fn dedupe(dir: &Path, others: &[PathBuf]) -> u64 {
+    let mut saved = 0;
+    for relative in files(dir, Path::new("")) {
+        let path = dir.join(&relative);
+        for other in others {
+            let original = other.join(&relative);
+            if identical(&path, &original) {
+                if let Ok(len) = link(&original, &path) {
+                    saved += len;
+                }
+                break;
+            }
+        }
+    }
+    saved
+}
+
+/// The files under `dir`, relative to it, skipping the wrapper's markers.
+fn files(dir: &Path, relative: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir.join(relative)) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => files.extend(self::files(dir, &relative.join(&name))),
+            Ok(kind) if kind.is_file() => files.push(relative.join(&name)),
+            _ => {}
+        }
+    }
+    files
+}
+
+/// Whether `a` and `b` are separate files with the same mode and contents.
+fn identical(a: &Path, b: &Path) -> bool {
+    let (Ok(meta_a), Ok(meta_b)) = (fs::symlink_metadata(a), fs::symlink_metadata(b)) else {
+        return false;
+    };
+    meta_a.is_file()
+        && meta_b.is_file()
+        && meta_a.len() == meta_b.len()
+        && meta_a.len() > 0
+        && meta_a.permissions() == meta_b.permissions()
+        && !same_file(&meta_a, &meta_b)
+        && same_contents(a, b).unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+    false
+}
+
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    let (mut buf_a, mut buf_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = a.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buf_b[..n])?;
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Replace `path` with a reflink or hard link to `original`, returning the
+/// bytes saved. The link is made beside `path` and renamed over it, so
+/// `path` is never missing.
+fn link(original: &Path, path: &Path) -> io::Result<u64> {
+    let len = fs::metadata(path)?.len();
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.dedupe-{}", name, std::process::id()));
+    fs::remove_file(&temp).ok();
+    reflink(original, &temp).or_else(|_| fs::hard_link(original, &temp))?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        fs::remove_file(&temp).ok();
+    })?;
+    Ok(len)
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(original: &Path, to: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let source = File::open(original)?;
+    let dest = fs::OpenOptions::new().write(true).create_new(true).open(to)?;
+    // SAFETY: both descriptors stay open for the duration of the call
+    if unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        let e = io::Error::last_os_error();
+        drop(dest);
+        fs::remove_file(to).ok();
+        return Err(e);
+    }
+    fs::set_permissions(to, source.metadata()?.permissions())
+}
+
+#[cfg(target_os = "macos")]
+fn reflink(original: &Path, to: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let original = CString::new(original.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both are valid NUL-terminated paths
+    if unsafe { libc::clonefile(original.as_ptr(), to.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_: &Path, _: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn links_identical_files_only() {
+        let root = env::temp_dir().join(format!("bldr-dedupe-{}", std::process::id()));
+        let (old, new) = (root.join("1.0.0"), root.join("1.1.0"));
+        for (dir, changed) in [(&old, "one"), (&new, "two")] {
+            fs::create_dir_all(dir.join("lib")).unwrap();
+            fs::write(dir.join("lib/std.d"), "module std;").unwrap();
+            fs::write(dir.join("bldr"), changed).unwrap();
+            fs::write(dir.join(".used"), "0").unwrap();
+        }
+        
+        assert_eq!(dedupe(&new, std::slice::from_ref(&old)), "module std;".len() as u64);
+        assert_eq!(fs::read_to_string(new.join("lib/std.d")).unwrap(), "module std;");
+        assert_eq!(fs::read_to_string(new.join("bldr")).unwrap(), "two");
+        // Already shared: nothing more to save
+        #[cfg(unix)]
+        assert_eq!(dedupe(&new, &[old]), 0);
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,77 @@
+//! Warm-start shortcut: remember which core binary an environment resolved
+//! to, so later invocations skip resolution and go straight to exec.
+//!
+//! The record lives at `<cache>/resolved/<fingerprint>` and holds nothing but
+//! the binary path, so reading it is a single small read. The fingerprint
+//! covers the requested version and every environment variable that can change
+//! resolution; a stale record (binary deleted) is dropped by the caller.
+
+use crate::{cache, perms};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Environment variables (besides `BLDR_*`) that feed into resolution.
+const INPUTS: &[&str] = &["HOME", "XDG_CACHE_HOME", "LOCALAPPDATA"];
+
+fn fingerprint(version: &str) -> u64 {
+    // FNV-1a; stable across runs, unlike std's RandomState
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes.iter().chain(std::iter::once(&0u8)) {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+    
+    feed(version.as_bytes());
+    let mut vars: Vec<(String, String)> = env::vars()
+        .filter(|(k, _)| k.starts_with("BLDR_") || INPUTS.contains(&k.as_str()))
+        .collect();
+    vars.sort();
+    for (key, value) in vars {
+        feed(key.as_bytes());
+        feed(value.as_bytes());
+    }
+    hash
+}
+
+fn record_path(version: &str) -> PathBuf {
+    cache::root().join("resolved").join(format!("{:016x}", fingerprint(version)))
+}
+
+/// Binary previously resolved for this environment, if recorded.
+pub fn lookup(version: &str) -> Option<PathBuf> {
+    let bytes = fs::read(record_path(version)).ok()?;
+    let path = String::from_utf8(bytes).ok()?;
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Remember `binary` for this environment. Best effort.
+pub fn record(version: &str, binary: &std::path::Path) {
+    let path = record_path(version);
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    let Some(target) = binary.to_str() else {
+        return;
+    };
+    
+    // Write-then-rename so concurrent invocations never read a torn record
+    let tmp = dir.join(format!(".{}.tmp", std::process::id()));
+    if perms::create_dir_all(dir).is_err() {
+        return;
+    }
+    if perms::write(&tmp, target).and_then(|_| fs::rename(&tmp, &path)).is_err() {
+        fs::remove_file(&tmp).ok();
+    }
+}
+
+/// Drop the record for this environment (e.g. the binary disappeared).
+pub fn forget(version: &str) {
+    fs::remove_file(record_path(version)).ok();
+}
@@ -0,0 +1,117 @@
+//! A release host on a loopback port for tests, so the download path runs
+//! end to end without GitHub: plain HTTP/1.1 with byte ranges, one thread
+//! per connection. Point mirrors at [`Server::url`], or `BLDR_DOWNLOAD_BASE`
+//! at `url("")` with the release files under `/v<version>/`; plain HTTP
+//! needs `BLDR_INSECURE_LOCALHOST`, which [`Server::start`] sets.
+
+use std::collections::HashMap;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct Server {
+    base: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl Server {
+    /// Serve `files`, keyed by path (`/v1.2.3/SHA256SUMS`), until the test
+    /// process exits; anything else is a 404.
+    pub fn start(files: Vec<(String, Vec<u8>)>) -> Server {
+        env::set_var("BLDR_INSECURE_LOCALHOST", "1");
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("cannot listen on loopback");
+        let base = format!("http://{}", listener.local_addr().expect("no local address"));
+        let files = Arc::new(files.into_iter().collect::<HashMap<_, _>>());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        
+        let log = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let files = files.clone();
+                let log = log.clone();
+                thread::spawn(move || serve(stream, &files, &log));
+            }
+        });
+        Server { base, requests }
+    }
+    
+    /// `http://127.0.0.1:<port><path>`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+    
+    /// Paths requested so far, in order, with `Range` values appended
+    /// (`/a.tar.gz bytes=0-99`).
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().map(|log| log.clone()).unwrap_or_default()
+    }
+}
+
+fn serve(stream: TcpStream, files: &HashMap<String, Vec<u8>>, log: &Mutex<Vec<String>>) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut range = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("range") {
+                range = Some(value.trim().to_string());
+            }
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    if let Ok(mut log) = log.lock() {
+        log.push(match &range {
+            Some(range) => format!("{} {}", path, range),
+            None => path.to_string(),
+        });
+    }
+    
+    let Some(body) = files.get(path) else {
+        respond(&stream, "404 Not Found", &[], b"not found\n", method);
+        return;
+    };
+    match range.as_deref().and_then(|range| parse_range(range, body.len())) {
+        Some((start, end)) => {
+            let content_range = format!("Content-Range: bytes {}-{}/{}", start, end, body.len());
+            respond(&stream, "206 Partial Content", &[content_range], &body[start..=end], method);
+        }
+        None => respond(&stream, "200 OK", &[], body, method),
+    }
+}
+
+fn respond(mut stream: &TcpStream, status: &str, headers: &[String], body: &[u8], method: &str) {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for header in headers {
+        head.push_str(&format!("{}\r\n", header));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).ok();
+    if method != "HEAD" {
+        stream.write_all(body).ok();
+    }
+}
+
+/// Inclusive `(start, end)` of a `bytes=<start>-[<end>]` range within `len`.
+fn parse_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let start: usize = start.trim().parse().ok()?;
+    let end = match end.trim() {
+        "" => len.checked_sub(1)?,
+        end => end.parse::<usize>().ok()?.min(len.checked_sub(1)?),
+    };
+    (start <= end).then_some((start, end))
+}
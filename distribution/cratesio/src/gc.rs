@@ -0,0 +1,201 @@
+//! Garbage collection of installed core versions.
+//!
+//! Every install is kept until evicted here, least recently used first, once
+//! more than `BLDR_CACHE_KEEP` versions (5 by default; 0 keeps any number)
+//! are installed or they take more than `BLDR_CACHE_MAX_SIZE` (`500M`, `2G`;
+//! no cap by default). It runs after each new install and on
+//! `bldr wrapper cache gc`. Versions still in use are never evicted: the one
+//! just installed, the exact global and project pins, the current
+//! resolutions of floating selectors and the rollback target. A version
+//! another process is installing or removing is skipped.
+
+use crate::{cache, history, pin, progress, throttle, version};
+use std::cmp::Reverse;
+use std::env;
+use std::fs;
+use std::time::SystemTime;
+
+const DEFAULT_KEEP: usize = 5;
+
+/// How much of the cache to keep.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// Most versions to keep; `None` keeps any number.
+    pub keep: Option<usize>,
+    /// Most bytes the versions may take together.
+    pub max_size: Option<u64>,
+}
+
+impl Limits {
+    /// `BLDR_CACHE_KEEP` and `BLDR_CACHE_MAX_SIZE`.
+    pub fn from_env() -> Result<Limits, String> {
+        let keep = match env::var("BLDR_CACHE_KEEP") {
+            Ok(value) => parse_keep(&value).map_err(|e| format!("BLDR_CACHE_KEEP: {}", e))?,
+            Err(_) => Some(DEFAULT_KEEP),
+        };
+        let max_size = match env::var("BLDR_CACHE_MAX_SIZE") {
+            Ok(value) if !value.trim().is_empty() => Some(parse_size(&value).map_err(|e| format!("BLDR_CACHE_MAX_SIZE: {}", e))?),
+            _ => None,
+        };
+        Ok(Limits { keep, max_size })
+    }
+}
+
+/// A version count; `0` means no limit.
+pub fn parse_keep(value: &str) -> Result<Option<usize>, String> {
+    match value.trim().parse::<usize>() {
+        Ok(0) => Ok(None),
+        Ok(keep) => Ok(Some(keep)),
+        Err(_) => Err(format!("invalid version count '{}'", value.trim())),
+    }
+}
+
+/// `500M`, `2G` and so on, in powers of 1024.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    throttle::parse_rate(value).map_err(|_| format!("invalid size '{}' (expected e.g. 500M or 2G)", value.trim()))
+}
+
+/// An installed version as garbage collection sees it.
+struct Installed {
+    version: String,
+    used: SystemTime,
+    size: u64,
+}
+
+/// Collect after installing `just_installed`, reporting what was removed.
+pub fn after_install(just_installed: &str) {
+    let limits = match Limits::from_env() {
+        Ok(limits) => limits,
+        Err(e) => {
+            eprintln!("bldr: not cleaning the cache: {}", e);
+            return;
+        }
+    };
+    let removed = collect(limits, &[just_installed.to_string()], false);
+    if !removed.is_empty() {
+        let bytes: u64 = removed.iter().map(|(_, size)| size).sum();
+        let versions: Vec<&str> = removed.iter().map(|(version, _)| version.as_str()).collect();
+        eprintln!("Removed unused bldr {} from the cache ({} freed)", versions.join(", "), progress::megabytes(bytes));
+    }
+}
+
+/// Evict versions beyond `limits`, never those in `keep` or in use, and
+/// return each removed (or, on a dry run, removable) version and its size.
+pub fn collect(limits: Limits, keep: &[String], dry_run: bool) -> Vec<(String, u64)> {
+    let mut protected = in_use();
+    protected.extend(keep.iter().cloned());
+    let installed: Vec<Installed> = cache::installed()
+        .into_iter()
+        .map(|(version, _)| {
+            let dir = cache::root().join(&version);
+            // Never run since installing: the install time
+            let used = cache::last_used(&version)
+                .or_else(|| fs::metadata(&dir).and_then(|meta| meta.modified()).ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            Installed {
+                size: cache::size(&version),
+                version,
+                used,
+            }
+        })
+        .collect();
+    
+    let mut removed = Vec::new();
+    for version in plan(installed, &protected, limits) {
+        let Some(_lock) = cache::try_lock(&version.version) else {
+            continue;
+        };
+        if !dry_run && fs::remove_dir_all(cache::root().join(&version.version)).is_err() {
+            continue;
+        }
+        removed.push((version.version, version.size));
+    }
+    removed
+}
+
+/// Which versions to evict, least recently used first.
+fn plan(mut installed: Vec<Installed>, protected: &[String], limits: Limits) -> Vec<Installed> {
+    installed.sort_by_key(|i| Reverse(i.used));
+    let mut total: u64 = installed.iter().map(|i| i.size).sum();
+    let mut count = installed.len();
+    let mut evicted = Vec::new();
+    while let Some(oldest) = installed.iter().rposition(|i| !protected.contains(&i.version)) {
+        let over_count = limits.keep.is_some_and(|keep| count > keep);
+        let over_size = limits.max_size.is_some_and(|max| total > max);
+        if !over_count && !over_size {
+            break;
+        }
+        let version = installed.remove(oldest);
+        total -= version.size;
+        count -= 1;
+        evicted.push(version);
+    }
+    evicted
+}
+
+/// Versions something still points at.
+fn in_use() -> Vec<String> {
+    let mut versions: Vec<String> = Vec::new();
+    let project = env::current_dir().ok().and_then(|cwd| pin::find_project(&cwd));
+    for path in project.into_iter().chain(pin::global_path()) {
+        if let Some(version::Selector::Exact(version)) = pin::read(&path).and_then(|v| version::Selector::parse(&v)) {
+            versions.push(version);
+        }
+    }
+    if let Ok(entries) = fs::read_dir(cache::root().join("resolved")) {
+        for entry in entries.flatten() {
+            if entry.path().extension().is_some_and(|ext| ext == "version") {
+                if let Ok(version) = fs::read_to_string(entry.path()) {
+                    versions.push(version.trim().to_string());
+                }
+            }
+        }
+    }
+    versions.extend(history::recent());
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    fn installed(versions: &[(&str, u64, u64)]) -> Vec<Installed> {
+        versions
+            .iter()
+            .map(|(version, age, size)| Installed {
+                version: version.to_string(),
+                used: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 - age),
+                size: *size,
+            })
+            .collect()
+    }
+    
+    fn names(evicted: Vec<Installed>) -> Vec<String> {
+        evicted.into_iter().map(|i| i.version).collect()
+    }
+    
+    #[test]
+    fn evicts_least_recently_used_beyond_the_limits() {
+        let cache = [("2.0.3", 30, 100), ("2.1.0", 10, 100), ("1.9.0", 90, 100), ("2.1.1", 1, 100)];
+        let keep = |n| Limits { keep: Some(n), max_size: None };
+        assert_eq!(names(plan(installed(&cache), &[], keep(2))), ["1.9.0", "2.0.3"]);
+        assert!(plan(installed(&cache), &[], keep(4)).is_empty());
+        // A pinned version stays however old; the next oldest goes instead
+        assert_eq!(names(plan(installed(&cache), &["1.9.0".to_string()], keep(3))), ["2.0.3"]);
+        
+        let budget = Limits { keep: None, max_size: Some(250) };
+        assert_eq!(names(plan(installed(&cache), &[], budget)), ["1.9.0", "2.0.3"]);
+        let protected = ["2.1.1".to_string(), "2.1.0".to_string(), "2.0.3".to_string(), "1.9.0".to_string()];
+        assert!(plan(installed(&cache), &protected, budget).is_empty());
+    }
+    
+    #[test]
+    fn parses_limits() {
+        assert_eq!(parse_keep("3"), Ok(Some(3)));
+        assert_eq!(parse_keep("0"), Ok(None));
+        assert!(parse_keep("-1").is_err());
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert!(parse_size("lots").unwrap_err().contains("invalid size"));
+    }
+}
@@ -0,0 +1,363 @@
+//! GitHub release metadata.
+//!
+//! API calls back off when GitHub rate-limits them: `Retry-After` or the
+//! `x-ratelimit-reset` time when given, otherwise exponential delays from a
+//! minute as GitHub asks for secondary limits, each with jitter so a fleet
+//! of CI runners does not retry in lockstep. Every successful response is
+//! kept under the cache directory; when the wait would exceed
+//! `BLDR_GITHUB_MAX_WAIT` seconds (default 60) the last good response is
+//! used instead of failing the invocation. With the ETag GitHub sent, the
+//! next request for it is conditional, so checking a floating version for
+//! news is a `304 Not Modified` that does not use up the rate limit.
+//!
+//! `BLDR_GITHUB_TOKEN` (or `GITHUB_TOKEN`) is sent to GitHub, raising the
+//! API rate limit for CI runners, and `BLDR_GITHUB_REPO` points the wrapper
+//! at a fork's releases. With a token, release assets are fetched through
+//! the API, which is how assets of a private repository can be downloaded.
+//! For GitHub Enterprise Server, `BLDR_GITHUB_HOST` names the instance; its
+//! API is at `https://<host>/api/v3` unless `BLDR_GITHUB_API_URL` says
+//! otherwise.
+
+use crate::{cache, http, perms, policy, rolling};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Repository releases are published from.
+const REPO: &str = "GriffinCanCode/bldr";
+
+const ATTEMPTS: u32 = 4;
+const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(60);
+const SECONDARY_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    /// RFC 3339 publication time.
+    #[serde(default)]
+    pub published_at: Option<String>,
+    /// Release notes (Markdown).
+    #[serde(default)]
+    pub body: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+pub struct Asset {
+    #[serde(default)]
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    pub browser_download_url: String,
+}
+
+/// Repository releases are looked up in: `BLDR_GITHUB_REPO`
+/// (`owner/name`), else the upstream one.
+pub fn repo() -> String {
+    env::var("BLDR_GITHUB_REPO")
+        .ok()
+        .filter(|repo| repo.contains('/'))
+        .unwrap_or_else(|| REPO.to_string())
+}
+
+/// Host of the GitHub instance: `BLDR_GITHUB_HOST`, else `github.com`.
+pub fn host() -> String {
+    env::var("BLDR_GITHUB_HOST")
+        .ok()
+        .map(|host| host.trim().trim_start_matches("https://").trim_end_matches('/').to_ascii_lowercase())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "github.com".to_string())
+}
+
+/// Base URL of the instance's REST API.
+pub fn api_url() -> String {
+    if let Some(url) = env::var("BLDR_GITHUB_API_URL").ok().filter(|url| !url.trim().is_empty()) {
+        return url.trim().trim_end_matches('/').to_string();
+    }
+    match host().as_str() {
+        "github.com" => "https://api.github.com".to_string(),
+        host => format!("https://{}/api/v3", host),
+    }
+}
+
+/// Token for GitHub: `BLDR_GITHUB_TOKEN`, else `GITHUB_TOKEN`.
+pub fn token() -> Option<String> {
+    ["BLDR_GITHUB_TOKEN", "GITHUB_TOKEN"]
+        .iter()
+        .find_map(|name| env::var(name).ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// `(name, id)` of each asset, by `<repo>@<tag>`.
+type AssetIds = HashMap<String, Vec<(String, u64)>>;
+
+/// The API URL of a `<host>/<repo>/releases/download/<tag>/<asset>`
+/// URL, when there is a token to fetch it with. Downloading it takes
+/// `Accept: application/octet-stream`.
+pub fn api_asset_url(url: &str) -> Option<String> {
+    token()?;
+    let path = url.strip_prefix(&format!("https://{}/", host()))?;
+    let parts: Vec<&str> = path.splitn(6, '/').collect();
+    let [owner, name, "releases", "download", tag, asset] = parts[..] else {
+        return None;
+    };
+    let repo = format!("{}/{}", owner, name);
+    
+    // One lookup per release and process; an install fetches several assets
+    static ASSETS: OnceLock<Mutex<AssetIds>> = OnceLock::new();
+    let mut known = ASSETS.get_or_init(Default::default).lock().ok()?;
+    let key = format!("{}@{}", repo, tag);
+    if !known.contains_key(&key) {
+        let release = release_by_tag(&repo, tag).ok()?;
+        known.insert(key.clone(), release.assets.into_iter().map(|a| (a.name, a.id)).collect());
+    }
+    let id = known[&key].iter().find(|(name, _)| name == asset)?.1;
+    Some(format!("{}/repos/{}/releases/assets/{}", api_url(), repo, id))
+}
+
+/// Download URLs of a release asset, in the order to try them, one per
+/// base from [`download_bases`]. `bldr wrapper mirror sync` writes the
+/// default `<base>/v<version>/<asset>` layout, so any mirror it fills can
+/// be used as a base.
+pub fn asset_urls(version: &str, asset: &str) -> Vec<String> {
+    download_bases().iter().map(|base| join_asset_url(base, version, asset)).collect()
+}
+
+/// Mirror bases from `BLDR_DOWNLOAD_BASE` (separated by commas or spaces),
+/// else the policy's `download_base`, else the GitHub instance's
+/// `releases/download`.
+/// A base with an `{asset}` placeholder is a template for repositories with
+/// their own layout, such as an Artifactory or Nexus generic repository
+/// (`https://artifacts.corp/artifactory/tools/bldr/{version}/{asset}`).
+/// An `s3://bucket/prefix` base is read from the bucket (see [`crate::s3`]),
+/// and an `oci://<registry>/<repository>` one from its `v<version>`
+/// artifact (see [`crate::oci`]).
+pub fn download_bases() -> Vec<String> {
+    let from_env: Vec<String> = env::var("BLDR_DOWNLOAD_BASE")
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|base| !base.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !from_env.is_empty() {
+        return from_env;
+    }
+    match policy::current() {
+        Ok(policy) if !policy.download_base.is_empty() => policy.download_base.clone(),
+        _ => vec![format!("https://{}/{}/releases/download", host(), repo())],
+    }
+}
+
+fn join_asset_url(base: &str, version: &str, asset: &str) -> String {
+    let base = base.trim();
+    if base.contains("{asset}") {
+        return base.replace("{version}", version).replace("{asset}", asset);
+    }
+    format!("{}/{}/{}", base.trim_end_matches('/'), tag(version), asset)
+}
+
+/// Release tag of `version`: `v2.0.3`, or a rolling tag such as `nightly`
+/// as is.
+pub fn tag(version: &str) -> String {
+    if rolling::is_rolling(version) {
+        version.to_string()
+    } else {
+        format!("v{}", version)
+    }
+}
+
+/// Look up a release by tag (e.g. `v2.0.3`).
+pub fn release_by_tag(repo: &str, tag: &str) -> Result<Release, String> {
+    let url = format!("{}/repos/{}/releases/tags/{}", api_url(), repo, tag);
+    let body = api_get(&url)?;
+    serde_json::from_str(&body).map_err(|e| format!("invalid release metadata for {}: {}", tag, e))
+}
+
+/// Most recent releases, newest first.
+pub fn releases(repo: &str) -> Result<Vec<Release>, String> {
+    let url = format!("{}/repos/{}/releases?per_page=100", api_url(), repo);
+    let body = api_get(&url)?;
+    serde_json::from_str(&body).map_err(|e| format!("invalid release list for {}: {}", repo, e))
+}
+
+/// GET an API URL, waiting out rate limits or falling back to the cache.
+fn api_get(url: &str) -> Result<String, String> {
+    let mut headers = vec!["Accept: application/vnd.github+json".to_string()];
+    let max_wait = env::var("BLDR_GITHUB_MAX_WAIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_MAX_WAIT);
+    
+    // Revalidate what is cached; a 304 does not count against the rate limit
+    let cached = cached(url);
+    if let Some((_, etag)) = &cached {
+        headers.push(format!("If-None-Match: {}", etag));
+    }
+    
+    for attempt in 0..ATTEMPTS {
+        let response = http::get(url, &headers)?;
+        if response.status == 304 {
+            if let Some((body, _)) = cached {
+                return Ok(body);
+            }
+        }
+        if response.is_success() {
+            remember(url, &response.body, response.header("etag"));
+            return Ok(response.body);
+        }
+        
+        let Some(wait) = rate_limit_wait(&response, attempt, unix_now()) else {
+            return Err(format!("GET {} failed: HTTP {}", url, response.status));
+        };
+        let wait = wait + jitter(wait);
+        if wait > max_wait || attempt + 1 == ATTEMPTS {
+            return recall(url).ok_or_else(|| {
+                format!(
+                    "GitHub API rate limit exceeded for {} (retry in {}s; BLDR_GITHUB_MAX_WAIT allows longer waits)",
+                    url,
+                    wait.as_secs()
+                )
+            });
+        }
+        eprintln!("bldr: GitHub API rate limit hit; retrying in {}s", wait.as_secs());
+        thread::sleep(wait);
+    }
+    unreachable!("the last attempt returns")
+}
+
+/// How long GitHub asks us to wait, or `None` if this is not a rate limit.
+fn rate_limit_wait(response: &http::Response, attempt: u32, now: u64) -> Option<Duration> {
+    if response.status != 403 && response.status != 429 {
+        return None;
+    }
+    if let Some(secs) = response.header("retry-after").and_then(|v| v.trim().parse().ok()) {
+        return Some(Duration::from_secs(secs));
+    }
+    if response.header("x-ratelimit-remaining") == Some("0") {
+        if let Some(reset) = response.header("x-ratelimit-reset").and_then(|v| v.trim().parse::<u64>().ok()) {
+            return Some(Duration::from_secs(reset.saturating_sub(now).max(1)));
+        }
+    }
+    // Secondary limits may come without headers; GitHub asks for at least a
+    // minute, growing exponentially while they persist
+    if response.status == 429 || response.body.to_ascii_lowercase().contains("rate limit") {
+        return Some(SECONDARY_BACKOFF * 2u32.pow(attempt));
+    }
+    None
+}
+
+/// Up to a quarter of `wait`, different per process.
+fn jitter(wait: Duration) -> Duration {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0)
+        ^ std::process::id().wrapping_mul(2_654_435_761);
+    wait / 4 * (seed % 1000) / 1000
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn cache_path(url: &str) -> PathBuf {
+    let digest = Sha256::digest(url.as_bytes());
+    let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    cache::root().join("github").join(format!("{}.json", name))
+}
+
+fn remember(url: &str, body: &str, etag: Option<&str>) {
+    let path = cache_path(url);
+    if let Some(dir) = path.parent() {
+        perms::create_dir_all(dir).ok();
+    }
+    perms::write(&path, body).ok();
+    match etag {
+        Some(etag) => perms::write(&path.with_extension("etag"), etag).ok(),
+        None => fs::remove_file(path.with_extension("etag")).ok(),
+    };
+}
+
+/// The cached response for `url` and the ETag it came with, when both are
+/// known.
+fn cached(url: &str) -> Option<(String, String)> {
+    let path = cache_path(url);
+    let etag = fs::read_to_string(path.with_extension("etag")).ok()?;
+    let body = fs::read_to_string(&path).ok()?;
+    Some((body, etag.trim().to_string())).filter(|(_, etag)| !etag.is_empty())
+}
+
+fn recall(url: &str) -> Option<String> {
+    let path = cache_path(url);
+    let body = fs::read_to_string(&path).ok()?;
+    let age = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .map(|d| format!(" from {}m ago", d.as_secs() / 60))
+        .unwrap_or_default();
+    eprintln!("bldr: warning: GitHub API rate limit exceeded; using cached release metadata{}", age);
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> http::Response {
+        http::Response {
+            status,
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: body.to_string(),
+        }
+    }
+    
+    #[test]
+    fn tags_rolling_releases_by_name() {
+        assert_eq!(tag("2.0.3"), "v2.0.3");
+        assert_eq!(tag("2.1.0-rc.1"), "v2.1.0-rc.1");
+        assert_eq!(tag("nightly"), "nightly");
+        assert_eq!(join_asset_url("https://mirror/bldr/", "nightly", "bldr-linux-amd64.tar.gz"), "https://mirror/bldr/nightly/bldr-linux-amd64.tar.gz");
+    }
+    
+    #[test]
+    fn reads_rate_limit_signals() {
+        let retry_after = response(403, &[("retry-after", "30")], "");
+        assert_eq!(rate_limit_wait(&retry_after, 0, 0), Some(Duration::from_secs(30)));
+        
+        let primary = response(403, &[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1090")], "");
+        assert_eq!(rate_limit_wait(&primary, 0, 1000), Some(Duration::from_secs(90)));
+        
+        let secondary = response(403, &[], r#"{"message": "You have exceeded a secondary rate limit"}"#);
+        assert_eq!(rate_limit_wait(&secondary, 2, 0), Some(Duration::from_secs(240)));
+        
+        assert_eq!(rate_limit_wait(&response(403, &[], "Resource not accessible"), 0, 0), None);
+        assert_eq!(rate_limit_wait(&response(404, &[("retry-after", "5")], ""), 0, 0), None);
+        assert!(jitter(Duration::from_secs(60)) <= Duration::from_secs(15));
+    }
+    
+    #[test]
+    fn joins_mirror_bases() {
+        assert_eq!(
+            join_asset_url("https://artifacts.corp/bldr/", "2.0.3", "bldr-linux-amd64.tar.gz"),
+            "https://artifacts.corp/bldr/v2.0.3/bldr-linux-amd64.tar.gz"
+        );
+        assert_eq!(join_asset_url("file:///srv/mirror", "2.0.3", "x.tar.gz"), "file:///srv/mirror/v2.0.3/x.tar.gz");
+        assert_eq!(
+            join_asset_url("https://nexus.corp/repository/tools/bldr/{version}/{asset}", "2.0.3", "x.tar.gz"),
+            "https://nexus.corp/repository/tools/bldr/2.0.3/x.tar.gz"
+        );
+    }
+}
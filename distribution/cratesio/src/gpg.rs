@@ -0,0 +1,99 @@
+//! Detached GPG signature check for downloaded release archives.
+//!
+//! With `BLDR_GPG_VERIFY` set (or `gpg` among the policy's `verification`
+//! schemes), `<asset>.asc` is fetched next to the archive and checked by
+//! `gpgv` against `BLDR_GPG_KEYRING` (the policy's `gpg_keyring` wins). The
+//! keyring is a binary export (`gpg --export KEYID > keys.gpg`), so only the
+//! keys an organization chose are trusted, never the user's own keyring.
+
+use crate::http;
+use crate::policy::{self, Mode};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Apply the configured policy to a downloaded archive fetched from `url`.
+/// Errors mean the archive must not be used.
+pub fn check(archive: &Path, url: &str) -> Result<(), String> {
+    let mode = Mode::for_scheme("gpg", "BLDR_GPG_VERIFY");
+    if mode == Mode::Off {
+        return Ok(());
+    }
+    
+    match verify(archive, url) {
+        Ok(fingerprint) => {
+            eprintln!("GPG signature: good signature from {}", fingerprint);
+            Ok(())
+        }
+        Err(e) if mode == Mode::Warn => {
+            eprintln!("bldr: warning: GPG signature check failed: {}", e);
+            Ok(())
+        }
+        Err(e) => Err(format!("GPG signature check failed: {}", e)),
+    }
+}
+
+fn keyring() -> Result<PathBuf, String> {
+    let path = policy::current()
+        .ok()
+        .and_then(|p| p.gpg_keyring.clone())
+        .or_else(|| env::var_os("BLDR_GPG_KEYRING").map(PathBuf::from))
+        .ok_or("no keyring configured (set BLDR_GPG_KEYRING)")?;
+    if !path.is_file() {
+        return Err(format!("keyring {} not found", path.display()));
+    }
+    // gpgv looks up bare names in its home directory
+    env::current_dir().map(|dir| dir.join(&path)).map_err(|e| e.to_string())
+}
+
+/// Verify `<url>.asc` over the archive; returns the signing key's fingerprint.
+fn verify(archive: &Path, url: &str) -> Result<String, String> {
+    let keyring = keyring()?;
+    
+    let mut signature = archive.as_os_str().to_owned();
+    signature.push(".asc");
+    let signature = PathBuf::from(signature);
+    http::download(&format!("{}.asc", url), &signature)?;
+    
+    let output = Command::new("gpgv")
+        .args(["--status-fd", "1", "--keyring"])
+        .arg(&keyring)
+        .arg(&signature)
+        .arg(archive)
+        .output();
+    fs::remove_file(&signature).ok();
+    let output = output.map_err(|e| format!("failed to run gpgv: {}", e))?;
+    
+    match valid_signer(&String::from_utf8_lossy(&output.stdout)) {
+        Some(fingerprint) if output.status.success() => Ok(fingerprint),
+        _ => Err(format!(
+            "{}.asc is not a good signature by a key in {}: {}",
+            url,
+            keyring.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+/// Fingerprint from the `VALIDSIG` status line, which gpgv only emits for a
+/// good signature by a key in the keyring.
+fn valid_signer(status: &str) -> Option<String> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn takes_the_fingerprint_from_validsig_only() {
+        let good = "[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 1234ABCD Release <r@example.com>\n[GNUPG:] VALIDSIG 0123456789ABCDEF0123456789ABCDEF1234ABCD 2026-01-01 1767225600 0 4 0 22 10 00 0123456789ABCDEF0123456789ABCDEF1234ABCD\n";
+        assert_eq!(valid_signer(good).as_deref(), Some("0123456789ABCDEF0123456789ABCDEF1234ABCD"));
+        assert!(valid_signer("[GNUPG:] BADSIG 1234ABCD Release <r@example.com>\n").is_none());
+    }
+}
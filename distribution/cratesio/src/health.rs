@@ -0,0 +1,105 @@
+//! Post-install health check.
+//!
+//! A freshly unpacked core must run `--version` within a few seconds and
+//! report the version it was installed as (any version, for a rolling tag
+//! such as `nightly`) before the cache trusts it. A
+//! core that crashes, hangs or reports another version (a corrupted
+//! download, a mislabelled release) is removed, and the install is tried
+//! once more.
+
+use crate::rolling;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long `--version` may take; a healthy core answers in milliseconds,
+/// but the first run of a new binary can be slowed by antivirus scans.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Check that `binary` runs and reports `version`.
+pub fn check(binary: &Path, version: &str) -> Result<(), String> {
+    check_within(binary, version, TIMEOUT)
+}
+
+fn check_within(binary: &Path, version: &str, timeout: Duration) -> Result<(), String> {
+    let mut child = Command::new(binary)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot run {}: {}", binary.display(), e))?;
+    
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < timeout => thread::sleep(Duration::from_millis(10)),
+            _ => {
+                child.kill().ok();
+                child.wait().ok();
+                return Err(format!("{} --version did not finish within {}s", binary.display(), timeout.as_secs()));
+            }
+        }
+    };
+    if !status.success() {
+        return Err(format!("{} --version failed ({})", binary.display(), status));
+    }
+    
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_string(&mut output).ok();
+    }
+    // A rolling tag's core reports whichever build it is
+    match reported_version(&output) {
+        Some(reported) if reported == version || rolling::is_rolling(version) => Ok(()),
+        Some(reported) => Err(format!("{} reports version {}, expected {}", binary.display(), reported, version)),
+        None => Err(format!("{} --version reported no version", binary.display())),
+    }
+}
+
+/// The first version-like word of `bldr version 2.0.3` and similar.
+fn reported_version(output: &str) -> Option<&str> {
+    output
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('v').trim_end_matches([',', ';', ')']))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()) && word.contains('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn reads_the_reported_version() {
+        assert_eq!(reported_version("bldr version 2.0.3\nHigh-performance build system\n"), Some("2.0.3"));
+        assert_eq!(reported_version("bldr v2.1.0-rc.1 (abc123)"), Some("2.1.0-rc.1"));
+        assert_eq!(reported_version("usage: bldr [options]"), None);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn runs_the_core_before_trusting_it() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+        
+        let dir = std::env::temp_dir().join(format!("bldr-health-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let core = |name: &str, script: &str| {
+            let path = dir.join(name);
+            fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        };
+        
+        let healthy = core("healthy", "echo bldr version 1.2.3");
+        assert!(check_within(&healthy, "1.2.3", TIMEOUT).is_ok());
+        assert!(check_within(&healthy, "1.2.4", TIMEOUT).unwrap_err().contains("reports version 1.2.3"));
+        assert!(check_within(&core("failing", "exit 3"), "1.2.3", TIMEOUT).is_err());
+        let hanging = core("hanging", "sleep 5");
+        assert!(check_within(&hanging, "1.2.3", Duration::from_millis(200)).unwrap_err().contains("did not finish"));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,44 @@
+//! The last two core versions that ran successfully, for
+//! `bldr wrapper rollback`.
+//!
+//! `<cache>/last-good` holds the version of the latest run that exited 0 and,
+//! on the next line, the one before it that differed. It is rewritten only
+//! when the version changes, so a normal run costs one small read.
+
+use crate::{cache, perms};
+use std::fs;
+use std::path::PathBuf;
+
+/// Note that `version` just ran successfully.
+pub fn record_success(version: &str) {
+    let (last, _) = read();
+    if last.as_deref() == Some(version) {
+        return;
+    }
+    let contents = match last {
+        Some(last) => format!("{}\n{}\n", version, last),
+        None => format!("{}\n", version),
+    };
+    perms::write(&path(), contents).ok();
+}
+
+/// The version that ran successfully before the latest one.
+pub fn previous() -> Option<String> {
+    read().1
+}
+
+/// Both recorded versions, latest first.
+pub fn recent() -> Vec<String> {
+    let (last, previous) = read();
+    last.into_iter().chain(previous).collect()
+}
+
+fn read() -> (Option<String>, Option<String>) {
+    let contents = fs::read_to_string(path()).unwrap_or_default();
+    let mut lines = contents.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string);
+    (lines.next(), lines.next())
+}
+
+fn path() -> PathBuf {
+    cache::root().join("last-good")
+}
@@ -0,0 +1,957 @@
+//! HTTP for the wrapper: release metadata, downloads and the transparency log.
+//!
+//! Requests go through a built-in client (the `native-http` feature, on by
+//! default), so installing a release needs no external tools. It applies the
+//! same IP family, address racing and proxies curl was given: `BLDR_SOCKS_PROXY`,
+//! `HTTPS_PROXY`/`ALL_PROXY` minus `NO_PROXY`, then the system settings, and
+//! it trusts the system certificate store, or only the PEM bundle named by
+//! `BLDR_CA_BUNDLE` or `SSL_CERT_FILE` behind TLS-intercepting proxies (curl
+//! gets it as `--cacert`). `BLDR_HTTP_CLIENT=curl`, or a build without the
+//! feature, runs curl instead, e.g. behind an HTTPS proxy the built-in client
+//! cannot talk to. `file://` URLs (mirrors, fixtures) are read directly,
+//! `s3://` ones through a presigned HTTPS URL and `oci://` ones from the
+//! registry blob they name. Anything else must be HTTPS, with TLS 1.2 or
+//! later and no redirects to plain HTTP.
+
+use crate::checksum::ChunkManifest;
+use crate::progress::Progress;
+use crate::{credentials, github, net, oci, offline, perms, policy, proxy, s3, throttle};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Transfers of one download, counting each resume after a broken
+/// connection.
+const RESUME_ATTEMPTS: u32 = 5;
+/// Connections a download is split across when the release publishes a
+/// chunk manifest for it, unless `BLDR_DOWNLOAD_CONNECTIONS` says otherwise.
+const DEFAULT_CONNECTIONS: usize = 4;
+
+/// A response read without failing on HTTP errors.
+pub struct Response {
+    pub status: u16,
+    /// Headers of the final response after redirects, names lowercased.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+    
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Whether requests use the built-in client rather than curl.
+#[cfg(feature = "native-http")]
+fn native() -> bool {
+    !env::var("BLDR_HTTP_CLIENT").is_ok_and(|client| client.eq_ignore_ascii_case("curl"))
+}
+
+/// Fetch a URL as text.
+pub fn get_text(url: &str, headers: &[String]) -> Result<String, String> {
+    let response = get(url, headers)?;
+    if !response.is_success() {
+        return Err(format!("GET {} failed: HTTP {}", url, response.status));
+    }
+    Ok(response.body)
+}
+
+/// Fetch a URL as text without any proxy (PAC scripts).
+pub fn get_direct(url: &str) -> Result<String, String> {
+    let response = request("GET", url, &[], None, true)?;
+    if !response.is_success() {
+        return Err(format!("GET {} failed: HTTP {}", url, response.status));
+    }
+    Ok(response.body)
+}
+
+/// GET a URL and return the final status, headers and body, including for
+/// HTTP errors (rate limits carry their details in headers).
+pub fn get(url: &str, headers: &[String]) -> Result<Response, String> {
+    request("GET", url, headers, None, false)
+}
+
+/// HEAD a URL: the final status and headers, e.g. to revalidate an ETag.
+pub fn head(url: &str, headers: &[String]) -> Result<Response, String> {
+    request("HEAD", url, headers, None, false)
+}
+
+/// POST a JSON body and return the response as text.
+pub fn post_json(url: &str, body: &str) -> Result<String, String> {
+    let headers = ["Content-Type: application/json".to_string()];
+    let response = request("POST", url, &headers, Some(body), false)?;
+    if !response.is_success() {
+        return Err(format!("POST {} failed: HTTP {}", url, response.status));
+    }
+    Ok(response.body)
+}
+
+fn request(method: &str, url: &str, headers: &[String], body: Option<&str>, direct: bool) -> Result<Response, String> {
+    if let Some(path) = local_path(url).filter(|_| method == "HEAD") {
+        // A local file's size and modification time stand in for its ETag
+        let meta = fs::metadata(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let modified = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).unwrap_or_default();
+        let etag = format!("W/\"{}-{}\"", meta.len(), modified.as_secs());
+        return Ok(Response { status: 200, headers: vec![("etag".to_string(), etag)], body: String::new() });
+    }
+    if let Some(path) = local_path(url) {
+        // A missing file answers as a web server would
+        return match fs::read_to_string(&path) {
+            Ok(body) => Ok(Response { status: 200, headers: Vec::new(), body }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Response { status: 404, headers: Vec::new(), body: String::new() }),
+            Err(e) => Err(format!("cannot read {}: {}", path.display(), e)),
+        };
+    }
+    offline_check(url)?;
+    
+    let mut headers = headers.to_vec();
+    let api = if matches!(method, "GET" | "HEAD") { resolve(url, &mut headers)? } else { None };
+    let url = api.as_deref().unwrap_or(url);
+    // PAC scripts are commonly served over plain HTTP (WPAD)
+    if !direct {
+        require_https(url)?;
+    }
+    
+    #[cfg(feature = "native-http")]
+    if native() {
+        return client::request(method, url, &headers, body, direct);
+    }
+    curl_request(method, url, &headers, body, direct)
+}
+
+/// Refuse network access in offline mode, rather than wait for it to time
+/// out.
+fn offline_check(url: &str) -> Result<(), String> {
+    match offline::enabled_by() {
+        Some(var) => Err(format!("cannot fetch {}: network access is off ({} is set)", url, var)),
+        None => Ok(()),
+    }
+}
+
+/// Refuse anything but HTTPS for what goes over the network, mirrors and
+/// API URLs from the configuration included; `http://` is let through only
+/// to loopback test servers with `BLDR_INSECURE_LOCALHOST` set.
+fn require_https(url: &str) -> Result<(), String> {
+    let https = url.get(..8).is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://"));
+    let http = url.get(..7).is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"));
+    if https || (http && insecure(url)) {
+        return Ok(());
+    }
+    Err(format!(
+        "refusing to fetch {}: downloads must use https:// (BLDR_INSECURE_LOCALHOST allows http:// only to localhost)",
+        url
+    ))
+}
+
+/// Where to fetch `url` from instead, if anywhere: the GitHub API, a
+/// presigned S3 URL or a registry blob, with the headers it needs added to
+/// `headers`.
+fn resolve(url: &str, headers: &mut Vec<String>) -> Result<Option<String>, String> {
+    if url.starts_with("oci://") {
+        return oci::blob_url(url, headers).map(Some);
+    }
+    Ok(github_asset(url, headers).or_else(|| s3::https_url(url)))
+}
+
+/// The API URL to fetch a GitHub release asset from instead, with the
+/// header that asks for its contents added to `headers`.
+fn github_asset(url: &str, headers: &mut Vec<String>) -> Option<String> {
+    let api = github::api_asset_url(url)?;
+    headers.push("Accept: application/octet-stream".to_string());
+    Some(api)
+}
+
+/// Write `url` to `dest`, sending `headers` too; with `resume`, continue
+/// from what `dest` holds. With `progress`, the transfer is reported on
+/// stderr.
+fn fetch(url: &str, dest: &Path, headers: &[String], resume: bool, progress: bool) -> Result<(), String> {
+    if let Some(path) = local_path(url) {
+        return fs::copy(&path, dest)
+            .map(drop)
+            .map_err(|e| format!("cannot copy {}: {}", path.display(), e));
+    }
+    offline_check(url)?;
+    
+    let mut headers = headers.to_vec();
+    let api = resolve(url, &mut headers)?;
+    let url = api.as_deref().unwrap_or(url);
+    require_https(url)?;
+    
+    #[cfg(feature = "native-http")]
+    if native() {
+        return client::fetch(url, dest, &headers, resume, progress);
+    }
+    curl_fetch(url, dest, &headers, resume, progress)
+}
+
+/// PEM bundle that replaces the system trust store for HTTPS:
+/// `BLDR_CA_BUNDLE`, else `SSL_CERT_FILE`.
+fn ca_bundle() -> Option<PathBuf> {
+    ["BLDR_CA_BUNDLE", "SSL_CERT_FILE"]
+        .iter()
+        .find_map(|name| env::var_os(name).filter(|v| !v.is_empty()))
+        .map(PathBuf::from)
+}
+
+/// Whether certificates go unchecked for `url`: with `BLDR_INSECURE_LOCALHOST`
+/// set, for loopback hosts only, so tests can point the downloader at a mock
+/// server with a self-signed certificate. Never meant for real mirrors.
+fn insecure(url: &str) -> bool {
+    env::var_os("BLDR_INSECURE_LOCALHOST").is_some_and(|value| !value.is_empty() && value != "0")
+        && net::host_port(url).is_some_and(|(host, _)| is_loopback(&host))
+}
+
+fn is_loopback(host: &str) -> bool {
+    host == "localhost" || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// The `file://` URL of a local file, which must exist.
+pub fn file_url(path: &Path) -> Result<String, String> {
+    let path = fs::canonicalize(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let path = path.to_string_lossy().replace('\\', "/");
+    // Canonical Windows paths come back as \\?\C:\...
+    let path = path.strip_prefix("//?/").unwrap_or(&path);
+    if path.starts_with('/') {
+        Ok(format!("file://{}", path))
+    } else {
+        Ok(format!("file:///{}", path))
+    }
+}
+
+/// The file a `file://` URL names.
+fn local_path(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?;
+    // file:///C:/mirror on Windows
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &path[1..],
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// curl invocation for `url` with the system proxy or raced address applied,
+/// and the config it must be given on stdin.
+fn curl(url: &str, headers: &[String]) -> (Command, String) {
+    let mut cmd = Command::new("curl");
+    cmd.arg("-fsSL");
+    let secrets = configure(&mut cmd, url, headers);
+    (cmd, secrets)
+}
+
+/// Apply the IP family, proxy, raced address, `headers` and credentials for
+/// `url`. Credentials are returned as curl config for stdin (`-K -`) rather
+/// than put on the command line, where other users could read them.
+fn configure(cmd: &mut Command, url: &str, headers: &[String]) -> String {
+    let family = net::Family::from_env();
+    if let Some(flag) = family.curl_flag() {
+        cmd.arg(flag);
+    }
+    if let Some(bundle) = ca_bundle() {
+        cmd.arg("--cacert").arg(bundle);
+    }
+    // TLS 1.2 at least, and no redirect down to plain HTTP
+    cmd.arg("--tlsv1.2");
+    if insecure(url) {
+        cmd.arg("--insecure");
+    } else if url.starts_with("https://") {
+        cmd.args(["--proto", "=https", "--proto-redir", "=https"]);
+    }
+    
+    let mut secrets = String::new();
+    if let Some(proxy) = proxy::for_request(url) {
+        secrets.push_str(&format!("proxy = {}\n", config_quote(&proxy)));
+    } else if proxy::env_configured() {
+        // Excluded by NO_PROXY; keep curl from applying the variables its
+        // own way
+        cmd.args(["--noproxy", "*"]);
+    } else if let Some(resolve) = pinned_address(url, family) {
+        cmd.args(["--resolve", &resolve]);
+    }
+    for header in with_credentials(url, headers) {
+        if is_authorization(&header) {
+            secrets.push_str(&format!("header = {}\n", config_quote(&header)));
+        } else {
+            cmd.args(["-H", &header]);
+        }
+    }
+    if !secrets.is_empty() {
+        cmd.args(["-K", "-"]).stdin(Stdio::piped());
+    }
+    secrets
+}
+
+/// `headers` plus the credentials for `url`'s host, unless they carry
+/// their own (a registry token).
+fn with_credentials(url: &str, headers: &[String]) -> Vec<String> {
+    let mut headers = headers.to_vec();
+    if !headers.iter().any(|header| is_authorization(header)) {
+        headers.extend(credentials::auth_header(url));
+    }
+    headers
+}
+
+fn is_authorization(header: &str) -> bool {
+    header.split_once(':').is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+}
+
+fn config_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Start curl, handing it `secrets` on stdin.
+fn spawn_curl(cmd: &mut Command, secrets: &str) -> Result<Child, String> {
+    let mut child = cmd.spawn().map_err(|e| format!("failed to run curl: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(secrets.as_bytes()).map_err(|e| format!("failed to run curl: {}", e))?;
+    }
+    Ok(child)
+}
+
+/// `--resolve` entry pinning the URL's host to the address that won the race.
+fn pinned_address(url: &str, family: net::Family) -> Option<String> {
+    let (host, port) = net::host_port(url)?;
+    if host.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    
+    match net::fastest_address(&host, port, family)? {
+        IpAddr::V4(ip) => Some(format!("{}:{}:{}", host, port, ip)),
+        IpAddr::V6(ip) => Some(format!("{}:{}:[{}]", host, port, ip)),
+    }
+}
+
+fn curl_request(method: &str, url: &str, headers: &[String], body: Option<&str>, direct: bool) -> Result<Response, String> {
+    let mut cmd = Command::new("curl");
+    cmd.args(["-sSL", "-D", "-"]);
+    let secrets = if direct {
+        cmd.args(["--noproxy", "*"]);
+        for header in headers {
+            cmd.args(["-H", header]);
+        }
+        String::new()
+    } else {
+        configure(&mut cmd, url, headers)
+    };
+    match method {
+        "GET" => {}
+        "HEAD" => {
+            cmd.arg("--head");
+        }
+        _ => {
+            cmd.args(["-X", method]);
+        }
+    }
+    if let Some(body) = body {
+        cmd.args(["--data-binary", body]);
+    }
+    
+    cmd.arg(url).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let output = spawn_curl(&mut cmd, &secrets)?
+        .wait_with_output()
+        .map_err(|e| format!("failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} {} failed: {}",
+            method,
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_response(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| format!("{} {} returned no HTTP response", method, url))
+}
+
+/// Split `curl -D -` output: one header block per hop (redirects, 100
+/// Continue), then the body.
+fn parse_response(raw: &str) -> Option<Response> {
+    let mut rest = raw;
+    let mut last = None;
+    while rest.starts_with("HTTP/") {
+        let (block, body) = match rest.find("\r\n\r\n") {
+            Some(end) => (&rest[..end], &rest[end + 4..]),
+            None => (rest, ""),
+        };
+        last = Some(block);
+        rest = body;
+    }
+    
+    let mut lines = last?.lines();
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    Some(Response { status, headers, body: rest.to_string() })
+}
+
+fn curl_fetch(url: &str, dest: &Path, headers: &[String], resume: bool, progress: bool) -> Result<(), String> {
+    let dest_str = dest.to_str().ok_or("destination path is not valid UTF-8")?;
+    let (mut cmd, secrets) = curl(url, headers);
+    if let Some(rate) = throttle::max_rate() {
+        cmd.args(["--limit-rate", &rate.to_string()]);
+    }
+    if resume {
+        cmd.args(["-C", "-"]);
+    }
+    let mut child = spawn_curl(cmd.args(["-o", dest_str, url]), &secrets)?;
+    
+    // curl does not say how far it is when silenced; watch the file grow
+    let size = || fs::metadata(dest).map(|meta| meta.len()).unwrap_or(0);
+    let mut bar = progress.then(|| Progress::new(None, size()));
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| format!("failed to run curl: {}", e))? {
+            break status;
+        }
+        if let Some(bar) = bar.as_mut() {
+            bar.update(size());
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+    if let Some(bar) = bar.as_mut() {
+        bar.finish();
+    }
+    
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("download of {} failed", url))
+    }
+}
+
+/// The built-in client.
+#[cfg(feature = "native-http")]
+mod client {
+    use super::{with_credentials, Response};
+    use crate::progress::Progress;
+    use crate::{net, proxy, throttle, VERSION};
+    #[cfg(not(windows))]
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified};
+    #[cfg(not(windows))]
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    #[cfg(not(windows))]
+    use rustls::{DigitallySignedStruct, SignatureScheme};
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Read, Write};
+    use std::net::{SocketAddr, ToSocketAddrs};
+    use std::path::Path;
+    use std::sync::Arc;
+    #[cfg(not(windows))]
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+    /// A download that stalls this long fails (and resumes on the next run).
+    const READ_TIMEOUT: Duration = Duration::from_secs(60);
+    const MAX_REDIRECTS: u32 = 10;
+    
+    fn agent(url: &str, direct: bool) -> Result<ureq::Agent, String> {
+        let family = net::Family::from_env();
+        let mut builder = ureq::AgentBuilder::new()
+            .user_agent(&format!("bldr/{}", VERSION))
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .redirects(MAX_REDIRECTS)
+            .https_only(!direct && !super::insecure(url))
+            .resolver(move |netloc: &str| resolve(netloc, family));
+        #[cfg(windows)]
+        {
+            builder = builder.tls_connector(tls_connector(super::ca_bundle().as_deref(), super::insecure(url))?);
+        }
+        #[cfg(not(windows))]
+        if super::insecure(url) {
+            builder = builder.tls_config(loopback_tls_config()?);
+        } else if let Some(bundle) = super::ca_bundle() {
+            builder = builder.tls_config(tls_config(&bundle)?);
+        }
+        if !direct {
+            if let Some(configured) = proxy::for_request(url) {
+                builder = builder.proxy(to_proxy(&configured)?);
+            }
+        }
+        Ok(builder.build())
+    }
+    
+    /// Trust only the certificates in `bundle`.
+    #[cfg(not(windows))]
+    fn tls_config(bundle: &Path) -> Result<Arc<rustls::ClientConfig>, String> {
+        static CONFIG: OnceLock<Result<Arc<rustls::ClientConfig>, String>> = OnceLock::new();
+        CONFIG
+            .get_or_init(|| {
+                let pem = fs::read(bundle).map_err(|e| format!("cannot read CA bundle {}: {}", bundle.display(), e))?;
+                let mut roots = rustls::RootCertStore::empty();
+                for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                    let cert = cert.map_err(|e| format!("invalid CA bundle {}: {}", bundle.display(), e))?;
+                    roots
+                        .add(cert)
+                        .map_err(|e| format!("invalid certificate in {}: {}", bundle.display(), e))?;
+                }
+                if roots.is_empty() {
+                    return Err(format!("CA bundle {} holds no certificates", bundle.display()));
+                }
+                let provider = Arc::new(rustls::crypto::ring::default_provider());
+                let config = rustls::ClientConfig::builder_with_provider(provider)
+                    .with_safe_default_protocol_versions()
+                    .map_err(|e| format!("cannot set up TLS: {}", e))?
+                    .with_root_certificates(roots)
+                    .with_no_client_auth();
+                Ok(Arc::new(config))
+            })
+            .clone()
+    }
+    
+    /// TLS that takes any certificate from a loopback host and refuses every
+    /// other host, redirects included, for [`super::insecure`].
+    #[cfg(not(windows))]
+    fn loopback_tls_config() -> Result<Arc<rustls::ClientConfig>, String> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("cannot set up TLS: {}", e))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(LoopbackOnly(provider)))
+            .with_no_client_auth();
+        Ok(Arc::new(config))
+    }
+    
+    #[cfg(not(windows))]
+    #[derive(Debug)]
+    struct LoopbackOnly(Arc<rustls::crypto::CryptoProvider>);
+    
+    #[cfg(not(windows))]
+    impl rustls::client::danger::ServerCertVerifier for LoopbackOnly {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            let loopback = match server_name {
+                ServerName::DnsName(name) => super::is_loopback(name.as_ref()),
+                ServerName::IpAddress(ip) => std::net::IpAddr::from(*ip).is_loopback(),
+                _ => false,
+            };
+            if loopback {
+                Ok(ServerCertVerified::assertion())
+            } else {
+                Err(rustls::Error::General(format!(
+                    "BLDR_INSECURE_LOCALHOST covers only loopback hosts, not {:?}",
+                    server_name
+                )))
+            }
+        }
+        
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+        }
+        
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+        }
+        
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.0.signature_verification_algorithms.supported_schemes()
+        }
+    }
+    
+    /// SChannel, trusting only the certificates in `bundle` when given, or
+    /// any certificate when `insecure`.
+    #[cfg(windows)]
+    fn tls_connector(bundle: Option<&Path>, insecure: bool) -> Result<Arc<ureq::native_tls::TlsConnector>, String> {
+        let mut builder = ureq::native_tls::TlsConnector::builder();
+        builder.min_protocol_version(Some(ureq::native_tls::Protocol::Tlsv12));
+        builder.danger_accept_invalid_certs(insecure);
+        if let Some(bundle) = bundle {
+            let pem = fs::read_to_string(bundle).map_err(|e| format!("cannot read CA bundle {}: {}", bundle.display(), e))?;
+            let mut found = false;
+            for block in pem.split_inclusive("-----END CERTIFICATE-----").filter(|b| b.contains("-----BEGIN CERTIFICATE-----")) {
+                let cert = ureq::native_tls::Certificate::from_pem(block.trim().as_bytes())
+                    .map_err(|e| format!("invalid certificate in {}: {}", bundle.display(), e))?;
+                builder.add_root_certificate(cert);
+                found = true;
+            }
+            if !found {
+                return Err(format!("CA bundle {} holds no certificates", bundle.display()));
+            }
+            builder.disable_built_in_roots(true);
+        }
+        builder.build().map(Arc::new).map_err(|e| format!("cannot set up TLS: {}", e))
+    }
+    
+    /// Addresses for `host:port`: the one that won the connection race, else
+    /// every address of the allowed family.
+    fn resolve(netloc: &str, family: net::Family) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(addr) = netloc.parse::<SocketAddr>() {
+            return Ok(vec![addr]);
+        }
+        if let Some((host, port)) = netloc.rsplit_once(':').and_then(|(host, port)| Some((host, port.parse().ok()?))) {
+            if let Some(ip) = net::fastest_address(host, port, family) {
+                return Ok(vec![SocketAddr::new(ip, port)]);
+            }
+        }
+        let addrs: Vec<SocketAddr> = netloc.to_socket_addrs()?.filter(|addr| family.allows(addr)).collect();
+        if addrs.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no address of the allowed IP family"));
+        }
+        Ok(addrs)
+    }
+    
+    /// A proxy from [`proxy`] for ureq, which lets SOCKS5 proxies resolve
+    /// names (curl's `socks5h`) and cannot reach HTTPS proxies.
+    fn to_proxy(spec: &str) -> Result<ureq::Proxy, String> {
+        let spec = match spec.split_once("://") {
+            Some(("socks5h", rest)) => format!("socks5://{}", rest),
+            Some(("https", _)) => {
+                return Err("the built-in HTTP client cannot use HTTPS proxies; set BLDR_HTTP_CLIENT=curl".to_string())
+            }
+            _ => spec.to_string(),
+        };
+        ureq::Proxy::new(&spec).map_err(|e| format!("invalid proxy setting: {}", e))
+    }
+    
+    pub fn request(method: &str, url: &str, headers: &[String], body: Option<&str>, direct: bool) -> Result<Response, String> {
+        let mut request = agent(url, direct)?.request(method, url);
+        for header in with_credentials(url, headers) {
+            if let Some((name, value)) = header.split_once(':') {
+                request = request.set(name.trim(), value.trim());
+            }
+        }
+        let result = match body {
+            Some(body) => request.send_string(body),
+            None => request.call(),
+        };
+        let response = match result {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(format!("{} failed: {}", method, e)),
+        };
+        
+        let status = response.status();
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = response.header(&name)?.to_string();
+                Some((name.to_ascii_lowercase(), value))
+            })
+            .collect();
+        let body = response
+            .into_string()
+            .map_err(|e| format!("{} {}: cannot read the response: {}", method, url, e))?;
+        Ok(Response { status, headers, body })
+    }
+    
+    pub fn fetch(url: &str, dest: &Path, headers: &[String], resume: bool, progress: bool) -> Result<(), String> {
+        let offset = if resume { fs::metadata(dest).map(|meta| meta.len()).unwrap_or(0) } else { 0 };
+        let mut request = agent(url, false)?.get(url);
+        for header in with_credentials(url, headers) {
+            if let Some((name, value)) = header.split_once(':') {
+                request = request.set(name.trim(), value.trim());
+            }
+        }
+        if offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", offset));
+        }
+        let response = request.call().map_err(|e| format!("download failed: {}", e))?;
+        
+        // A server that ignores the range sends the whole file again
+        let append = offset > 0 && response.status() == 206;
+        let start = if append { offset } else { 0 };
+        let total = response.header("Content-Length").and_then(|len| len.parse::<u64>().ok()).map(|len| start + len);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(dest)
+            .map_err(|e| format!("cannot write {}: {}", dest.display(), e))?;
+        
+        let mut bar = progress.then(|| Progress::new(total, start));
+        let rate = throttle::max_rate();
+        let mut reader = response.into_reader();
+        let mut buf = [0u8; 64 * 1024];
+        let mut done = start;
+        let copied = loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(n) => {
+                    if let Err(e) = file.write_all(&buf[..n]) {
+                        break Err(e);
+                    }
+                    if let Some(rate) = rate {
+                        throttle::pace(n, rate);
+                    }
+                    done += n as u64;
+                    if let Some(bar) = bar.as_mut() {
+                        bar.update(done);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        if let Some(bar) = bar.as_mut() {
+            bar.finish();
+        }
+        copied.map_err(|e| format!("download of {} failed: {}", url, e))
+    }
+}
+
+/// Download a URL to a file.
+pub fn download(url: &str, dest: &Path) -> Result<(), String> {
+    policy::current()?.check_url(url)?;
+    fetch(url, dest, &[], false, false)
+}
+
+/// Download a URL to a file, resuming an earlier interrupted attempt.
+///
+/// Bytes land in `<dest>.partial`, which survives failures, and the rest is
+/// requested with a `Range` header. Before resuming, the existing prefix is
+/// checked against the release's `<url>.chunks` manifest and cut back to
+/// the last intact chunk. Without a manifest the prefix is kept only when
+/// `checksummed` says the caller verifies the finished file, and the
+/// download starts over otherwise. A transfer that breaks off after making
+/// progress is resumed again straight away.
+///
+/// A fresh download with a manifest of several chunks is instead fetched
+/// over several connections, a chunk per range request (see
+/// [`download_chunks`]), falling back to one stream if that fails.
+pub fn download_resumable(url: &str, dest: &Path, checksummed: bool) -> Result<(), String> {
+    policy::current()?.check_url(url)?;
+    let partial = partial_path(dest);
+    
+    let mut offset = verify_partial(url, &partial, checksummed);
+    let connections = connections();
+    if offset == 0 && connections > 1 && local_path(url).is_none() {
+        let manifest = get_text(&format!("{}.chunks", url), &[]).and_then(|text| ChunkManifest::parse(&text));
+        if let Some(manifest) = manifest.ok().filter(|manifest| manifest.count() > 1) {
+            match download_chunks(url, &partial, &manifest, connections) {
+                Ok(()) => return move_into_place(&partial, dest),
+                Err(e) => {
+                    fs::remove_file(&partial).ok();
+                    eprintln!("bldr: {}; downloading in one stream", e);
+                }
+            }
+        }
+    }
+    
+    let mut attempts = 1;
+    while let Err(e) = fetch(url, &partial, &[], offset > 0, true) {
+        let len = fs::metadata(&partial).map(|meta| meta.len()).unwrap_or(0);
+        if attempts == RESUME_ATTEMPTS {
+            return Err(e);
+        }
+        attempts += 1;
+        if len > offset {
+            eprintln!("bldr: {}; resuming at {} bytes", e, len);
+            offset = len;
+        } else if offset > 0 {
+            // Server refused the range or the file changed; start over
+            fs::remove_file(&partial).ok();
+            offset = 0;
+        } else {
+            return Err(e);
+        }
+    }
+    
+    move_into_place(&partial, dest)
+}
+
+fn move_into_place(partial: &Path, dest: &Path) -> Result<(), String> {
+    fs::rename(partial, dest).map_err(|e| format!("failed to move {} into place: {}", partial.display(), e))?;
+    perms::set_file(dest).map_err(|e| format!("cannot set permissions on {}: {}", dest.display(), e))
+}
+
+/// `BLDR_DOWNLOAD_CONNECTIONS`, else [`DEFAULT_CONNECTIONS`]; 1 downloads in
+/// a single stream, as does a capped download rate.
+fn connections() -> usize {
+    if throttle::max_rate().is_some() {
+        return 1;
+    }
+    env::var("BLDR_DOWNLOAD_CONNECTIONS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&n: &usize| n > 0)
+        .unwrap_or(DEFAULT_CONNECTIONS)
+}
+
+/// Download `url` into `partial` one chunk of `manifest` per range request,
+/// over up to `connections` at a time. Each chunk is checked against its
+/// digest as it arrives and fetched again on a failure or mismatch, up to
+/// [`RESUME_ATTEMPTS`] times; the caller still verifies the whole file.
+fn download_chunks(url: &str, partial: &Path, manifest: &ChunkManifest, connections: usize) -> Result<(), String> {
+    let count = manifest.count();
+    let chunk_path = |i: usize| {
+        let mut name = partial.as_os_str().to_owned();
+        name.push(format!(".{}", i));
+        PathBuf::from(name)
+    };
+    let fetch_chunk = |i: usize| -> Result<u64, String> {
+        let path = chunk_path(i);
+        let range = [match manifest.range(i) {
+            (start, Some(end)) => format!("Range: bytes={}-{}", start, end),
+            (start, None) => format!("Range: bytes={}-", start),
+        }];
+        let mut error = String::new();
+        for _ in 0..RESUME_ATTEMPTS {
+            let data = fetch(url, &path, &range, false, false)
+                .and_then(|_| fs::read(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e)));
+            match data {
+                Ok(data) if manifest.matches(i, &data) => return Ok(data.len() as u64),
+                // The whole file: ranges are not supported, so retrying is no use
+                Ok(data) if data.len() as u64 > manifest.chunk_size() => {
+                    return Err(format!("{} does not support range requests", url));
+                }
+                Ok(_) => error = "checksum mismatch".to_string(),
+                Err(e) => error = e,
+            }
+        }
+        Err(format!("chunk {} of {} failed: {}", i + 1, count, error))
+    };
+    let cleanup = || (0..count).for_each(|i| drop(fs::remove_file(chunk_path(i))));
+    
+    // The first chunk on its own, so a server that ignores ranges is caught
+    // before it sends the whole file once per connection
+    let first = fetch_chunk(0).inspect_err(|_| cleanup())?;
+    let bound = count as u64 * manifest.chunk_size();
+    let state = Mutex::new((Progress::new(Some(bound), 0), first, None::<String>));
+    let next = AtomicUsize::new(1);
+    thread::scope(|scope| {
+        for _ in 0..connections.min(count - 1) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= count || state.lock().map_or(true, |state| state.2.is_some()) {
+                    break;
+                }
+                let result = fetch_chunk(i);
+                let Ok(mut state) = state.lock() else { break };
+                match result {
+                    Ok(len) => {
+                        state.1 += len;
+                        let done = state.1;
+                        state.0.update(done);
+                    }
+                    Err(e) => state.2 = Some(e),
+                }
+            });
+        }
+    });
+    let (mut bar, _, failed) = state.into_inner().map_err(|_| "download thread panicked".to_string())?;
+    bar.finish();
+    if let Some(e) = failed {
+        cleanup();
+        return Err(e);
+    }
+    
+    let assembled = File::create(partial).and_then(|mut file| {
+        (0..count).try_for_each(|i| io::copy(&mut File::open(chunk_path(i))?, &mut file).map(drop))
+    });
+    cleanup();
+    assembled.map_err(|e| format!("cannot write {}: {}", partial.display(), e))
+}
+
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_owned();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// Truncate a leftover partial download to its verified prefix and return
+/// its length (0 when there is nothing worth resuming). Without a manifest,
+/// a `checksummed` download keeps everything.
+fn verify_partial(url: &str, partial: &Path, checksummed: bool) -> u64 {
+    let len = match fs::metadata(partial) {
+        Ok(meta) if meta.len() > 0 => meta.len(),
+        _ => return 0,
+    };
+    
+    let verified = match get_text(&format!("{}.chunks", url), &[]).and_then(|text| ChunkManifest::parse(&text)) {
+        Ok(manifest) => manifest.verified_prefix(partial).unwrap_or(0),
+        Err(_) if checksummed => len,
+        Err(_) => 0,
+    };
+    
+    if verified < len {
+        eprintln!("bldr: discarding {} unverified bytes of the previous download", len - verified);
+        let truncated = OpenOptions::new().write(true).open(partial).and_then(|f| f.set_len(verified));
+        if truncated.is_err() {
+            fs::remove_file(partial).ok();
+            return 0;
+        }
+    }
+    if verified > 0 {
+        eprintln!("Resuming download at {} bytes", verified);
+    }
+    verified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn keeps_the_final_response_after_redirects() {
+        let raw = "HTTP/1.1 302 Found\r\nLocation: /b\r\n\r\nHTTP/2 403\r\nRetry-After: 30\r\nX-RateLimit-Remaining: 0\r\n\r\n{\"message\": \"limited\"}";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status, 403);
+        assert_eq!(response.header("retry-after"), Some("30"));
+        assert_eq!(response.header("X-RateLimit-Remaining"), Some("0"));
+        assert_eq!(response.body, "{\"message\": \"limited\"}");
+        assert!(parse_response("not http").is_none());
+    }    
+    #[test]
+    fn file_urls_name_the_file_they_came_from() {
+        let path = env::temp_dir().join(format!("bldr-file-url-{}", std::process::id()));
+        fs::write(&path, b"archive").unwrap();
+        let url = file_url(&path).unwrap();
+        assert!(url.starts_with("file:///"));
+        assert_eq!(local_path(&url).map(|local| fs::read(local).unwrap()), Some(b"archive".to_vec()));
+        fs::remove_file(&path).ok();
+        assert!(file_url(&path).is_err());
+    }
+    
+    #[test]
+    fn refuses_plaintext_but_for_loopback() {
+        assert!(is_loopback("localhost") && is_loopback("127.0.0.1") && is_loopback("::1"));
+        assert!(!is_loopback("github.com") && !is_loopback("10.0.0.1") && !is_loopback("localhost.example.com"));
+        assert!(require_https("https://github.com/org/bldr/releases").is_ok());
+        assert!(require_https("http://github.com/org/bldr/releases").is_err());
+        assert!(require_https("ftp://mirror.example.com/bldr").is_err());
+    }
+    
+    #[test]
+    fn keeps_unmanifested_partials_only_when_checksummed() {
+        let partial = env::temp_dir().join(format!("bldr-partial-{}", std::process::id()));
+        let url = "file:///nonexistent/bldr.tar.gz";
+        
+        fs::write(&partial, b"0123456789").unwrap();
+        assert_eq!(verify_partial(url, &partial, true), 10);
+        assert_eq!(verify_partial(url, &partial, false), 0);
+        assert_eq!(fs::metadata(&partial).map(|meta| meta.len()).unwrap_or(0), 0);
+        fs::remove_file(&partial).ok();
+    }
+}
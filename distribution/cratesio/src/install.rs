@@ -0,0 +1,244 @@
+//! Unpacking release archives.
+//!
+//! Archives are tarballs compressed with gzip or zstd, read in-process so
+//! installing needs no `tar`, `gzip` or `zstd` on the host (Windows, minimal
+//! containers). An archive is refused outright, before anything is run,
+//! when an entry would land outside the install directory, a link points out
+//! of it, an entry is anything but a file, directory or link, or the whole
+//! would unpack to more than [`MAX_UNPACKED`] bytes. On Unix the archive's
+//! executable bits are kept, then normalised by [`perms`] like everything
+//! else the wrapper writes.
+
+use crate::{binfmt, perms};
+use flate2::read::GzDecoder;
+use ruzstd::decoding::StreamingDecoder;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// The first bytes of a zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// Most a release archive may unpack to; releases are tens of megabytes,
+/// so this only stops decompression bombs and corrupt size fields.
+const MAX_UNPACKED: u64 = 1 << 30;
+
+/// File name of the core binary inside a release archive.
+pub fn binary_name() -> &'static str {
+    if cfg!(windows) { "bldr.exe" } else { "bldr" }
+}
+
+/// [`binary_name`] in another platform's archive.
+pub fn binary_name_for(os: &str) -> &'static str {
+    if os == "windows" { "bldr.exe" } else { "bldr" }
+}
+
+/// Unpack a release archive into `dir` and return the executable core binary.
+pub fn extract(archive: &Path, dir: &Path) -> Result<PathBuf, String> {
+    unpack(archive, dir)?;
+    
+    let binary_path = dir.join(binary_name());
+    if !binary_path.exists() {
+        return Err(format!("{} does not contain {}", archive.display(), binary_name()));
+    }
+    // Built for another platform: remove it so the version is not cached
+    if let Err(e) = binfmt::check(&binary_path) {
+        fs::remove_file(&binary_path).ok();
+        return Err(e);
+    }
+    
+    // The archive's modes went through the umask; give the tree explicit ones
+    perms::apply_tree(dir).map_err(|e| format!("cannot set permissions in {}: {}", dir.display(), e))?;
+    make_executable(&binary_path).map_err(|e| format!("cannot mark {} executable: {}", binary_path.display(), e))?;
+    Ok(binary_path)
+}
+
+/// Unpack a `.tar.gz` or `.tar.zst` into `dir`, told apart by their
+/// contents rather than the file name.
+pub fn unpack(archive: &Path, dir: &Path) -> Result<(), String> {
+    let open = || File::open(archive).map_err(|e| format!("cannot open {}: {}", archive.display(), e));
+    let mut magic = [0u8; 4];
+    let zstd = open()?.read_exact(&mut magic).is_ok() && magic == ZSTD_MAGIC;
+    
+    let decoder: Box<dyn Read> = if zstd {
+        let decoder = StreamingDecoder::new(BufReader::new(open()?))
+            .map_err(|e| format!("failed to extract {}: {}", archive.display(), e))?;
+        Box::new(decoder)
+    } else {
+        Box::new(GzDecoder::new(open()?))
+    };
+    unpack_entries(decoder, dir, MAX_UNPACKED).map_err(|e| format!("failed to extract {}: {}", archive.display(), e))
+}
+
+/// Unpack a tar stream into `dir` entry by entry, checking each before it
+/// is written.
+fn unpack_entries(tarball: impl Read, dir: &Path, limit: u64) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    let mut archive = tar::Archive::new(tarball);
+    let mut total: u64 = 0;
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path().map_err(|e| e.to_string())?.into_owned();
+        if !contained(&path) {
+            return Err(format!("entry {} would land outside {}", path.display(), dir.display()));
+        }
+        
+        match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous | tar::EntryType::Directory => {}
+            kind @ (tar::EntryType::Symlink | tar::EntryType::Link) => {
+                let target = entry
+                    .link_name()
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| format!("link {} has no target", path.display()))?;
+                // Symlinks resolve from their own directory, hard links from
+                // the archive root
+                let resolved = match kind {
+                    tar::EntryType::Symlink => path.parent().unwrap_or(Path::new("")).join(&target),
+                    _ => target.to_path_buf(),
+                };
+                if !contained(&resolved) {
+                    return Err(format!("link {} -> {} points outside {}", path.display(), target.display(), dir.display()));
+                }
+            }
+            kind => return Err(format!("entry {} has unsupported type {:?}", path.display(), kind)),
+        }
+        
+        total = total.saturating_add(entry.header().size().map_err(|e| e.to_string())?);
+        if total > limit {
+            return Err(format!("archive unpacks to more than {} bytes", limit));
+        }
+        if !entry.unpack_in(dir).map_err(|e| format!("{}: {}", path.display(), e))? {
+            return Err(format!("entry {} would land outside {}", path.display(), dir.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a relative archive path stays within the directory it is
+/// unpacked into, judged by its components alone.
+fn contained(path: &Path) -> bool {
+    let mut depth: usize = 0;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(up) => depth = up,
+                None => return false,
+            },
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+pub fn make_executable(path: &Path) -> std::io::Result<()> {
+    perms::make_executable(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs;
+    
+    #[test]
+    fn unpacks_release_archives_in_process() {
+        let root = std::env::temp_dir().join(format!("bldr-install-{}", std::process::id()));
+        let archive = root.join("release.tar.gz");
+        let dir = root.join("1.2.3");
+        fs::create_dir_all(&dir).unwrap();
+        
+        let core = binfmt::host_header();
+        let mut builder = tar::Builder::new(GzEncoder::new(File::create(&archive).unwrap(), Compression::default()));
+        for (name, mode, contents) in [(binary_name(), 0o755, &core[..]), ("LICENSE", 0o644, b"MIT\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(mode);
+            header.set_cksum();
+            builder.append_data(&mut header, name, contents).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        
+        let binary = extract(&archive, &dir).unwrap();
+        assert_eq!(fs::read(&binary).unwrap(), core);
+        assert_eq!(fs::read_to_string(dir.join("LICENSE")).unwrap(), "MIT\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&binary), 0o755);
+            assert_eq!(mode(&dir.join("LICENSE")), 0o644);
+        }
+        
+        // The same tarball compressed with zstd
+        let tar_bytes = {
+            let mut bytes = Vec::new();
+            GzDecoder::new(File::open(&archive).unwrap()).read_to_end(&mut bytes).unwrap();
+            bytes
+        };
+        let zst = root.join("release.tar.zst");
+        fs::write(&zst, ruzstd::encoding::compress_to_vec(&tar_bytes[..], ruzstd::encoding::CompressionLevel::Fastest)).unwrap();
+        let binary = extract(&zst, &root.join("zstd")).unwrap();
+        assert_eq!(fs::read(&binary).unwrap(), core);
+        
+        // A core that is not an executable for this machine is not kept
+        let mut builder = tar::Builder::new(GzEncoder::new(File::create(&archive).unwrap(), Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(10);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, binary_name(), &b"#!/bin/sh\n"[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        assert!(extract(&archive, &root.join("script")).unwrap_err().contains("not an executable"));
+        assert!(!root.join("script").join(binary_name()).exists());
+        
+        fs::write(&archive, "not gzip").unwrap();
+        assert!(extract(&archive, &dir.join("broken")).is_err());
+        fs::remove_dir_all(&root).ok();
+    }
+    
+    /// A tarball of `(name, type, link target, contents)` entries, names
+    /// written as given so hostile ones can be built.
+    fn raw_tarball(entries: &[(&str, tar::EntryType, &str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, kind, link, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(*kind);
+            if !link.is_empty() {
+                header.set_link_name(link).unwrap();
+            }
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, contents.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+    
+    #[test]
+    fn refuses_entries_escaping_the_install_directory() {
+        let root = std::env::temp_dir().join(format!("bldr-traversal-{}", std::process::id()));
+        let unpack = |name: &str, tarball: Vec<u8>, limit: u64| unpack_entries(&tarball[..], &root.join(name), limit);
+        let file = tar::EntryType::Regular;
+        let symlink = tar::EntryType::Symlink;
+        
+        assert!(unpack("parent", raw_tarball(&[("../evil", file, "", "x")]), MAX_UNPACKED).is_err());
+        assert!(unpack("absolute", raw_tarball(&[("/tmp/evil", file, "", "x")]), MAX_UNPACKED).is_err());
+        assert!(unpack("nested", raw_tarball(&[("lib/../../evil", file, "", "x")]), MAX_UNPACKED).is_err());
+        assert!(unpack("symlink", raw_tarball(&[("lib/passwd", symlink, "../../etc/passwd", "")]), MAX_UNPACKED).is_err());
+        assert!(unpack("symlink-abs", raw_tarball(&[("passwd", symlink, "/etc/passwd", "")]), MAX_UNPACKED).is_err());
+        assert!(unpack("hardlink", raw_tarball(&[("passwd", tar::EntryType::Link, "../passwd", "")]), MAX_UNPACKED).is_err());
+        assert!(unpack("fifo", raw_tarball(&[("pipe", tar::EntryType::Fifo, "", "")]), MAX_UNPACKED).is_err());
+        assert!(!root.parent().unwrap().join("evil").exists());
+        
+        // Links within the directory are fine; the size cap counts every entry
+        let inside = raw_tarball(&[("lib/libcore.so.2", file, "", "elf"), ("lib/libcore.so", symlink, "libcore.so.2", "")]);
+        assert!(unpack("inside", inside, MAX_UNPACKED).is_ok());
+        let large = raw_tarball(&[("a", file, "", "12345"), ("b", file, "", "67890")]);
+        assert!(unpack("large", large.clone(), 10).is_ok());
+        assert!(unpack("too-large", large, 9).is_err());
+        fs::remove_dir_all(&root).ok();
+    }
+}
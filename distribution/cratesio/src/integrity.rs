@@ -0,0 +1,100 @@
+//! Integrity of installed core binaries.
+//!
+//! An install records the binary's SHA-256, size and modification time in
+//! `.integrity` beside it. Each run compares size and modification time, a
+//! single `stat`; only when they differ is the binary hashed again. A binary
+//! whose hash no longer matches (disk corruption, an interrupted copy, an
+//! overwrite) is reinstalled rather than run. Versions installed before
+//! this record one on their next run.
+
+use crate::{checksum, perms};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const RECORD_FILE: &str = ".integrity";
+
+/// `<sha256> <size> <mtime nanoseconds>`.
+struct Record {
+    sha256: String,
+    size: u64,
+    modified: u128,
+}
+
+/// Record the freshly installed `binary` as good.
+pub fn record(binary: &Path) {
+    if let Ok(sha256) = checksum::sha256_file(binary) {
+        write(binary, &sha256);
+    }
+}
+
+/// Check that `binary` is still what was installed.
+pub fn verify(binary: &Path) -> Result<(), String> {
+    let Some(recorded) = read(binary) else {
+        record(binary);
+        return Ok(());
+    };
+    let (size, modified) = stat(binary).ok_or_else(|| format!("cannot read {}", binary.display()))?;
+    if size == recorded.size && modified == recorded.modified {
+        return Ok(());
+    }
+    let sha256 = checksum::sha256_file(binary).map_err(|e| format!("cannot read {}: {}", binary.display(), e))?;
+    if sha256 != recorded.sha256 {
+        return Err(format!("SHA-256 {} where {} was installed", sha256, recorded.sha256));
+    }
+    // Same contents, touched or linked since: refresh the fast path
+    write(binary, &sha256);
+    Ok(())
+}
+
+fn write(binary: &Path, sha256: &str) {
+    if let Some((size, modified)) = stat(binary) {
+        perms::write(&record_path(binary), format!("{} {} {}\n", sha256, size, modified)).ok();
+    }
+}
+
+fn read(binary: &Path) -> Option<Record> {
+    let contents = fs::read_to_string(record_path(binary)).ok()?;
+    let mut fields = contents.split_whitespace();
+    Some(Record {
+        sha256: fields.next()?.to_string(),
+        size: fields.next()?.parse().ok()?,
+        modified: fields.next()?.parse().ok()?,
+    })
+}
+
+fn stat(binary: &Path) -> Option<(u64, u128)> {
+    let meta = fs::metadata(binary).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    Some((meta.len(), modified))
+}
+
+fn record_path(binary: &Path) -> PathBuf {
+    binary.with_file_name(RECORD_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    
+    #[test]
+    fn detects_changed_contents_only() {
+        let dir = env::temp_dir().join(format!("bldr-integrity-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("bldr");
+        fs::write(&binary, "core").unwrap();
+        record(&binary);
+        assert!(verify(&binary).is_ok());
+        
+        // Rewritten with the same bytes: hashed again, still good
+        fs::write(&binary, "core").unwrap();
+        let file = fs::File::options().write(true).open(&binary).unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+        assert!(verify(&binary).is_ok());
+        
+        fs::write(&binary, "junk").unwrap();
+        assert!(verify(&binary).unwrap_err().contains("SHA-256"));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
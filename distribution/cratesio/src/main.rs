@@ -1,22 +1,185 @@
 use std::env;
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::{Command, exit};
+use std::time::SystemTime;
+
+mod argfile;
+mod attestation;
+mod binfmt;
+mod cache;
+mod changelog;
+mod channel;
+mod checksum;
+mod commands;
+mod config;
+mod consent;
+mod cosign;
+mod crash;
+mod credentials;
+mod dedupe;
+mod fastpath;
+#[cfg(test)]
+mod fixture;
+mod health;
+mod history;
+mod gc;
+mod github;
+mod gpg;
+mod http;
+mod install;
+mod integrity;
+mod minisign;
+mod net;
+mod oci;
+mod offline;
+mod perms;
+mod pin;
+mod platform;
+mod policy;
+mod profile;
+mod progress;
+mod proxy;
+mod rolling;
+mod s3;
+mod shared;
+mod system;
+mod throttle;
+mod tlog;
+mod update_check;
+mod version;
 
 const VERSION: &str = "2.0.3";
+/// Downloads of a release archive before a checksum mismatch, or a core
+/// failing its health check, is fatal.
+const DOWNLOAD_ATTEMPTS: u32 = 2;
 
 fn main() {
-    let binary_path = get_or_download_binary();
+    config::apply();
+    let mut argv = env::args();
+    let argv0 = argv.next().unwrap_or_default();
+    let args: Vec<String> = argv.collect();
+    if matches!(args.first().map(String::as_str), Some("wrapper" | "self")) {
+        exit(commands::run(&args[1..]));
+    }
+    
+    let args = match argfile::expand(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("bldr: {}", e);
+            exit(2);
+        }
+    };
+    let (no_changelog, args) = take_switch(args, "--no-changelog");
+    let (yes, args) = take_switch(args, "--yes");
+    let (print_path, args) = take_switch(args, "--print-binary-path");
+    let (profiler, args) = match profile::take_flag(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("bldr: {}", e);
+            exit(2);
+        }
+    };
+    
+    // Invoked as bldr-2.1, bldr-nightly, ...: the name picks the version,
+    // then BLDR_VERSION, then project and global pins, then the channel
+    let selector = version::Selector::from_argv0(&argv0)
+        .or_else(pin::from_env)
+        .or_else(pin::selector)
+        .or_else(channel::selector)
+        .unwrap_or_default();
+    
+    let policy = match policy::current() {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!("bldr: {}", e);
+            exit(1);
+        }
+    };
+    
+    // A core of the user's choosing, e.g. a local build: pins, channels and
+    // downloads do not apply
+    if let Some(path) = bin_override() {
+        if let Err(e) = policy.check_override() {
+            eprintln!("bldr: {}", e);
+            exit(1);
+        }
+        if print_path {
+            println!("{}", path.display());
+            exit(0);
+        }
+        if let Some(profiler) = profiler {
+            exit(profile::run(profiler, &path, &args));
+        }
+        match launch_override(&path, &args) {
+            Ok(code) => exit(code),
+            Err(e) => {
+                eprintln!("bldr: cannot run BLDR_BIN {}: {}", path.display(), e);
+                exit(1);
+            }
+        }
+    }
+    
+    // Warm path: a recorded resolution for this environment goes straight to
+    // exec. Disallowed versions fall through so the violation is reported below.
+    if let (version::Selector::Exact(version), None) = (&selector, profiler) {
+        let lookup = fastpath::lookup(version).filter(|path| policy.check_version(version).is_ok() && integrity::verify(path).is_ok());
+        if let Some(path) = lookup {
+            if print_path {
+                println!("{}", path.display());
+                exit(0);
+            }
+            match launch(&path, version, &args) {
+                Ok(code) => exit(code),
+                Err(_) => fastpath::forget(version),
+            }
+        }
+    }
+    
+    let resolved = match version::resolve(&selector) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            eprintln!("bldr: cannot resolve version for {}: {}", argv0, e);
+            exit(1);
+        }
+    };
+    if let Err(e) = policy.check_version(&resolved.version) {
+        eprintln!("bldr: {}", e);
+        exit(1);
+    }
+    if let Some(previous) = &resolved.previous {
+        if !changelog::suppressed(no_changelog) {
+            changelog::print(previous, &resolved.version, &resolved.notes);
+        }
+    }
+    let version = resolved.version;
+    
+    // A core of this version already on PATH spares a download
+    let cached = cache::root().join(&version).join(install::binary_name()).is_file();
+    let system = if cached { None } else { system::find(&version) };
+    let from_system = system.is_some();
+    let binary_path = system.or_else(|| get_or_download_binary(&version, consent::assumed(yes)));
     
     match binary_path {
         Some(path) => {
-            let args: Vec<String> = env::args().skip(1).collect();
-            let status = Command::new(&path)
-                .args(&args)
-                .status()
-                .expect("Failed to execute bldr");
-            exit(status.code().unwrap_or(1));
+            if selector.is_exact() && !from_system {
+                fastpath::record(&version, &path);
+            }
+            if print_path {
+                println!("{}", path.display());
+                exit(0);
+            }
+            if let Some(profiler) = profiler {
+                exit(profile::run(profiler, &path, &args));
+            }
+            match launch(&path, &version, &args) {
+                Ok(code) => exit(code),
+                Err(e) => {
+                    eprintln!("bldr: cannot run {}: {}", path.display(), e);
+                    exit(1);
+                }
+            }
         }
         None => {
             eprintln!("bldr: Failed to download binary for this platform.");
@@ -28,91 +191,358 @@ fn main() {
     }
 }
 
-fn get_or_download_binary() -> Option<PathBuf> {
-    let cache_dir = dirs::cache_dir()
-        .unwrap_or_else(|| PathBuf::from("/tmp"))
-        .join("bldr")
-        .join(VERSION);
-    
-    let binary_name = if cfg!(windows) { "bldr.exe" } else { "bldr" };
-    let binary_path = cache_dir.join(binary_name);
-    
-    // Return cached binary if exists
-    if binary_path.exists() {
-        return Some(binary_path);
+/// Remove a wrapper-only switch from the arguments, leaving anything after
+/// `--` for the core.
+fn take_switch(args: Vec<String>, switch: &str) -> (bool, Vec<String>) {
+    let end = args.iter().position(|a| a == "--").unwrap_or(args.len());
+    let found = args[..end].iter().any(|a| a == switch);
+    let rest = args
+        .into_iter()
+        .enumerate()
+        .filter(|(i, a)| *i >= end || a != switch)
+        .map(|(_, a)| a)
+        .collect();
+    (found, rest)
+}
+
+/// The core `BLDR_BIN` names, run in place of the one the version selects.
+pub fn bin_override() -> Option<PathBuf> {
+    env::var_os("BLDR_BIN").filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+/// Run the `BLDR_BIN` core and return its exit code.
+fn launch_override(path: &Path, args: &[String]) -> io::Result<i32> {
+    cache::check_secure(path).map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?;
+    Ok(Command::new(path).args(args).status()?.code().unwrap_or(1))
+}
+
+/// Run the core binary and return its exit code, diagnosing crashes.
+fn launch(path: &Path, version: &str, args: &[String]) -> io::Result<i32> {
+    cache::check_secure(path).map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?;
+    cache::mark_used(version);
+    let check_updates = update_check::enabled();
+    if check_updates {
+        update_check::start();
     }
+    let started = SystemTime::now();
+    let mut child = Command::new(path).args(args).spawn()?;
+    let pid = child.id();
+    let status = child.wait()?;
     
-    // Determine platform
-    let (os, arch) = get_platform();
-    let asset_name = format!("bldr-{}-{}", os, arch);
-    let url = format!(
-        "https://github.com/GriffinCanCode/bldr/releases/download/v{}/{}.tar.gz",
-        VERSION, asset_name
-    );
+    let code = match crash::detect(&status, pid) {
+        Some(crash) => crash::handle(crash, path, version, args, started),
+        None => status.code().unwrap_or(1),
+    };
+    if code == 0 {
+        history::record_success(version);
+    }
+    if check_updates {
+        update_check::notice(version);
+    }
+    Ok(code)
+}
+
+fn get_or_download_binary(version: &str, assume_yes: bool) -> Option<PathBuf> {
+    let binary_path = cache::root().join(version).join(install::binary_name());
+    if let Err(e) = cache::check_secure(&binary_path) {
+        eprintln!("bldr: {}", e);
+        exit(1);
+    }
     
-    eprintln!("Downloading bldr v{} for {}-{}...", VERSION, os, arch);
+    // Return cached binary if exists, unless it is a rolling release that
+    // has since been republished
+    let refresh = binary_path.exists() && rolling::is_rolling(version) && rolling::outdated(version);
+    if binary_path.exists() && !refresh {
+        match integrity::verify(&binary_path) {
+            Ok(()) => return Some(binary_path),
+            Err(e) => {
+                eprintln!("bldr: the cached bldr {} is corrupted ({}); installing it again", version, e);
+                let _lock = cache::lock(version).ok();
+                fs::remove_dir_all(cache::root().join(version)).ok();
+            }
+        }
+    }
+    // Say why nothing can be installed rather than failing the download
+    let root = cache::root();
+    if let Err(e) = cache::check_writable(&root) {
+        eprintln!("bldr: cannot write to the cache {}: {}", root.display(), e);
+        eprintln!("Set BLDR_CACHE_DIR to a writable directory to install bldr {}.", version);
+        exit(1);
+    }
+    if let Err(e) = policy::current().and_then(|p| p.check_download(version)) {
+        eprintln!("bldr: {}", e);
+        exit(1);
+    }
     
-    // Create cache directory
-    fs::create_dir_all(&cache_dir).ok()?;
+    // An archive carried over by hand, else the mirrors
+    let mirrors = match env::var_os("BLDR_LOCAL_ARCHIVE").filter(|path| !path.is_empty()) {
+        Some(path) => match http::file_url(Path::new(&path)) {
+            Ok(url) => vec![(url, None)],
+            Err(e) => {
+                eprintln!("bldr: BLDR_LOCAL_ARCHIVE: {}", e);
+                exit(1);
+            }
+        },
+        None => release_mirrors(version),
+    };
     
-    let archive_path = cache_dir.join("bldr.tar.gz");
+    // Offline, only a file:// mirror can still provide it, or the shared
+    // cache when the digest to check its copy against is embedded
+    let shared = !refresh && shared::has(version) && checksum::pins(version);
+    if offline::enabled() && !shared && !mirrors.iter().any(|(url, _)| url.starts_with("file://")) {
+        eprintln!("bldr: {}", offline::not_installed(version));
+        exit(1);
+    }
     
-    // Download
-    let status = Command::new("curl")
-        .args(["-fsSL", "-o", archive_path.to_str()?, &url])
-        .status()
-        .ok()?;
+    // Say what is about to be fetched before the first download
+    let source = mirrors.first().map(|(url, _)| url.as_str()).unwrap_or_default();
+    if !consent::confirm(version, source, assume_yes) {
+        eprintln!("bldr: download declined; nothing was installed");
+        exit(1);
+    }
     
-    if !status.success() {
-        return None;
+    // One process installs; others wait, then use what it installed
+    let _lock = cache::lock(version)
+        .map_err(|e| eprintln!("bldr: warning: {}; installing without a lock", e))
+        .ok();
+    if binary_path.exists() && !refresh {
+        return Some(binary_path);
+    }
+    install_release(version, &mirrors)
+}
+
+/// `(.tar.gz URL, .tar.zst URL)` of this platform's archive on each mirror.
+fn release_mirrors(version: &str) -> Vec<(String, Option<String>)> {
+    let (os, arch) = platform::current();
+    platform_mirrors(version, os, arch)
+}
+
+/// [`release_mirrors`] for the archive of another platform.
+fn platform_mirrors(version: &str, os: &str, arch: &str) -> Vec<(String, Option<String>)> {
+    let asset_name = platform::asset_name(os, arch);
+    let urls = github::asset_urls(version, &format!("{}.tar.gz", asset_name));
+    let zst_urls = github::asset_urls(version, &format!("{}.tar.zst", asset_name));
+    urls.into_iter().zip(zst_urls.into_iter().map(Some)).collect()
+}
+
+/// Install `version` into the cache from the first of `mirrors` that serves
+/// an archive passing every check, and return the binary once it has run
+/// and reported that version. A core failing that is removed and installed
+/// once more.
+fn install_release(version: &str, mirrors: &[(String, Option<String>)]) -> Option<PathBuf> {
+    let cache_dir = cache::root().join(version);
+    for attempt in 1..=DOWNLOAD_ATTEMPTS {
+        let (binary, archive) = install_release_into(&cache_dir, version, mirrors)?;
+        let checked = health::check(&binary, version);
+        if checked.is_ok() {
+            shared::publish(version, &archive);
+        }
+        fs::remove_file(&archive).ok();
+        match checked {
+            Ok(()) => {
+                if rolling::is_rolling(version) {
+                    rolling::record(version);
+                }
+                integrity::record(&binary);
+                eprintln!("Done! Cached at {}", binary.display());
+                dedupe::after_install(version);
+                gc::after_install(version);
+                return Some(binary);
+            }
+            Err(e) => {
+                fs::remove_dir_all(&cache_dir).ok();
+                eprintln!("bldr: the installed core failed its health check: {}", e);
+                if attempt < DOWNLOAD_ATTEMPTS {
+                    eprintln!("Installing again...");
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Download and unpack `version` into `cache_dir`, without the health check,
+/// returning the binary and the archive, left for the caller to remove.
+fn install_release_into(cache_dir: &Path, version: &str, mirrors: &[(String, Option<String>)]) -> Option<(PathBuf, PathBuf)> {
+    let (os, arch) = platform::current();
+    match mirrors {
+        [(url, None)] if url.starts_with("file://") => eprintln!("Installing bldr {} from {}...", github::tag(version), url),
+        _ => eprintln!("Downloading bldr {} for {}-{}...", github::tag(version), os, arch),
     }
     
-    // Extract
-    let status = Command::new("tar")
-        .args(["-xzf", archive_path.to_str()?, "-C", cache_dir.to_str()?])
-        .status()
-        .ok()?;
+    // Create cache directory
+    perms::create_dir_all(cache_dir).ok()?;
+    let (source, archive_path) = fetch_release(cache_dir, version, mirrors)?;
+    cache::record_source(cache_dir, &source);
     
-    if !status.success() {
+    // Extract
+    match install::extract(&archive_path, cache_dir) {
+        Ok(binary_path) => Some((binary_path, archive_path)),
+        Err(e) => {
+            fs::remove_file(&archive_path).ok();
+            eprintln!("bldr: {}", e);
+            None
+        }
+    }
+}
+
+/// Download `version`'s archive into `dir` from the first of `mirrors` that
+/// serves one passing every check, and return its URL and path.
+fn fetch_release(dir: &Path, version: &str, mirrors: &[(String, Option<String>)]) -> Option<(String, PathBuf)> {
+    // Mirrors in order; one that is unreachable or serves a bad archive
+    // hands over to the next. Each is asked for the smaller, faster to
+    // unpack .tar.zst first when the release lists one.
+    let mut source = None;
+    'mirrors: for (i, (url, zst_url)) in mirrors.iter().enumerate() {
+        let candidates = match zst_url.as_ref().filter(|zst_url| checksum::listed(zst_url, version)) {
+            Some(zst_url) => vec![zst_url, url],
+            None => vec![url],
+        };
+        for url in candidates {
+            let archive_path = dir.join(archive_name(url));
+            match download_release(url, version, &archive_path) {
+                Ok(()) => {
+                    source = Some((url, archive_path));
+                    break 'mirrors;
+                }
+                Err(e) => {
+                    fs::remove_file(&archive_path).ok();
+                    eprintln!("bldr: {}", e);
+                }
+            }
+        }
+        if let Some((next, _)) = mirrors.get(i + 1) {
+            eprintln!("Trying the next mirror, {}...", next);
+        }
+    }
+    let Some((source, archive_path)) = source else {
+        eprintln!("bldr: could not install bldr {}; run the command again to retry", github::tag(version));
         return None;
+    };
+    if mirrors.len() > 1 {
+        eprintln!("Downloaded from {}", source);
     }
+    Some((source.clone(), archive_path))
+}
+
+/// Cache file name for the archive at `url`, keeping its compression.
+fn archive_name(url: &str) -> &'static str {
+    if url.ends_with(".tar.zst") { "bldr.tar.zst" } else { "bldr.tar.gz" }
+}
+
+/// Download the release archive from `url` into `archive` and run every
+/// configured check on it.
+fn download_release(url: &str, version: &str, archive: &Path) -> Result<(), String> {
+    // What the archive must hash to, checked before anything is extracted
+    // or run
+    let expected = checksum::expected_for(url, version)?;
     
-    // Make executable
-    if binary_path.exists() {
-        let mut perms = fs::metadata(&binary_path).ok()?.permissions();
-        perms.set_mode(0o755);
-        fs::set_permissions(&binary_path, perms).ok()?;
+    // The shared cache's copy stands in for the download only when it
+    // matches a known digest; anyone who can write the share could have
+    // put it there
+    let copied = expected.is_some() && shared::fetch(url, version, archive);
+    let from_share = copied
+        && expected.as_deref().is_some_and(|expected| {
+            checksum::verify(archive, expected)
+                .map_err(|e| eprintln!("bldr: the shared cache's copy is not the release ({}); downloading it", e))
+                .is_ok()
+        });
+    if copied && !from_share {
+        fs::remove_file(archive).ok();
     }
-    
-    // Cleanup archive
-    fs::remove_file(&archive_path).ok();
-    
-    if binary_path.exists() {
-        eprintln!("Done! Cached at {}", binary_path.display());
-        Some(binary_path)
-    } else {
-        None
+    if !from_share {
+        // Download; a corrupted transfer gets one fresh attempt
+        for attempt in 1..=DOWNLOAD_ATTEMPTS {
+            http::download_resumable(url, archive, expected.is_some())?;
+            let Some(expected) = &expected else {
+                break;
+            };
+            match checksum::verify(archive, expected) {
+                Ok(()) => break,
+                Err(e) if attempt == DOWNLOAD_ATTEMPTS => return Err(format!("{}; the download was discarded", e)),
+                Err(e) => {
+                    fs::remove_file(archive).ok();
+                    eprintln!("bldr: {}", e);
+                    eprintln!("Downloading again...");
+                }
+            }
+        }
     }
+    
+    tlog::check(archive)
+        .and_then(|_| gpg::check(archive, url))
+        .and_then(|_| minisign::check(archive, url))
+        .and_then(|_| cosign::check(archive, url, version))
+        .and_then(|_| attestation::check(archive, version))
 }
 
-fn get_platform() -> (&'static str, &'static str) {
-    let os = if cfg!(target_os = "macos") {
-        "darwin"
-    } else if cfg!(target_os = "linux") {
-        "linux"
-    } else if cfg!(target_os = "windows") {
-        "windows"
-    } else {
-        "unknown"
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
     
-    let arch = if cfg!(target_arch = "aarch64") {
-        "arm64"
-    } else if cfg!(target_arch = "x86_64") {
-        "amd64"
-    } else {
-        "unknown"
-    };
+    /// A release tarball holding a stand-in core binary: this machine's
+    /// executable header, then `output`.
+    fn release_tar(output: &str) -> Vec<u8> {
+        let contents = [&binfmt::host_header()[..], output.as_bytes()].concat();
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, install::binary_name(), &contents[..]).unwrap();
+        builder.into_inner().unwrap()
+    }
     
-    (os, arch)
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        io::Write::write_all(&mut encoder, data).unwrap();
+        encoder.finish().unwrap()
+    }
+    
+    fn sha256(data: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+    
+    #[test]
+    fn installs_a_release_from_a_mock_host() {
+        let tar = release_tar("fixture");
+        let gz = gzip(&tar);
+        let zst = ruzstd::encoding::compress_to_vec(&tar[..], ruzstd::encoding::CompressionLevel::Fastest);
+        let sums = format!("{}  bldr-test.tar.gz\n{}  bldr-test.tar.zst\n", sha256(&gz), sha256(&zst));
+        let server = fixture::Server::start(vec![
+            ("/v0.0.1/bldr-test.tar.gz".to_string(), gz.clone()),
+            ("/v0.0.1/bldr-test.tar.zst".to_string(), zst),
+            ("/v0.0.1/SHA256SUMS".to_string(), sums.into_bytes()),
+            ("/bad/v0.0.1/bldr-test.tar.gz".to_string(), b"not the release".to_vec()),
+            ("/bad/v0.0.1/SHA256SUMS".to_string(), format!("{}  bldr-test.tar.gz\n", sha256(&gz)).into_bytes()),
+        ]);
+        let dir = env::temp_dir().join(format!("bldr-fixture-{}", std::process::id()));
+        
+        // A mirror serving a corrupt archive hands over to the next, which
+        // is asked for its .tar.zst first
+        let mirrors = [
+            (server.url("/bad/v0.0.1/bldr-test.tar.gz"), None),
+            (server.url("/v0.0.1/bldr-test.tar.gz"), Some(server.url("/v0.0.1/bldr-test.tar.zst"))),
+        ];
+        let (binary, archive) = install_release_into(&dir, "0.0.1", &mirrors).unwrap();
+        assert_eq!(binary, dir.join(install::binary_name()));
+        assert_eq!(archive, dir.join("bldr.tar.zst"));
+        assert!(fs::read(&binary).unwrap().ends_with(b"fixture"));
+        assert_eq!(
+            fs::read_to_string(dir.join(".source")).unwrap().trim(),
+            server.url("/v0.0.1/bldr-test.tar.zst")
+        );
+        assert!(!dir.join("bldr.tar.gz").exists());
+        let requests = server.requests();
+        assert!(requests.iter().any(|r| r.starts_with("/bad/v0.0.1/bldr-test.tar.gz")));
+        assert!(requests.iter().any(|r| r.starts_with("/v0.0.1/bldr-test.tar.zst")));
+        assert!(!requests.iter().any(|r| r.starts_with("/v0.0.1/bldr-test.tar.gz")));
+        
+        // Nothing is left to install from when every mirror fails
+        assert!(install_release_into(&dir.join("missing"), "0.0.2", &[(server.url("/v0.0.2/bldr-test.tar.gz"), None)]).is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
 }
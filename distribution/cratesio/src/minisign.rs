@@ -0,0 +1,113 @@
+//! minisign (Ed25519) signatures of release archives.
+//!
+//! Releases publish `<asset>.minisig` next to each archive, made with the
+//! key whose public half is embedded in the crate (`minisign.pub`, written
+//! by tools/release.sh). When the build has a key, a published signature is
+//! always checked and a bad one rejects the archive; a missing signature is
+//! only an error with `BLDR_REQUIRE_SIGNATURE=1` (or `minisign` among the
+//! policy's `verification` schemes), and `BLDR_REQUIRE_SIGNATURE=warn`
+//! downgrades every failure to a warning.
+
+use crate::http;
+use crate::policy::Mode;
+use minisign_verify::{PublicKey, Signature};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const EMBEDDED_KEY: &str = include_str!("minisign.pub");
+
+/// Apply the configured policy to a downloaded archive fetched from `url`.
+/// Errors mean the archive must not be used.
+pub fn check(archive: &Path, url: &str) -> Result<(), String> {
+    let mode = Mode::for_scheme("minisign", "BLDR_REQUIRE_SIGNATURE");
+    let key = match public_key(EMBEDDED_KEY) {
+        Ok(key) => key,
+        Err(_) if mode == Mode::Off => return Ok(()),
+        Err(e) => return fail(mode, e),
+    };
+    
+    let signature = match http::get_text(&format!("{}.minisig", url), &[]) {
+        Ok(text) => text,
+        Err(_) if mode == Mode::Off => return Ok(()),
+        Err(e) => return fail(mode, format!("no signature published: {}", e)),
+    };
+    
+    match verify(archive, &key, &signature) {
+        Ok(comment) => {
+            eprintln!("minisign signature: good ({})", comment);
+            Ok(())
+        }
+        // A signature that is there but wrong is never ignored
+        Err(e) if mode == Mode::Off => Err(format!("minisign signature check failed: {}", e)),
+        Err(e) => fail(mode, e),
+    }
+}
+
+fn fail(mode: Mode, error: String) -> Result<(), String> {
+    if mode == Mode::Warn {
+        eprintln!("bldr: warning: minisign signature check failed: {}", error);
+        Ok(())
+    } else {
+        Err(format!("minisign signature check failed: {}", error))
+    }
+}
+
+/// The key line of a `.pub` file; `#` and `untrusted comment:` lines are
+/// skipped.
+fn public_key(contents: &str) -> Result<PublicKey, String> {
+    let line = contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("untrusted comment:"))
+        .ok_or("this build has no embedded public key")?;
+    PublicKey::from_base64(line).map_err(|e| format!("invalid embedded public key: {}", e))
+}
+
+/// Check `signature` (a `.minisig` file) over the archive; returns the
+/// signature's trusted comment.
+fn verify(archive: &Path, key: &PublicKey, signature: &str) -> Result<String, String> {
+    let signature = Signature::decode(signature).map_err(|e| format!("unreadable signature: {}", e))?;
+    let mut verifier = key.verify_stream(&signature).map_err(|e| e.to_string())?;
+    
+    let mut file = File::open(archive).map_err(|e| format!("cannot read {}: {}", archive.display(), e))?;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        verifier.update(&buf[..n]);
+    }
+    verifier.finalize().map_err(|e| e.to_string())?;
+    Ok(signature.trusted_comment().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+    
+    const KEY: &str = "untrusted comment: minisign public key\nRWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\n";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+    
+    #[test]
+    fn verifies_archives_against_the_key() {
+        let key = public_key(KEY).unwrap();
+        
+        let path = env::temp_dir().join(format!("bldr-minisign-{}", std::process::id()));
+        fs::write(&path, b"test").unwrap();
+        let good = verify(&path, &key, SIGNATURE);
+        fs::write(&path, b"tesT").unwrap();
+        let tampered = verify(&path, &key, SIGNATURE);
+        fs::remove_file(&path).ok();
+        
+        assert_eq!(good.as_deref(), Ok("timestamp:1556193335\tfile:test"));
+        assert!(tampered.is_err());
+        assert!(public_key("# no key yet\n").is_err());
+    }
+}
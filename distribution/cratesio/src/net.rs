@@ -0,0 +1,174 @@
+//! Connection racing for dual-stack hosts (RFC 8305 "Happy Eyeballs").
+//!
+//! On networks with broken IPv6 a plain connect can hang on the first
+//! address for minutes. We resolve the host, interleave address families
+//! and start a new attempt every 250ms until one connects, then pin the HTTP
+//! client to the winner (curl with `--resolve`). `BLDR_IP_FAMILY=4|6`
+//! restricts both the race and the client to one family.
+
+use std::collections::HashMap;
+use std::env;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Family {
+    Any,
+    V4,
+    V6,
+}
+
+impl Family {
+    pub fn from_env() -> Family {
+        match env::var("BLDR_IP_FAMILY").unwrap_or_default().to_ascii_lowercase().as_str() {
+            "4" | "v4" | "ipv4" | "inet" => Family::V4,
+            "6" | "v6" | "ipv6" | "inet6" => Family::V6,
+            _ => Family::Any,
+        }
+    }
+    
+    /// curl flag forcing this family, if any.
+    pub fn curl_flag(self) -> Option<&'static str> {
+        match self {
+            Family::Any => None,
+            Family::V4 => Some("-4"),
+            Family::V6 => Some("-6"),
+        }
+    }
+    
+    pub fn allows(self, addr: &SocketAddr) -> bool {
+        match self {
+            Family::Any => true,
+            Family::V4 => addr.is_ipv4(),
+            Family::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+type Winners = HashMap<(String, u16), Option<IpAddr>>;
+
+static WINNERS: OnceLock<Mutex<Winners>> = OnceLock::new();
+
+/// Split a URL into host and port (default from the scheme).
+pub fn host_port(url: &str) -> Option<(String, u16)> {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+    let authority = rest.split('/').next()?;
+    let authority = authority.rsplit('@').next()?;
+    let default_port = if scheme == "http" { 80 } else { 443 };
+    
+    // Bracketed IPv6 literal
+    if let Some(stripped) = authority.strip_prefix('[') {
+        let (host, after) = stripped.split_once(']')?;
+        let port = after.strip_prefix(':').and_then(|p| p.parse().ok()).unwrap_or(default_port);
+        return Some((host.to_ascii_lowercase(), port));
+    }
+    
+    match authority.split_once(':') {
+        Some((host, port)) => Some((host.to_ascii_lowercase(), port.parse().ok()?)),
+        None => Some((authority.to_ascii_lowercase(), default_port)),
+    }
+}
+
+/// Address that connected first for `host:port`, memoised per process.
+pub fn fastest_address(host: &str, port: u16, family: Family) -> Option<IpAddr> {
+    let key = (host.to_string(), port);
+    let winners = WINNERS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(cached) = winners.lock().ok()?.get(&key) {
+        return *cached;
+    }
+    
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map(|a| a.filter(|addr| family.allows(addr)).collect())
+        .unwrap_or_default();
+    let winner = race(interleave(addrs)).map(|a| a.ip());
+    
+    if let Ok(mut map) = winners.lock() {
+        map.insert(key, winner);
+    }
+    winner
+}
+
+/// Alternate families, starting with the resolver's first preference.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().map(|a| a.is_ipv6()).unwrap_or(true);
+    let (mut primary, mut secondary): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|a| a.is_ipv6() == first_v6);
+    primary.reverse();
+    secondary.reverse();
+    
+    let mut out = Vec::with_capacity(primary.len() + secondary.len());
+    loop {
+        match (primary.pop(), secondary.pop()) {
+            (None, None) => break,
+            (a, b) => out.extend(a.into_iter().chain(b)),
+        }
+    }
+    out
+}
+
+/// Start staggered connects and return the first address to succeed.
+fn race(addrs: Vec<SocketAddr>) -> Option<SocketAddr> {
+    if addrs.len() <= 1 {
+        return addrs.first().copied();
+    }
+    
+    let (tx, rx) = mpsc::channel();
+    let total = addrs.len();
+    
+    for (i, addr) in addrs.into_iter().enumerate() {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            thread::sleep(ATTEMPT_DELAY * i as u32);
+            let ok = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).is_ok();
+            tx.send((addr, ok)).ok();
+        });
+    }
+    drop(tx);
+    
+    // Losing attempts finish in the background and are dropped
+    for _ in 0..total {
+        match rx.recv() {
+            Ok((addr, true)) => return Some(addr),
+            Ok((_, false)) => continue,
+            Err(_) => break,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn splits_urls() {
+        assert_eq!(host_port("https://GitHub.com/a/b"), Some(("github.com".to_string(), 443)));
+        assert_eq!(host_port("http://mirror:8080/x"), Some(("mirror".to_string(), 8080)));
+        assert_eq!(host_port("https://[::1]:8443/"), Some(("::1".to_string(), 8443)));
+    }
+    
+    #[test]
+    fn interleaves_families() {
+        let addrs: Vec<SocketAddr> = ["[::1]:1", "[::2]:1", "127.0.0.1:1", "127.0.0.2:1"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let order: Vec<bool> = interleave(addrs).iter().map(|a| a.is_ipv6()).collect();
+        assert_eq!(order, vec![true, false, true, false]);
+    }
+    
+    #[test]
+    fn race_prefers_a_reachable_address() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let good = listener.local_addr().unwrap();
+        // Port 1 on loopback is refused immediately
+        let bad: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        assert_eq!(race(vec![bad, good]), Some(good));
+    }
+}
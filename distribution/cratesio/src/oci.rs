@@ -0,0 +1,236 @@
+//! OCI registries as download mirrors.
+//!
+//! A mirror base of `oci://<registry>/<repository>`, such as
+//! `oci://ghcr.io/griffincancode/bldr`, reads each release from the artifact
+//! tagged `v<version>` in that repository, laid out the way `oras push`
+//! writes it: one layer per release file, named by its
+//! `org.opencontainers.image.title` annotation. An asset URL
+//! (`<base>/v<version>/<asset>`) resolves to the blob of the layer with that
+//! title, so checksums and signatures published alongside are found the same
+//! way as on any other mirror.
+//!
+//! Pulls are anonymous unless the registry asks for credentials, which come
+//! from its `auth` entry in the Docker config (`$DOCKER_CONFIG/config.json`,
+//! else `~/.docker/config.json`; credential stores are not consulted), else
+//! the credentials for its host (see [`crate::credentials`]), else on
+//! ghcr.io the GitHub token.
+
+use crate::{credentials, github, http};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+const MANIFEST_TYPES: &str =
+    "Accept: application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+const TITLE: &str = "org.opencontainers.image.title";
+
+/// A pulled manifest: the token it was read with and `(title, digest)` of
+/// each layer.
+#[derive(Clone)]
+struct Artifact {
+    token: Option<String>,
+    layers: Vec<(String, String)>,
+}
+
+/// The blob URL to fetch an `oci://` asset URL from, with the registry
+/// token it needs added to `headers`.
+pub fn blob_url(url: &str, headers: &mut Vec<String>) -> Result<String, String> {
+    let (reference, asset) = url
+        .strip_prefix("oci://")
+        .and_then(|rest| rest.rsplit_once('/'))
+        .ok_or_else(|| format!("invalid OCI URL {}", url))?;
+    let (repository, tag) = reference.rsplit_once('/').ok_or_else(|| format!("invalid OCI URL {}", url))?;
+    let (registry, name) = repository
+        .split_once('/')
+        .ok_or_else(|| format!("{} names no repository", url))?;
+    let registry = api_host(registry);
+    
+    // One pull per artifact and process; an install fetches several files
+    static ARTIFACTS: OnceLock<Mutex<HashMap<String, Artifact>>> = OnceLock::new();
+    let key = format!("{}/{}:{}", registry, name, tag);
+    let cached = ARTIFACTS.get_or_init(Default::default).lock().ok().and_then(|known| known.get(&key).cloned());
+    let artifact = match cached {
+        Some(artifact) => artifact,
+        None => {
+            let artifact = pull_manifest(registry, name, tag)?;
+            if let Ok(mut known) = ARTIFACTS.get_or_init(Default::default).lock() {
+                known.insert(key.clone(), artifact.clone());
+            }
+            artifact
+        }
+    };
+    
+    let digest = artifact
+        .layers
+        .iter()
+        .find(|(title, _)| title == asset)
+        .map(|(_, digest)| digest)
+        .ok_or_else(|| format!("{} has no layer titled {}", key, asset))?;
+    if let Some(token) = &artifact.token {
+        headers.push(format!("Authorization: Bearer {}", token));
+    }
+    Ok(format!("https://{}/v2/{}/blobs/{}", registry, name, digest))
+}
+
+/// Docker Hub's registry API is not served from `docker.io` itself.
+fn api_host(registry: &str) -> &str {
+    match registry {
+        "docker.io" | "index.docker.io" => "registry-1.docker.io",
+        _ => registry,
+    }
+}
+
+fn pull_manifest(registry: &str, name: &str, tag: &str) -> Result<Artifact, String> {
+    let url = format!("https://{}/v2/{}/manifests/{}", registry, name, tag);
+    let mut response = http::get(&url, &[MANIFEST_TYPES.to_string()])?;
+    let mut token = None;
+    if response.status == 401 {
+        let challenge = response.header("www-authenticate").unwrap_or_default().to_string();
+        let authorization = authorize(registry, name, &challenge)?;
+        response = http::get(&url, &[MANIFEST_TYPES.to_string(), authorization.clone()])?;
+        token = authorization.strip_prefix("Authorization: Bearer ").map(str::to_string);
+    }
+    if !response.is_success() {
+        return Err(format!("cannot read {}/{}:{}: HTTP {}", registry, name, tag, response.status));
+    }
+    
+    let manifest: Value =
+        serde_json::from_str(&response.body).map_err(|e| format!("invalid manifest for {}/{}:{}: {}", registry, name, tag, e))?;
+    Ok(Artifact {
+        token,
+        layers: layers(&manifest),
+    })
+}
+
+/// `(title, digest)` of each titled layer of a manifest.
+fn layers(manifest: &Value) -> Vec<(String, String)> {
+    manifest["layers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|layer| {
+            let title = layer["annotations"][TITLE].as_str()?;
+            let digest = layer["digest"].as_str()?;
+            Some((title.to_string(), digest.to_string()))
+        })
+        .collect()
+}
+
+/// The `Authorization` header answering a registry's `WWW-Authenticate`
+/// challenge: a bearer token from its token service, or the login itself
+/// for `Basic`.
+fn authorize(registry: &str, name: &str, challenge: &str) -> Result<String, String> {
+    let (scheme, params) = parse_challenge(challenge).ok_or_else(|| format!("{} refused the pull", registry))?;
+    let login = login(registry);
+    if scheme.eq_ignore_ascii_case("basic") {
+        return login.ok_or_else(|| format!("{} needs credentials", registry));
+    }
+    
+    let realm = params
+        .get("realm")
+        .ok_or_else(|| format!("{} sent a bearer challenge without a realm", registry))?;
+    let scope = params
+        .get("scope")
+        .cloned()
+        .unwrap_or_else(|| format!("repository:{}:pull", name));
+    let separator = if realm.contains('?') { '&' } else { '?' };
+    let mut url = format!("{}{}scope={}", realm, separator, credentials::percent_encode(&scope));
+    if let Some(service) = params.get("service") {
+        url.push_str(&format!("&service={}", credentials::percent_encode(service)));
+    }
+    let body = http::get_text(&url, login.as_slice()).map_err(|e| format!("cannot get a token for {}: {}", registry, e))?;
+    
+    // Distribution's `token`, or OAuth2's `access_token`
+    let reply: Value = serde_json::from_str(&body).map_err(|e| format!("invalid token from {}: {}", registry, e))?;
+    reply["token"]
+        .as_str()
+        .or_else(|| reply["access_token"].as_str())
+        .map(|token| format!("Authorization: Bearer {}", token))
+        .ok_or_else(|| format!("{} returned no token", registry))
+}
+
+/// `(scheme, params)` of a `WWW-Authenticate` header such as
+/// `Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="..."`.
+fn parse_challenge(header: &str) -> Option<(String, HashMap<String, String>)> {
+    let (scheme, rest) = header.trim().split_once(' ').unwrap_or((header.trim(), ""));
+    if scheme.is_empty() {
+        return None;
+    }
+    let mut params = HashMap::new();
+    let mut rest = rest.trim();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        params.insert(key, value.to_string());
+        rest = next.trim_start_matches(',').trim();
+    }
+    Some((scheme.to_string(), params))
+}
+
+/// Basic credentials for the token service; `None` leaves it to the
+/// credentials for its host, if any.
+fn login(registry: &str) -> Option<String> {
+    docker_auth(registry)
+        .or_else(|| {
+            let token = github::token().filter(|_| registry == "ghcr.io")?;
+            Some(credentials::base64_encode(format!("bldr:{}", token).as_bytes()))
+        })
+        .map(|auth| format!("Authorization: Basic {}", auth))
+}
+
+/// The registry's base64 `user:password` from the Docker config.
+fn docker_auth(registry: &str) -> Option<String> {
+    let dir = env::var_os("DOCKER_CONFIG")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".docker")))?;
+    let config: Value = serde_json::from_str(&fs::read_to_string(dir.join("config.json")).ok()?).ok()?;
+    let auths = config["auths"].as_object()?;
+    auths
+        .iter()
+        .find(|(key, _)| {
+            let host = key.trim_start_matches("https://").trim_start_matches("http://");
+            let host = host.split('/').next().unwrap_or_default();
+            api_host(host) == registry
+        })
+        .and_then(|(_, entry)| entry["auth"].as_str())
+        .filter(|auth| !auth.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn reads_challenges_and_titled_layers() {
+        let (scheme, params) =
+            parse_challenge(r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:org/bldr:pull""#)
+                .unwrap();
+        assert_eq!(scheme, "Bearer");
+        assert_eq!(params["realm"], "https://ghcr.io/token");
+        assert_eq!(params["service"], "ghcr.io");
+        assert_eq!(params["scope"], "repository:org/bldr:pull");
+        assert_eq!(parse_challenge(r#"Basic realm="registry""#).unwrap().0, "Basic");
+        
+        let manifest = serde_json::json!({
+            "layers": [
+                {"digest": "sha256:aa", "annotations": {TITLE: "bldr-linux-amd64.tar.gz"}},
+                {"digest": "sha256:bb"},
+                {"digest": "sha256:cc", "annotations": {TITLE: "SHA256SUMS"}},
+            ]
+        });
+        assert_eq!(
+            layers(&manifest),
+            [
+                ("bldr-linux-amd64.tar.gz".to_string(), "sha256:aa".to_string()),
+                ("SHA256SUMS".to_string(), "sha256:cc".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! Offline mode: `BLDR_OFFLINE`, or cargo's `CARGO_NET_OFFLINE`.
+//!
+//! Nothing goes over the network. Cached versions run as usual, floating
+//! selectors (`latest`, `2.1`) resolve among the installed versions, and
+//! anything that would download fails at once instead of waiting on
+//! connection timeouts. `file://` mirrors stay available, so a cache can be
+//! filled from a local mirror while offline.
+
+use crate::cache;
+use std::env;
+
+/// The variable that turned offline mode on, if any.
+pub fn enabled_by() -> Option<&'static str> {
+    ["BLDR_OFFLINE", "CARGO_NET_OFFLINE"].into_iter().find(|name| {
+        env::var(name).is_ok_and(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "no" | "off"))
+    })
+}
+
+pub fn enabled() -> bool {
+    enabled_by().is_some()
+}
+
+/// Why `version` cannot be installed offline, and how to get it into the
+/// cache.
+pub fn not_installed(version: &str) -> String {
+    format!(
+        "bldr v{} is not installed and network access is off ({} is set)\n\
+         \x20 To pre-seed the cache, either:\n\
+         \x20   - run `BLDR_VERSION={} bldr --version` once with network access\n\
+         \x20   - copy {} from a machine that has it\n\
+         \x20   - point BLDR_DOWNLOAD_BASE at a file:// mirror made with `bldr wrapper mirror sync`",
+        version,
+        enabled_by().unwrap_or("BLDR_OFFLINE"),
+        version,
+        cache::root().join(version).display()
+    )
+}
@@ -0,0 +1,153 @@
+//! Explicit permissions for what the wrapper writes.
+//!
+//! Left to the umask, a cache created under a restrictive umask locks out
+//! the other users of a shared cache, and a permissive one leaves files that
+//! security scanners flag. Cache entries, installed core versions and
+//! downloaded assets therefore get fixed modes: 0755 for directories, 0644
+//! for files and 0755 for executables, overridden with `BLDR_DIR_MODE`,
+//! `BLDR_FILE_MODE` and `BLDR_EXEC_MODE` (octal, e.g. `BLDR_DIR_MODE=2775`
+//! for a group-shared cache). Modes only exist on Unix; elsewhere these are
+//! plain writes.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Modes {
+    pub dir: u32,
+    pub file: u32,
+    pub exec: u32,
+}
+
+impl Modes {
+    pub const DEFAULT: Modes = Modes { dir: 0o755, file: 0o644, exec: 0o755 };
+    
+    /// Modes from the environment, read once per process.
+    pub fn current() -> Modes {
+        static MODES: OnceLock<Modes> = OnceLock::new();
+        *MODES.get_or_init(|| Modes {
+            dir: from_env("BLDR_DIR_MODE", Modes::DEFAULT.dir),
+            file: from_env("BLDR_FILE_MODE", Modes::DEFAULT.file),
+            exec: from_env("BLDR_EXEC_MODE", Modes::DEFAULT.exec),
+        })
+    }
+}
+
+fn from_env(var: &str, default: u32) -> u32 {
+    let Ok(value) = env::var(var) else {
+        return default;
+    };
+    parse_mode(&value).unwrap_or_else(|| {
+        eprintln!("bldr: ignoring {}={} (expected an octal mode such as {:o})", var, value, default);
+        default
+    })
+}
+
+/// `644`, `0644` or `0o644`; at most the permission and setuid/setgid/sticky bits.
+fn parse_mode(value: &str) -> Option<u32> {
+    let digits = value.trim();
+    let digits = digits.strip_prefix("0o").unwrap_or(digits);
+    u32::from_str_radix(digits, 8).ok().filter(|mode| *mode <= 0o7777)
+}
+
+/// `fs::create_dir_all`, giving the directories it creates the directory mode.
+pub fn create_dir_all(path: &Path) -> io::Result<()> {
+    let missing: Vec<&Path> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+    fs::create_dir_all(path)?;
+    for dir in missing.into_iter().rev() {
+        set_mode(dir, Modes::current().dir)?;
+    }
+    Ok(())
+}
+
+/// `fs::write` with the file mode.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    fs::write(path, contents)?;
+    set_file(path)
+}
+
+/// Give a file written some other way (downloaded, renamed into place) the
+/// file mode.
+pub fn set_file(path: &Path) -> io::Result<()> {
+    set_mode(path, Modes::current().file)
+}
+
+pub fn make_executable(path: &Path) -> io::Result<()> {
+    set_mode(path, Modes::current().exec)
+}
+
+/// Normalize an unpacked tree: directories and files get their modes, and
+/// files the archive marked executable the executable mode.
+pub fn apply_tree(dir: &Path) -> io::Result<()> {
+    set_mode(dir, Modes::current().dir)?;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            apply_tree(&entry.path())?;
+        } else if kind.is_file() {
+            let mode = if is_executable(&entry.metadata()?) { Modes::current().exec } else { Modes::current().file };
+            set_mode(&entry.path(), mode)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    
+    meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    
+    #[test]
+    fn overrides_the_umask_and_parses_octal() {
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        let root = env::temp_dir().join(format!("bldr-perms-{}", std::process::id()));
+        let nested = root.join("1.2.3");
+        
+        create_dir_all(&nested).unwrap();
+        write(&nested.join("memo"), "1.2.3").unwrap();
+        fs::write(nested.join("bldr"), "").unwrap();
+        fs::set_permissions(nested.join("bldr"), fs::Permissions::from_mode(0o700)).unwrap();
+        fs::set_permissions(&nested, fs::Permissions::from_mode(0o700)).unwrap();
+        apply_tree(&nested).unwrap();
+        
+        assert_eq!(mode(&root), 0o755);
+        assert_eq!(mode(&nested), 0o755);
+        assert_eq!(mode(&nested.join("memo")), 0o644);
+        assert_eq!(mode(&nested.join("bldr")), 0o755);
+        fs::remove_dir_all(&root).ok();
+        
+        assert_eq!(parse_mode("2775"), Some(0o2775));
+        assert_eq!(parse_mode("0o640"), Some(0o640));
+        assert_eq!(parse_mode("0644"), Some(0o644));
+        assert_eq!(parse_mode("rw-r--r--"), None);
+        assert_eq!(parse_mode("17777"), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Version pins: a `.bldr-version` file in the project (nearest ancestor
+//! wins) or a global pin in the user's config directory.
+
+use crate::version::Selector;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_FILE: &str = ".bldr-version";
+
+/// Nearest `.bldr-version` at or above `start`.
+pub fn find_project(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|candidate| candidate.is_file())
+}
+
+/// `<config>/bldr/version`.
+pub fn global_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("bldr").join("version"))
+}
+
+/// First non-comment line of a pin file.
+pub fn read(path: &Path) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+}
+
+/// Write a pin file, replacing any previous pin in one step so a concurrent
+/// invocation reads either the old version or the new one.
+pub fn write(path: &Path, version: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let staged = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&staged, format!("{}\n", version))?;
+    fs::rename(&staged, path).inspect_err(|_| {
+        fs::remove_file(&staged).ok();
+    })
+}
+
+/// `BLDR_VERSION`, as exported by the shell hook or by hand: an exact
+/// release (`2.1.3`), a prefix (`2.1`) or a channel. It overrides the
+/// project and global pins, so one installed wrapper can run any release.
+pub fn from_env() -> Option<Selector> {
+    let value = env::var("BLDR_VERSION").ok().filter(|v| !v.trim().is_empty())?;
+    let selector = Selector::parse(&value);
+    if selector.is_none() {
+        eprintln!("bldr: ignoring BLDR_VERSION={}: not a version, prefix or channel", value);
+    }
+    selector
+}
+
+/// Selector pinned for the current directory, project pin first. A pin
+/// that does not parse is reported, not silently skipped.
+pub fn selector() -> Option<Selector> {
+    let project = env::current_dir().ok().and_then(|cwd| find_project(&cwd));
+    project.into_iter().chain(global_path()).find_map(|path| {
+        let pinned = read(&path)?;
+        let selector = Selector::parse(&pinned);
+        if selector.is_none() {
+            eprintln!("bldr: ignoring {}: '{}' is not a version, prefix or channel", path.display(), pinned);
+        }
+        selector
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn finds_the_nearest_project_pin() {
+        let root = env::temp_dir().join(format!("bldr-pin-{}", std::process::id()));
+        let nested = root.join("app").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(PROJECT_FILE), "# pinned for CI\n\n2.1.3\n").unwrap();
+        assert_eq!(find_project(&nested), Some(root.join(PROJECT_FILE)));
+        assert_eq!(read(&root.join(PROJECT_FILE)).as_deref(), Some("2.1.3"));
+        
+        write(&root.join("app").join(PROJECT_FILE), "2.0").unwrap();
+        let nearest = find_project(&nested).unwrap();
+        assert_eq!(nearest, root.join("app").join(PROJECT_FILE));
+        assert_eq!(read(&nearest).and_then(|v| Selector::parse(&v)), Some(Selector::Prefix("2.0".into())));
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,36 @@
+/// Release asset naming for the host platform, e.g. ("darwin", "arm64").
+pub fn current() -> (&'static str, &'static str) {
+    let os = if cfg!(target_os = "macos") {
+        "darwin"
+    } else if cfg!(target_os = "linux") {
+        "linux"
+    } else if cfg!(target_os = "windows") {
+        "windows"
+    } else {
+        "unknown"
+    };
+    
+    let arch = if cfg!(target_arch = "aarch64") {
+        "arm64"
+    } else if cfg!(target_arch = "x86_64") {
+        "amd64"
+    } else {
+        "unknown"
+    };
+    
+    (os, arch)
+}
+
+/// Platforms we publish release assets for.
+pub const PUBLISHED: &[(&str, &str)] = &[
+    ("darwin", "arm64"),
+    ("darwin", "amd64"),
+    ("linux", "amd64"),
+    ("linux", "arm64"),
+    ("windows", "amd64"),
+];
+
+/// Base asset name (without archive extension) for a platform.
+pub fn asset_name(os: &str, arch: &str) -> String {
+    format!("bldr-{}-{}", os, arch)
+}
@@ -0,0 +1,363 @@
+//! Organization policy: an admin-managed TOML file that constrains what the
+//! wrapper may run and fetch.
+//!
+//! ```toml
+//! allowed_versions = [">=2.0.0, <3", "2.0.3"]
+//! require_verification = true
+//! verification = ["tlog", "gpg", "minisign", "attestation"]
+//! gpg_keyring = "/etc/bldr/release-keys.gpg"
+//! download_base = ["https://artifacts.example.com/bldr", "https://mirror.example.net/bldr"]
+//! max_download_rate = "2M"
+//! allowed_mirrors = ["https://artifacts.example.com/bldr/", "github.com"]
+//! auto_download = false
+//! update_check = false
+//! contact = "Ask #build-infra before changing bldr versions"
+//! ```
+//!
+//! The system file (`/etc/bldr/policy.toml`, `%ProgramData%\bldr\policy.toml`)
+//! always wins. `BLDR_POLICY_FILE` is only consulted when no system policy
+//! exists, so users cannot point around an installed one. A policy that
+//! cannot be read or parsed stops the wrapper rather than being ignored.
+
+use crate::{throttle, version};
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Version requirements; a version must satisfy at least one.
+    #[serde(default)]
+    allowed_versions: Vec<String>,
+    /// Downloads must pass verification; overrides `BLDR_TLOG_VERIFY` and
+    /// the other per-scheme variables.
+    #[serde(default)]
+    pub require_verification: bool,
+    /// Schemes `require_verification` enforces; empty means `["tlog"]`.
+    #[serde(default)]
+    verification: Vec<String>,
+    /// Keyring for `gpg` verification; overrides `BLDR_GPG_KEYRING`.
+    pub gpg_keyring: Option<PathBuf>,
+    /// Mirrors releases are downloaded from (`<base>/v<version>/<asset>`,
+    /// or a template with `{version}` and `{asset}`), one or a list tried
+    /// in order; `BLDR_DOWNLOAD_BASE` overrides it.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub download_base: Vec<String>,
+    /// File holding the token for the mirrors; `BLDR_DOWNLOAD_TOKEN_FILE`
+    /// overrides it.
+    pub download_token_file: Option<PathBuf>,
+    /// Cap on the download rate (`500K`, `2M`); `BLDR_MAX_DOWNLOAD_RATE` can
+    /// only lower it.
+    pub max_download_rate: Option<String>,
+    /// URL prefixes or host names downloads may come from.
+    #[serde(default)]
+    allowed_mirrors: Vec<String>,
+    /// When false, only versions already in the cache can run.
+    #[serde(default = "enabled")]
+    auto_download: bool,
+    /// When false, the wrapper never checks for newer releases.
+    #[serde(default = "enabled")]
+    pub update_check: bool,
+    /// Who to ask, shown with every violation.
+    contact: Option<String>,
+    #[serde(skip)]
+    source: Option<PathBuf>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// A string or a list of strings.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+/// Verification schemes the policy can require.
+const SCHEMES: &[&str] = &["tlog", "gpg", "sha256", "minisign", "cosign", "attestation"];
+
+/// How strictly a verification scheme is applied to downloads.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Off,
+    Warn,
+    Require,
+}
+
+impl Mode {
+    /// Required when the policy enforces `scheme`, else read from `var`
+    /// (`warn`, `ci` to require only when `CI` is set, or any other
+    /// non-false value to require).
+    pub fn for_scheme(scheme: &str, var: &str) -> Mode {
+        match current() {
+            Ok(policy) if policy.requires(scheme) => Mode::Require,
+            _ => Mode::parse(&env::var(var).unwrap_or_default(), env::var_os("CI").is_some()),
+        }
+    }
+    
+    fn parse(value: &str, in_ci: bool) -> Mode {
+        match value.to_ascii_lowercase().as_str() {
+            "" | "0" | "off" | "false" | "no" => Mode::Off,
+            "warn" => Mode::Warn,
+            "ci" if !in_ci => Mode::Off,
+            _ => Mode::Require,
+        }
+    }
+}
+
+static POLICY: OnceLock<Result<Policy, String>> = OnceLock::new();
+
+/// The policy in effect, loaded once per process. No policy file means an
+/// empty policy that allows everything.
+pub fn current() -> Result<&'static Policy, String> {
+    POLICY.get_or_init(load).as_ref().map_err(Clone::clone)
+}
+
+fn system_path() -> PathBuf {
+    if cfg!(windows) {
+        env::var_os("ProgramData")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+            .join("bldr")
+            .join("policy.toml")
+    } else {
+        PathBuf::from("/etc/bldr/policy.toml")
+    }
+}
+
+fn load() -> Result<Policy, String> {
+    let system = system_path();
+    let path = if system.exists() {
+        system
+    } else {
+        match env::var_os("BLDR_POLICY_FILE") {
+            Some(path) => PathBuf::from(path),
+            None => return Ok(Policy::default_allow()),
+        }
+    };
+    
+    let contents = fs::read_to_string(&path).map_err(|e| format!("cannot read policy {}: {}", path.display(), e))?;
+    parse(&contents, &path)
+}
+
+fn parse(contents: &str, path: &Path) -> Result<Policy, String> {
+    let mut policy: Policy =
+        toml::from_str(contents).map_err(|e| format!("invalid policy {}: {}", path.display(), e))?;
+    for requirement in &policy.allowed_versions {
+        Requirement::parse(requirement)
+            .ok_or_else(|| format!("invalid policy {}: bad version requirement '{}'", path.display(), requirement))?;
+    }
+    if let Some(scheme) = policy.verification.iter().find(|s| !SCHEMES.contains(&s.as_str())) {
+        return Err(format!(
+            "invalid policy {}: unknown verification scheme '{}' (known: {})",
+            path.display(),
+            scheme,
+            SCHEMES.join(", ")
+        ));
+    }
+    if let Some(rate) = &policy.max_download_rate {
+        throttle::parse_rate(rate).map_err(|e| format!("invalid policy {}: max_download_rate: {}", path.display(), e))?;
+    }
+    policy.source = Some(path.to_path_buf());
+    Ok(policy)
+}
+
+impl Policy {
+    fn default_allow() -> Policy {
+        Policy {
+            auto_download: true,
+            update_check: true,
+            ..Policy::default()
+        }
+    }
+    
+    /// Whether downloads must pass `scheme`.
+    pub fn requires(&self, scheme: &str) -> bool {
+        self.require_verification
+            && if self.verification.is_empty() {
+                scheme == "tlog"
+            } else {
+                self.verification.iter().any(|s| s == scheme)
+            }
+    }
+    
+    /// Refuse versions outside `allowed_versions`.
+    pub fn check_version(&self, version: &str) -> Result<(), String> {
+        if self.allowed_versions.is_empty()
+            || self
+                .allowed_versions
+                .iter()
+                .filter_map(|r| Requirement::parse(r))
+                .any(|r| r.matches(version))
+        {
+            return Ok(());
+        }
+        Err(self.violation(&format!(
+            "bldr {} is not allowed (allowed: {})",
+            version,
+            self.allowed_versions.join(" or ")
+        )))
+    }
+    
+    /// Refuse `BLDR_BIN` when only some versions are allowed, as nothing
+    /// says which version it is.
+    pub fn check_override(&self) -> Result<(), String> {
+        if self.allowed_versions.is_empty() {
+            return Ok(());
+        }
+        Err(self.violation("BLDR_BIN is not allowed while the policy limits which versions run"))
+    }
+    
+    /// Refuse to fetch a version that is not already installed.
+    pub fn check_download(&self, version: &str) -> Result<(), String> {
+        if self.auto_download {
+            return Ok(());
+        }
+        Err(self.violation(&format!(
+            "bldr {} is not installed and automatic downloads are disabled",
+            version
+        )))
+    }
+    
+    /// Refuse downloads from anywhere but `allowed_mirrors`.
+    pub fn check_url(&self, url: &str) -> Result<(), String> {
+        if self.allowed_mirrors.is_empty() || self.allowed_mirrors.iter().any(|m| mirror_allows(m, url)) {
+            return Ok(());
+        }
+        Err(self.violation(&format!(
+            "downloads from {} are not allowed (allowed: {})",
+            url,
+            self.allowed_mirrors.join(", ")
+        )))
+    }
+    
+    fn violation(&self, what: &str) -> String {
+        let mut message = format!("policy violation: {}", what);
+        if let Some(source) = &self.source {
+            message.push_str(&format!("\n      set by {}", source.display()));
+        }
+        if let Some(contact) = &self.contact {
+            message.push_str(&format!("\n      {}", contact));
+        }
+        message
+    }
+}
+
+/// `https://host/path/` entries match by prefix; bare entries match the host.
+fn mirror_allows(mirror: &str, url: &str) -> bool {
+    if mirror.contains("://") {
+        url.starts_with(mirror)
+    } else {
+        url.split_once("://")
+            .map(|(_, rest)| rest.split(['/', ':', '?']).next().unwrap_or("").eq_ignore_ascii_case(mirror))
+            .unwrap_or(false)
+    }
+}
+
+/// One entry of `allowed_versions`: comma-separated comparators
+/// (`>=2.0, <3`), or a bare version or prefix (`2.0.3`, `2.1`).
+struct Requirement(Vec<(Op, String)>);
+
+#[derive(Clone, Copy)]
+enum Op {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Prefix,
+}
+
+impl Requirement {
+    fn parse(s: &str) -> Option<Requirement> {
+        let mut comparators = Vec::new();
+        for part in s.split(',').map(str::trim) {
+            let (op, rest) = [(">=", Op::Ge), ("<=", Op::Le), (">", Op::Gt), ("<", Op::Lt), ("=", Op::Eq)]
+                .iter()
+                .find_map(|(prefix, op)| part.strip_prefix(prefix).map(|rest| (*op, rest.trim())))
+                .unwrap_or((Op::Prefix, part));
+            let rest = rest.trim_start_matches('v');
+            if !rest.starts_with(|c: char| c.is_ascii_digit()) || !rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') {
+                return None;
+            }
+            comparators.push((op, rest.to_string()));
+        }
+        Some(Requirement(comparators))
+    }
+    
+    fn matches(&self, version: &str) -> bool {
+        self.0.iter().all(|(op, bound)| {
+            let order = version::compare(version, bound);
+            match op {
+                Op::Eq => version == bound,
+                Op::Lt => order.is_lt(),
+                Op::Le => order.is_le(),
+                Op::Gt => order.is_gt(),
+                Op::Ge => order.is_ge(),
+                Op::Prefix => version == bound || version.starts_with(&format!("{}.", bound)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn enforces_version_ranges() {
+        let policy = parse("allowed_versions = [\">=2.0, <3\", \"3.1\"]", Path::new("policy.toml")).unwrap();
+        assert!(policy.check_version("2.0.3").is_ok());
+        assert!(policy.check_version("3.1.4").is_ok());
+        assert!(policy.check_version("3.0.0").is_err());
+        assert!(policy.check_version("1.9.9").is_err());
+        assert!(parse("allowed_versions = [\">= two\"]", Path::new("policy.toml")).is_err());
+    }
+    
+    #[test]
+    fn restricts_mirrors_and_downloads() {
+        let policy = parse(
+            "allowed_mirrors = [\"https://artifacts.example.com/bldr/\", \"github.com\"]\nauto_download = false\ncontact = \"ask infra\"",
+            Path::new("/etc/bldr/policy.toml"),
+        )
+        .unwrap();
+        assert!(policy.check_url("https://github.com/GriffinCanCode/bldr/releases/download/v2.0.3/x.tar.gz").is_ok());
+        assert!(policy.check_url("https://artifacts.example.com/bldr/v2.0.3/x.tar.gz").is_ok());
+        let err = policy.check_url("https://evil.example.com/x.tar.gz").unwrap_err();
+        assert!(err.contains("/etc/bldr/policy.toml") && err.contains("ask infra"));
+        assert!(policy.check_download("2.0.3").is_err());
+        assert!(parse("auto_downlaod = false", Path::new("policy.toml")).is_err());
+        
+        let one = parse("download_base = \"https://a.example.com\"", Path::new("policy.toml")).unwrap();
+        let many = parse("download_base = [\"https://a.example.com\", \"https://b.example.com\"]", Path::new("policy.toml")).unwrap();
+        assert_eq!(one.download_base, ["https://a.example.com"]);
+        assert_eq!(many.download_base.len(), 2);
+    }
+    
+    #[test]
+    fn selects_verification_schemes() {
+        let default = parse("require_verification = true", Path::new("policy.toml")).unwrap();
+        assert!(default.requires("tlog") && !default.requires("gpg"));
+        
+        let gpg = parse("require_verification = true\nverification = [\"gpg\"]", Path::new("policy.toml")).unwrap();
+        assert!(gpg.requires("gpg") && !gpg.requires("tlog"));
+        assert!(parse("verification = [\"pgp\"]", Path::new("policy.toml")).is_err());
+        
+        assert_eq!(Mode::parse("", true), Mode::Off);
+        assert_eq!(Mode::parse("Warn", false), Mode::Warn);
+        assert_eq!(Mode::parse("ci", false), Mode::Off);
+        assert_eq!(Mode::parse("ci", true), Mode::Require);
+    }
+}
@@ -0,0 +1,230 @@
+//! `--profile-child`: run the core binary under the platform profiler.
+//!
+//! The wrapper strips the flag, launches the child under `perf record`
+//! (Linux), `xctrace` / `sample` (macOS) or `dtrace`, writes the output into
+//! a timestamped results directory and prints how to open it.
+
+use crate::{cache, perms};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FLAG: &str = "--profile-child";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Profiler {
+    Perf,
+    Xctrace,
+    Sample,
+    Dtrace,
+}
+
+impl Profiler {
+    fn parse(name: &str) -> Option<Profiler> {
+        match name {
+            "perf" => Some(Profiler::Perf),
+            "xctrace" | "instruments" => Some(Profiler::Xctrace),
+            "sample" => Some(Profiler::Sample),
+            "dtrace" => Some(Profiler::Dtrace),
+            _ => None,
+        }
+    }
+    
+    /// Best available profiler for this platform.
+    fn detect() -> Option<Profiler> {
+        if cfg!(target_os = "linux") {
+            Some(Profiler::Perf)
+        } else if cfg!(target_os = "macos") {
+            if on_path("xctrace") {
+                Some(Profiler::Xctrace)
+            } else {
+                Some(Profiler::Sample)
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// Remove `--profile-child[=tool]` from the wrapper's arguments. Arguments
+/// after `--` belong to the core and are left alone.
+pub fn take_flag(args: Vec<String>) -> Result<(Option<Profiler>, Vec<String>), String> {
+    let mut profiler = None;
+    let mut rest = Vec::with_capacity(args.len());
+    let mut passthrough = false;
+    
+    for arg in args {
+        if passthrough {
+            rest.push(arg);
+            continue;
+        }
+        if arg == "--" {
+            passthrough = true;
+            rest.push(arg);
+        } else if arg == FLAG {
+            profiler = Some(Profiler::detect().ok_or("--profile-child is not supported on this platform")?);
+        } else if let Some(name) = arg.strip_prefix("--profile-child=") {
+            profiler = Some(Profiler::parse(name).ok_or_else(|| format!("unknown profiler '{}'", name))?);
+        } else {
+            rest.push(arg);
+        }
+    }
+    
+    Ok((profiler, rest))
+}
+
+/// Run `binary args` under `profiler`; returns the child's exit code.
+pub fn run(profiler: Profiler, binary: &Path, args: &[String]) -> i32 {
+    let dir = match results_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("bldr: cannot create profile directory: {}", e);
+            return 1;
+        }
+    };
+    
+    let (mut cmd, output) = match profiler {
+        Profiler::Perf => {
+            let output = dir.join("perf.data");
+            let mut cmd = Command::new("perf");
+            cmd.args(["record", "-g", "-o"]).arg(&output).arg("--").arg(binary).args(args);
+            (cmd, output)
+        }
+        Profiler::Xctrace => {
+            let output = dir.join("bldr.trace");
+            let mut cmd = Command::new("xctrace");
+            cmd.args(["record", "--template", "Time Profiler", "--output"])
+                .arg(&output)
+                .arg("--launch")
+                .arg("--")
+                .arg(binary)
+                .args(args);
+            (cmd, output)
+        }
+        Profiler::Dtrace => {
+            let output = dir.join("dtrace-stacks.txt");
+            let child = std::iter::once(binary.display().to_string())
+                .chain(args.iter().map(|a| shell_quote(a)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let mut cmd = Command::new("dtrace");
+            cmd.args(["-x", "ustackframes=100", "-n", "profile-997 /pid == $target/ { @[ustack()] = count(); }", "-o"])
+                .arg(&output)
+                .arg("-c")
+                .arg(child);
+            (cmd, output)
+        }
+        Profiler::Sample => return run_with_sample(&dir, binary, args),
+    };
+    
+    eprintln!("bldr: profiling with {}; results in {}", format!("{:?}", profiler).to_lowercase(), dir.display());
+    let code = match cmd.status() {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(e) => {
+            eprintln!("bldr: cannot launch profiler: {}", e);
+            return 1;
+        }
+    };
+    
+    print_next_steps(profiler, &output);
+    code
+}
+
+/// `sample` attaches to a running process, so start the child first.
+fn run_with_sample(dir: &Path, binary: &Path, args: &[String]) -> i32 {
+    let output = dir.join("sample.txt");
+    let mut child = match Command::new(binary).args(args).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("bldr: failed to execute bldr: {}", e);
+            return 1;
+        }
+    };
+    
+    eprintln!("bldr: profiling with sample; results in {}", dir.display());
+    let sampler = Command::new("sample")
+        .arg(child.id().to_string())
+        .args(["3600", "1", "-mayDie", "-file"])
+        .arg(&output)
+        .stdout(Stdio::null())
+        .spawn();
+    
+    let code = child.wait().map(|s| s.code().unwrap_or(1)).unwrap_or(1);
+    if let Ok(mut sampler) = sampler {
+        // sample notices the exit and writes its report
+        sampler.wait().ok();
+    }
+    
+    print_next_steps(Profiler::Sample, &output);
+    code
+}
+
+fn print_next_steps(profiler: Profiler, output: &Path) {
+    if !output.exists() {
+        eprintln!("bldr: profiler produced no output at {}", output.display());
+        return;
+    }
+    
+    eprintln!();
+    eprintln!("Profile written to {}", output.display());
+    match profiler {
+        Profiler::Perf => {
+            eprintln!("  perf report -i {}", output.display());
+            eprintln!("  perf script -i {} | stackcollapse-perf.pl | flamegraph.pl > bldr.svg", output.display());
+        }
+        Profiler::Xctrace => eprintln!("  open {}", output.display()),
+        Profiler::Sample => eprintln!("  less {}", output.display()),
+        Profiler::Dtrace => eprintln!("  stackcollapse.pl {} | flamegraph.pl > bldr.svg", output.display()),
+    }
+}
+
+/// `BLDR_PROFILE_DIR`, or `<cache>/profiles`, plus a per-run timestamp.
+fn results_dir() -> std::io::Result<PathBuf> {
+    let base = env::var_os("BLDR_PROFILE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| cache::root().join("profiles"));
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let dir = base.join(format!("{}-{}", stamp, std::process::id()));
+    perms::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+fn shell_quote(arg: &str) -> String {
+    if arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+    
+    #[test]
+    fn strips_flag_before_separator() {
+        let (profiler, rest) = take_flag(strings(&["build", "--profile-child=perf", "//app"])).unwrap();
+        assert_eq!(profiler, Some(Profiler::Perf));
+        assert_eq!(rest, strings(&["build", "//app"]));
+    
+        let (profiler, rest) = take_flag(strings(&["run", "--", "--profile-child=perf"])).unwrap();
+        assert_eq!(profiler, None);
+        assert_eq!(rest, strings(&["run", "--", "--profile-child=perf"]));
+    }
+    
+    #[test]
+    fn rejects_unknown_profilers() {
+        assert!(take_flag(strings(&["--profile-child=vtune"])).is_err());
+    }
+}
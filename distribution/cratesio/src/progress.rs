@@ -0,0 +1,111 @@
+//! Download progress on stderr.
+//!
+//! On a terminal a single line is redrawn with a bar, the bytes so far, the
+//! percentage and an ETA; elsewhere (CI logs, pipes) a plain line is printed
+//! every few seconds instead, so logs show the download is alive without
+//! filling up with carriage returns.
+
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+const REDRAW_EVERY: Duration = Duration::from_millis(100);
+const LOG_EVERY: Duration = Duration::from_secs(5);
+
+pub struct Progress {
+    /// Total size, when the server said.
+    total: Option<u64>,
+    /// Bytes that were already there when the transfer (re)started.
+    start: u64,
+    done: u64,
+    started: Instant,
+    last: Option<Instant>,
+    tty: bool,
+}
+
+impl Progress {
+    /// Progress of a transfer of `total` bytes that starts at `start`.
+    pub fn new(total: Option<u64>, start: u64) -> Progress {
+        Progress {
+            total,
+            start,
+            done: start,
+            started: Instant::now(),
+            last: None,
+            tty: std::io::stderr().is_terminal(),
+        }
+    }
+    
+    /// Record that `done` bytes are now there.
+    pub fn update(&mut self, done: u64) {
+        self.done = done;
+        let every = if self.tty { REDRAW_EVERY } else { LOG_EVERY };
+        let due = match self.last {
+            Some(last) => last.elapsed() >= every,
+            None => !self.tty || self.started.elapsed() >= every,
+        };
+        if due {
+            self.draw();
+        }
+    }
+    
+    /// Draw the final state and end the line.
+    pub fn finish(&mut self) {
+        if self.tty && self.last.is_some() {
+            self.draw();
+            eprintln!();
+        }
+    }
+    
+    fn draw(&mut self) {
+        self.last = Some(Instant::now());
+        let line = render(self.total, self.start, self.done, self.started.elapsed(), self.tty);
+        let mut stderr = std::io::stderr().lock();
+        if self.tty {
+            write!(stderr, "\r{}\x1b[K", line).ok();
+        } else {
+            writeln!(stderr, "{}", line).ok();
+        }
+        stderr.flush().ok();
+    }
+}
+
+fn render(total: Option<u64>, start: u64, done: u64, elapsed: Duration, bar: bool) -> String {
+    let rate = (done - start) as f64 / elapsed.as_secs_f64().max(0.001);
+    let Some(total) = total.filter(|&total| total > 0) else {
+        return format!("  {} ({}/s)", megabytes(done), megabytes(rate as u64));
+    };
+    
+    let fraction = (done as f64 / total as f64).min(1.0);
+    let eta = if rate > 0.0 {
+        let secs = (total.saturating_sub(done) as f64 / rate) as u64;
+        format!("ETA {}:{:02}", secs / 60, secs % 60)
+    } else {
+        "ETA --:--".to_string()
+    };
+    let numbers = format!("{} / {}  {:>3}%  {}", megabytes(done), megabytes(total), (fraction * 100.0) as u32, eta);
+    if bar {
+        let filled = (fraction * BAR_WIDTH as f64) as usize;
+        format!("  [{}{}] {}", "=".repeat(filled), " ".repeat(BAR_WIDTH - filled), numbers)
+    } else {
+        format!("  {}", numbers)
+    }
+}
+
+pub fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn renders_bytes_percent_and_eta() {
+        let mb = 1024 * 1024;
+        let line = render(Some(30 * mb), 0, 12 * mb, Duration::from_secs(4), true);
+        assert_eq!(line, "  [============                  ] 12.0 MB / 30.0 MB   40%  ETA 0:06");
+        assert_eq!(render(Some(30 * mb), 10 * mb, 10 * mb, Duration::from_secs(1), false), "  10.0 MB / 30.0 MB   33%  ETA --:--");
+        assert_eq!(render(None, 0, 3 * mb, Duration::from_secs(3), false), "  3.0 MB (1.0 MB/s)");
+    }
+}
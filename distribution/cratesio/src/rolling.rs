@@ -0,0 +1,64 @@
+//! Rolling releases: a tag republished in place, such as a `nightly`
+//! release rebuilt every night, rather than a version number.
+//!
+//! Its cache directory keeps the tag's name, and the ETag of the asset it
+//! was installed from is recorded beside it. At most once per
+//! [`CHECK_INTERVAL`] a conditional HEAD asks the mirror whether the asset
+//! changed; only then is it downloaded again. When that cannot be told
+//! (offline, network trouble, a mirror without ETags) the installed binary
+//! keeps being used.
+
+use crate::{cache, http, offline, perms};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// File in a rolling version's directory holding its asset's ETag; its
+/// modification time is the last check.
+const ETAG_FILE: &str = ".etag";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Whether `version` names a rolling tag rather than a release version.
+pub fn is_rolling(version: &str) -> bool {
+    version.starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+/// Whether the asset the installed `version` came from has changed since.
+pub fn outdated(version: &str) -> bool {
+    let path = etag_path(version);
+    let Some(recorded) = fs::read_to_string(&path).ok().map(|etag| etag.trim().to_string()) else {
+        return false;
+    };
+    let checked = fs::metadata(&path).and_then(|meta| meta.modified()).ok().and_then(|t| t.elapsed().ok());
+    if checked.is_some_and(|age| age < CHECK_INTERVAL) || offline::enabled() {
+        return false;
+    }
+    let Some(url) = cache::source(version) else {
+        return false;
+    };
+    
+    let Ok(response) = http::head(&url, &[format!("If-None-Match: {}", recorded)]) else {
+        return false;
+    };
+    let changed = response.is_success() && response.header("etag").is_some_and(|etag| etag.trim() != recorded);
+    if !changed {
+        // Checked; wait another interval
+        perms::write(&path, &recorded).ok();
+    }
+    changed
+}
+
+/// Record the ETag of the asset `version` was just installed from.
+pub fn record(version: &str) {
+    let etag = cache::source(version)
+        .and_then(|url| http::head(&url, &[]).ok())
+        .and_then(|response| response.header("etag").map(|etag| etag.trim().to_string()));
+    match etag {
+        Some(etag) => perms::write(&etag_path(version), etag).ok(),
+        None => fs::remove_file(etag_path(version)).ok(),
+    };
+}
+
+fn etag_path(version: &str) -> PathBuf {
+    cache::root().join(version).join(ETAG_FILE)
+}
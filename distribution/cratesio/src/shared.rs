@@ -0,0 +1,227 @@
+//! A team cache of release archives on a network share (NFS, SMB).
+//!
+//! `BLDR_SHARED_CACHE` names a directory many machines mount. A release
+//! archive this machine installs is published there as
+//! `<share>/<version>/bldr-<os>-<arch>.tar.gz` (or `.tar.zst`) for the
+//! others, and a version not in the local cache is installed from there, if
+//! present, instead of downloaded. `BLDR_SHARED_CACHE_READONLY=1` makes a
+//! machine a consumer only, as does a share it cannot write to.
+//!
+//! Every host can write to the share, so nothing on it is trusted: an
+//! archive copied from it stands in for the download only when it matches a
+//! digest known without the share, the release's published checksum or the
+//! one embedded for this wrapper's version, and then gets every check the
+//! download would, after the policy allows the version and the download is
+//! agreed to. Without such a digest, or when the copy does not match it, the
+//! archive is downloaded instead.
+//!
+//! Network filesystems differ in what they guarantee, so the share relies on
+//! as little as possible:
+//!
+//! - A published archive counts only once its `<archive>.complete` marker
+//!   exists, written after it; a half-copied archive is never used.
+//! - Publishing stages the copy under `.incoming/` and renames it into
+//!   place; where the rename is refused, it is copied in place instead,
+//!   marker last.
+//! - Hosts take turns through lock files created exclusively (`O_EXCL`,
+//!   which NFSv3 and SMB honour, unlike `flock`) naming the holder. A lock
+//!   older than [`STALE_LOCK`] is from a crashed host and is broken.
+//! - Nothing is run from the share: archives are copied and unpacked into
+//!   the local cache and health-checked there.
+
+use crate::{github, platform, rolling};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Suffix of the marker written after a published archive.
+const COMPLETE: &str = ".complete";
+/// Longer than any copy of a release takes.
+const STALE_LOCK: Duration = Duration::from_secs(10 * 60);
+
+/// The shared cache directory, if configured.
+pub fn dir() -> Option<PathBuf> {
+    env::var_os("BLDR_SHARED_CACHE").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+/// Whether the shared cache has a complete archive of `version` for this
+/// platform.
+pub fn has(version: &str) -> bool {
+    let (os, arch) = platform::current();
+    let asset = platform::asset_name(os, arch);
+    dir().is_some_and(|root| ["tar.zst", "tar.gz"].iter().any(|ext| complete(&root.join(version).join(format!("{}.{}", asset, ext)))))
+}
+
+fn read_only() -> bool {
+    env::var("BLDR_SHARED_CACHE_READONLY").is_ok_and(|v| !matches!(v.trim(), "" | "0" | "false" | "no"))
+}
+
+fn complete(archive: &Path) -> bool {
+    marker(archive).is_file()
+}
+
+fn marker(archive: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(COMPLETE);
+    PathBuf::from(path)
+}
+
+/// Copy the shared cache's copy of the release archive `url` names into
+/// `archive`, when it has a complete one. The caller verifies it exactly as
+/// if it had been downloaded from `url`.
+pub fn fetch(url: &str, version: &str, archive: &Path) -> bool {
+    let Some(root) = dir() else {
+        return false;
+    };
+    let name = url.rsplit('/').next().unwrap_or(url);
+    let source = root.join(version).join(name);
+    if rolling::is_rolling(version) || !complete(&source) {
+        return false;
+    }
+    eprintln!("Copying {} {} from the shared cache {}...", name, github::tag(version), root.display());
+    match fs::copy(&source, archive) {
+        Ok(_) => true,
+        Err(e) => {
+            fs::remove_file(archive).ok();
+            eprintln!("bldr: cannot copy from the shared cache: {}", e);
+            false
+        }
+    }
+}
+
+/// Publish `archive`, the verified release archive `version` was just
+/// installed from, to the shared cache. Best effort: another host
+/// publishing it, a read-only share or any error just leaves it
+/// unpublished.
+pub fn publish(version: &str, archive: &Path) {
+    let Some(root) = dir() else {
+        return;
+    };
+    let (os, arch) = platform::current();
+    let ext = if archive.to_string_lossy().ends_with(".tar.zst") { "tar.zst" } else { "tar.gz" };
+    let name = format!("{}.{}", platform::asset_name(os, arch), ext);
+    let dest = root.join(version).join(&name);
+    if rolling::is_rolling(version) || read_only() || complete(&dest) {
+        return;
+    }
+    let Some(_lock) = Lock::acquire(&root, version) else {
+        return;
+    };
+    if complete(&dest) {
+        return;
+    }
+    
+    let staging = root.join(".incoming").join(format!("{}-{}-{}-{}", version, name, host(), std::process::id()));
+    let published = fs::create_dir_all(staging.parent().unwrap_or(&root))
+        .and_then(|()| fs::create_dir_all(root.join(version)))
+        .and_then(|()| fs::copy(archive, &staging))
+        .and_then(|_| match fs::rename(&staging, &dest) {
+            Ok(()) => Ok(()),
+            // No rename into place here: copy over whatever an earlier
+            // interrupted copy left, completing it with the marker last
+            Err(_) => fs::copy(archive, &dest).map(drop),
+        })
+        .and_then(|()| fs::write(marker(&dest), version));
+    fs::remove_file(&staging).ok();
+    match published {
+        Ok(()) => eprintln!("Shared bldr {} in {}", github::tag(version), root.display()),
+        Err(e) => eprintln!("bldr: warning: cannot publish to the shared cache {}: {}", root.display(), e),
+    }
+}
+
+/// Copy the files of `from` into `to`, skipping the wrapper's own markers
+/// (`.complete`, `.used`, ...) and leftover archives.
+pub fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            copy_tree(&entry.path(), &to.join(&name))?;
+        } else if kind.is_file() {
+            fs::copy(entry.path(), to.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// An exclusive lock file on the share, removed when dropped.
+struct Lock(PathBuf);
+
+impl Lock {
+    /// The lock for `version`, or `None` while another host holds it.
+    fn acquire(root: &Path, version: &str) -> Option<Lock> {
+        let dir = root.join(".locks");
+        fs::create_dir_all(&dir).ok()?;
+        let path = dir.join(format!("{}.lock", version));
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{} {}", host(), std::process::id()).ok();
+                    return Some(Lock(path));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&path)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .and_then(|t| t.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK);
+                    if !stale {
+                        return None;
+                    }
+                    fs::remove_file(&path).ok();
+                }
+                Err(_) => return None,
+            }
+        }
+        None
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+/// This machine's name, to tell hosts apart in lock and staging names.
+fn host() -> String {
+    let name = env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    name.unwrap_or_else(|| "host".to_string())
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn one_host_publishes_at_a_time() {
+        let root = env::temp_dir().join(format!("bldr-shared-{}", std::process::id()));
+        let held = Lock::acquire(&root, "1.2.3").unwrap();
+        assert!(Lock::acquire(&root, "1.2.3").is_none());
+        assert!(Lock::acquire(&root, "1.2.4").is_some());
+        drop(held);
+        assert!(Lock::acquire(&root, "1.2.3").is_some());
+        
+        // A crashed host's lock is broken once stale
+        let path = root.join(".locks").join("1.2.3.lock");
+        let file = OpenOptions::new().create(true).truncate(true).write(true).open(&path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - STALE_LOCK * 2).unwrap();
+        assert!(Lock::acquire(&root, "1.2.3").is_some());
+        fs::remove_dir_all(&root).ok();
+    }
+}
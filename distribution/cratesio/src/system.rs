@@ -0,0 +1,57 @@
+//! Cores installed outside the wrapper's cache: copies `bldr wrapper
+//! install-bin` put on `PATH`, and Homebrew's formula.
+//!
+//! When the cache lacks the version a directory runs, a core of exactly that
+//! version on `PATH` runs instead of a download. Its version is read from
+//! where it is installed (`.bldr-core/<version>/bldr`,
+//! `Cellar/bldr/<version>/bin/bldr`) rather than by running it, as a `bldr`
+//! on `PATH` may be another wrapper. Cores another user could replace are
+//! passed over, as in the cache.
+
+use crate::{cache, install};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where `install-bin` keeps copies of the core, inside the bin directory.
+pub const STORE: &str = ".bldr-core";
+
+/// A core of `version` on `PATH` other than this wrapper.
+pub fn find(version: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    let wrapper = env::current_exe().and_then(fs::canonicalize).ok();
+    env::split_paths(&path)
+        .filter_map(|dir| fs::canonicalize(dir.join(install::binary_name())).ok())
+        .filter(|core| Some(core) != wrapper.as_ref() && core.is_file())
+        .find(|core| installed_version(core).as_deref() == Some(version) && cache::check_secure(core).is_ok())
+}
+
+/// The version a core's install location records.
+fn installed_version(core: &Path) -> Option<String> {
+    let name = |dir: &Path| dir.file_name()?.to_str().map(str::to_string);
+    let dir = core.parent()?;
+    let above = dir.parent()?;
+    if name(above).as_deref() == Some(STORE) {
+        return name(dir);
+    }
+    // Homebrew adds `_<revision>` to formulae rebuilt at the same version
+    let formula = above.parent()?;
+    if name(dir).as_deref() == Some("bin") && name(formula).as_deref() == Some("bldr") && name(formula.parent()?).as_deref() == Some("Cellar") {
+        return name(above)?.split('_').next().map(str::to_string);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn reads_versions_from_install_locations() {
+        let version = |path: &str| installed_version(Path::new(path));
+        assert_eq!(version("/home/me/.local/bin/.bldr-core/2.0.3/bldr").as_deref(), Some("2.0.3"));
+        assert_eq!(version("/opt/homebrew/Cellar/bldr/2.0.4_1/bin/bldr").as_deref(), Some("2.0.4"));
+        assert_eq!(version("/home/me/.cargo/bin/bldr"), None);
+        assert_eq!(version("/opt/homebrew/Cellar/other/2.0.4/bin/bldr"), None);
+    }
+}
@@ -0,0 +1,95 @@
+//! Download rate limit for metered or shared connections.
+//!
+//! `BLDR_MAX_DOWNLOAD_RATE` or the policy's `max_download_rate` caps the
+//! transfer rate of release and toolchain downloads, in curl's `--limit-rate`
+//! syntax: bytes per second, or with a `K`, `M` or `G` suffix (`500K`,
+//! `1.5M`). When both are set the lower one applies, so a policy cap cannot
+//! be raised by users. A capped download uses a single connection; the
+//! built-in client paces its reads, and curl is given `--limit-rate`.
+
+use crate::policy;
+use std::env;
+use std::sync::OnceLock;
+#[cfg(feature = "native-http")]
+use std::sync::Mutex;
+#[cfg(feature = "native-http")]
+use std::thread;
+#[cfg(feature = "native-http")]
+use std::time::{Duration, Instant};
+
+/// The cap in bytes per second, if any.
+pub fn max_rate() -> Option<u64> {
+    static RATE: OnceLock<Option<u64>> = OnceLock::new();
+    *RATE.get_or_init(load)
+}
+
+fn load() -> Option<u64> {
+    let from_env = env::var("BLDR_MAX_DOWNLOAD_RATE")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .and_then(|value| {
+            parse_rate(&value)
+                .map_err(|e| eprintln!("bldr: warning: ignoring BLDR_MAX_DOWNLOAD_RATE: {}", e))
+                .ok()
+        });
+    let from_policy = policy::current()
+        .ok()
+        .and_then(|policy| policy.max_download_rate.as_deref())
+        .and_then(|value| parse_rate(value).ok());
+    from_env.into_iter().chain(from_policy).min()
+}
+
+/// Parse `100000`, `500K`, `1.5M` or `1G` (powers of 1024) into bytes per
+/// second.
+pub fn parse_rate(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, unit) = match value.char_indices().find(|(_, c)| c.is_ascii_alphabetic()) {
+        Some((i, _)) => value.split_at(i),
+        None => (value, ""),
+    };
+    let multiplier = match unit.to_ascii_lowercase().as_str() {
+        "" => 1.0,
+        "k" => 1024.0,
+        "m" => 1024.0 * 1024.0,
+        "g" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format!("invalid rate '{}' (expected e.g. 500K or 2M)", value)),
+    };
+    match number.trim().parse::<f64>() {
+        Ok(number) if number * multiplier >= 1.0 => Ok((number * multiplier) as u64),
+        _ => Err(format!("invalid rate '{}' (expected e.g. 500K or 2M)", value)),
+    }
+}
+
+/// Account for `bytes` just received at `rate` bytes per second, sleeping
+/// until they are due. Shared by every transfer in the process.
+#[cfg(feature = "native-http")]
+pub fn pace(bytes: usize, rate: u64) {
+    // When the transfer so far may end; never earlier than now, so idle
+    // time is not saved up for a burst
+    static DUE: Mutex<Option<Instant>> = Mutex::new(None);
+    let now = Instant::now();
+    let due = {
+        let Ok(mut due) = DUE.lock() else { return };
+        let start = due.filter(|&due| due > now).unwrap_or(now);
+        let end = start + Duration::from_secs_f64(bytes as f64 / rate as f64);
+        *due = Some(end);
+        end
+    };
+    thread::sleep(due.saturating_duration_since(now));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_curl_style_rates() {
+        assert_eq!(parse_rate("100000"), Ok(100000));
+        assert_eq!(parse_rate("500K"), Ok(500 * 1024));
+        assert_eq!(parse_rate("1.5m"), Ok(3 * 512 * 1024));
+        assert_eq!(parse_rate(" 1G "), Ok(1 << 30));
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("2MB").is_err());
+        assert!(parse_rate("0").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Transparency-log cross-check for downloaded release archives.
+//!
+//! With `BLDR_TLOG_VERIFY` set, the archive digest must appear in a Rekor
+//! log (`BLDR_TLOG_URL`, default the public Sigstore instance) and the entry's
+//! inclusion proof must hash up to the log's root. A release pipeline that
+//! serves different bytes to different users then has to publish every
+//! variant in a public, append-only log to get past the wrapper.
+
+use crate::policy::Mode;
+use crate::{checksum, http};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+
+const DEFAULT_LOG_URL: &str = "https://rekor.sigstore.dev";
+
+#[derive(Deserialize)]
+struct LogEntry {
+    body: String,
+    #[serde(rename = "logIndex")]
+    log_index: u64,
+    verification: Option<Verification>,
+}
+
+#[derive(Deserialize)]
+struct Verification {
+    #[serde(rename = "inclusionProof")]
+    inclusion_proof: Option<InclusionProof>,
+}
+
+#[derive(Deserialize)]
+struct InclusionProof {
+    #[serde(rename = "logIndex")]
+    log_index: u64,
+    #[serde(rename = "treeSize")]
+    tree_size: u64,
+    #[serde(rename = "rootHash")]
+    root_hash: String,
+    hashes: Vec<String>,
+}
+
+/// Apply the configured policy to a downloaded archive. Errors mean the
+/// archive must not be used.
+pub fn check(archive: &Path) -> Result<(), String> {
+    let mode = Mode::for_scheme("tlog", "BLDR_TLOG_VERIFY");
+    if mode == Mode::Off {
+        return Ok(());
+    }
+
+    let digest = checksum::sha256_file(archive).map_err(|e| format!("cannot hash {}: {}", archive.display(), e))?;
+    let log_url = env::var("BLDR_TLOG_URL").unwrap_or_else(|_| DEFAULT_LOG_URL.to_string());
+
+    match verify(&log_url, &digest) {
+        Ok(index) => {
+            eprintln!("Transparency log: sha256:{} found at {} (entry {})", digest, log_url, index);
+            Ok(())
+        }
+        Err(e) if mode == Mode::Warn => {
+            eprintln!("bldr: warning: transparency log check failed: {}", e);
+            Ok(())
+        }
+        Err(e) => Err(format!("transparency log check failed: {}", e)),
+    }
+}
+
+/// Find a log entry recording `digest` and verify its inclusion proof.
+/// Returns the entry's log index.
+fn verify(log_url: &str, digest: &str) -> Result<u64, String> {
+    let log_url = log_url.trim_end_matches('/');
+    let query = format!("{{\"hash\":\"sha256:{}\"}}", digest);
+    let body = http::post_json(&format!("{}/api/v1/index/retrieve", log_url), &query)?;
+    let uuids: Vec<String> = serde_json::from_str(&body).map_err(|e| format!("invalid index response: {}", e))?;
+
+    if uuids.is_empty() {
+        return Err(format!("no entry for sha256:{} in {}", digest, log_url));
+    }
+
+    let mut last_error = String::new();
+    for uuid in &uuids {
+        match verify_entry(log_url, uuid, digest) {
+            Ok(index) => return Ok(index),
+            Err(e) => last_error = e,
+        }
+    }
+
+    Err(last_error)
+}
+
+fn verify_entry(log_url: &str, uuid: &str, digest: &str) -> Result<u64, String> {
+    let body = http::get_text(&format!("{}/api/v1/log/entries/{}", log_url, uuid), &[])?;
+    let entries: HashMap<String, LogEntry> =
+        serde_json::from_str(&body).map_err(|e| format!("invalid log entry {}: {}", uuid, e))?;
+    let entry = entries.into_values().next().ok_or_else(|| format!("log entry {} is empty", uuid))?;
+
+    let leaf = base64_decode(&entry.body).ok_or_else(|| format!("log entry {} has an undecodable body", uuid))?;
+    if !String::from_utf8_lossy(&leaf).contains(digest) {
+        return Err(format!("log entry {} does not record sha256:{}", uuid, digest));
+    }
+
+    let proof = entry
+        .verification
+        .and_then(|v| v.inclusion_proof)
+        .ok_or_else(|| format!("log entry {} has no inclusion proof", uuid))?;
+
+    let leaf_hash = leaf_hash(&leaf);
+    // Entry UUIDs end with the hex leaf hash (optionally prefixed by a tree ID)
+    if !uuid.ends_with(&hex(&leaf_hash)) {
+        return Err(format!("log entry {} does not match its body", uuid));
+    }
+
+    let root = decode_hash(&proof.root_hash).ok_or("inclusion proof has a malformed root hash")?;
+    let path = proof
+        .hashes
+        .iter()
+        .map(|h| decode_hash(h))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("inclusion proof has a malformed hash")?;
+
+    if !verify_inclusion(proof.log_index, proof.tree_size, leaf_hash, &path, root) {
+        return Err(format!("inclusion proof for log entry {} does not verify", uuid));
+    }
+
+    Ok(entry.log_index)
+}
+
+fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// RFC 9162 section 2.1.3.2 inclusion proof verification.
+fn verify_inclusion(index: u64, tree_size: u64, leaf: [u8; 32], path: &[[u8; 32]], root: [u8; 32]) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+
+    let (mut fnode, mut snode) = (index, tree_size - 1);
+    let mut hash = leaf;
+
+    for sibling in path {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            hash = node_hash(sibling, &hash);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+
+    snode == 0 && hash == root
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hash(s: &str) -> Option<[u8; 32]> {
+    if s.len() != 64 {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for c in s.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inclusion_proofs_verify_against_root() {
+        // Five-leaf tree: root = H(H(H(a,b),H(c,d)), e)
+        let leaves: Vec<[u8; 32]> = (0u8..5).map(|i| leaf_hash(&[i])).collect();
+        let ab = node_hash(&leaves[0], &leaves[1]);
+        let cd = node_hash(&leaves[2], &leaves[3]);
+        let abcd = node_hash(&ab, &cd);
+        let root = node_hash(&abcd, &leaves[4]);
+
+        assert!(verify_inclusion(2, 5, leaves[2], &[leaves[3], ab, leaves[4]], root));
+        assert!(verify_inclusion(4, 5, leaves[4], &[abcd], root));
+        assert!(!verify_inclusion(3, 5, leaves[2], &[leaves[3], ab, leaves[4]], root));
+        assert!(!verify_inclusion(5, 5, leaves[4], &[abcd], root));
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(base64_decode("aGVsbG8gd29ybGQ=").unwrap(), b"hello world");
+        assert!(base64_decode("not base64!").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+//! Update notices: a one-line note after the core exits when a newer stable
+//! release is out.
+//!
+//! The check never holds up a build. Launching reads the last result from
+//! `<cache>/update-check`; when that is older than `BLDR_UPDATE_CHECK_INTERVAL`
+//! (a week by default) a detached `bldr wrapper update-check` asks the
+//! releases API in the background, with its output discarded, for the next
+//! run to report. Nothing is ever installed; the notice points at
+//! `bldr self update`. `BLDR_NO_UPDATE_CHECK=1`, `update_check = false` in the
+//! policy, offline mode, CI and a non-terminal stderr turn it off.
+
+use crate::{cache, offline, perms, policy, rolling, version};
+use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Whether this invocation checks for and reports newer releases.
+pub fn enabled() -> bool {
+    let disabled = env::var("BLDR_NO_UPDATE_CHECK").is_ok_and(|v| !matches!(v.trim(), "" | "0" | "false" | "no"));
+    !disabled
+        && policy::current().is_ok_and(|policy| policy.update_check)
+        && !offline::enabled()
+        && env::var_os("CI").is_none()
+        && std::io::stderr().is_terminal()
+}
+
+/// Start a background check when the last one is older than the interval.
+pub fn start() {
+    let checked = read_state().map(|(checked, _)| checked);
+    let due = checked
+        .and_then(|t| t.elapsed().ok())
+        .is_none_or(|age| age >= interval());
+    if !due {
+        return;
+    }
+    // Stamp it first so runs until the check finishes do not start another
+    let newest = read_state().and_then(|(_, newest)| newest);
+    write_state(newest.as_deref());
+    let Ok(exe) = env::current_exe() else {
+        return;
+    };
+    let mut command = Command::new(exe);
+    command
+        .args(["wrapper", "update-check"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    detach(&mut command);
+    command.spawn().ok();
+}
+
+/// Keep the check running when the terminal closes, and out of Ctrl-C's way.
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(windows)]
+fn detach(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+/// Print the notice if the last check found a release newer than `running`.
+pub fn notice(running: &str) {
+    if rolling::is_rolling(running) {
+        return;
+    }
+    if let Some(newest) = read_state().and_then(|(_, newest)| newest) {
+        if version::compare(&newest, running).is_gt() {
+            eprintln!("bldr: {} is available (running {}); update with `bldr self update`", newest, running);
+        }
+    }
+}
+
+/// `bldr wrapper update-check`: look up the newest stable release and
+/// record it for [`notice`].
+pub fn run() -> i32 {
+    match version::latest(&version::Selector::Channel("stable".to_string())) {
+        Ok(newest) => {
+            write_state(Some(&newest));
+            0
+        }
+        Err(e) => {
+            eprintln!("bldr wrapper update-check: {}", e);
+            1
+        }
+    }
+}
+
+/// `BLDR_UPDATE_CHECK_INTERVAL`, else [`DEFAULT_INTERVAL`].
+fn interval() -> Duration {
+    env::var("BLDR_UPDATE_CHECK_INTERVAL")
+        .ok()
+        .and_then(|value| version::parse_duration(&value))
+        .unwrap_or(DEFAULT_INTERVAL)
+}
+
+fn state_path() -> PathBuf {
+    cache::root().join("update-check")
+}
+
+/// When the last check ran and the newest release it found: `<secs> [<version>]`.
+fn read_state() -> Option<(SystemTime, Option<String>)> {
+    let contents = fs::read_to_string(state_path()).ok()?;
+    let mut fields = contents.split_whitespace();
+    let checked = UNIX_EPOCH.checked_add(Duration::from_secs(fields.next()?.parse().ok()?))?;
+    Some((checked, fields.next().map(str::to_string)))
+}
+
+fn write_state(newest: Option<&str>) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let path = state_path();
+    if let Some(dir) = path.parent() {
+        perms::create_dir_all(dir).ok();
+    }
+    perms::write(&path, format!("{} {}\n", now, newest.unwrap_or(""))).ok();
+}
@@ -0,0 +1,344 @@
+//! Which core version an invocation asks for.
+//!
+//! The wrapper is a multi-call binary: linked as `bldr-2.1` it runs the
+//! newest 2.1.x release, as `bldr-2.0.3` exactly that release, and as
+//! `bldr-nightly` / `bldr-latest` whatever the channel currently points at.
+//!
+//! Floating selectors are resolved against the GitHub releases API and the
+//! answer is reused for `BLDR_RESOLVE_TTL` (seconds, or `30m`, `12h`, `1d`;
+//! an hour by default) before the API is asked again.
+
+use crate::{cache, github, offline, perms, rolling, VERSION};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// How long a floating selector's resolution is reused before re-querying,
+/// unless `BLDR_RESOLVE_TTL` says otherwise.
+const FLOATING_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Selector {
+    /// A full `major.minor.patch[-pre]` version.
+    Exact(String),
+    /// A version prefix such as `2` or `2.1`.
+    Prefix(String),
+    /// A named channel: `latest`/`stable` or `nightly`/`beta`.
+    Channel(String),
+}
+
+impl Selector {
+    pub fn parse(s: &str) -> Option<Selector> {
+        let s = s.trim().trim_start_matches('v');
+        match s {
+            "latest" | "stable" | "nightly" | "beta" => return Some(Selector::Channel(s.to_string())),
+            "" => return None,
+            _ => {}
+        }
+    
+        let (core, pre) = s.split_once('-').unwrap_or((s, ""));
+        let parts: Vec<&str> = core.split('.').collect();
+        if parts.iter().any(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit())) || parts.len() > 3 {
+            return None;
+        }
+    
+        if parts.len() == 3 {
+            Some(Selector::Exact(s.to_string()))
+        } else if pre.is_empty() {
+            Some(Selector::Prefix(core.to_string()))
+        } else {
+            None
+        }
+    }
+    
+    /// Selector pinned by the invocation name, e.g. `bldr-2.1` or `bldr-nightly.exe`.
+    pub fn from_argv0(argv0: &str) -> Option<Selector> {
+        let name = Path::new(argv0).file_name()?.to_str()?;
+        let name = name.strip_suffix(".exe").unwrap_or(name);
+        let suffix = name.strip_prefix("bldr-").or_else(|| name.strip_prefix("bldr@"))?;
+        Selector::parse(suffix)
+    }
+    
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Selector::Exact(_))
+    }
+    
+    pub fn label(&self) -> &str {
+        match self {
+            Selector::Exact(s) | Selector::Prefix(s) | Selector::Channel(s) => s,
+        }
+    }
+}
+
+impl Default for Selector {
+    fn default() -> Selector {
+        Selector::Exact(VERSION.to_string())
+    }
+}
+
+/// Outcome of resolving a selector.
+pub struct Resolved {
+    /// Concrete version, without the `v`.
+    pub version: String,
+    /// What a floating selector pointed at before, when it just moved.
+    pub previous: Option<String>,
+    /// Release notes for the versions moved across, oldest first.
+    pub notes: Vec<(String, String)>,
+}
+
+/// Resolve a selector to a concrete version.
+pub fn resolve(selector: &Selector) -> Result<Resolved, String> {
+    resolve_within(selector, floating_ttl())
+}
+
+/// Resolve a selector against the releases API now, however recent the
+/// recorded resolution is.
+pub fn refresh(selector: &Selector) -> Result<Resolved, String> {
+    resolve_within(selector, Duration::ZERO)
+}
+
+fn resolve_within(selector: &Selector, ttl: Duration) -> Result<Resolved, String> {
+    if let Selector::Exact(version) = selector {
+        return Ok(Resolved {
+            version: version.clone(),
+            previous: None,
+            notes: Vec::new(),
+        });
+    }
+    
+    let memo = cache::root().join("resolved").join(format!("{}.version", selector.label()));
+    let previous = fs::read_to_string(&memo).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let fresh = fs::metadata(&memo)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .map(|age| age < ttl)
+        .unwrap_or(false);
+    if let (true, Some(version)) = (fresh, &previous) {
+        return Ok(Resolved {
+            version: version.clone(),
+            previous: None,
+            notes: Vec::new(),
+        });
+    }
+    if offline::enabled() {
+        return newest_installed(selector, previous.as_deref());
+    }
+    
+    let releases = github::releases(&github::repo())?;
+    let version = newest(selector, &releases)?;
+    
+    if let Some(dir) = memo.parent() {
+        perms::create_dir_all(dir).ok();
+        perms::write(&memo, &version).ok();
+    }
+    
+    let previous = previous.filter(|p| *p != version);
+    let notes = match &previous {
+        Some(from) => notes_between(selector, &releases, from, &version),
+        None => Vec::new(),
+    };
+    
+    Ok(Resolved { version, previous, notes })
+}
+
+/// Release notes for the versions `selector` moves across going from `from`
+/// to `to`, oldest first; empty when the releases cannot be listed.
+pub fn notes(selector: &Selector, from: &str, to: &str) -> Vec<(String, String)> {
+    github::releases(&github::repo())
+        .map(|releases| notes_between(selector, &releases, from, to))
+        .unwrap_or_default()
+}
+
+fn notes_between(selector: &Selector, releases: &[github::Release], from: &str, to: &str) -> Vec<(String, String)> {
+    let mut notes: Vec<(String, String)> = releases
+        .iter()
+        .filter(|r| !r.draft && matches(selector, r.tag_name.trim_start_matches('v'), r.prerelease))
+        .map(|r| (r.tag_name.trim_start_matches('v').to_string(), r.body.clone().unwrap_or_default()))
+        .filter(|(tag, _)| compare(tag, from).is_gt() && compare(tag, to).is_le())
+        .collect();
+    notes.sort_by(|a, b| compare(&a.0, &b.0));
+    notes
+}
+
+/// Installed binary a selector would run, without touching the network:
+/// the last recorded resolution if still installed, else the newest installed
+/// match. Used where waiting on a download is not an option.
+pub fn installed_binary(selector: &Selector) -> Option<std::path::PathBuf> {
+    let installed = cache::installed();
+    let memo = fs::read_to_string(cache::root().join("resolved").join(format!("{}.version", selector.label())))
+        .ok()
+        .map(|v| v.trim().to_string());
+    
+    if let Some((_, binary)) = installed
+        .iter()
+        .find(|(v, _)| Some(v) == memo.as_ref() || matches!(selector, Selector::Exact(e) if e == v))
+    {
+        return Some(binary.clone());
+    }
+    installed
+        .into_iter()
+        .filter(|(v, _)| !selector.is_exact() && matches(selector, v, v.contains('-')))
+        .max_by(|a, b| compare(&a.0, &b.0))
+        .map(|(_, binary)| binary)
+}
+
+/// Offline resolution: the last resolution when it is still installed,
+/// else the newest installed match.
+fn newest_installed(selector: &Selector, previous: Option<&str>) -> Result<Resolved, String> {
+    let installed: Vec<String> = cache::installed().into_iter().map(|(version, _)| version).collect();
+    let version = previous
+        .filter(|previous| installed.iter().any(|v| v == previous))
+        .map(str::to_string)
+        .or_else(|| {
+            installed
+                .iter()
+                .filter(|v| matches(selector, v, v.contains('-')))
+                .max_by(|a, b| compare(a, b))
+                .cloned()
+        })
+        .ok_or_else(|| format!("no installed version matches '{}' and network access is off", selector.label()))?;
+    Ok(Resolved {
+        version,
+        previous: None,
+        notes: Vec::new(),
+    })
+}
+
+/// What a selector points at right now, without recording the resolution,
+/// so the next interactive run still reports the move.
+pub fn latest(selector: &Selector) -> Result<String, String> {
+    match selector {
+        Selector::Exact(version) => Ok(version.clone()),
+        _ => newest(selector, &github::releases(&github::repo())?),
+    }
+}
+
+fn newest(selector: &Selector, releases: &[github::Release]) -> Result<String, String> {
+    // A release tagged with the channel's own name (`nightly`), republished
+    // in place, is the channel
+    if let Selector::Channel(channel) = selector {
+        if releases.iter().any(|r| !r.draft && r.tag_name == *channel) {
+            return Ok(channel.clone());
+        }
+    }
+    let tags: Vec<(&str, bool)> = releases
+        .iter()
+        .filter(|r| !r.draft)
+        .map(|r| (r.tag_name.trim_start_matches('v'), r.prerelease))
+        .collect();
+    pick(selector, &tags).ok_or_else(|| format!("no release matches '{}'", selector.label()))
+}
+
+fn matches(selector: &Selector, tag: &str, prerelease: bool) -> bool {
+    // A rolling tag (`nightly`) only ever stands for its own channel
+    if rolling::is_rolling(tag) {
+        return matches!(selector, Selector::Channel(c) if c == tag);
+    }
+    match selector {
+        Selector::Exact(v) => tag == v,
+        Selector::Prefix(p) => !prerelease && (tag.starts_with(&format!("{}.", p)) || tag == p),
+        Selector::Channel(c) if c == "nightly" => true,
+        // Betas and release candidates, not nightly or dev builds
+        Selector::Channel(c) if c == "beta" => {
+            !tag.split_once('-').is_some_and(|(_, pre)| pre.starts_with("nightly") || pre.starts_with("dev"))
+        }
+        Selector::Channel(_) => !prerelease,
+    }
+}
+
+/// Choose the best release for a floating selector.
+fn pick(selector: &Selector, releases: &[(&str, bool)]) -> Option<String> {
+    releases
+        .iter()
+        .filter(|(tag, prerelease)| matches(selector, tag, *prerelease))
+        .max_by(|a, b| compare(a.0, b.0))
+        .map(|(tag, _)| tag.to_string())
+}
+
+/// `BLDR_RESOLVE_TTL`, else [`FLOATING_TTL`]; `0` asks the API every run.
+fn floating_ttl() -> Duration {
+    match env::var("BLDR_RESOLVE_TTL") {
+        Ok(value) => parse_duration(&value).unwrap_or_else(|| {
+            eprintln!("bldr: ignoring BLDR_RESOLVE_TTL={}: expected seconds or a number with s, m, h or d", value);
+            FLOATING_TTL
+        }),
+        Err(_) => FLOATING_TTL,
+    }
+}
+
+/// `90`, `90s`, `30m`, `12h` or `1d`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last()? {
+        (at, c) if c.is_ascii_alphabetic() => (&s[..at], c.to_ascii_lowercase()),
+        _ => (s, 's'),
+    };
+    let scale = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return None,
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(scale).map(Duration::from_secs)
+}
+
+/// Semver-ish ordering: numeric components, then releases above prereleases.
+pub fn compare(a: &str, b: &str) -> std::cmp::Ordering {
+    let split = |v: &str| {
+        let (core, pre) = v.split_once('-').map(|(c, p)| (c, Some(p.to_string()))).unwrap_or((v, None));
+        let nums: Vec<u64> = core.split('.').map(|p| p.parse().unwrap_or(0)).collect();
+        (nums, pre)
+    };
+    let (a_nums, a_pre) = split(a);
+    let (b_nums, b_pre) = split(b);
+    
+    a_nums.cmp(&b_nums).then_with(|| match (a_pre, b_pre) {
+        (None, None) => std::cmp::Ordering::Equal,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (Some(x), Some(y)) => x.cmp(&y),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_invocation_names() {
+        assert_eq!(Selector::from_argv0("/usr/local/bin/bldr-2.1"), Some(Selector::Prefix("2.1".into())));
+        assert_eq!(Selector::from_argv0("bldr-2.0.3.exe"), Some(Selector::Exact("2.0.3".into())));
+        assert_eq!(Selector::from_argv0("bldr-nightly"), Some(Selector::Channel("nightly".into())));
+        assert_eq!(Selector::from_argv0("/usr/bin/bldr"), None);
+        assert_eq!(Selector::from_argv0("bldr-lsp"), None);
+    }
+    
+    #[test]
+    fn picks_newest_matching_release() {
+        let releases = [("2.2.0-rc1", true), ("2.1.10", false), ("2.1.9", false), ("2.0.3", false)];
+        assert_eq!(pick(&Selector::Prefix("2.1".into()), &releases).as_deref(), Some("2.1.10"));
+        assert_eq!(pick(&Selector::Channel("latest".into()), &releases).as_deref(), Some("2.1.10"));
+        assert_eq!(pick(&Selector::Channel("nightly".into()), &releases).as_deref(), Some("2.2.0-rc1"));
+        assert_eq!(pick(&Selector::Prefix("3".into()), &releases), None);
+        
+        let releases = [("2.2.0-nightly.20261016", true), ("2.2.0-beta.2", true), ("2.1.10", false), ("nightly", false)];
+        assert_eq!(pick(&Selector::Channel("beta".into()), &releases).as_deref(), Some("2.2.0-beta.2"));
+        assert_eq!(pick(&Selector::Channel("stable".into()), &releases).as_deref(), Some("2.1.10"));
+        assert!(matches(&Selector::Channel("nightly".into()), "nightly", false));
+        assert!(!matches(&Selector::Channel("stable".into()), "nightly", false));
+    }    
+    #[test]
+    fn parses_resolution_ttls() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("12H"), Some(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(24 * 60 * 60)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("1w"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
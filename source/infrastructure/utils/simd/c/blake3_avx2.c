@@ -174,11 +174,15 @@ void blake3_hash_many_avx2(
             }
         }
         
-        /* Store results */
+        /* Store results (lane is not a constant, so no _mm256_extract_epi32) */
+        uint32_t words[8][8];
+        for (int i = 0; i < 8; i++) {
+            _mm256_storeu_si256((__m256i*)words[i], cv[i]);
+        }
         for (size_t lane = 0; lane < batch_size; lane++) {
             uint8_t* output = out + (base + lane) * 32;
             for (int i = 0; i < 8; i++) {
-                ((uint32_t*)output)[i] = _mm256_extract_epi32(cv[i], lane);
+                ((uint32_t*)output)[i] = words[i][lane];
             }
         }
     }
@@ -20,11 +20,19 @@ error() { echo -e "${RED}✗${NC} $1" >&2; exit 1; }
 
 # Version files
 CARGO_TOML="distribution/cratesio/Cargo.toml"
+SYS_CARGO_TOML="distribution/builder-core-sys/Cargo.toml"
+HASH_CARGO_TOML="distribution/bldr-hash/Cargo.toml"
 MAIN_RS="distribution/cratesio/src/main.rs"
+PINNED_SUMS="distribution/cratesio/src/pinned.sha256"
+MINISIGN_PUB="distribution/cratesio/src/minisign.pub"
 PACKAGE_JSON="tools/vscode/builder-lang/package.json"
 HOMEBREW_FORMULA="distribution/homebrew/main/bldr.rb"
 BUILDER_ENTRY="source/builder_entry.d"
 
+# Platforms every release publishes bldr-<os>-<arch> archives for; keep in
+# step with the wrapper's platform table
+PUBLISHED_PLATFORMS=(darwin-arm64 darwin-amd64 linux-amd64 linux-arm64 windows-amd64)
+
 get_current_version() {
     grep -E '^version = "' "$CARGO_TOML" | head -1 | sed 's/version = "\(.*\)"/\1/'
 }
@@ -47,14 +55,28 @@ validate_prereqs() {
     command -v cargo &>/dev/null || error "Cargo not installed"
     command -v dub &>/dev/null || error "Dub not installed"
     command -v curl &>/dev/null || error "curl not installed"
+    command -v ldc2 &>/dev/null || error "LDC (ldc2) not installed"
+    command -v zstd &>/dev/null || error "zstd not installed"
     
     gh auth status &>/dev/null || error "Not authenticated with GitHub CLI"
     
     [[ -f "$CARGO_TOML" ]] || error "Missing $CARGO_TOML"
     [[ -f "$MAIN_RS" ]] || error "Missing $MAIN_RS"
+    [[ -f "$PINNED_SUMS" ]] || error "Missing $PINNED_SUMS"
+    if [[ -n "${BLDR_RELEASE_MINISIGN_KEY:-}" ]]; then
+        command -v minisign &>/dev/null || error "minisign not installed (BLDR_RELEASE_MINISIGN_KEY is set)"
+        [[ -f "$(minisign_public_key)" ]] || error "Missing $(minisign_public_key)"
+    fi
+    if [[ "${BLDR_RELEASE_COSIGN:-}" == "1" ]]; then
+        command -v cosign &>/dev/null || error "cosign not installed (BLDR_RELEASE_COSIGN is set)"
+    fi
+    if [[ -n "${BLDR_RELEASE_OCI:-}" ]]; then
+        command -v oras &>/dev/null || error "oras not installed (BLDR_RELEASE_OCI is set)"
+    fi
     [[ -f "$PACKAGE_JSON" ]] || error "Missing $PACKAGE_JSON"
     [[ -f "$HOMEBREW_FORMULA" ]] || error "Missing $HOMEBREW_FORMULA"
     [[ -f "$BUILDER_ENTRY" ]] || error "Missing $BUILDER_ENTRY"
+    [[ -f "$SYS_CARGO_TOML" ]] || error "Missing $SYS_CARGO_TOML"
     
     success "All prerequisites met"
 }
@@ -78,8 +100,10 @@ update_version_files() {
     local old="$1" new="$2"
     log "Updating version: $old → $new"
     
-    # Cargo.toml
+    # Cargo.toml (wrapper, -sys and hash crates)
     sed -i '' "s/^version = \"$old\"/version = \"$new\"/" "$CARGO_TOML"
+    sed -i '' "s/^version = \"$old\"/version = \"$new\"/" "$SYS_CARGO_TOML"
+    sed -i '' "s/^version = \"$old\"/version = \"$new\"/" "$HASH_CARGO_TOML"
     
     # main.rs
     sed -i '' "s/const VERSION: \&str = \"[^\"]*\"/const VERSION: \&str = \"$new\"/" "$MAIN_RS"
@@ -100,24 +124,175 @@ update_version_files() {
 build_release() {
     log "Building release..."
     make build
+    make lib
+    make lib-shared
     success "Build complete"
 }
 
+release_arch() {
+    [[ "$(uname -m)" == "arm64" || "$(uname -m)" == "aarch64" ]] && echo "arm64" || echo "amd64"
+}
+
+release_os() {
+    [[ "$(uname -s)" == "Darwin" ]] && echo "darwin" || echo "linux"
+}
+
+# bldr-<os>-<arch>.tar.gz, and the same tarball as .tar.zst, which wrappers
+# fetch instead when the release lists it: smaller and faster to unpack
 create_tarball() {
-    log "Creating release tarball..."
+    log "Creating release tarballs..." >&2
     mkdir -p dist/release
     cp bin/bldr dist/release/
     
-    local arch
-    arch=$(uname -m)
-    [[ "$arch" == "arm64" ]] && arch="arm64" || arch="amd64"
+    local name="bldr-$(release_os)-$(release_arch)"
+    (cd dist/release && tar -czf "$name.tar.gz" bldr)
+    (cd dist/release && tar -cf - bldr | zstd -q -19 -f -o "$name.tar.zst")
     
-    local tarball="bldr-darwin-$arch.tar.gz"
-    (cd dist/release && tar -czf "$tarball" bldr)
+    echo "dist/release/$name.tar.gz dist/release/$name.tar.zst"
+}
+
+# The other platforms' archives, built by CI for this version and downloaded
+# into BLDR_RELEASE_ARTIFACTS (default dist/artifacts), join this host's, so
+# SHA256SUMS and the checksums pinned in the wrapper cover every platform.
+# Refuses to go on while any published platform's archive is missing
+collect_platform_archives() {
+    local artifacts="${BLDR_RELEASE_ARTIFACTS:-dist/artifacts}"
+    log "Collecting other platforms' archives from $artifacts..." >&2
+    local platform ext missing=() archives=()
+    for platform in "${PUBLISHED_PLATFORMS[@]}"; do
+        for ext in tar.gz tar.zst; do
+            local name="bldr-$platform.$ext"
+            if [[ ! -f "dist/release/$name" && -f "$artifacts/$name" ]]; then
+                cp "$artifacts/$name" dist/release/
+            fi
+            [[ -f "dist/release/$name" ]] && archives+=("dist/release/$name")
+        done
+        [[ -f "dist/release/bldr-$platform.tar.gz" ]] || missing+=("$platform")
+    done
+    [[ ${#missing[@]} -eq 0 ]] || error "No archive for ${missing[*]} in $artifacts; put CI's bldr-<os>-<arch>.tar.gz (and .tar.zst) there"
+    echo "${archives[@]}"
+}
+
+# Static (and shared) library for builder-core-sys, bundled with the D runtime
+# it was compiled against so Rust projects can link it without a D toolchain
+create_lib_tarball() {
+    log "Creating static library tarball..." >&2
+    mkdir -p dist/release/lib
+    cp dist/lib/libbuilder-core.a dist/release/lib/
+    cp dist/lib/libbuilder-core.so dist/lib/libbuilder-core.dylib dist/release/lib/ 2>/dev/null || true
+    
+    local ldc_lib
+    ldc_lib="$(dirname "$(command -v ldc2)")/../lib"
+    for runtime in libdruntime-ldc.a libphobos2-ldc.a; do
+        [[ -f "$ldc_lib/$runtime" ]] || error "Missing $runtime in $ldc_lib"
+        cp "$ldc_lib/$runtime" dist/release/lib/
+    done
+    
+    local tarball="libbuilder-core-$(release_os)-$(release_arch).tar.gz"
+    (cd dist/release/lib && tar -czf "../$tarball" ./*)
     
     echo "dist/release/$tarball"
 }
 
+# <archive>.chunks: SHA-256 of every 1 MiB chunk, so the wrapper can check
+# the part of an interrupted download it already has before resuming
+write_chunk_manifests() {
+    log "Writing chunk manifests..." >&2
+    local chunk_size=1048576
+    local file manifests=()
+    for file in dist/release/*.tar.{gz,zst}; do
+        local size count i
+        size=$(wc -c < "$file")
+        count=$(( (size + chunk_size - 1) / chunk_size ))
+        {
+            echo "chunk-size $chunk_size"
+            for ((i = 0; i < count; i++)); do
+                dd if="$file" bs="$chunk_size" skip="$i" count=1 2>/dev/null | shasum -a 256 | cut -d' ' -f1
+            done
+        } > "$file.chunks"
+        manifests+=("$file.chunks")
+    done
+    echo "${manifests[@]}"
+}
+
+# <archive>.asc: detached signatures for wrappers running with BLDR_GPG_VERIFY;
+# only made when BLDR_RELEASE_GPG_KEY names a signing key.
+# <archive>.minisig: checked by every wrapper built with the embedded key;
+# only made when BLDR_RELEASE_MINISIGN_KEY names a minisign secret key.
+# <archive>.cosign.bundle: keyless Sigstore signatures for BLDR_COSIGN_VERIFY;
+# only made with BLDR_RELEASE_COSIGN=1, from the release workflow, whose
+# identity is what wrappers check
+sign_archives() {
+    local file signatures=()
+    if [[ -n "${BLDR_RELEASE_GPG_KEY:-}" ]]; then
+        log "Signing archives with $BLDR_RELEASE_GPG_KEY..." >&2
+        for file in dist/release/*.tar.{gz,zst}; do
+            gpg --batch --yes --local-user "$BLDR_RELEASE_GPG_KEY" --armor --detach-sign --output "$file.asc" "$file" >&2
+            signatures+=("$file.asc")
+        done
+    fi
+    if [[ -n "${BLDR_RELEASE_MINISIGN_KEY:-}" ]]; then
+        log "Signing archives with minisign..." >&2
+        for file in dist/release/*.tar.{gz,zst}; do
+            minisign -S -s "$BLDR_RELEASE_MINISIGN_KEY" -m "$file" -x "$file.minisig" \
+                -t "bldr $(basename "$file")" >&2
+            signatures+=("$file.minisig")
+        done
+    fi
+    if [[ "${BLDR_RELEASE_COSIGN:-}" == "1" ]]; then
+        log "Signing archives with cosign..." >&2
+        for file in dist/release/*.tar.{gz,zst}; do
+            cosign sign-blob --yes --bundle "$file.cosign.bundle" "$file" >&2
+            signatures+=("$file.cosign.bundle")
+        done
+    fi
+    echo "${signatures[@]}"
+}
+
+write_checksums() {
+    log "Writing SHA256SUMS..." >&2
+    (cd dist/release && shasum -a 256 ./*.tar.{gz,zst} | sed 's| \./| |' > SHA256SUMS)
+    echo "dist/release/SHA256SUMS"
+}
+
+# Embed the checksums in the wrapper crate, which checks downloads of its own
+# version against them rather than whatever the release serves later
+pin_checksums() {
+    local version="$1"
+    log "Pinning checksums in the wrapper crate..."
+    {
+        echo "# SHA-256 of this crate's own release assets, written by tools/release.sh"
+        echo "# when the release is cut: a \`version\` line, then the release's SHA256SUMS."
+        echo "version $version"
+        cat dist/release/SHA256SUMS
+    } > "$PINNED_SUMS"
+    local platform
+    for platform in "${PUBLISHED_PLATFORMS[@]}"; do
+        grep -q " bldr-$platform\.tar\.gz$" "$PINNED_SUMS" || error "No checksum pinned for bldr-$platform.tar.gz"
+    done
+    success "Pinned $(grep -c '\.tar\.\(gz\|zst\)$' dist/release/SHA256SUMS) asset checksums"
+}
+
+# Public half of BLDR_RELEASE_MINISIGN_KEY: BLDR_RELEASE_MINISIGN_PUBKEY, or
+# the .pub next to the secret key as `minisign -G` writes it
+minisign_public_key() {
+    echo "${BLDR_RELEASE_MINISIGN_PUBKEY:-${BLDR_RELEASE_MINISIGN_KEY%.key}.pub}"
+}
+
+# Embed the release signing key in the wrapper crate, so the wrapper checks
+# .minisig files against the key it was published with
+pin_public_key() {
+    [[ -n "${BLDR_RELEASE_MINISIGN_KEY:-}" ]] || return 0
+    log "Embedding the minisign public key in the wrapper crate..."
+    {
+        echo "# minisign public key for release archives, written by tools/release.sh from"
+        echo "# the .pub next to BLDR_RELEASE_MINISIGN_KEY. Builds without a key line skip"
+        echo "# signature checks unless BLDR_REQUIRE_SIGNATURE is set."
+        grep -v '^untrusted comment:' "$(minisign_public_key)"
+    } > "$MINISIGN_PUB"
+    success "Embedded $(tail -1 "$MINISIGN_PUB")"
+}
+
 verify_build() {
     local version="$1"
     log "Verifying build..."
@@ -151,10 +326,11 @@ push_to_remote() {
 }
 
 create_github_release() {
-    local version="$1" tarball="$2"
+    local version="$1"
+    shift
     log "Creating GitHub release v$version..."
     
-    gh release create "v$version" "$tarball" \
+    gh release create "v$version" "$@" \
         --title "v$version" \
         --notes "Release v$version" \
         --latest
@@ -162,6 +338,26 @@ create_github_release() {
     success "GitHub release created"
 }
 
+# Push the release files as one OCI artifact tagged v<version>, for wrappers
+# with an oci:// download base; only done when BLDR_RELEASE_OCI names the
+# repository (ghcr.io/griffincancode/bldr). Each file becomes a layer titled
+# with its name, which is how the wrapper finds it.
+push_oci_artifact() {
+    local version="$1"
+    shift
+    [[ -n "${BLDR_RELEASE_OCI:-}" ]] || return 0
+    log "Pushing $BLDR_RELEASE_OCI:v$version..."
+    
+    local file names=()
+    for file in "$@"; do
+        names+=("$(basename "$file")")
+    done
+    (cd dist/release && oras push "$BLDR_RELEASE_OCI:v$version" \
+        --artifact-type application/vnd.bldr.release.v1 "${names[@]}")
+    
+    success "OCI artifact pushed"
+}
+
 wait_for_release_propagation() {
     local version="$1"
     log "Waiting for release to propagate..."
@@ -200,6 +396,16 @@ update_homebrew_sha() {
 publish_crates() {
     log "Publishing to crates.io..."
     
+    (cd distribution/builder-core-sys && cargo publish)
+    
+    # bldr-hash compiles the engine's C sources; ship a copy inside the crate
+    local hash_c="distribution/bldr-hash/c"
+    rm -rf "$hash_c"
+    mkdir -p "$hash_c/crypto" "$hash_c/simd"
+    cp -R source/infrastructure/utils/crypto/c "$hash_c/crypto/"
+    cp -R source/infrastructure/utils/simd/c "$hash_c/simd/"
+    (cd distribution/bldr-hash && cargo publish --allow-dirty)
+    rm -rf "$hash_c"
     (cd distribution/cratesio && cargo publish)
     
     success "Published to crates.io"
@@ -262,12 +468,22 @@ main() {
     build_release
     verify_build "$new_version"
     
-    local tarball
-    tarball=$(create_tarball)
+    local tarball lib_tarball checksums chunk_manifests signatures
+    create_tarball > /dev/null
+    tarball=$(collect_platform_archives)
+    lib_tarball=$(create_lib_tarball)
+    checksums=$(write_checksums)
+    pin_checksums "$new_version"
+    pin_public_key
+    chunk_manifests=$(write_chunk_manifests)
+    signatures=$(sign_archives)
     
     commit_and_tag "$new_version"
     push_to_remote "$new_version"
-    create_github_release "$new_version" "$tarball"
+    # shellcheck disable=SC2086 # one argument per tarball, manifest and signature
+    create_github_release "$new_version" $tarball "$lib_tarball" "$checksums" $chunk_manifests $signatures
+    # shellcheck disable=SC2086
+    push_oci_artifact "$new_version" $tarball "$lib_tarball" "$checksums" $chunk_manifests $signatures
     wait_for_release_propagation "$new_version"
     update_homebrew_sha "$new_version"
     publish_crates